@@ -1,10 +1,13 @@
+use std::env;
+
 use anyhow::{Context, Result};
 
 use async_smarthome2::control::{message::TextMessage, protocol::client::Client};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let client = Client::connect("127.0.0.1:55332")
+    let key = env::var("SMARTHOME_KEY").context("SMARTHOME_KEY is not set")?;
+    let client = Client::connect("127.0.0.1:55332", key)
         .await
         .context("Failed to connect to the server")?;
 
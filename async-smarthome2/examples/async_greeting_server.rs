@@ -1,6 +1,9 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    env,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use anyhow::{Context, Result};
@@ -13,7 +16,8 @@ use async_smarthome2::control::{
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let server = Server::bind("127.0.0.1:55332")
+    let key = env::var("SMARTHOME_KEY").context("SMARTHOME_KEY is not set")?;
+    let server = Server::bind("127.0.0.1:55332", key)
         .await
         .context("Failed to bind a socket")?;
 
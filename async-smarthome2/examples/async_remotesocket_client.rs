@@ -1,9 +1,14 @@
+use std::env;
+
 use anyhow::{Context, Result};
 use tokio::fs;
 
-use async_smarthome2::device::{
-    socket::{RemoteSmartSocket, SwitchOffEvent, SwitchOnEvent},
-    AsyncDevice,
+use async_smarthome2::{
+    control::retry::RetryPolicy,
+    device::{
+        socket::{RemoteSmartSocket, SwitchOffEvent, SwitchOnEvent},
+        AsyncDevice,
+    },
 };
 
 #[tokio::main]
@@ -12,7 +17,8 @@ async fn main() -> Result<()> {
         .await
         .unwrap_or_else(|_| String::from("127.0.0.1:55333"));
 
-    let mut remote_socket = RemoteSmartSocket::connect(addr)
+    let key = env::var("SMARTHOME_KEY").context("SMARTHOME_KEY is not set")?;
+    let mut remote_socket = RemoteSmartSocket::reconnecting(addr, key, RetryPolicy::default())
         .await
         .context("Failed connect to the server")?;
 
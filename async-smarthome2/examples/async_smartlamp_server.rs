@@ -0,0 +1,35 @@
+use std::env;
+
+use anyhow::{Context, Result};
+use tokio::{fs, signal};
+
+use async_smarthome2::{control::server::SmartLampServer, device::lamp::SmartLamp};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let mut lamp = SmartLamp::new("Удаленная лампа").with_color_temperature(4000)?;
+    lamp.set_brightness(70)?;
+
+    let addr = fs::read_to_string("settings/addr")
+        .await
+        .unwrap_or_else(|_| String::from("127.0.0.1:55333"));
+    let key = env::var("SMARTHOME_KEY").context("SMARTHOME_KEY is not set")?;
+    let server = SmartLampServer::bind(addr, key, lamp)
+        .await
+        .context("Failed to bind a socket")?;
+
+    let shutdown = server.shutdown_handle();
+    tokio::spawn(async move {
+        signal::ctrl_c().await.unwrap();
+        shutdown.shutdown();
+    });
+    server.run().await;
+
+    if let Ok(lamp) = server.into_lamp() {
+        println!("Состояние лампы: {}", lamp);
+    }
+
+    Ok(())
+}
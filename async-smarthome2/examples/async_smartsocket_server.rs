@@ -1,4 +1,4 @@
-use std::sync::atomic::Ordering;
+use std::env;
 
 use anyhow::{Context, Result};
 use tokio::{fs, signal};
@@ -15,18 +15,21 @@ async fn main() -> Result<()> {
     let addr = fs::read_to_string("settings/addr")
         .await
         .unwrap_or_else(|_| String::from("127.0.0.1:55333"));
-    let (server, control) = SmartSocketServer::bind(addr, socket)
+    let key = env::var("SMARTHOME_KEY").context("SMARTHOME_KEY is not set")?;
+    let server = SmartSocketServer::bind(addr, key, socket)
         .await
         .context("Failed to bind a socket")?;
 
+    let shutdown = server.shutdown_handle();
     tokio::spawn(async move {
         signal::ctrl_c().await.unwrap();
-
-        if let Some(w) = control.upgrade() {
-            (*w).store(false, Ordering::Relaxed);
-        }
+        shutdown.shutdown();
     });
     server.run().await;
 
+    if let Ok(socket) = server.into_socket() {
+        println!("Состояние розетки: {}", socket);
+    }
+
     Ok(())
 }
@@ -1,43 +1,524 @@
-use tokio::net::ToSocketAddrs;
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex as StdMutex, RwLock,
+    },
+};
+
+use futures::{future::BoxFuture, stream, Stream};
+use tokio::{
+    net::{self, ToSocketAddrs},
+    sync::Mutex,
+    time,
+};
+use uuid::Uuid;
 
 use crate::{
     control::{
-        message::{ControlRequest, ControlResponse, ControlResponseData},
+        message::{ControlRequest, ControlResponse},
         protocol::client::Client,
+        retry::{ConnectionStatus, RetryPolicy, StatusListener},
     },
-    error::{ConnectionError, RequestError},
+    device::DeviceState,
+    error::{ConnectionError, RecvError, RequestError, SendError},
 };
 
+// Способ повторного подключения к серверу.
+type Connector = Box<dyn Fn() -> BoxFuture<'static, Result<Client, ConnectionError>> + Send + Sync>;
+
+// Ответы, ожидаемые на отправленные запросы.
+type Pending = StdMutex<HashMap<u64, Slot>>;
+
+// Состояние запроса клиента.
+enum Slot {
+    // Запрос еще не отправлен.
+    Unsent,
+
+    // Ответ еще не получен.
+    Waiting(ControlRequest),
+
+    // Ответ получен, но еще не забран.
+    Ready(Box<ControlResponse>),
+
+    // Соединение потеряно до получения ответа на запрос, который
+    // нельзя безопасно повторить.
+    Interrupted,
+
+    // Ответ не нужен: запрос отправлен клиентом самостоятельно при
+    // восстановлении соединения.
+    Detached,
+}
+
 ///
-/// Клиент подсистемы управления "умного" дома.
+/// Клиент подсистемы управления "умного" дома. Допускает одновременную
+/// отправку нескольких запросов по одному соединению.
 ///
 pub struct ControlClient {
-    client: Client,
+    // Текущее соединение с сервером.
+    client: RwLock<Arc<Client>>,
+
+    // Количество восстановлений соединения.
+    generation: AtomicU64,
+
+    // Идентификатор следующего запроса.
+    next_id: AtomicU64,
+
+    // Отправленные запросы и полученные, но еще не забранные ответы на них.
+    pending: Pending,
+
+    // Полученные, но еще не забранные уведомления сервера.
+    notifications: StdMutex<VecDeque<(Uuid, DeviceState)>>,
+
+    // Блокировка чтения ответов от сервера.
+    reader: Mutex<()>,
+
+    // Блокировка отправки запросов серверу.
+    writer: Mutex<()>,
+
+    // Способ повторного подключения и политика повторных попыток.
+    // Отсутствует, если клиент не восстанавливает соединение.
+    reconnect: Option<(Connector, RetryPolicy)>,
+
+    // Запросы на подписку, повторяемые после восстановления соединения.
+    subscriptions: StdMutex<Vec<ControlRequest>>,
+
+    // Текущее состояние соединения.
+    status: StdMutex<ConnectionStatus>,
+
+    // Обработчик изменений состояния соединения.
+    listener: Option<StatusListener>,
+}
+
+impl From<Client> for ControlClient {
+    ///
+    /// Создать клиент подсистемы управления из настроенного и
+    /// подключенного клиента для обмена сообщениями.
+    ///
+    #[inline]
+    fn from(client: Client) -> Self {
+        Self {
+            client: RwLock::new(Arc::new(client)),
+            generation: AtomicU64::new(0),
+            next_id: AtomicU64::new(1),
+            pending: StdMutex::new(HashMap::new()),
+            notifications: StdMutex::new(VecDeque::new()),
+            reader: Mutex::new(()),
+            writer: Mutex::new(()),
+            reconnect: None,
+            subscriptions: StdMutex::new(Vec::new()),
+            status: StdMutex::new(ConnectionStatus::Connected),
+            listener: None,
+        }
+    }
 }
 
 impl ControlClient {
     ///
-    /// Подключиться к серверу с заданным адресом.
+    /// Подключиться к серверу с заданным адресом и общим с сервером ключом
+    /// для аутентификации.
     ///
-    pub async fn connect<A>(addrs: A) -> Result<Self, ConnectionError>
+    pub async fn connect<A, K>(addrs: A, key: K) -> Result<Self, ConnectionError>
     where
         A: ToSocketAddrs,
+        K: AsRef<[u8]>,
     {
+        Ok(Self::from(Client::connect(addrs, key).await?))
+    }
+
+    ///
+    /// Подключиться к серверу с заданным адресом в режиме восстановления
+    /// соединения. При потере соединения клиент подключается повторно
+    /// в соответствии с заданной политикой.
+    ///
+    pub async fn reconnecting<A, K>(
+        addrs: A,
+        key: K,
+        policy: RetryPolicy,
+    ) -> Result<Self, ConnectionError>
+    where
+        A: ToSocketAddrs,
+        K: AsRef<[u8]>,
+    {
+        let key = key.as_ref().to_vec();
+        let addrs: Vec<SocketAddr> = net::lookup_host(addrs).await?.collect();
+        Self::reconnecting_with(
+            move || {
+                let (addrs, key) = (addrs.clone(), key.clone());
+                async move { Client::connect(&addrs[..], key).await }
+            },
+            policy,
+        )
+        .await
+    }
+
+    ///
+    /// Подключиться к серверу с помощью заданной функции в режиме
+    /// восстановления соединения. Функция вызывается повторно при каждой
+    /// попытке подключения, что позволяет использовать клиент для обмена
+    /// сообщениями с произвольными настройками.
+    ///
+    pub async fn reconnecting_with<F, R>(
+        connector: F,
+        policy: RetryPolicy,
+    ) -> Result<Self, ConnectionError>
+    where
+        F: Fn() -> R + Send + Sync + 'static,
+        R: Future<Output = Result<Client, ConnectionError>> + Send + 'static,
+    {
+        let connector: Connector = Box::new(move || Box::pin(connector()));
+        let client = establish(&connector, &policy, None).await?;
+
         Ok(Self {
-            client: Client::connect(addrs).await?,
+            reconnect: Some((connector, policy)),
+            ..Self::from(client)
         })
     }
 
+    ///
+    /// Сообщать заданному обработчику об изменениях состояния соединения.
+    ///
+    #[inline]
+    pub fn with_status_listener(self, listener: StatusListener) -> Self {
+        Self {
+            listener: Some(listener),
+            ..self
+        }
+    }
+
+    ///
+    /// Получить текущее состояние соединения.
+    ///
+    #[inline]
+    pub fn status(&self) -> ConnectionStatus {
+        *self.status.lock().unwrap()
+    }
+
     ///
     /// Отправить запрос серверу и получить ответ от него.
     ///
-    pub async fn request(&self, req: ControlRequest) -> Result<Box<ControlResponse>, RequestError> {
-        let response: Box<ControlResponse> = self.client.request(req).await?;
+    pub async fn request(
+        &self,
+        mut req: ControlRequest,
+    ) -> Result<Box<ControlResponse>, RequestError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        req.set_id(id);
+
+        let _guard = PendingGuard::register(&self.pending, id);
+        self.deliver(req.clone()).await?;
+        if self.reconnect.is_some() && req.is_subscription() {
+            self.subscriptions.lock().unwrap().push(req);
+        }
+        let response = self.wait(id).await?;
 
-        if let ControlResponseData::Error(message) = response.data {
-            Err(RequestError::ServerError(message))
+        if let Some(error) = response.error() {
+            Err(RequestError::ServerError(Box::new(error.clone().into())))
         } else {
             Ok(response)
         }
     }
+
+    ///
+    /// Получить количество запросов, ожидающих ответа.
+    ///
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.pending
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|s| !matches!(s, Slot::Detached))
+            .count()
+    }
+
+    ///
+    /// Получить поток уведомлений сервера об изменении состояния
+    /// устройств, на которые оформлена подписка. Поток возвращает
+    /// идентификатор комнаты и новое состояние устройства и завершается
+    /// при разрыве соединения.
+    ///
+    pub fn notifications(
+        &self,
+    ) -> impl Stream<Item = Result<(Uuid, DeviceState), RequestError>> + '_ {
+        stream::unfold(Some(self), |client| async move {
+            let client = client?;
+            match client.next_notification().await {
+                Ok(notification) => Some((Ok(notification), Some(client))),
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+
+    // Получить текущее соединение с сервером.
+    fn client(&self) -> Arc<Client> {
+        self.client.read().unwrap().clone()
+    }
+
+    // Отправить запрос серверу. Запрос, который не удалось отправить,
+    // сервер не получил, поэтому в режиме восстановления соединения он
+    // отправляется повторно после подключения независимо от его вида.
+    async fn deliver(&self, mut req: ControlRequest) -> Result<(), RequestError> {
+        loop {
+            let generation = {
+                let _lock = self.writer.lock().await;
+                let generation = self.generation.load(Ordering::Acquire);
+                let client = self.client();
+                req.set_version(client.version());
+                match client.send(req.clone()).await {
+                    Ok(()) => {
+                        let mut pending = self.pending.lock().unwrap();
+                        if let Some(slot @ Slot::Unsent) = pending.get_mut(&req.id()) {
+                            *slot = Slot::Waiting(req);
+                        }
+
+                        return Ok(());
+                    }
+                    Err(e @ SendError::Io(_)) if self.reconnect.is_some() => {
+                        log::warn!("Connection lost when sending data: {}", e);
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+
+                generation
+            };
+
+            let _lock = self.reader.lock().await;
+            if self.generation.load(Ordering::Acquire) == generation {
+                self.reconnect().await?;
+            }
+        }
+    }
+
+    // Дождаться ответа на запрос с заданным идентификатором.
+    async fn wait(&self, id: u64) -> Result<Box<ControlResponse>, RequestError> {
+        loop {
+            let _lock = self.reader.lock().await;
+            if let Some(response) = self.take(id)? {
+                return Ok(response);
+            }
+
+            self.receive().await?;
+        }
+    }
+
+    // Дождаться очередного уведомления сервера. Уведомления могут не
+    // поступать сколь угодно долго, поэтому истечение времени ожидания
+    // данных ошибкой не считается.
+    async fn next_notification(&self) -> Result<(Uuid, DeviceState), RequestError> {
+        loop {
+            let _lock = self.reader.lock().await;
+            if let Some(notification) = self.notifications.lock().unwrap().pop_front() {
+                return Ok(notification);
+            }
+
+            match self.receive().await {
+                Ok(()) | Err(RequestError::Recv(RecvError::Timeout)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // Получить очередное сообщение сервера и сохранить его до востребования.
+    // В режиме восстановления соединения потерянное соединение
+    // восстанавливается. Вызывается при захваченной блокировке чтения.
+    async fn receive(&self) -> Result<(), RequestError> {
+        let response: Box<ControlResponse> = match self.client().recv().await {
+            Ok(response) => response,
+            Err(RecvError::Timeout) => return Err(RecvError::Timeout.into()),
+            Err(e) if self.reconnect.is_some() => {
+                log::warn!("Connection lost when receiving data: {}", e);
+                return self.reconnect().await;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some(notification) = response.notification() {
+            self.notifications.lock().unwrap().push_back(notification);
+            return Ok(());
+        }
+
+        let mut pending = self.pending.lock().unwrap();
+        match pending.get_mut(&response.id()) {
+            Some(Slot::Detached) => {
+                pending.remove(&response.id());
+                if let Some(error) = response.error() {
+                    log::warn!("Cannot restore subscription: {}", error);
+                }
+            }
+            Some(slot) => *slot = Slot::Ready(response),
+            None => log::warn!("Unexpected response to request {}", response.id()),
+        }
+
+        Ok(())
+    }
+
+    // Восстановить соединение с сервером. Запросы, которые можно безопасно
+    // повторить, и запросы на подписку отправляются повторно, остальные
+    // запросы, ожидающие ответа, завершаются ошибкой. Вызывается при
+    // захваченной блокировке чтения. Все попытки, включая повторные после
+    // неудачной отправки запросов через новое соединение, учитываются
+    // единым счетчиком политики.
+    async fn reconnect(&self) -> Result<(), RequestError> {
+        let (connector, policy) = match self.reconnect {
+            Some((ref connector, ref policy)) => (connector, policy),
+            None => return Ok(()),
+        };
+
+        let _lock = self.writer.lock().await;
+        {
+            let mut pending = self.pending.lock().unwrap();
+            pending.retain(|_, slot| !matches!(slot, Slot::Detached));
+            for slot in pending.values_mut() {
+                if matches!(slot, Slot::Waiting(ref req) if !req.is_idempotent()) {
+                    *slot = Slot::Interrupted;
+                }
+            }
+        }
+
+        let mut result = Ok(());
+        for attempt in 1..=policy.max_attempts() {
+            let client = match try_connect(connector, policy, self.listener.as_ref(), attempt).await
+            {
+                Ok(client) => Arc::new(client),
+                Err(e) if !e.is_transient() || attempt >= policy.max_attempts() => {
+                    self.set_status(ConnectionStatus::Disconnected);
+                    return Err(e.into());
+                }
+                Err(e) => {
+                    log::warn!("Connection attempt {} failed: {}", attempt, e);
+                    continue;
+                }
+            };
+            *self.client.write().unwrap() = client.clone();
+            self.generation.fetch_add(1, Ordering::AcqRel);
+            self.set_status(ConnectionStatus::Connected);
+
+            let resend = {
+                let mut pending = self.pending.lock().unwrap();
+                let mut resend: Vec<_> = pending
+                    .values()
+                    .filter_map(|slot| match slot {
+                        Slot::Waiting(req) => Some(req.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                for subscription in self.subscriptions.lock().unwrap().iter() {
+                    let mut req = subscription.clone();
+                    req.set_id(self.next_id.fetch_add(1, Ordering::Relaxed));
+                    pending.insert(req.id(), Slot::Detached);
+                    resend.push(req);
+                }
+
+                resend
+            };
+
+            result = async {
+                for mut req in resend {
+                    req.set_version(client.version());
+                    client.send(req).await?;
+                }
+
+                Ok::<_, SendError>(())
+            }
+            .await;
+            match result {
+                Ok(()) => return Ok(()),
+                Err(ref e) => log::warn!("Connection lost when resending requests: {}", e),
+            }
+
+            self.pending
+                .lock()
+                .unwrap()
+                .retain(|_, slot| !matches!(slot, Slot::Detached));
+        }
+
+        self.set_status(ConnectionStatus::Disconnected);
+        Ok(result?)
+    }
+
+    // Изменить состояние соединения и сообщить об этом обработчику.
+    fn set_status(&self, status: ConnectionStatus) {
+        *self.status.lock().unwrap() = status;
+        if let Some(ref listener) = self.listener {
+            listener.call(status);
+        }
+    }
+
+    // Забрать полученный ответ на запрос с заданным идентификатором.
+    fn take(&self, id: u64) -> Result<Option<Box<ControlResponse>>, RequestError> {
+        let mut pending = self.pending.lock().unwrap();
+        match pending.get(&id) {
+            Some(Slot::Unsent) | Some(Slot::Waiting(_)) => Ok(None),
+            Some(Slot::Ready(_)) | Some(Slot::Interrupted) => match pending.remove(&id) {
+                Some(Slot::Ready(response)) => Ok(Some(response)),
+                _ => Err(RequestError::Interrupted(id)),
+            },
+            Some(Slot::Detached) | None => Err(RequestError::UnknownRequest(id)),
+        }
+    }
+}
+
+// Подключиться к серверу с помощью заданной функции, выполняя повторные
+// попытки в соответствии с политикой.
+async fn establish(
+    connector: &Connector,
+    policy: &RetryPolicy,
+    listener: Option<&StatusListener>,
+) -> Result<Client, ConnectionError> {
+    let mut attempt = 1;
+    loop {
+        match try_connect(connector, policy, listener, attempt).await {
+            Ok(client) => return Ok(client),
+            Err(e) if !e.is_transient() || attempt >= policy.max_attempts() => return Err(e),
+            Err(e) => log::warn!("Connection attempt {} failed: {}", attempt, e),
+        }
+
+        attempt += 1;
+    }
+}
+
+// Выполнить одну попытку подключения к серверу после задержки,
+// определяемой политикой для попытки с заданным номером.
+async fn try_connect(
+    connector: &Connector,
+    policy: &RetryPolicy,
+    listener: Option<&StatusListener>,
+    attempt: u32,
+) -> Result<Client, ConnectionError> {
+    if let Some(listener) = listener {
+        listener.call(ConnectionStatus::Reconnecting(attempt));
+    }
+
+    time::sleep(policy.backoff(attempt)).await;
+    connector().await
+}
+
+///
+/// Регистрирует ожидание ответа на запрос и снимает его по завершении
+/// или отмене запроса.
+///
+struct PendingGuard<'a> {
+    pending: &'a Pending,
+    id: u64,
+}
+
+impl<'a> PendingGuard<'a> {
+    ///
+    /// Зарегистрировать ожидание ответа на запрос.
+    ///
+    fn register(pending: &'a Pending, id: u64) -> Self {
+        pending.lock().unwrap().insert(id, Slot::Unsent);
+        Self { pending, id }
+    }
+}
+
+impl<'a> Drop for PendingGuard<'a> {
+    ///
+    /// Снять ожидание ответа на запрос.
+    ///
+    fn drop(&mut self) {
+        self.pending.lock().unwrap().remove(&self.id);
+    }
 }
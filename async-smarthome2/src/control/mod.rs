@@ -1,4 +1,5 @@
 pub mod client;
 pub mod message;
 pub mod protocol;
+pub mod retry;
 pub mod server;
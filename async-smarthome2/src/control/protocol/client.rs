@@ -1,10 +1,23 @@
+use std::{io, net::Shutdown, time::Duration};
+
 use rand::{self, Rng};
 use serde::{de, Serialize};
-use tokio::net::{TcpStream, ToSocketAddrs};
+use socket2::SockRef;
+use tokio::{
+    net::{TcpStream, ToSocketAddrs},
+    sync::Mutex,
+};
 
 use crate::{
-    control::protocol::{consts::MASK, mask, recv_message, send_message, Message},
-    error::{ConnectionError, RequestError},
+    control::protocol::{
+        codec::Codec,
+        consts::{CLIENT_ROLE, NONCE_SIZE, SERVER_ROLE, TAG_SIZE},
+        envelope::Envelope,
+        handshake::{self, Capabilities, HandshakeRequest, HandshakeResponse},
+        read_exact_async, recv_buffered, recv_message, send_buffered, send_message, timeout,
+        write_all_async, Limits, Message, Outgoing, ProtocolVersion,
+    },
+    error::{ConnectionError, RecvError, RequestError, SendError},
 };
 
 ///
@@ -12,18 +25,34 @@ use crate::{
 ///
 pub struct Client {
     stream: TcpStream,
+    version: ProtocolVersion,
+    capabilities: Capabilities,
+    codec: Codec,
+    limits: Limits,
+    writer: Mutex<Outgoing>,
+    reader: Mutex<Vec<u8>>,
 }
 
 impl Client {
     ///
-    /// Подключиться к серверу с заданным адресом.
+    /// Подключиться к серверу с заданным адресом и общим с сервером ключом
+    /// для аутентификации, используя остальные настройки по умолчанию.
     ///
-    pub async fn connect<A>(addrs: A) -> Result<Self, ConnectionError>
+    pub async fn connect<A, K>(addrs: A, key: K) -> Result<Self, ConnectionError>
     where
         A: ToSocketAddrs,
+        K: AsRef<[u8]>,
     {
-        let stream = TcpStream::connect(addrs).await?;
-        Self::try_handshake(stream).await
+        Self::builder(key).connect(addrs).await
+    }
+
+    ///
+    /// Создать объект для построения клиента с заданным общим с сервером
+    /// ключом для аутентификации и остальными настройками по умолчанию.
+    ///
+    #[inline]
+    pub fn builder<K: AsRef<[u8]>>(key: K) -> ClientBuilder {
+        ClientBuilder::new(key)
     }
 
     ///
@@ -34,25 +63,278 @@ impl Client {
         R: Message + Serialize,
         S: Message + de::DeserializeOwned,
     {
-        send_message(req, &self.stream).await?;
-        let response = recv_message(&self.stream).await?;
+        self.send(req).await?;
+        let response = self.recv().await?;
 
         Ok(response)
     }
 
+    ///
+    /// Отправить сообщение серверу, не дожидаясь ответа.
+    ///
+    pub async fn send<M: Message + Serialize>(&self, message: M) -> Result<(), SendError> {
+        self.send_any(&Envelope::seal_with(self.codec, message)?)
+            .await
+    }
+
+    ///
+    /// Отправить серверу сообщение из конверта, не дожидаясь ответа.
+    ///
+    /// Если время ожидания истекло посреди кадра, соединение закрывается:
+    /// продолжить обмен сообщениями по нему уже нельзя.
+    ///
+    pub async fn send_any(&self, envelope: &Envelope) -> Result<(), SendError> {
+        let mut outgoing = self.writer.lock().await;
+        let result = timeout(
+            self.limits.write_timeout,
+            send_buffered(envelope, self.codec, &self.stream, &mut outgoing),
+        )
+        .await;
+
+        match result {
+            Err(SendError::Timeout) if outgoing.is_partial() => {
+                outgoing.clear();
+                self.shutdown();
+                Err(SendError::Io(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "sending timed out in the middle of a frame",
+                )))
+            }
+            Err(e) => {
+                outgoing.clear();
+                Err(e)
+            }
+            Ok(()) => Ok(()),
+        }
+    }
+
+    ///
+    /// Получить очередное сообщение от сервера.
+    ///
+    pub async fn recv<M: Message + de::DeserializeOwned>(&self) -> Result<Box<M>, RecvError> {
+        self.recv_any().await?.open()
+    }
+
+    ///
+    /// Получить от сервера сообщение произвольного типа в конверте.
+    ///
+    /// Если время ожидания истекло посреди кадра, соединение закрывается:
+    /// продолжить обмен сообщениями по нему уже нельзя.
+    ///
+    pub async fn recv_any(&self) -> Result<Envelope, RecvError> {
+        let mut incoming = self.reader.lock().await;
+        let result = timeout(
+            self.limits.read_timeout,
+            recv_buffered(
+                &self.stream,
+                self.codec,
+                self.limits.max_frame_size,
+                &mut incoming,
+            ),
+        )
+        .await;
+
+        match result {
+            Err(e) if !incoming.is_empty() => {
+                incoming.clear();
+                self.shutdown();
+                match e {
+                    RecvError::Timeout => Err(RecvError::Io(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "receiving timed out in the middle of a frame",
+                    ))),
+                    e => Err(e),
+                }
+            }
+            result => result,
+        }
+    }
+
+    ///
+    /// Получить согласованную с сервером версию протокола.
+    ///
+    #[inline]
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    ///
+    /// Получить согласованный с сервером набор возможностей.
+    ///
+    #[inline]
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    ///
+    /// Получить согласованный с сервером кодек данных сообщений.
+    ///
+    #[inline]
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    // Закрыть соединение в обе стороны.
+    fn shutdown(&self) {
+        if let Err(e) = SockRef::from(&self.stream).shutdown(Shutdown::Both) {
+            log::debug!("Failed to shut down connection: {}", e);
+        }
+    }
+
     // Подтвердить handshake.
-    async fn try_handshake(stream: TcpStream) -> Result<Self, ConnectionError> {
-        let data = rand::thread_rng().gen::<[u8; 32]>();
-        super::write_all_async(&stream, &data).await?;
+    async fn try_handshake(
+        stream: TcpStream,
+        key: &[u8],
+        capabilities: Capabilities,
+        limits: Limits,
+    ) -> Result<Self, ConnectionError> {
+        let client_nonce = rand::thread_rng().gen::<[u8; NONCE_SIZE]>();
+        write_all_async(&stream, &client_nonce).await?;
 
-        let mut bytes = [0u8; 32];
-        super::read_exact_async(&stream, &mut bytes).await?;
+        let mut server_nonce = [0u8; NONCE_SIZE];
+        read_exact_async(&stream, &mut server_nonce).await?;
+        let mut server_tag = [0u8; TAG_SIZE];
+        read_exact_async(&stream, &mut server_tag).await?;
 
-        let bytes = mask(bytes, MASK);
-        if bytes != data {
-            return Err(ConnectionError::BadHandshake);
+        if !handshake::verify(key, SERVER_ROLE, &client_nonce, &server_nonce, &server_tag) {
+            return Err(ConnectionError::AuthenticationFailed);
         }
 
-        Ok(Self { stream })
+        let client_tag = handshake::sign(key, CLIENT_ROLE, &server_nonce, &client_nonce);
+        write_all_async(&stream, &client_tag).await?;
+
+        send_message(HandshakeRequest::new(capabilities), Codec::Bincode, &stream).await?;
+        match *recv_message::<HandshakeResponse>(&stream, Codec::Bincode, limits.max_frame_size)
+            .await?
+        {
+            HandshakeResponse::Accepted(code, capabilities) => {
+                let version =
+                    ProtocolVersion::from_code(code).ok_or(ConnectionError::BadHandshake)?;
+
+                let codec = Codec::select(capabilities).ok_or(ConnectionError::BadHandshake)?;
+
+                Ok(Self {
+                    stream,
+                    version,
+                    capabilities,
+                    codec,
+                    limits,
+                    writer: Mutex::default(),
+                    reader: Mutex::default(),
+                })
+            }
+
+            HandshakeResponse::UnsupportedVersion(versions) => {
+                Err(ConnectionError::UnsupportedVersion(versions))
+            }
+
+            HandshakeResponse::IncompatibleCapabilities(capabilities) => {
+                Err(ConnectionError::IncompatibleCapabilities(capabilities))
+            }
+
+            HandshakeResponse::AuthenticationFailed => Err(ConnectionError::AuthenticationFailed),
+        }
+    }
+}
+
+///
+/// Структура для построения экземпляра клиента.
+///
+pub struct ClientBuilder {
+    ///
+    /// Общий с сервером ключ для аутентификации.
+    ///
+    key: Vec<u8>,
+
+    ///
+    /// Запрашиваемый у сервера набор возможностей.
+    ///
+    capabilities: Capabilities,
+
+    ///
+    /// Ограничения, накладываемые на соединение.
+    ///
+    limits: Limits,
+}
+
+impl ClientBuilder {
+    ///
+    /// Создать экземпляр построителя клиента с заданным общим с сервером
+    /// ключом для аутентификации и остальными настройками по умолчанию.
+    ///
+    #[inline]
+    pub fn new<K: AsRef<[u8]>>(key: K) -> Self {
+        Self {
+            key: key.as_ref().to_vec(),
+            capabilities: Capabilities::supported(),
+            limits: Limits::default(),
+        }
+    }
+
+    ///
+    /// Запросить у сервера заданный набор возможностей.
+    ///
+    #[inline]
+    pub fn with_capabilities(self, capabilities: Capabilities) -> Self {
+        Self {
+            capabilities,
+            ..self
+        }
+    }
+
+    ///
+    /// Кодировать данные сообщений только заданным кодеком, заменив
+    /// остальные кодеки в запрашиваемом наборе возможностей.
+    ///
+    #[inline]
+    pub fn with_codec(self, codec: Codec) -> Self {
+        Self {
+            capabilities: self.capabilities.without(Capabilities::CODECS) | codec.capability(),
+            ..self
+        }
+    }
+
+    ///
+    /// Принимать от сервера сообщения с размером данных не более заданного.
+    ///
+    #[inline]
+    pub fn with_max_frame_size(mut self, max_frame_size: u32) -> Self {
+        self.limits.max_frame_size = max_frame_size;
+        self
+    }
+
+    ///
+    /// Ожидать данные от сервера не дольше заданного времени
+    /// (`None` снимает ограничение).
+    ///
+    #[inline]
+    pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.read_timeout = timeout.filter(|t| !t.is_zero());
+        self
+    }
+
+    ///
+    /// Ожидать отправки данных серверу не дольше заданного времени
+    /// (`None` снимает ограничение).
+    ///
+    #[inline]
+    pub fn with_write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.write_timeout = timeout.filter(|t| !t.is_zero());
+        self
+    }
+
+    ///
+    /// Подключиться к серверу с заданным адресом.
+    ///
+    pub async fn connect<A>(self, addrs: A) -> Result<Client, ConnectionError>
+    where
+        A: ToSocketAddrs,
+    {
+        let stream = TcpStream::connect(addrs).await?;
+        timeout(
+            self.limits.read_timeout,
+            Client::try_handshake(stream, &self.key, self.capabilities, self.limits),
+        )
+        .await
     }
 }
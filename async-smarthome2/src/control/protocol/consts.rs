@@ -1,6 +0,0 @@
-pub const TEXT_MESSAGE_ID: u16 = 0xFFFF;
-pub const CONTROL_REQUEST_ID: u16 = 0x1;
-pub const CONTROL_RESPONSE_ID: u16 = 0x2;
-pub const THERMOMETER_MESSAGE_ID: u16 = 0x4;
-
-pub(crate) const MASK: &[u8; 32] = b"j*#H8wp/@^HQY9S>8N`Wdwh_HH)m=Jsa";
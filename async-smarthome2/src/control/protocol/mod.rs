@@ -1,32 +1,74 @@
-use std::io;
+use std::{future::Future, io, time::Duration};
 
-use bincode::{self, Options};
-use serde::{de, Deserialize, Serialize};
-use tokio::net::TcpStream;
+use serde::{de, Serialize};
+use tokio::{net::TcpStream, time};
 
-use crate::error::{RecvError, SendError};
+use crate::{
+    control::protocol::{
+        codec::Codec,
+        consts::{DEFAULT_HANDSHAKE_TIMEOUT, DEFAULT_MAX_FRAME_SIZE, DEFAULT_TIMEOUT},
+        envelope::Envelope,
+        frame::{FrameCodec, FrameHeader, HEADER_SIZE},
+    },
+    error::{RecvError, SendError},
+};
 
 pub mod client;
-pub mod consts;
 pub mod server;
 
+pub use smarthome2_protocol::{
+    codec, consts, envelope, frame, handshake, Message, ProtocolVersion,
+};
+
 ///
-/// Типаж для отправки и получения сообщений по сети.
+/// Ограничения, накладываемые на соединение.
 ///
-pub trait Message {
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limits {
+    // Максимальный размер данных одного сообщения.
+    pub(crate) max_frame_size: u32,
+
+    // Время ожидания данных при чтении сообщения.
+    pub(crate) read_timeout: Option<Duration>,
+
+    // Время ожидания при записи сообщения.
+    pub(crate) write_timeout: Option<Duration>,
+
+    // Время ожидания начала следующего сообщения.
+    pub(crate) idle_timeout: Option<Duration>,
+
+    // Время, за которое должно завершиться подтверждение связи.
+    pub(crate) handshake_timeout: Option<Duration>,
+}
+
+impl Default for Limits {
     ///
-    /// Идентификатор типа сообщения.
+    /// Ограничения по умолчанию.
     ///
-    const TYPE: u16;
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            read_timeout: Some(DEFAULT_TIMEOUT),
+            write_timeout: Some(DEFAULT_TIMEOUT),
+            idle_timeout: None,
+            handshake_timeout: Some(DEFAULT_HANDSHAKE_TIMEOUT),
+        }
+    }
 }
 
-///
-/// Версия протокола.
-///
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ProtocolVersion {
-    #[serde(rename = "1.0")]
-    V1_0,
+// Выполнить операцию, ограничив время ее выполнения.
+pub(crate) async fn timeout<T, E, F>(duration: Option<Duration>, future: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+    E: From<io::Error>,
+{
+    match duration {
+        Some(duration) => time::timeout(duration, future)
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))?,
+        None => future.await,
+    }
 }
 
 // Асинхронно прочитать заданное количество байт.
@@ -35,7 +77,7 @@ pub(crate) async fn read_exact_async(s: &TcpStream, buf: &mut [u8]) -> io::Resul
     while red < buf.len() {
         s.readable().await?;
         match s.try_read(&mut buf[red..]) {
-            Ok(0) => break,
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
             Ok(n) => {
                 red += n;
             }
@@ -55,7 +97,7 @@ pub(crate) async fn write_all_async(stream: &TcpStream, buf: &[u8]) -> io::Resul
         stream.writable().await?;
 
         match stream.try_write(&buf[written..]) {
-            Ok(0) => break,
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
             Ok(n) => {
                 written += n;
             }
@@ -67,53 +109,162 @@ pub(crate) async fn write_all_async(stream: &TcpStream, buf: &[u8]) -> io::Resul
     Ok(())
 }
 
-// Отправить сообщение.
+// Отправить сообщение, закодированное заданным кодеком.
 pub(crate) async fn send_message<M: Message + Serialize>(
     message: M,
+    codec: Codec,
     stream: &TcpStream,
 ) -> Result<(), SendError> {
-    let bytes = M::TYPE.to_be_bytes();
-    write_all_async(stream, &bytes).await?;
+    send_envelope(&Envelope::seal_with(codec, message)?, codec, stream).await
+}
 
-    let data = bincode::options().with_big_endian().serialize(&message)?;
-    let size = data.len() as u32;
-    let bytes = size.to_be_bytes();
-    write_all_async(stream, &bytes).await?;
-    write_all_async(stream, data.as_ref()).await?;
+// Отправить сообщение из конверта, если оно закодировано заданным кодеком.
+pub(crate) async fn send_envelope(
+    envelope: &Envelope,
+    codec: Codec,
+    stream: &TcpStream,
+) -> Result<(), SendError> {
+    let mut frame = Vec::new();
+    FrameCodec::new(codec).encode(envelope, &mut frame)?;
+    // Заголовок и данные записываются отдельно: запись в соединение,
+    // закрытое другой стороной, завершится ошибкой еще до отправки
+    // данных, и запрос не будет считаться доставленным.
+    let (header, data) = frame.split_at(HEADER_SIZE);
+    write_all_async(stream, header).await?;
+    write_all_async(stream, data).await?;
 
     Ok(())
 }
 
-// Получить сообщение.
+// Получить сообщение, закодированное заданным кодеком.
 pub(crate) async fn recv_message<M: Message + de::DeserializeOwned>(
     stream: &TcpStream,
+    codec: Codec,
+    max_frame_size: u32,
 ) -> Result<Box<M>, RecvError> {
-    let mut bytes = [0u8; 2];
-    read_exact_async(stream, &mut bytes).await?;
-    let message_type = u16::from_be_bytes(bytes);
-    if message_type != M::TYPE {
-        return Err(RecvError::BadType(message_type));
-    }
+    recv_envelope(stream, codec, max_frame_size).await?.open()
+}
 
-    let mut bytes = [0u8; 4];
+// Получить сообщение произвольного типа в конверте.
+pub(crate) async fn recv_envelope(
+    stream: &TcpStream,
+    codec: Codec,
+    max_frame_size: u32,
+) -> Result<Envelope, RecvError> {
+    let mut bytes = [0u8; HEADER_SIZE];
     read_exact_async(stream, &mut bytes).await?;
-    let len = u32::from_be_bytes(bytes);
+    let header = FrameHeader::parse(&bytes, max_frame_size)?;
 
-    let mut data = vec![0u8; len as _];
+    let mut data = vec![0u8; header.size() as _];
     read_exact_async(stream, &mut data).await?;
-    let message = bincode::options()
-        .with_big_endian()
-        .deserialize(&data[..])?;
 
-    Ok(Box::new(message))
+    Ok(Envelope::from_parts(header.message_type(), codec, data))
+}
+
+// Кадр, отправляемый по частям: отправка, прерванная отменой, продолжается
+// с того же места при следующей отправке.
+#[derive(Default)]
+pub(crate) struct Outgoing {
+    frame: Vec<u8>,
+    written: usize,
+}
+
+impl Outgoing {
+    // Начата ли, но не завершена запись кадра.
+    pub(crate) fn is_partial(&self) -> bool {
+        self.written > 0 && self.written < self.frame.len()
+    }
+
+    // Забыть кадр, отправленный не полностью.
+    pub(crate) fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    // Записать байты кадра до заданной позиции.
+    async fn write_until(&mut self, stream: &TcpStream, end: usize) -> io::Result<()> {
+        while self.written < end {
+            stream.writable().await?;
+
+            match stream.try_write(&self.frame[self.written..end]) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.written += n;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
 }
 
-// Маскировать бинарные данные.
-pub(crate) fn mask<const N: usize>(data: [u8; N], mask: &[u8]) -> [u8; N] {
-    let mut result = [0u8; N];
-    for (idx, (&v1, &v2)) in data.iter().zip(mask.iter().cycle()).enumerate() {
-        result[idx] = v1 ^ v2;
+// Отправить сообщение из конверта, предварительно дописав кадр, отправка
+// которого была прервана.
+pub(crate) async fn send_buffered(
+    envelope: &Envelope,
+    codec: Codec,
+    stream: &TcpStream,
+    outgoing: &mut Outgoing,
+) -> Result<(), SendError> {
+    let mut frame = Vec::new();
+    FrameCodec::new(codec).encode(envelope, &mut frame)?;
+    if outgoing.is_partial() {
+        let end = outgoing.frame.len();
+        outgoing.write_until(stream, end).await?;
     }
 
-    result
+    *outgoing = Outgoing { frame, written: 0 };
+    // Заголовок и данные записываются отдельно, как и в `send_envelope`.
+    outgoing.write_until(stream, HEADER_SIZE).await?;
+    let end = outgoing.frame.len();
+    outgoing.write_until(stream, end).await?;
+
+    Ok(())
+}
+
+// Получить сообщение произвольного типа в конверте, накапливая байты
+// кадра в буфере: чтение, прерванное отменой, продолжается с того же
+// места при следующем получении.
+pub(crate) async fn recv_buffered(
+    stream: &TcpStream,
+    codec: Codec,
+    max_frame_size: u32,
+    incoming: &mut Vec<u8>,
+) -> Result<Envelope, RecvError> {
+    fill(stream, incoming, HEADER_SIZE).await?;
+    let mut bytes = [0u8; HEADER_SIZE];
+    bytes.copy_from_slice(&incoming[..HEADER_SIZE]);
+    let header = FrameHeader::parse(&bytes, max_frame_size)?;
+
+    fill(stream, incoming, HEADER_SIZE + header.size() as usize).await?;
+    let data = incoming.split_off(HEADER_SIZE);
+    incoming.clear();
+
+    Ok(Envelope::from_parts(header.message_type(), codec, data))
+}
+
+// Дочитать в буфер байты до заданного размера.
+async fn fill(stream: &TcpStream, buffer: &mut Vec<u8>, size: usize) -> io::Result<()> {
+    while buffer.len() < size {
+        stream.readable().await?;
+
+        let red = buffer.len();
+        buffer.resize(size, 0);
+        match stream.try_read(&mut buffer[red..]) {
+            Ok(0) => {
+                buffer.truncate(red);
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            Ok(n) => buffer.truncate(red + n),
+            Err(e) => {
+                buffer.truncate(red);
+                if e.kind() != io::ErrorKind::WouldBlock {
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    Ok(())
 }
@@ -1,10 +1,21 @@
-use std::{io, net::SocketAddr};
+use std::{io, net::SocketAddr, sync::Arc, time::Duration};
 
+use rand::{self, Rng};
 use serde::{de, Serialize};
-use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::{
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Mutex, OwnedSemaphorePermit, Semaphore},
+};
 
 use crate::{
-    control::protocol::{consts::MASK, mask, recv_message, send_message, Message},
+    control::protocol::{
+        codec::Codec,
+        consts::{CLIENT_ROLE, NONCE_SIZE, SERVER_ROLE, TAG_SIZE},
+        envelope::Envelope,
+        handshake::{self, Capabilities, HandshakeRequest, HandshakeResponse},
+        read_exact_async, recv_envelope, recv_message, send_envelope, send_message, timeout,
+        write_all_async, Limits, Message, ProtocolVersion,
+    },
     error::{BindError, ConnectionError, RecvError, SendError},
 };
 
@@ -13,36 +24,305 @@ use crate::{
 ///
 pub struct Server {
     listener: TcpListener,
+    settings: Arc<Settings>,
+    connections: Option<Arc<Semaphore>>,
+}
+
+// Настройки сервера, необходимые для подтверждения связи с клиентами.
+struct Settings {
+    key: Vec<u8>,
+    capabilities: Capabilities,
+    limits: Limits,
 }
 
 impl Server {
     ///
-    /// Выполнить привязку сервера к сокету.
+    /// Выполнить привязку сервера к сокету с заданным общим с клиентами
+    /// ключом для аутентификации, используя остальные настройки по умолчанию.
     ///
-    pub async fn bind<A>(addrs: A) -> Result<Self, BindError>
+    pub async fn bind<A, K>(addrs: A, key: K) -> Result<Self, BindError>
     where
         A: ToSocketAddrs,
+        K: AsRef<[u8]>,
     {
-        let listener = TcpListener::bind(addrs).await?;
-        Ok(Self { listener })
+        Self::builder(key).bind(addrs).await
+    }
+
+    ///
+    /// Создать объект для построения сервера с заданным общим с клиентами
+    /// ключом для аутентификации и остальными настройками по умолчанию.
+    ///
+    #[inline]
+    pub fn builder<K: AsRef<[u8]>>(key: K) -> ServerBuilder {
+        ServerBuilder::new(key)
+    }
+
+    ///
+    /// Получить адрес, к которому привязан сервер.
+    ///
+    #[inline]
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    ///
+    /// Получить общий с клиентами ключ для аутентификации.
+    ///
+    #[inline]
+    pub(crate) fn key(&self) -> &[u8] {
+        &self.settings.key
     }
 
     ///
     /// Получить входящее соединение.
     ///
     pub async fn accept(&self) -> Result<Connection, ConnectionError> {
-        let (connection, _) = self.listener.accept().await?;
-        Self::try_handshake(connection).await
+        self.accept_incoming().await?.handshake().await
+    }
+
+    ///
+    /// Получить входящее соединение, не подтверждая связь с клиентом.
+    /// Подтверждение выполняется вызовом [`Incoming::handshake`], что
+    /// позволяет не задерживать прием других соединений.
+    ///
+    pub async fn accept_incoming(&self) -> Result<Incoming, ConnectionError> {
+        let (stream, _) = self.listener.accept().await?;
+        let permit = match self.connections {
+            Some(ref connections) => Some(
+                connections
+                    .clone()
+                    .try_acquire_owned()
+                    .map_err(|_| ConnectionError::TooManyConnections)?,
+            ),
+            None => None,
+        };
+
+        Ok(Incoming {
+            stream,
+            settings: self.settings.clone(),
+            permit,
+        })
+    }
+}
+
+///
+/// Представляет принятое соединение, связь по которому еще не подтверждена.
+///
+pub struct Incoming {
+    stream: TcpStream,
+    settings: Arc<Settings>,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Incoming {
+    ///
+    /// Получить адрес клиента.
+    ///
+    #[inline]
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    ///
+    /// Подтвердить связь с клиентом. Подтверждение должно завершиться за
+    /// время, заданное ограничениями сервера.
+    ///
+    pub async fn handshake(self) -> Result<Connection, ConnectionError> {
+        timeout(self.settings.limits.handshake_timeout, self.try_handshake()).await
     }
 
     // Подтвердить handshake.
-    async fn try_handshake(stream: TcpStream) -> Result<Connection, ConnectionError> {
-        let mut bytes = [0u8; 32];
-        super::read_exact_async(&stream, &mut bytes).await?;
-        let bytes = mask(bytes, MASK);
-        super::write_all_async(&stream, &bytes).await?;
+    async fn try_handshake(self) -> Result<Connection, ConnectionError> {
+        let Self {
+            stream,
+            settings,
+            permit,
+        } = self;
+
+        let mut client_nonce = [0u8; NONCE_SIZE];
+        read_exact_async(&stream, &mut client_nonce).await?;
+
+        let server_nonce = rand::thread_rng().gen::<[u8; NONCE_SIZE]>();
+        let server_tag = handshake::sign(&settings.key, SERVER_ROLE, &client_nonce, &server_nonce);
+        write_all_async(&stream, &server_nonce).await?;
+        write_all_async(&stream, &server_tag).await?;
+
+        let mut client_tag = [0u8; TAG_SIZE];
+        read_exact_async(&stream, &mut client_tag).await?;
+        let authenticated = handshake::verify(
+            &settings.key,
+            CLIENT_ROLE,
+            &server_nonce,
+            &client_nonce,
+            &client_tag,
+        );
+
+        let request = recv_message::<HandshakeRequest>(
+            &stream,
+            Codec::Bincode,
+            settings.limits.max_frame_size,
+        )
+        .await?;
+        let response = if authenticated {
+            request.negotiate(settings.capabilities)
+        } else {
+            HandshakeResponse::AuthenticationFailed
+        };
+        send_message(response.clone(), Codec::Bincode, &stream).await?;
+
+        match response {
+            HandshakeResponse::Accepted(code, capabilities) => Ok(Connection {
+                stream,
+                version: ProtocolVersion::from_code(code).ok_or(ConnectionError::BadHandshake)?,
+                capabilities,
+                codec: Codec::select(capabilities).unwrap_or_default(),
+                limits: settings.limits,
+                writer: Mutex::new(()),
+                _permit: permit,
+            }),
+
+            HandshakeResponse::UnsupportedVersion(versions) => {
+                Err(ConnectionError::UnsupportedVersion(versions))
+            }
+
+            HandshakeResponse::IncompatibleCapabilities(capabilities) => {
+                Err(ConnectionError::IncompatibleCapabilities(capabilities))
+            }
+
+            HandshakeResponse::AuthenticationFailed => Err(ConnectionError::AuthenticationFailed),
+        }
+    }
+}
+
+///
+/// Структура для построения экземпляра сервера.
+///
+pub struct ServerBuilder {
+    ///
+    /// Общий с клиентами ключ для аутентификации.
+    ///
+    key: Vec<u8>,
+
+    ///
+    /// Предоставляемый клиентам набор возможностей.
+    ///
+    capabilities: Capabilities,
+
+    ///
+    /// Ограничения, накладываемые на соединения.
+    ///
+    limits: Limits,
+
+    ///
+    /// Максимальное количество одновременных соединений.
+    ///
+    max_connections: Option<usize>,
+}
+
+impl ServerBuilder {
+    ///
+    /// Создать экземпляр построителя сервера с заданным общим с клиентами
+    /// ключом для аутентификации и остальными настройками по умолчанию.
+    ///
+    #[inline]
+    pub fn new<K: AsRef<[u8]>>(key: K) -> Self {
+        Self {
+            key: key.as_ref().to_vec(),
+            capabilities: Capabilities::supported(),
+            limits: Limits::default(),
+            max_connections: None,
+        }
+    }
+
+    ///
+    /// Предоставлять клиентам только заданный набор возможностей.
+    ///
+    #[inline]
+    pub fn with_capabilities(self, capabilities: Capabilities) -> Self {
+        Self {
+            capabilities: capabilities & Capabilities::supported(),
+            ..self
+        }
+    }
+
+    ///
+    /// Принимать от клиентов сообщения с размером данных не более заданного.
+    ///
+    #[inline]
+    pub fn with_max_frame_size(mut self, max_frame_size: u32) -> Self {
+        self.limits.max_frame_size = max_frame_size;
+        self
+    }
+
+    ///
+    /// Ожидать продолжения начатого клиентом сообщения не дольше заданного
+    /// времени (`None` снимает ограничение).
+    ///
+    #[inline]
+    pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.read_timeout = timeout.filter(|t| !t.is_zero());
+        self
+    }
+
+    ///
+    /// Ожидать отправки данных клиенту не дольше заданного времени
+    /// (`None` снимает ограничение).
+    ///
+    #[inline]
+    pub fn with_write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.write_timeout = timeout.filter(|t| !t.is_zero());
+        self
+    }
+
+    ///
+    /// Ожидать следующего запроса клиента не дольше заданного времени
+    /// (`None` снимает ограничение).
+    ///
+    #[inline]
+    pub fn with_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.idle_timeout = timeout.filter(|t| !t.is_zero());
+        self
+    }
+
+    ///
+    /// Ожидать завершения подтверждения связи с клиентом не дольше заданного
+    /// времени (`None` снимает ограничение).
+    ///
+    #[inline]
+    pub fn with_handshake_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.handshake_timeout = timeout.filter(|t| !t.is_zero());
+        self
+    }
+
+    ///
+    /// Обслуживать одновременно не более заданного количества клиентов.
+    ///
+    #[inline]
+    pub fn with_max_connections(self, max_connections: usize) -> Self {
+        Self {
+            max_connections: Some(max_connections),
+            ..self
+        }
+    }
 
-        Ok(Connection { stream })
+    ///
+    /// Выполнить привязку сервера к сокету.
+    ///
+    pub async fn bind<A>(self, addrs: A) -> Result<Server, BindError>
+    where
+        A: ToSocketAddrs,
+    {
+        Ok(Server {
+            listener: TcpListener::bind(addrs).await?,
+            settings: Arc::new(Settings {
+                key: self.key,
+                capabilities: self.capabilities,
+                limits: self.limits,
+            }),
+            connections: self
+                .max_connections
+                .map(|max| Arc::new(Semaphore::new(max))),
+        })
     }
 }
 
@@ -51,15 +331,38 @@ impl Server {
 ///
 pub struct Connection {
     stream: TcpStream,
+    version: ProtocolVersion,
+    capabilities: Capabilities,
+    codec: Codec,
+    limits: Limits,
+    writer: Mutex<()>,
+    _permit: Option<OwnedSemaphorePermit>,
 }
 
 impl Connection {
     ///
-    /// Отправить ответ сервера.
+    /// Отправить ответ сервера. Одновременные отправки из разных задач
+    /// выполняются поочередно.
     ///
-    #[inline]
     pub async fn send<M: Message + Serialize>(&self, response: M) -> Result<(), SendError> {
-        send_message(response, &self.stream).await
+        let _lock = self.writer.lock().await;
+        timeout(
+            self.limits.write_timeout,
+            send_message(response, self.codec, &self.stream),
+        )
+        .await
+    }
+
+    ///
+    /// Отправить клиенту сообщение из конверта.
+    ///
+    pub async fn send_any(&self, envelope: &Envelope) -> Result<(), SendError> {
+        let _lock = self.writer.lock().await;
+        timeout(
+            self.limits.write_timeout,
+            send_envelope(envelope, self.codec, &self.stream),
+        )
+        .await
     }
 
     ///
@@ -67,7 +370,19 @@ impl Connection {
     ///
     #[inline]
     pub async fn recv<M: Message + de::DeserializeOwned>(&self) -> Result<Box<M>, RecvError> {
-        recv_message(&self.stream).await
+        self.recv_any().await?.open()
+    }
+
+    ///
+    /// Получить от клиента сообщение произвольного типа в конверте.
+    ///
+    pub async fn recv_any(&self) -> Result<Envelope, RecvError> {
+        timeout(self.limits.idle_timeout, self.stream.readable()).await?;
+        timeout(
+            self.limits.read_timeout,
+            recv_envelope(&self.stream, self.codec, self.limits.max_frame_size),
+        )
+        .await
     }
 
     ///
@@ -77,4 +392,28 @@ impl Connection {
     pub fn peer_addr(&self) -> io::Result<SocketAddr> {
         self.stream.peer_addr()
     }
+
+    ///
+    /// Получить согласованную с клиентом версию протокола.
+    ///
+    #[inline]
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    ///
+    /// Получить согласованный с клиентом набор возможностей.
+    ///
+    #[inline]
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    ///
+    /// Получить согласованный с клиентом кодек данных сообщений.
+    ///
+    #[inline]
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
 }
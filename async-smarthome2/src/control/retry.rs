@@ -0,0 +1 @@
+pub use smarthome2_protocol::retry::*;
@@ -1,115 +1,792 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc, Weak,
+use std::{
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
 };
 
 use log;
-use tokio::{net::ToSocketAddrs, sync::Mutex};
+use tokio::{
+    net::{self, ToSocketAddrs},
+    sync::{broadcast, watch, Mutex, Semaphore},
+    task::{JoinHandle, JoinSet},
+    time,
+};
+use uuid::Uuid;
+
+use smarthome2_core::dispatch::{self, Action, Transaction};
 
 use crate::{
     control::{
-        message::{ControlRequest, ControlRequestData, ControlResponse},
-        protocol::server::Server,
+        message::{ControlRequest, ControlRequestData, ControlResponse, DeviceSpec, Selector},
+        protocol::{
+            handshake::Capabilities,
+            server::{Connection, Incoming, Server},
+        },
     },
     device::{
-        socket::{SmartSocket, SwitchOffEvent, SwitchOnEvent},
-        AsyncDevice, StateEvent,
+        lamp::{RemoteSmartLamp, SmartLamp},
+        sensor::{RemoteSensor, SmartSensor},
+        socket::{RemoteSmartSocket, SmartSocket, SwitchOffEvent, SwitchOnEvent},
+        thermometer::{RemoteThermometer, SmartThermometer},
+        AsyncDevice, DeviceState, EventData, StateEvent, StateListener,
     },
     error::{BindError, DeviceError},
+    house::{DeviceNotifier, RoomGetter, SmartHouse},
 };
 
+// Количество уведомлений, ожидающих отправки подписчику.
+const NOTIFICATION_CAPACITY: usize = 16;
+
+// Количество запросов одного соединения, обрабатываемых одновременно.
+// При достижении предела следующий запрос соединения не читается, пока
+// не завершится обработка одного из текущих. Запросы, изменяющие
+// состояние, выполняются по одному в порядке поступления.
+const MAX_IN_FLIGHT: usize = 32;
+
+// Время ожидания завершения обработки соединений по умолчанию при
+// остановке сервера.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
 ///
-/// Сервер управления "умной" розеткой.
+/// Дескриптор для остановки сервера из другой задачи.
 ///
-pub struct SmartSocketServer {
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle(Arc<watch::Sender<bool>>);
+
+impl Default for ShutdownHandle {
+    ///
+    /// Создать дескриптор для остановки сервера.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self(Arc::new(watch::Sender::new(false)))
+    }
+}
+
+impl ShutdownHandle {
+    ///
+    /// Запросить остановку сервера. Сервер перестает принимать новые
+    /// соединения, а обслуживаемые соединения закрываются после
+    /// обработки текущих запросов.
+    ///
+    #[inline]
+    pub fn shutdown(&self) {
+        self.0.send_replace(true);
+    }
+
+    ///
+    /// Проверить, запрошена ли остановка сервера.
+    ///
+    #[inline]
+    pub fn is_shutdown(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    ///
+    /// Дождаться запроса на остановку сервера.
+    ///
+    async fn wait(&self) {
+        let _ = self.0.subscribe().wait_for(|s| *s).await;
+    }
+}
+
+///
+/// Сервер подсистемы управления "умного" дома.
+///
+pub struct ControlServer {
     ///
     /// Сервер обмена сообщениями.
     ///
     server: Server,
 
     ///
-    /// Экземпляр "умной" розетки.
+    /// Экземпляр "умного" дома.
+    ///
+    house: Arc<Mutex<SmartHouse>>,
+
+    ///
+    /// Канал рассылки уведомлений об изменении состояния устройств.
     ///
-    socket: Arc<Mutex<SmartSocket>>,
+    notifications: broadcast::Sender<ControlResponse>,
 
     ///
-    /// Флаг завершения работы сервера.
+    /// Общий с удаленными устройствами ключ для аутентификации.
     ///
-    working: Arc<AtomicBool>,
+    device_key: Arc<[u8]>,
+
+    ///
+    /// Дескриптор для остановки сервера.
+    ///
+    shutdown: ShutdownHandle,
+
+    ///
+    /// Время ожидания завершения обработки соединений при остановке.
+    ///
+    shutdown_timeout: Duration,
 }
 
-impl SmartSocketServer {
+impl ControlServer {
     ///
-    /// Выполнить привязку сервера к сокету и экземпляру "умной" розетки.
+    /// Выполнить привязку сервера к сокету и экземпляру "умного" дома с
+    /// заданным общим с клиентами ключом для аутентификации.
     ///
-    pub async fn bind<A>(
-        addrs: A,
-        socket: SmartSocket,
-    ) -> Result<(Self, Weak<AtomicBool>), BindError>
+    pub async fn bind<A, K>(addrs: A, key: K, house: SmartHouse) -> Result<Self, BindError>
     where
         A: ToSocketAddrs,
+        K: AsRef<[u8]>,
     {
-        let working = Arc::new(AtomicBool::new(true));
+        Ok(Self::with_server(Server::bind(addrs, key).await?, house))
+    }
+
+    ///
+    /// Создать сервер на основе настроенного сервера обмена сообщениями
+    /// и экземпляра "умного" дома.
+    ///
+    pub fn with_server(server: Server, mut house: SmartHouse) -> Self {
+        let (notifications, _) = broadcast::channel(NOTIFICATION_CAPACITY);
+        for room in house.iter_mut() {
+            let room_id = room.id();
+            for device_ref in room.iter_mut() {
+                watch(&notifications, room_id, device_ref);
+            }
+        }
+
+        Self {
+            device_key: server.key().into(),
+            server,
+            house: Arc::new(Mutex::new(house)),
+            notifications,
+            shutdown: ShutdownHandle::default(),
+            shutdown_timeout: SHUTDOWN_TIMEOUT,
+        }
+    }
+
+    ///
+    /// Подключаться к удаленным устройствам, добавляемым по запросам
+    /// клиентов, с заданным общим ключом для аутентификации. По умолчанию
+    /// используется ключ самого сервера.
+    ///
+    #[inline]
+    pub fn with_device_key<K: AsRef<[u8]>>(self, key: K) -> Self {
+        Self {
+            device_key: key.as_ref().into(),
+            ..self
+        }
+    }
+
+    ///
+    /// Ожидать завершения обработки соединений при остановке сервера
+    /// не дольше заданного времени. По истечении этого времени
+    /// незавершенные соединения прерываются.
+    ///
+    #[inline]
+    pub fn with_shutdown_timeout(self, shutdown_timeout: Duration) -> Self {
+        Self {
+            shutdown_timeout,
+            ..self
+        }
+    }
 
-        Ok((
-            Self {
-                server: Server::bind(addrs).await?,
-                socket: Arc::new(Mutex::new(socket)),
-                working: working.clone(),
-            },
-            Arc::downgrade(&working),
-        ))
+    ///
+    /// Получить дескриптор для остановки сервера.
+    ///
+    #[inline]
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
     }
 
     ///
-    /// Запустить сервер для обработки сообщений.
+    /// Вернуть экземпляр "умного" дома после остановки сервера. Если дом
+    /// еще используется, возвращается сервер.
+    ///
+    #[allow(clippy::result_large_err)]
+    pub fn into_house(self) -> Result<SmartHouse, Self> {
+        match Arc::try_unwrap(self.house) {
+            Ok(house) => Ok(house.into_inner()),
+            Err(house) => Err(Self { house, ..self }),
+        }
+    }
+
+    ///
+    /// Запустить сервер для обработки сообщений. Возвращает управление
+    /// после остановки сервера с помощью дескриптора остановки.
     ///
     pub async fn run(&self) {
-        while (*self.working).load(Ordering::Relaxed) {
-            let connection = match self.server.accept().await {
-                Ok(c) => c,
-                Err(e) => {
-                    log::error!("Cannot establish connection {}", e);
-                    continue;
+        let mut workers = JoinSet::new();
+        while let Some(incoming) = accept(&self.server, &self.shutdown).await {
+            let house = self.house.clone();
+            let notifications = self.notifications.clone();
+            let device_key = self.device_key.clone();
+            let shutdown = self.shutdown.clone();
+            while workers.try_join_next().is_some() {}
+            workers.spawn(async move {
+                let Some(connection) = establish(incoming).await else {
+                    return;
+                };
+                let mut requests = JoinSet::new();
+                let permits = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+                let topics: Arc<StdMutex<Vec<Topic>>> = Arc::default();
+                let mut forwarder: Option<JoinHandle<()>> = None;
+                loop {
+                    let request = tokio::select! {
+                        _ = shutdown.wait() => break,
+                        r = connection.recv::<ControlRequest>() => r,
+                    };
+                    let request = match request {
+                        Ok(r) => r,
+                        Err(e) => {
+                            log::warn!("Connection lost when receiving data: {}", e);
+                            break;
+                        }
+                    };
+
+                    if request.is_subscription() {
+                        let mut response = Self::subscribe(
+                            &connection,
+                            &house,
+                            &notifications,
+                            &topics,
+                            &mut forwarder,
+                            &request,
+                        )
+                        .await;
+                        response.set_id(request.id());
+                        if connection.send(response).await.is_err() {
+                            log::warn!("Connection lost when sending data");
+                            break;
+                        }
+                        continue;
+                    }
+
+                    // Запросы на чтение обрабатываются независимо, ответы
+                    // отправляются по мере готовности. Запрос, изменяющий
+                    // состояние, занимает все места, поэтому выполняется
+                    // после завершения предыдущих запросов и до начала
+                    // следующих: очередь семафора соблюдает порядок.
+                    let weight = if request.is_idempotent() {
+                        1
+                    } else {
+                        MAX_IN_FLIGHT as u32
+                    };
+                    let Ok(permit) = permits.clone().acquire_many_owned(weight).await else {
+                        break;
+                    };
+                    let connection = connection.clone();
+                    let house = house.clone();
+                    let notifications = notifications.clone();
+                    let device_key = device_key.clone();
+                    while requests.try_join_next().is_some() {}
+                    requests.spawn(async move {
+                        let mut response = if request.version() > connection.version() {
+                            ControlResponse::with_error(DeviceError::UnsupportedVersion(
+                                request.version(),
+                            ))
+                        } else {
+                            Self::dispatch(house, &notifications, &device_key, request.as_ref())
+                                .await
+                        };
+                        response.set_id(request.id());
+
+                        if connection.send(response).await.is_err() {
+                            log::warn!("Connection lost when sending data");
+                        }
+                        drop(permit);
+                    });
                 }
-            };
 
-            let addr = match connection.peer_addr() {
-                Ok(addr) => addr.to_string(),
-                Err(_) => "unknown".to_owned(),
+                while requests.join_next().await.is_some() {}
+                if let Some(forwarder) = forwarder {
+                    forwarder.abort();
+                }
+            });
+        }
+
+        join(workers, self.shutdown_timeout).await;
+    }
+
+    ///
+    /// Подписать соединение на изменения состояния устройств.
+    ///
+    async fn subscribe(
+        connection: &Arc<Connection>,
+        house: &Mutex<SmartHouse>,
+        notifications: &broadcast::Sender<ControlResponse>,
+        topics: &Arc<StdMutex<Vec<Topic>>>,
+        forwarder: &mut Option<JoinHandle<()>>,
+        req: &ControlRequest,
+    ) -> ControlResponse {
+        if req.version() > connection.version() {
+            return ControlResponse::with_error(DeviceError::UnsupportedVersion(req.version()));
+        }
+
+        if !connection
+            .capabilities()
+            .contains(Capabilities::PUSH_NOTIFICATIONS)
+        {
+            return ControlResponse::with_error(DeviceError::NotificationsDisabled);
+        }
+
+        let topic = match *req.data() {
+            ControlRequestData::Subscribe(ref selector) => {
+                match dispatch::locate(&*house.lock().await, selector) {
+                    Ok((room_id, device_id)) => Topic::Device(room_id, device_id),
+                    Err(e) => return ControlResponse::with_error(DeviceError::from(e)),
+                }
+            }
+            _ => Topic::All,
+        };
+
+        log::info!("Subscribing connection to {:?}", topic);
+        topics.lock().unwrap().push(topic);
+
+        if forwarder.is_none() {
+            let topics = topics.clone();
+            *forwarder = Some(forward(
+                connection.clone(),
+                notifications.subscribe(),
+                move |notification| match notification.notification() {
+                    Some((room_id, state)) => topics
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .any(|t| t.matches(room_id, state.device_id())),
+                    None => false,
+                },
+            ));
+        }
+
+        ControlResponse::done()
+    }
+
+    ///
+    /// Выполнить диспетчеризацию запроса.
+    ///
+    async fn dispatch(
+        house: Arc<Mutex<SmartHouse>>,
+        notifications: &broadcast::Sender<ControlResponse>,
+        key: &[u8],
+        req: &ControlRequest,
+    ) -> ControlResponse {
+        match *req.data() {
+            ControlRequestData::AttachDevice(room_id, ref spec) => {
+                Self::attach_device(&house, notifications, key, room_id, spec)
+                    .await
+                    .unwrap_or_else(ControlResponse::with_error)
+            }
+
+            ControlRequestData::Batch(ref items) => {
+                // Устройства, добавляемые пакетом, создаются до блокировки
+                // дома, так как подключение к удаленному устройству может
+                // занять длительное время.
+                let mut devices = Vec::with_capacity(items.len());
+                for item in items {
+                    devices.push(match *item {
+                        ControlRequestData::AttachDevice(_, ref spec) => {
+                            Some(build_device(spec, key).await)
+                        }
+                        _ => None,
+                    });
+                }
+
+                let mut lock = house.lock().await;
+                let mut results = Vec::with_capacity(items.len());
+                for (item, device) in items.iter().zip(devices) {
+                    results.push(match (item, device) {
+                        (&ControlRequestData::AttachDevice(room_id, _), Some(device)) => device
+                            .and_then(|device| {
+                                Self::insert_device(&mut lock, notifications, room_id, device)
+                            })
+                            .unwrap_or_else(ControlResponse::with_error),
+                        (item, _) => Self::execute(&mut lock, notifications, item).await,
+                    });
+                }
+                ControlResponse::with_results(results)
+            }
+
+            ControlRequestData::Transaction(ref items) => {
+                Self::transaction(&mut *house.lock().await, notifications, items).await
+            }
+
+            ref data => Self::execute(&mut *house.lock().await, notifications, data).await,
+        }
+    }
+
+    ///
+    /// Выполнить запрос над заблокированным экземпляром "умного" дома.
+    /// Запросы на добавление устройств выполняются без блокировки дома
+    /// и здесь не обрабатываются.
+    ///
+    async fn execute(
+        house: &mut SmartHouse,
+        notifications: &broadcast::Sender<ControlResponse>,
+        data: &ControlRequestData,
+    ) -> ControlResponse {
+        trace(data);
+        match dispatch::execute(house, data) {
+            Ok(Action::Respond(response)) => response,
+            Ok(Action::Notify(room_id, device_id, event)) => {
+                match notify(house, notifications, room_id, device_id, event).await {
+                    Ok(s) => ControlResponse::with_state(s),
+                    Err(e) => ControlResponse::with_error(e),
+                }
+            }
+            Err(e) => ControlResponse::with_error(DeviceError::from(e)),
+        }
+    }
+
+    ///
+    /// Выполнить пакет запросов по принципу "все или ничего". Пакет может
+    /// содержать только запросы на получение сведений об устройствах и на
+    /// их включение и выключение, так как откатить удается только
+    /// переключение устройств. При ошибке выполнение прекращается, а
+    /// переключенные пакетом устройства возвращаются в прежнее состояние,
+    /// о чем подписчики получают уведомления.
+    ///
+    async fn transaction(
+        house: &mut SmartHouse,
+        notifications: &broadcast::Sender<ControlResponse>,
+        items: &[ControlRequestData],
+    ) -> ControlResponse {
+        let mut transaction = match Transaction::new(items) {
+            Ok(transaction) => transaction,
+            Err(e) => return ControlResponse::with_error(DeviceError::from(e)),
+        };
+
+        while let Some(item) = transaction.next_request() {
+            let response = match dispatch::execute(house, item) {
+                Ok(Action::Respond(response)) => response,
+                Ok(action @ Action::Notify(room_id, device_id, event)) => {
+                    if transaction.needs_state(&action) {
+                        if let Ok(state) = house
+                            .async_notify(room_id, device_id, Box::pin(StateEvent::new()))
+                            .await
+                        {
+                            transaction.save(room_id, &state);
+                        }
+                    }
+                    match notify(house, notifications, room_id, device_id, event).await {
+                        Ok(s) => ControlResponse::with_state(s),
+                        Err(e) => ControlResponse::with_error(e),
+                    }
+                }
+                Err(e) => ControlResponse::with_error(DeviceError::from(e)),
             };
 
-            log::info!("New client connected: {}", addr);
+            if !transaction.complete(response) {
+                log::info!("Rolling back transaction of {} request(s)", items.len());
+            }
+        }
 
-            let socket = self.socket.clone();
-            tokio::spawn(async move {
+        let rollback: Vec<_> = transaction.rollback().collect();
+        for (room_id, device_id, event) in rollback {
+            if let Err(e) = notify(house, notifications, room_id, device_id, event).await {
+                log::error!("Cannot restore device {} state: {}", device_id, e);
+            }
+        }
+
+        transaction.into_response()
+    }
+
+    ///
+    /// Добавить в комнату устройство с заданным описанием. Подключение к
+    /// удаленному устройству выполняется без блокировки "умного" дома.
+    ///
+    async fn attach_device(
+        house: &Mutex<SmartHouse>,
+        notifications: &broadcast::Sender<ControlResponse>,
+        key: &[u8],
+        room_id: Uuid,
+        spec: &DeviceSpec,
+    ) -> Result<ControlResponse, DeviceError> {
+        if house.lock().await.get(room_id).is_none() {
+            return Err(DeviceError::IllegalRoomId(room_id));
+        }
+
+        let device = build_device(spec, key).await?;
+        Self::insert_device(&mut *house.lock().await, notifications, room_id, device)
+    }
+
+    ///
+    /// Добавить созданное устройство в комнату и рассылать уведомления
+    /// об изменениях его состояния.
+    ///
+    fn insert_device(
+        house: &mut SmartHouse,
+        notifications: &broadcast::Sender<ControlResponse>,
+        room_id: Uuid,
+        mut device: Box<dyn AsyncDevice>,
+    ) -> Result<ControlResponse, DeviceError> {
+        log::info!(
+            "Attaching device {} \"{}\" to room {}",
+            device.id(),
+            device.name(),
+            room_id
+        );
+        watch(notifications, room_id, device.as_mut());
+
+        Ok(dispatch::insert_device(house, room_id, device)?)
+    }
+}
+
+///
+/// Сервер управления отдельным устройством.
+///
+pub struct SmartDeviceServer<D> {
+    ///
+    /// Сервер обмена сообщениями.
+    ///
+    server: Server,
+
+    ///
+    /// Экземпляр устройства.
+    ///
+    device: Arc<Mutex<D>>,
+
+    ///
+    /// Канал рассылки уведомлений об изменении состояния устройства.
+    ///
+    notifications: broadcast::Sender<ControlResponse>,
+
+    ///
+    /// Дескриптор для остановки сервера.
+    ///
+    shutdown: ShutdownHandle,
+
+    ///
+    /// Время ожидания завершения обработки соединений при остановке.
+    ///
+    shutdown_timeout: Duration,
+}
+
+///
+/// Сервер управления "умной" розеткой.
+///
+pub type SmartSocketServer = SmartDeviceServer<SmartSocket>;
+
+///
+/// Сервер управления "умной" лампой.
+///
+pub type SmartLampServer = SmartDeviceServer<SmartLamp>;
+
+impl<D: AsyncDevice> SmartDeviceServer<D> {
+    ///
+    /// Выполнить привязку сервера к сокету и экземпляру устройства с
+    /// заданным общим с клиентами ключом для аутентификации.
+    ///
+    pub async fn bind<A, K>(addrs: A, key: K, device: D) -> Result<Self, BindError>
+    where
+        A: ToSocketAddrs,
+        K: AsRef<[u8]>,
+    {
+        Ok(Self::with_server(Server::bind(addrs, key).await?, device))
+    }
+
+    ///
+    /// Создать сервер на основе настроенного сервера обмена сообщениями
+    /// и экземпляра устройства.
+    ///
+    pub fn with_server(server: Server, device: D) -> Self {
+        let (notifications, _) = broadcast::channel(NOTIFICATION_CAPACITY);
+
+        Self {
+            server,
+            device: Arc::new(Mutex::new(device)),
+            notifications,
+            shutdown: ShutdownHandle::default(),
+            shutdown_timeout: SHUTDOWN_TIMEOUT,
+        }
+    }
+
+    ///
+    /// Ожидать завершения обработки соединений при остановке сервера
+    /// не дольше заданного времени. По истечении этого времени
+    /// незавершенные соединения прерываются.
+    ///
+    #[inline]
+    pub fn with_shutdown_timeout(self, shutdown_timeout: Duration) -> Self {
+        Self {
+            shutdown_timeout,
+            ..self
+        }
+    }
+
+    ///
+    /// Получить дескриптор для остановки сервера.
+    ///
+    #[inline]
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    ///
+    /// Вернуть экземпляр устройства после остановки сервера. Если
+    /// устройство еще используется, возвращается сервер.
+    ///
+    #[allow(clippy::result_large_err)]
+    pub fn into_device(self) -> Result<D, Self> {
+        match Arc::try_unwrap(self.device) {
+            Ok(device) => Ok(device.into_inner()),
+            Err(device) => Err(Self { device, ..self }),
+        }
+    }
+
+    ///
+    /// Запустить сервер для обработки сообщений. Возвращает управление
+    /// после остановки сервера с помощью дескриптора остановки.
+    ///
+    pub async fn run(&self) {
+        let mut workers = JoinSet::new();
+        while let Some(incoming) = accept(&self.server, &self.shutdown).await {
+            let device = self.device.clone();
+            let notifications = self.notifications.clone();
+            let shutdown = self.shutdown.clone();
+            while workers.try_join_next().is_some() {}
+            workers.spawn(async move {
+                let Some(connection) = establish(incoming).await else {
+                    return;
+                };
+                let mut requests = JoinSet::new();
+                let permits = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+                let mut forwarder: Option<JoinHandle<()>> = None;
                 loop {
-                    let request = connection.recv::<ControlRequest>().await;
+                    let request = tokio::select! {
+                        _ = shutdown.wait() => break,
+                        r = connection.recv::<ControlRequest>() => r,
+                    };
                     let request = match request {
                         Ok(r) => r,
-                        Err(_) => {
-                            log::warn!("Connection lost when receiving data");
+                        Err(e) => {
+                            log::warn!("Connection lost when receiving data: {}", e);
                             break;
                         }
                     };
 
-                    let response = Self::dispatch(socket.clone(), request.as_ref()).await;
-                    if connection.send(response).await.is_err() {
-                        log::warn!("Connection lost when sending data");
-                        break;
+                    if let Some(mut response) = Self::subscribe(
+                        &connection,
+                        &device,
+                        &notifications,
+                        &mut forwarder,
+                        &request,
+                    )
+                    .await
+                    {
+                        response.set_id(request.id());
+                        if connection.send(response).await.is_err() {
+                            log::warn!("Connection lost when sending data");
+                            break;
+                        }
+                        continue;
                     }
+
+                    // Запросы на чтение обрабатываются независимо, ответы
+                    // отправляются по мере готовности. Запрос, изменяющий
+                    // состояние, занимает все места, поэтому выполняется
+                    // после завершения предыдущих запросов и до начала
+                    // следующих: очередь семафора соблюдает порядок.
+                    let weight = if request.is_idempotent() {
+                        1
+                    } else {
+                        MAX_IN_FLIGHT as u32
+                    };
+                    let Ok(permit) = permits.clone().acquire_many_owned(weight).await else {
+                        break;
+                    };
+                    let connection = connection.clone();
+                    let device = device.clone();
+                    let notifications = notifications.clone();
+                    while requests.try_join_next().is_some() {}
+                    requests.spawn(async move {
+                        let mut response = if request.version() > connection.version() {
+                            ControlResponse::with_error(DeviceError::UnsupportedVersion(
+                                request.version(),
+                            ))
+                        } else {
+                            Self::dispatch(device, &notifications, request.as_ref()).await
+                        };
+                        response.set_id(request.id());
+
+                        if connection.send(response).await.is_err() {
+                            log::warn!("Connection lost when sending data");
+                        }
+                        drop(permit);
+                    });
+                }
+
+                while requests.join_next().await.is_some() {}
+                if let Some(forwarder) = forwarder {
+                    forwarder.abort();
                 }
             });
         }
+
+        join(workers, self.shutdown_timeout).await;
+    }
+
+    ///
+    /// Обработать запрос на подписку на изменения состояния устройства.
+    /// Для остальных запросов возвращает `None`.
+    ///
+    async fn subscribe(
+        connection: &Arc<Connection>,
+        device: &Mutex<D>,
+        notifications: &broadcast::Sender<ControlResponse>,
+        forwarder: &mut Option<JoinHandle<()>>,
+        req: &ControlRequest,
+    ) -> Option<ControlResponse> {
+        match *req.data() {
+            ControlRequestData::Subscribe(ref selector) => {
+                let selector = match selector.parts() {
+                    Ok((_, selector)) => selector,
+                    Err(e) => return Some(ControlResponse::with_error(e)),
+                };
+                let lock = device.lock().await;
+                if !selector.matches(lock.id(), lock.name()) {
+                    return Some(ControlResponse::with_error(match selector {
+                        Selector::Id(id) => DeviceError::IllegalDeviceId(id),
+                        Selector::Name(name) => DeviceError::IllegalDeviceName(name),
+                    }));
+                }
+            }
+            ControlRequestData::SubscribeAll => {}
+            _ => return None,
+        }
+
+        if req.version() > connection.version() {
+            return Some(ControlResponse::with_error(
+                DeviceError::UnsupportedVersion(req.version()),
+            ));
+        }
+
+        if !connection
+            .capabilities()
+            .contains(Capabilities::PUSH_NOTIFICATIONS)
+        {
+            return Some(ControlResponse::with_error(
+                DeviceError::NotificationsDisabled,
+            ));
+        }
+
+        if forwarder.is_none() {
+            *forwarder = Some(forward(
+                connection.clone(),
+                notifications.subscribe(),
+                |_| true,
+            ));
+        }
+
+        Some(ControlResponse::done())
     }
 
     ///
     /// Выполнить диспетчеризацию запроса.
     ///
-    async fn dispatch(socket: Arc<Mutex<SmartSocket>>, req: &ControlRequest) -> ControlResponse {
-        match req.data {
+    async fn dispatch(
+        device: Arc<Mutex<D>>,
+        notifications: &broadcast::Sender<ControlResponse>,
+        req: &ControlRequest,
+    ) -> ControlResponse {
+        match *req.data() {
             ControlRequestData::AcquireRemoteDeviceState => {
-                let mut lock = socket.lock().await;
+                let mut lock = device.lock().await;
                 log::info!("Requesting device {} state", lock.id());
 
                 match lock.async_notify(Box::pin(StateEvent::new())).await {
@@ -119,28 +796,62 @@ impl SmartSocketServer {
             }
 
             ControlRequestData::AcquireRemoteDeviceName => {
-                let lock = socket.lock().await;
+                let lock = device.lock().await;
                 log::info!("Obtaining device {} name \"{}\"", lock.id(), lock.name());
 
                 ControlResponse::with_name(lock.id(), lock.name())
             }
 
+            ControlRequestData::AcquireRemoteDeviceCapabilities => {
+                let lock = device.lock().await;
+                log::info!("Obtaining device {} capabilities", lock.id());
+
+                ControlResponse::with_capabilities(lock.capabilities())
+            }
+
             ControlRequestData::SwitchOnRemoteDevice => {
-                let mut lock = socket.lock().await;
+                let mut lock = device.lock().await;
                 log::info!("Switching on device {}", lock.id());
 
                 match lock.async_notify(Box::pin(SwitchOnEvent::new())).await {
-                    Ok(s) => ControlResponse::with_state(s),
+                    Ok(s) => {
+                        // Удаленное устройство не относится ни к одной комнате.
+                        let _ = notifications
+                            .send(ControlResponse::with_notification(Uuid::nil(), s.clone()));
+                        ControlResponse::with_state(s)
+                    }
                     Err(e) => ControlResponse::with_error(e),
                 }
             }
 
             ControlRequestData::SwitchOffRemoteDevice => {
-                let mut lock = socket.lock().await;
+                let mut lock = device.lock().await;
                 log::info!("Switching off device {}", lock.id());
 
                 match lock.async_notify(Box::pin(SwitchOffEvent::new())).await {
-                    Ok(s) => ControlResponse::with_state(s),
+                    Ok(s) => {
+                        // Удаленное устройство не относится ни к одной комнате.
+                        let _ = notifications
+                            .send(ControlResponse::with_notification(Uuid::nil(), s.clone()));
+                        ControlResponse::with_state(s)
+                    }
+                    Err(e) => ControlResponse::with_error(e),
+                }
+            }
+
+            ControlRequestData::NotifyRemoteDevice(event) => {
+                let mut lock = device.lock().await;
+                log::info!("Notifying device {} with {:?}", lock.id(), event);
+
+                match lock.async_notify(Box::into_pin(Box::from(event))).await {
+                    Ok(s) => {
+                        if !event.is_query() {
+                            // Удаленное устройство не относится ни к одной комнате.
+                            let _ = notifications
+                                .send(ControlResponse::with_notification(Uuid::nil(), s.clone()));
+                        }
+                        ControlResponse::with_state(s)
+                    }
                     Err(e) => ControlResponse::with_error(e),
                 }
             }
@@ -149,3 +860,235 @@ impl SmartSocketServer {
         }
     }
 }
+
+impl SmartSocketServer {
+    ///
+    /// Вернуть экземпляр "умной" розетки после остановки сервера. Если
+    /// розетка еще используется, возвращается сервер.
+    ///
+    #[allow(clippy::result_large_err)]
+    #[inline]
+    pub fn into_socket(self) -> Result<SmartSocket, Self> {
+        self.into_device()
+    }
+}
+
+impl SmartLampServer {
+    ///
+    /// Вернуть экземпляр "умной" лампы после остановки сервера. Если
+    /// лампа еще используется, возвращается сервер.
+    ///
+    #[allow(clippy::result_large_err)]
+    #[inline]
+    pub fn into_lamp(self) -> Result<SmartLamp, Self> {
+        self.into_device()
+    }
+}
+
+///
+/// Предмет подписки соединения на изменения состояния устройств.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Topic {
+    // Изменения состояния устройства в комнате.
+    Device(Uuid, Uuid),
+
+    // Изменения состояния всех устройств.
+    All,
+}
+
+impl Topic {
+    ///
+    /// Проверить, относится ли изменение состояния устройства к предмету
+    /// подписки.
+    ///
+    fn matches(&self, room_id: Uuid, device_id: Uuid) -> bool {
+        match *self {
+            Self::Device(r, d) => r == room_id && d == device_id,
+            Self::All => true,
+        }
+    }
+}
+
+// Дождаться очередного входящего соединения. Возвращает `None` после
+// запроса на остановку сервера. Связь с клиентом подтверждается в задаче
+// обработки соединения, чтобы не задерживать прием других соединений.
+async fn accept(server: &Server, shutdown: &ShutdownHandle) -> Option<Incoming> {
+    loop {
+        let incoming = tokio::select! {
+            _ = shutdown.wait() => return None,
+            c = server.accept_incoming() => c,
+        };
+        match incoming {
+            Ok(incoming) => return Some(incoming),
+            Err(e) => log::error!("Cannot establish connection {}", e),
+        }
+    }
+}
+
+// Подтвердить связь с клиентом по принятому соединению.
+async fn establish(incoming: Incoming) -> Option<Arc<Connection>> {
+    let addr = match incoming.peer_addr() {
+        Ok(addr) => addr.to_string(),
+        Err(_) => "unknown".to_owned(),
+    };
+
+    match incoming.handshake().await {
+        Ok(connection) => {
+            log::info!(
+                "New client connected: {} (protocol {}, capabilities {})",
+                addr,
+                connection.version(),
+                connection.capabilities()
+            );
+            Some(Arc::new(connection))
+        }
+        Err(e) => {
+            log::warn!("Cannot establish connection with {}: {}", addr, e);
+            None
+        }
+    }
+}
+
+// Дождаться завершения обработки соединений не дольше заданного времени,
+// после чего прервать незавершенные соединения.
+async fn join(mut workers: JoinSet<()>, shutdown_timeout: Duration) {
+    let finished = time::timeout(shutdown_timeout, async {
+        while workers.join_next().await.is_some() {}
+    })
+    .await;
+    if finished.is_err() {
+        log::warn!(
+            "{} connection(s) did not finish before the shutdown deadline",
+            workers.len()
+        );
+        workers.shutdown().await;
+    }
+}
+
+// Запустить задачу пересылки клиенту уведомлений, отобранных фильтром.
+fn forward<F>(
+    connection: Arc<Connection>,
+    mut receiver: broadcast::Receiver<ControlResponse>,
+    filter: F,
+) -> JoinHandle<()>
+where
+    F: Fn(&ControlResponse) -> bool + Send + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(notification) => {
+                    if filter(&notification) && connection.send(notification).await.is_err() {
+                        log::warn!("Connection lost when sending notifications");
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    log::warn!("{} notifications are skipped", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+// Записать в журнал запрос на изменение состава "умного" дома.
+fn trace(data: &ControlRequestData) {
+    match *data {
+        ControlRequestData::CreateRoom(ref name) => log::info!("Creating room \"{}\"", name),
+        ControlRequestData::DeleteRoom(room_id) => log::info!("Deleting room {}", room_id),
+        ControlRequestData::RenameRoom(room_id, ref name) => {
+            log::info!("Renaming room {} to \"{}\"", room_id, name)
+        }
+        ControlRequestData::DetachDevice(room_id, device_id) => {
+            log::info!("Detaching device {} from room {}", device_id, room_id)
+        }
+        _ => {}
+    }
+}
+
+// Передать событие устройству и, если событие изменяет состояние
+// устройства, разослать уведомление о его новом состоянии.
+async fn notify(
+    house: &mut SmartHouse,
+    notifications: &broadcast::Sender<ControlResponse>,
+    room_id: Uuid,
+    device_id: Uuid,
+    event: EventData,
+) -> Result<DeviceState, DeviceError> {
+    let state = house
+        .async_notify(room_id, device_id, Box::into_pin(Box::from(event)))
+        .await?;
+    if !event.is_query() {
+        let _ = notifications.send(ControlResponse::with_notification(room_id, state.clone()));
+    }
+
+    Ok(state)
+}
+
+// Установить для устройства обработчик, рассылающий уведомления об
+// изменениях его состояния.
+fn watch(
+    notifications: &broadcast::Sender<ControlResponse>,
+    room_id: Uuid,
+    device: &mut dyn AsyncDevice,
+) {
+    let notifications = notifications.clone();
+    device.watch(StateListener::new(move |state| {
+        let _ = notifications.send(ControlResponse::with_notification(room_id, state));
+    }));
+}
+
+// Создать устройство по описанию из запроса клиента. Адреса удаленных
+// термометра и датчика проверяются заранее, так как привязка их сокетов
+// выполняется в отдельной задаче.
+async fn build_device(spec: &DeviceSpec, key: &[u8]) -> Result<Box<dyn AsyncDevice>, DeviceError> {
+    Ok(match spec {
+        DeviceSpec::Socket { name } => Box::new(SmartSocket::new(name)),
+        DeviceSpec::Thermometer { name, temperature } => {
+            Box::new(SmartThermometer::new(name, *temperature))
+        }
+        DeviceSpec::Lamp { name } => Box::new(SmartLamp::new(name)),
+        DeviceSpec::Sensor { name, kind, value } => Box::new(SmartSensor::new(name, *kind, *value)),
+        DeviceSpec::RemoteSocket { address } => {
+            Box::new(RemoteSmartSocket::connect(address.as_str(), key).await?)
+        }
+        DeviceSpec::RemoteLamp { address } => {
+            Box::new(RemoteSmartLamp::connect(address.as_str(), key).await?)
+        }
+        DeviceSpec::RemoteThermometer {
+            name,
+            bind,
+            address,
+        } => {
+            let _ = net::lookup_host(bind.as_str()).await?;
+            let _ = net::lookup_host(address.as_str()).await?;
+            Box::new(
+                RemoteThermometer::builder()
+                    .with_name(name)
+                    .bind(bind.clone())
+                    .connect(address.clone())
+                    .build()
+                    .await,
+            )
+        }
+        DeviceSpec::RemoteSensor {
+            name,
+            kind,
+            bind,
+            address,
+        } => {
+            let _ = net::lookup_host(bind.as_str()).await?;
+            let _ = net::lookup_host(address.as_str()).await?;
+            Box::new(
+                RemoteSensor::builder(*kind)
+                    .with_name(name)
+                    .bind(bind.clone())
+                    .connect(address.clone())
+                    .build()
+                    .await,
+            )
+        }
+    })
+}
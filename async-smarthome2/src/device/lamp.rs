@@ -0,0 +1,212 @@
+use std::{fmt, pin::Pin};
+
+use async_trait::async_trait;
+use futures::executor::block_on;
+use tokio::net::ToSocketAddrs;
+use uuid::Uuid;
+
+pub use smarthome2_core::device::lamp::{
+    SetBrightnessEvent, SetColorTemperatureEvent, SmartLamp, MAX_BRIGHTNESS, MAX_COLOR_TEMPERATURE,
+    MIN_COLOR_TEMPERATURE,
+};
+
+use crate::{
+    control::{client::ControlClient, message::ControlRequest, retry::RetryPolicy},
+    device::{
+        AsyncDevice, DeviceCapabilities, DeviceKind, DeviceState, Event, EventData, StateEvent,
+    },
+    error::DeviceError,
+};
+
+///
+/// Структура, описывающая взаимодействие с удаленной "умной" лампой
+/// по протоколу TCP.
+///
+pub struct RemoteSmartLamp {
+    ///
+    /// Идентификатор "умной" лампы.
+    ///
+    id: Uuid,
+
+    ///
+    /// Имя "умной" лампы.
+    ///
+    name: String,
+
+    ///
+    /// Возможности "умной" лампы, полученные при подключении.
+    ///
+    capabilities: DeviceCapabilities,
+
+    ///
+    /// Клиент для взаимодействия с удаленной умной лампой.
+    ///
+    client: ControlClient,
+}
+
+impl fmt::Display for RemoteSmartLamp {
+    ///
+    /// Получить информацию об "умной" лампе с помощью форматирования.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = block_on(
+            self.client
+                .request(ControlRequest::acquire_remote_device_state()),
+        )
+        .ok()
+        .and_then(|response| response.state())
+        .filter(|state| state.device_id() == self.id)
+        .ok_or(fmt::Error)?;
+        let (enabled, brightness) = state.enabled().zip(state.brightness()).ok_or(fmt::Error)?;
+
+        let mut v = vec![format!(
+            "умная лампа \"{}\" ({}). Состояние: ",
+            self.name, self.id
+        )];
+
+        if enabled {
+            v.push(format!("включена, яркость {} %", brightness));
+            if let Some(kelvin) = state.color_temperature() {
+                v.push(format!(", цветовая температура {} K", kelvin));
+            }
+            v.push(".".to_string());
+        } else {
+            v.push("выключена.".to_string());
+        }
+
+        write!(f, "{}", v.join(""))
+    }
+}
+
+#[async_trait]
+impl AsyncDevice for RemoteSmartLamp {
+    ///
+    /// Идентификатор удаленной "умной" лампы.
+    ///
+    #[inline]
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    ///
+    /// Получить имя удаленной "умной" лампы.
+    ///
+    #[inline]
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    ///
+    /// Получить описание возможностей удаленной "умной" лампы.
+    ///
+    #[inline]
+    fn capabilities(&self) -> DeviceCapabilities {
+        self.capabilities.clone()
+    }
+
+    ///
+    /// Обработать событие устройством.
+    ///
+    async fn async_notify(&mut self, e: Pin<Box<dyn Event>>) -> Result<DeviceState, DeviceError> {
+        let request = if e.is::<StateEvent>() {
+            ControlRequest::acquire_remote_device_state()
+        } else if let Some(data) = e.data() {
+            ControlRequest::notify_remote_device(data)
+        } else {
+            return Err(DeviceError::NotImplementedEvent(e.id()));
+        };
+
+        self.request(request).await
+    }
+}
+
+impl RemoteSmartLamp {
+    ///
+    /// Подключиться к серверу с заданным адресом и общим с сервером ключом
+    /// для аутентификации.
+    ///
+    pub async fn connect<A, K>(addrs: A, key: K) -> Result<Self, DeviceError>
+    where
+        A: ToSocketAddrs,
+        K: AsRef<[u8]>,
+    {
+        Self::with_client(ControlClient::connect(addrs, key).await?).await
+    }
+
+    ///
+    /// Подключиться к серверу с заданным адресом в режиме восстановления
+    /// соединения с заданной политикой повторных попыток и общим с сервером
+    /// ключом для аутентификации.
+    ///
+    pub async fn reconnecting<A, K>(
+        addrs: A,
+        key: K,
+        policy: RetryPolicy,
+    ) -> Result<Self, DeviceError>
+    where
+        A: ToSocketAddrs,
+        K: AsRef<[u8]>,
+    {
+        Self::with_client(ControlClient::reconnecting(addrs, key, policy).await?).await
+    }
+
+    ///
+    /// Создать удаленную "умную" лампу на основе подключенного клиента
+    /// подсистемы управления. Если сервер управляет устройством другого
+    /// вида, возвращается ошибка.
+    ///
+    pub async fn with_client(client: ControlClient) -> Result<Self, DeviceError> {
+        let response = client
+            .request(ControlRequest::acquire_remote_device_name())
+            .await?;
+        let (id, name) = response.name().ok_or(DeviceError::UnexpectedMessage)?;
+
+        let capabilities = client
+            .request(ControlRequest::acquire_remote_device_capabilities())
+            .await?
+            .capabilities()
+            .filter(|capabilities| capabilities.kind() == DeviceKind::Lamp)
+            .cloned()
+            .ok_or(DeviceError::UnexpectedMessage)?;
+
+        Ok(Self {
+            id,
+            name: name.to_owned(),
+            capabilities,
+            client,
+        })
+    }
+
+    ///
+    /// Установить яркость свечения удаленной "умной" лампы.
+    ///
+    pub async fn set_brightness(&self, percent: u8) -> Result<DeviceState, DeviceError> {
+        self.request(ControlRequest::notify_remote_device(
+            EventData::SetBrightness { percent },
+        ))
+        .await
+    }
+
+    ///
+    /// Установить цветовую температуру свечения удаленной "умной" лампы.
+    ///
+    pub async fn set_color_temperature(&self, kelvin: u16) -> Result<DeviceState, DeviceError> {
+        self.request(ControlRequest::notify_remote_device(
+            EventData::SetColorTemperature { kelvin },
+        ))
+        .await
+    }
+
+    // Выполнить запрос и получить из ответа состояние лампы.
+    async fn request(&self, request: ControlRequest) -> Result<DeviceState, DeviceError> {
+        let response = self.client.request(request).await?;
+
+        if let Some(state) = response.state() {
+            if state.device_id() == self.id {
+                return Ok(state);
+            }
+        }
+
+        Err(DeviceError::UnexpectedMessage)
+    }
+}
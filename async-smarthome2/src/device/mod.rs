@@ -1,24 +1,22 @@
 use std::{fmt, pin::Pin};
 
 use async_trait::async_trait;
-use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+pub use smarthome2_core::device::{
+    Component, DeviceCapabilities, DeviceKind, DeviceState, Event, EventData, EventKind,
+    Measurement, Model, Quantity, SensorKind, StateEvent, Unit, Value,
+};
+
+use smarthome2_core::dispatch::Managed;
+
 use crate::error::DeviceError;
 
+pub mod lamp;
+pub mod sensor;
 pub mod socket;
 pub mod thermometer;
 
-///
-/// Типаж, описывающий событие.
-///
-pub trait Event: Send + Sync + 'static {
-    ///
-    /// Получить идентификатор класса события.
-    ///
-    fn id(&self) -> Uuid;
-}
-
 ///
 /// Типаж, описывающий асинхронное устройство.
 ///
@@ -34,134 +32,125 @@ pub trait AsyncDevice: fmt::Display + Send + Sync + 'static {
     ///
     fn name(&self) -> &str;
 
+    ///
+    /// Получить описание возможностей устройства: его вид, обрабатываемые
+    /// события и сообщаемые величины.
+    ///
+    fn capabilities(&self) -> DeviceCapabilities;
+
     ///
     /// Обработать событие устройством.
     ///
     async fn async_notify(&mut self, e: Pin<Box<dyn Event>>) -> Result<DeviceState, DeviceError>;
+
+    ///
+    /// Установить обработчик изменений состояния, происходящих по
+    /// инициативе самого устройства. По умолчанию такие изменения
+    /// не отслеживаются.
+    ///
+    fn watch(&mut self, _listener: StateListener) {}
 }
 
 ///
-/// Структура, содержащая состояние устройства после обработки
-/// события.
+/// Обработчик изменений состояния устройства.
 ///
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub struct DeviceState {
-    // Идентификатор устройства.
-    device_id: Uuid,
-    // Идентификатор события.
-    event_id: Uuid,
-
-    // Измеряемая температура.
-    themperature: Option<f64>,
-    // Устройства находится во включенном состоянии.
-    enabled: Option<bool>,
-    // Потребляемая мощность.
-    power: Option<f64>,
+pub struct StateListener(Box<dyn Fn(DeviceState) + Send + Sync>);
+
+impl fmt::Debug for StateListener {
+    ///
+    /// Выполнить отладочное форматирование обработчика.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StateListener")
+    }
 }
 
-impl DeviceState {
+impl StateListener {
     ///
-    /// Получить состояние устройства для розетки.
+    /// Создать обработчик изменений состояния из функции.
     ///
     #[inline]
-    pub fn for_socket(device_id: Uuid, event_id: Uuid, enabled: bool, power: Option<f64>) -> Self {
-        Self {
-            device_id,
-            event_id,
-            themperature: None,
-            enabled: Some(enabled),
-            power,
-        }
+    pub fn new<F: Fn(DeviceState) + Send + Sync + 'static>(f: F) -> Self {
+        Self(Box::new(f))
     }
 
     ///
-    /// Получить состояние устройства для термометра.
+    /// Сообщить обработчику новое состояние устройства.
     ///
     #[inline]
-    pub fn for_thermometer(device_id: Uuid, event_id: Uuid, themperature: f64) -> Self {
-        Self {
-            device_id,
-            event_id,
-            themperature: Some(themperature),
-            enabled: None,
-            power: None,
-        }
+    pub fn call(&self, state: DeviceState) {
+        (self.0)(state)
     }
+}
 
+#[async_trait]
+impl<M: Model> AsyncDevice for M {
     ///
     /// Получить идентификатор устройства.
     ///
     #[inline]
-    pub fn device_id(&self) -> Uuid {
-        self.device_id
+    fn id(&self) -> Uuid {
+        Component::id(self)
     }
 
     ///
-    /// Получить идентификатор класса события.
+    /// Получить имя устройства.
     ///
     #[inline]
-    pub fn event_id(&self) -> Uuid {
-        self.event_id
+    fn name(&self) -> &str {
+        Component::name(self)
     }
 
     ///
-    /// Получить измеряемую температуру устройства.
+    /// Получить описание возможностей модели устройства.
     ///
     #[inline]
-    pub fn themperature(&self) -> Option<f64> {
-        self.themperature
+    fn capabilities(&self) -> DeviceCapabilities {
+        Model::capabilities(self)
     }
 
     ///
-    /// Определить, включено ли устройство.
+    /// Обработать событие моделью устройства.
     ///
-    #[inline]
-    pub fn enabled(&self) -> Option<bool> {
-        self.enabled
+    async fn async_notify(&mut self, e: Pin<Box<dyn Event>>) -> Result<DeviceState, DeviceError> {
+        Ok(self.handle(&*e)?)
     }
+}
 
+impl Component for dyn AsyncDevice {
     ///
-    /// Получить потребляемую мощность.
+    /// Получить идентификатор устройства.
     ///
     #[inline]
-    pub fn power(&self) -> Option<f64> {
-        self.power
+    fn id(&self) -> Uuid {
+        AsyncDevice::id(self)
     }
-}
-
-///
-/// Событие, для получение текущего состояния устройства.
-///
-pub struct StateEvent {}
 
-impl Event for StateEvent {
     ///
-    /// Получить идентификатор класса события.
+    /// Получить имя устройства.
     ///
-    fn id(&self) -> Uuid {
-        Self::ID
+    #[inline]
+    fn name(&self) -> &str {
+        AsyncDevice::name(self)
     }
 }
 
-impl Default for StateEvent {
+impl Managed for dyn AsyncDevice {
     ///
-    /// Экземпляр события по умолчанию.
+    /// Получить описание возможностей устройства.
     ///
     #[inline]
-    fn default() -> Self {
-        Self::new()
+    fn capabilities(&self) -> DeviceCapabilities {
+        AsyncDevice::capabilities(self)
     }
 }
 
-impl StateEvent {
-    // Идентификатор класса события.
-    pub(crate) const ID: Uuid = uuid::uuid!("c346ee2a-4cd1-4e46-8ca7-5b329721187e");
-
+impl<T: AsyncDevice> From<T> for Box<dyn AsyncDevice> {
     ///
-    /// Создать событие, для получения текущего состояния устройства.
+    /// Упаковать устройство для размещения в комнате.
     ///
     #[inline]
-    pub fn new() -> Self {
-        Self {}
+    fn from(device: T) -> Self {
+        Box::new(device)
     }
 }
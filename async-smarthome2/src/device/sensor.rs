@@ -0,0 +1,549 @@
+use std::{
+    fmt,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock as StdRwLock, Weak,
+    },
+    time,
+};
+
+use async_trait::async_trait;
+use bincode::{self, Options};
+use futures::executor::block_on;
+use log;
+use rand::{thread_rng, Rng};
+use statrs::distribution::Normal;
+use tokio::{
+    net::{ToSocketAddrs, UdpSocket},
+    sync::{Mutex, RwLock},
+};
+use uuid::Uuid;
+
+use smarthome2_core::device::sensor::title;
+pub use smarthome2_core::device::sensor::SmartSensor;
+
+use crate::{
+    control::message::SensorMessage,
+    device::{
+        AsyncDevice, DeviceCapabilities, DeviceState, Event, Measurement, SensorKind, StateEvent,
+        StateListener, Value,
+    },
+    error::DeviceError,
+};
+
+///
+/// Структура, описывающая взаимодействие с автономным "умным" датчиком.
+/// Автономный датчик имитирует показания датчика заданного вида.
+///
+#[derive(Debug)]
+pub struct AutonomousSensor {
+    ///
+    /// Сокет для отправки показаний датчика.
+    ///
+    socket: UdpSocket,
+
+    ///
+    /// Экземпляр "умного" датчика.
+    ///
+    sensor: Arc<Mutex<SmartSensor>>,
+
+    ///
+    /// Имитировать изменение показаний датчика.
+    ///
+    noisy: bool,
+
+    ///
+    /// Флаг для завершения работы датчика.
+    ///
+    working: Arc<AtomicBool>,
+}
+
+impl AutonomousSensor {
+    ///
+    /// Создать объект по умолчанию для построения экземпляра автономного
+    /// "умного" датчика.
+    ///
+    #[inline]
+    pub fn builder() -> AutonomousSensorBuilder<&'static str, &'static str> {
+        AutonomousSensorBuilder::<&str, &str>::new()
+    }
+
+    ///
+    /// Установить текущее показание датчика.
+    ///
+    pub async fn set_value<V: Into<Value>>(&self, value: V) {
+        let mut guard = self.sensor.lock().await;
+        guard.set_value(value);
+    }
+
+    ///
+    /// Отправлять дейтаграммы с показаниями датчика до завершения работы.
+    ///
+    pub async fn run(&self) -> Result<(), DeviceError> {
+        let duration = time::Duration::from_secs(3);
+
+        while (*self.working).load(Ordering::Relaxed) {
+            let (measurement, id) = {
+                let mut guard = self.sensor.lock().await;
+                if self.noisy {
+                    let value = simulate(guard.kind(), guard.value(), &mut thread_rng());
+                    guard.set_value(value);
+                }
+                (guard.measurement(), guard.id())
+            };
+
+            let message = SensorMessage::new(id).with_measurement(measurement);
+            let bytes = bincode::options().with_big_endian().serialize(&message)?;
+
+            log::info!("Sending value {} of the device {} ...", measurement, id);
+            self.socket.send(&bytes[..]).await?;
+
+            tokio::time::sleep(duration).await;
+        }
+
+        Ok(())
+    }
+}
+
+// Получить очередное показание датчика заданного вида. Числовые показания
+// получают нормальный шум, логические случайно переключаются.
+fn simulate<R: Rng>(kind: SensorKind, value: Value, rng: &mut R) -> Value {
+    match (kind, value) {
+        (SensorKind::Temperature, Value::Number(v)) => {
+            Value::Number(v + rng.sample(Normal::new(0.0, 1.0).unwrap()))
+        }
+        (SensorKind::Humidity, Value::Number(v)) => {
+            Value::Number((v + rng.sample(Normal::new(0.0, 2.0).unwrap())).clamp(0.0, 100.0))
+        }
+        (SensorKind::CarbonDioxide, Value::Number(v)) => {
+            Value::Number((v + rng.sample(Normal::new(0.0, 25.0).unwrap())).max(0.0))
+        }
+        (SensorKind::Motion, Value::Flag(_)) => Value::Flag(rng.gen_bool(0.2)),
+        (SensorKind::Contact, Value::Flag(v)) => Value::Flag(v ^ rng.gen_bool(0.1)),
+        (_, value) => value,
+    }
+}
+
+///
+/// Структура для построения экзкмпляра автономного "умного" датчика.
+///
+pub struct AutonomousSensorBuilder<BA: ToSocketAddrs, RA: ToSocketAddrs> {
+    ///
+    /// Адес привязки UDP-сокета.
+    ///
+    addr: BA,
+
+    ///
+    /// Адрес подключения удаленного датчика.
+    ///
+    remote_addr: RA,
+
+    ///
+    /// Имитировать изменение показаний датчика.
+    ///
+    noisy: bool,
+}
+
+impl<BA: ToSocketAddrs, RA: ToSocketAddrs> AutonomousSensorBuilder<BA, RA> {
+    ///
+    /// Установить адрес привязки сокета автономного "умного" датчика.
+    ///
+    #[inline]
+    pub fn bind<BA2: ToSocketAddrs>(self, addr: BA2) -> AutonomousSensorBuilder<BA2, RA> {
+        AutonomousSensorBuilder::<BA2, RA> {
+            addr,
+            remote_addr: self.remote_addr,
+            noisy: self.noisy,
+        }
+    }
+
+    ///
+    /// Установить адрес удаленного "умного" датчика.
+    ///
+    #[inline]
+    pub fn connect<RA2: ToSocketAddrs>(self, addr: RA2) -> AutonomousSensorBuilder<BA, RA2> {
+        AutonomousSensorBuilder::<BA, RA2> {
+            addr: self.addr,
+            remote_addr: addr,
+            noisy: self.noisy,
+        }
+    }
+
+    ///
+    /// Имитировать изменение показаний датчика в соответствии с его видом.
+    ///
+    #[inline]
+    pub fn with_noise(self) -> Self {
+        Self {
+            addr: self.addr,
+            remote_addr: self.remote_addr,
+            noisy: true,
+        }
+    }
+
+    ///
+    /// Выполнить построение экзкмпляра автономного "умного" датчика.
+    ///
+    pub async fn build(
+        self,
+        sensor: SmartSensor,
+    ) -> Result<(AutonomousSensor, Weak<AtomicBool>), DeviceError> {
+        let working = Arc::new(AtomicBool::new(true));
+        let s = AutonomousSensor {
+            socket: UdpSocket::bind(self.addr).await?,
+            sensor: Arc::new(Mutex::new(sensor)),
+            noisy: self.noisy,
+            working: working.clone(),
+        };
+        s.socket.connect(self.remote_addr).await?;
+
+        Ok((s, Arc::downgrade(&working)))
+    }
+}
+
+impl Default for AutonomousSensorBuilder<&str, &str> {
+    ///
+    /// Создать экземпляр по умолчанию построителя автономного
+    /// "умного" датчика.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AutonomousSensorBuilder<&str, &str> {
+    ///
+    /// Создать экземпляр с настройками по умолчанию построителя
+    /// автономного "умного" датчика.
+    ///
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            addr: "127.0.0.1:8001",
+            remote_addr: "127.0.0.1:8889",
+            noisy: false,
+        }
+    }
+}
+
+///
+/// Структура, описывающая взаимодействие с удаленным "умным" датчиком.
+///
+#[derive(Debug)]
+pub struct RemoteSensor {
+    ///
+    /// Имя удаленного "умного" датчика.
+    ///
+    name: String,
+
+    ///
+    /// Вид удаленного "умного" датчика.
+    ///
+    kind: SensorKind,
+
+    ///
+    /// Идентификатор и последнее полученное показание удаленного
+    /// "умного" датчика.
+    ///
+    data: Arc<RwLock<(Uuid, Option<Value>)>>,
+
+    ///
+    /// Обработчик получения новых показаний датчика.
+    ///
+    listener: Arc<StdRwLock<Option<StateListener>>>,
+
+    ///
+    /// Флаг для завершения связанной с удаленным "умным" датчиком задачи.
+    ///
+    control: Weak<AtomicBool>,
+}
+
+impl Drop for RemoteSensor {
+    ///
+    /// Выполнить остановку задачи при удалении экземпляра удаленного
+    /// "умного" датчика.
+    ///
+    fn drop(&mut self) {
+        if let Some(w) = self.control.upgrade() {
+            (*w).store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+impl fmt::Display for RemoteSensor {
+    ///
+    /// Получить информацию об удаленном "умном" датчике с помощью форматирования.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (id, value) = {
+            let guard = block_on(self.data.read());
+            *guard
+        };
+
+        write!(f, "{} \"{}\" ({}). ", title(self.kind), self.name, id)?;
+        match value {
+            Some(value) => write!(
+                f,
+                "Показание: {}.",
+                Measurement::from((self.kind.quantity(), value))
+            ),
+            None => write!(f, "Показания не получены."),
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncDevice for RemoteSensor {
+    ///
+    /// Получить идентификатор удаленного "умного" датчика.
+    ///
+    #[inline]
+    fn id(&self) -> Uuid {
+        block_on(self.data.read()).0
+    }
+
+    ///
+    /// Получить имя удаленного "умного" датчика.
+    ///
+    #[inline]
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    ///
+    /// Получить описание возможностей удаленного "умного" датчика.
+    ///
+    #[inline]
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::for_sensor(self.kind)
+    }
+
+    ///
+    /// Обработать событие устройством.
+    ///
+    async fn async_notify(&mut self, e: Pin<Box<dyn Event>>) -> Result<DeviceState, DeviceError> {
+        if e.is::<StateEvent>() {
+            let (id, value) = {
+                let guard = self.data.read().await;
+                *guard
+            };
+
+            let state = DeviceState::new(id, e.id());
+            Ok(match value {
+                Some(value) => state.with_measurement((self.kind.quantity(), value)),
+                None => state,
+            })
+        } else {
+            Err(DeviceError::NotImplementedEvent(e.id()))
+        }
+    }
+
+    ///
+    /// Установить обработчик получения новых показаний датчика.
+    ///
+    fn watch(&mut self, listener: StateListener) {
+        let mut guard = self.listener.write().unwrap();
+        *guard = Some(listener);
+    }
+}
+
+impl RemoteSensor {
+    ///
+    /// Создать объект по умолчанию для построения экземпляра удаленного
+    /// "умного" датчика заданного вида.
+    ///
+    #[inline]
+    pub fn builder(kind: SensorKind) -> RemoteSensorBuilder<&'static str, &'static str> {
+        RemoteSensorBuilder::<&str, &str>::new(kind)
+    }
+
+    ///
+    /// Получить вид удаленного "умного" датчика.
+    ///
+    #[inline]
+    pub fn kind(&self) -> SensorKind {
+        self.kind
+    }
+
+    ///
+    /// Получить последнее показание удаленного "умного" датчика, если
+    /// оно уже получено.
+    ///
+    pub async fn value(&self) -> Option<Value> {
+        self.data.read().await.1
+    }
+}
+
+///
+/// Структура для построения экзкмпляра удаленного "умного" датчика.
+///
+pub struct RemoteSensorBuilder<BA, RA>
+where
+    BA: 'static + ToSocketAddrs + Send,
+    RA: 'static + ToSocketAddrs + Send,
+{
+    ///
+    /// Имя удаленного "умного" датчика.
+    ///
+    name: String,
+
+    ///
+    /// Вид удаленного "умного" датчика.
+    ///
+    kind: SensorKind,
+
+    ///
+    /// Адес привязки UDP-сокета.
+    ///
+    addr: BA,
+
+    ///
+    /// Адрес подключения автономного датчика.
+    ///
+    remote_addr: RA,
+}
+
+impl<BA: ToSocketAddrs + Send, RA: ToSocketAddrs + Send> RemoteSensorBuilder<BA, RA> {
+    ///
+    /// Использовать имя удаленного "умного" датчика.
+    ///
+    #[inline]
+    pub fn with_name<D: AsRef<str>>(self, name: D) -> Self {
+        Self {
+            name: name.as_ref().to_string(),
+            ..self
+        }
+    }
+
+    ///
+    /// Установить адрес привязки сокета удаленного "умного" датчика.
+    ///
+    #[inline]
+    pub fn bind<BA2: 'static + ToSocketAddrs + Send>(
+        self,
+        addr: BA2,
+    ) -> RemoteSensorBuilder<BA2, RA> {
+        RemoteSensorBuilder::<BA2, RA> {
+            name: self.name,
+            kind: self.kind,
+            addr,
+            remote_addr: self.remote_addr,
+        }
+    }
+
+    ///
+    /// Установить адрес автономного "умного" датчика.
+    ///
+    #[inline]
+    pub fn connect<RA2: 'static + ToSocketAddrs + Send>(
+        self,
+        addr: RA2,
+    ) -> RemoteSensorBuilder<BA, RA2> {
+        RemoteSensorBuilder::<BA, RA2> {
+            name: self.name,
+            kind: self.kind,
+            addr: self.addr,
+            remote_addr: addr,
+        }
+    }
+
+    ///
+    /// Выполнить построение экзкмпляра удаленного "умного" датчика.
+    ///
+    pub async fn build(self) -> RemoteSensor {
+        let kind = self.kind;
+        let addr = self.addr;
+        let remote_addr = self.remote_addr;
+        let duration = time::Duration::from_millis(50);
+
+        let working = Arc::new(AtomicBool::new(true));
+        let control = Arc::downgrade(&working);
+
+        let data = Arc::new(RwLock::new((Uuid::nil(), None)));
+        let cloned = data.clone();
+
+        let listener: Arc<StdRwLock<Option<StateListener>>> = Arc::default();
+        let cloned_listener = listener.clone();
+
+        tokio::spawn(async move {
+            if let Ok(socket) = UdpSocket::bind(addr).await {
+                if socket.connect(remote_addr).await.is_ok() {
+                    let mut buf = [0u8; 512];
+                    while (*working).load(Ordering::Relaxed) {
+                        if let Ok(received) = socket.recv(&mut buf).await {
+                            let message = bincode::options()
+                                .with_big_endian()
+                                .deserialize::<SensorMessage>(&buf[..received]);
+                            if let Some((id, value)) = message
+                                .ok()
+                                .and_then(|m| Some((m.id(), m.value(kind.quantity())?)))
+                            {
+                                {
+                                    let mut guard = cloned.write().await;
+                                    *guard = (id, Some(value));
+                                }
+
+                                if let Some(ref listener) = *cloned_listener.read().unwrap() {
+                                    listener.call(
+                                        DeviceState::new(id, StateEvent::ID)
+                                            .with_measurement((kind.quantity(), value)),
+                                    );
+                                }
+                            } else {
+                                log::error!("Message deserialization error");
+                            }
+                        }
+
+                        tokio::time::sleep(duration).await;
+                    }
+                }
+            }
+        });
+
+        RemoteSensor {
+            name: self.name,
+            kind,
+            data,
+            listener,
+            control,
+        }
+    }
+}
+
+impl RemoteSensorBuilder<&str, &str> {
+    ///
+    /// Создать экземпляр с настройками по умолчанию построителя
+    /// удаленного "умного" датчика заданного вида.
+    ///
+    #[inline]
+    pub fn new(kind: SensorKind) -> Self {
+        Self {
+            name: "Untitled".to_owned(),
+            kind,
+            addr: "127.0.0.1:8889",
+            remote_addr: "127.0.0.1:8001",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn autonomous_sensor_builder_test() {
+        let builder = AutonomousSensor::builder()
+            .bind("192.168.0.1:55336")
+            .connect("192.168.0.2:55337")
+            .with_noise();
+
+        assert_eq!(builder.addr, "192.168.0.1:55336");
+        assert_eq!(builder.remote_addr, "192.168.0.2:55337");
+        assert!(builder.noisy);
+
+        let contact = simulate(SensorKind::Contact, Value::Flag(false), &mut thread_rng());
+        assert!(contact.as_bool().is_some());
+    }
+}
@@ -5,172 +5,16 @@ use futures::executor::block_on;
 use tokio::net::ToSocketAddrs;
 use uuid::Uuid;
 
+pub use smarthome2_core::device::socket::{
+    SetLoadEvent, SmartSocket, SwitchOffEvent, SwitchOnEvent,
+};
+
 use crate::{
-    control::{client::ControlClient, message::ControlRequest},
-    device::{AsyncDevice, DeviceState, Event, StateEvent},
+    control::{client::ControlClient, message::ControlRequest, retry::RetryPolicy},
+    device::{AsyncDevice, DeviceCapabilities, DeviceState, Event, StateEvent},
     error::DeviceError,
 };
 
-///
-/// Структура, описывающая взаимодействие с "умной" розеткой.
-///
-pub struct SmartSocket {
-    ///
-    /// Идентификатор "умной" розетки.
-    ///
-    id: Uuid,
-
-    ///
-    /// Имя "умной" розетки.
-    ///
-    name: String,
-
-    ///
-    /// Текущее состояние розетки.
-    ///
-    enabled: bool,
-
-    ///
-    /// Потребляемая мощность.
-    ///
-    power: f64,
-}
-
-impl fmt::Display for SmartSocket {
-    ///
-    /// Получить информацию об "умной" розетке с помощью форматирования.
-    ///
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut v = vec![format!(
-            "умная розетка \"{}\" ({}). Состояние: ",
-            self.name, self.id
-        )];
-
-        if self.enabled {
-            v.push(format!(
-                "включена, потребляемая мощность {} Вт.",
-                self.power
-            ));
-        } else {
-            v.push("выключена.".to_string());
-        }
-
-        write!(f, "{}", v.join(""))
-    }
-}
-
-#[async_trait]
-impl AsyncDevice for SmartSocket {
-    ///
-    /// Получить идентификатор "умной" розетки.
-    ///
-    #[inline]
-    fn id(&self) -> Uuid {
-        self.id
-    }
-
-    ///
-    /// Получить имя "умной" розетки.
-    ///
-    #[inline]
-    fn name(&self) -> &str {
-        self.name.as_str()
-    }
-
-    ///
-    /// Обработать событие устройством.
-    ///
-    async fn async_notify(&mut self, e: Pin<Box<dyn Event>>) -> Result<DeviceState, DeviceError> {
-        match e.id() {
-            StateEvent::ID => Ok(DeviceState::for_socket(
-                self.id,
-                e.id(),
-                self.enabled,
-                self.power(),
-            )),
-
-            SwitchOnEvent::ID => {
-                self.switch_on();
-                Ok(DeviceState::for_socket(
-                    self.id,
-                    e.id(),
-                    self.enabled,
-                    self.power(),
-                ))
-            }
-
-            SwitchOffEvent::ID => {
-                self.switch_off();
-                Ok(DeviceState::for_socket(
-                    self.id,
-                    e.id(),
-                    self.enabled,
-                    self.power(),
-                ))
-            }
-
-            id => Err(DeviceError::NotImplementedEvent(id)),
-        }
-    }
-}
-
-impl SmartSocket {
-    ///
-    /// Создать "умную" розетку в выключенном состоянии.
-    ///
-    #[inline]
-    pub fn new(name: &str) -> Self {
-        SmartSocket {
-            id: Uuid::new_v4(),
-            name: name.to_string(),
-            enabled: false,
-            power: 0.0,
-        }
-    }
-
-    ///
-    /// Включить "умную" розетку.
-    ///
-    #[inline]
-    pub fn switch_on(&mut self) {
-        self.enabled = true;
-    }
-
-    ///
-    /// Выключить "умную" розетку.
-    ///
-    #[inline]
-    pub fn switch_off(&mut self) {
-        self.enabled = false;
-    }
-
-    ///
-    /// Проверить, включена ли "умная" розетка.
-    ///
-    #[inline]
-    pub fn enabled(&self) -> bool {
-        self.enabled
-    }
-
-    ///
-    /// Получить текущее значение потребляемой мощности.
-    ///
-    pub fn power(&self) -> Option<f64> {
-        if self.enabled {
-            Some(self.power)
-        } else {
-            None
-        }
-    }
-
-    ///
-    /// Подключить нагрузку с заданной мощностью.
-    ///
-    pub fn plug(&mut self, power: f64) {
-        self.power = power;
-    }
-}
-
 ///
 /// Структура, описывающая взаимодействие с удаленной "умной" розеткой
 /// по протоколу TCP.
@@ -186,6 +30,11 @@ pub struct RemoteSmartSocket {
     ///
     name: String,
 
+    ///
+    /// Возможности "умной" розетки, полученные при подключении.
+    ///
+    capabilities: DeviceCapabilities,
+
     ///
     /// Клиент для взаимодействия с удаленной умной розеткой.
     ///
@@ -245,34 +94,32 @@ impl AsyncDevice for RemoteSmartSocket {
         self.name.as_str()
     }
 
+    ///
+    /// Получить описание возможностей удаленной "умной" розетки.
+    ///
+    #[inline]
+    fn capabilities(&self) -> DeviceCapabilities {
+        self.capabilities.clone()
+    }
+
     ///
     /// Обработать событие устройством.
     ///
     async fn async_notify(&mut self, e: Pin<Box<dyn Event>>) -> Result<DeviceState, DeviceError> {
-        let response = match e.id() {
-            SwitchOnEvent::ID => {
-                self.client
-                    .request(ControlRequest::switch_on_remote_device())
-                    .await?
-            }
-
-            SwitchOffEvent::ID => {
-                self.client
-                    .request(ControlRequest::switch_off_remote_device())
-                    .await?
-            }
-
-            StateEvent::ID => {
-                self.client
-                    .request(ControlRequest::acquire_remote_device_state())
-                    .await?
-            }
-
-            id => {
-                return Err(DeviceError::NotImplementedEvent(id));
-            }
+        let request = if e.is::<SwitchOnEvent>() {
+            ControlRequest::switch_on_remote_device()
+        } else if e.is::<SwitchOffEvent>() {
+            ControlRequest::switch_off_remote_device()
+        } else if e.is::<StateEvent>() {
+            ControlRequest::acquire_remote_device_state()
+        } else if let Some(data) = e.data() {
+            ControlRequest::notify_remote_device(data)
+        } else {
+            return Err(DeviceError::NotImplementedEvent(e.id()));
         };
 
+        let response = self.client.request(request).await?;
+
         if let Some(state) = response.state() {
             if state.device_id() == self.id {
                 return Ok(state);
@@ -285,121 +132,56 @@ impl AsyncDevice for RemoteSmartSocket {
 
 impl RemoteSmartSocket {
     ///
-    /// Подключиться к серверу с заданным адресом.
+    /// Подключиться к серверу с заданным адресом и общим с сервером ключом
+    /// для аутентификации.
     ///
-    pub async fn connect<A>(addrs: A) -> Result<Self, DeviceError>
+    pub async fn connect<A, K>(addrs: A, key: K) -> Result<Self, DeviceError>
     where
         A: ToSocketAddrs,
+        K: AsRef<[u8]>,
     {
-        let client = ControlClient::connect(addrs).await?;
-
-        let response = client
-            .request(ControlRequest::acquire_remote_device_name())
-            .await?;
-        if let Some((id, name)) = response.name() {
-            Ok(Self {
-                id,
-                name: name.to_owned(),
-                client,
-            })
-        } else {
-            Err(DeviceError::UnexpectedMessage)
-        }
-    }
-}
-
-///
-/// Событие, для включения "умной" розетки.
-///
-pub struct SwitchOnEvent {}
-
-impl Event for SwitchOnEvent {
-    ///
-    /// Получить идентификатор класса события.
-    ///
-    fn id(&self) -> Uuid {
-        Self::ID
+        Self::with_client(ControlClient::connect(addrs, key).await?).await
     }
-}
 
-impl Default for SwitchOnEvent {
     ///
-    /// Экземпляр события по умолчанию.
+    /// Подключиться к серверу с заданным адресом в режиме восстановления
+    /// соединения с заданной политикой повторных попыток и общим с сервером
+    /// ключом для аутентификации.
     ///
-    #[inline]
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl SwitchOnEvent {
-    // Идентификатор класса события.
-    pub(crate) const ID: Uuid = uuid::uuid!("56848c21-6600-48d9-a50a-9a0f83486408");
-
-    ///
-    /// Создать событие, для для включения "умной" розетки.
-    ///
-    #[inline]
-    pub fn new() -> Self {
-        Self {}
-    }
-}
-
-///
-/// Событие, для выключения "умной" розетки.
-///
-pub struct SwitchOffEvent {}
-
-impl Event for SwitchOffEvent {
-    ///
-    /// Получить идентификатор класса события.
-    ///
-    fn id(&self) -> Uuid {
-        Self::ID
-    }
-}
-
-impl Default for SwitchOffEvent {
-    ///
-    /// Экземпляр события по умолчанию.
-    ///
-    #[inline]
-    fn default() -> Self {
-        Self::new()
+    pub async fn reconnecting<A, K>(
+        addrs: A,
+        key: K,
+        policy: RetryPolicy,
+    ) -> Result<Self, DeviceError>
+    where
+        A: ToSocketAddrs,
+        K: AsRef<[u8]>,
+    {
+        Self::with_client(ControlClient::reconnecting(addrs, key, policy).await?).await
     }
-}
-
-impl SwitchOffEvent {
-    // Идентификатор класса события.
-    pub(crate) const ID: Uuid = uuid::uuid!("4ca18a36-38e0-410a-9c71-ccf4f109ebd4");
 
     ///
-    /// Создать событие, для для включения "умной" розетки.
+    /// Создать удаленную "умную" розетку на основе подключенного
+    /// клиента подсистемы управления.
     ///
-    #[inline]
-    pub fn new() -> Self {
-        Self {}
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn smart_socket_test() {
-        let mut socket1 = SmartSocket::new("Socket1");
-        assert_eq!(socket1.name.as_str(), "Socket1");
-        assert!(!socket1.enabled);
-        assert_eq!(socket1.power, 0.0);
-
-        socket1.switch_on();
-        assert!(socket1.enabled);
-
-        socket1.plug(1000.0);
-        assert_eq!(socket1.power, 1000.0);
-
-        socket1.switch_off();
-        assert!(!socket1.enabled);
+    pub async fn with_client(client: ControlClient) -> Result<Self, DeviceError> {
+        let response = client
+            .request(ControlRequest::acquire_remote_device_name())
+            .await?;
+        let (id, name) = response.name().ok_or(DeviceError::UnexpectedMessage)?;
+
+        let capabilities = client
+            .request(ControlRequest::acquire_remote_device_capabilities())
+            .await?
+            .capabilities()
+            .cloned()
+            .ok_or(DeviceError::UnexpectedMessage)?;
+
+        Ok(Self {
+            id,
+            name: name.to_owned(),
+            capabilities,
+            client,
+        })
     }
 }
@@ -3,7 +3,7 @@ use std::{
     pin::Pin,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc, Weak,
+        Arc, RwLock as StdRwLock, Weak,
     },
     time,
 };
@@ -20,100 +20,17 @@ use tokio::{
 };
 use uuid::Uuid;
 
+pub use smarthome2_core::device::thermometer::{SetTemperatureEvent, SmartThermometer};
+
 use crate::{
-    control::message::ThermometerMessage,
-    device::{AsyncDevice, DeviceState, Event, StateEvent},
+    control::message::SensorMessage,
+    device::{
+        AsyncDevice, DeviceCapabilities, DeviceKind, DeviceState, Event, Quantity, StateEvent,
+        StateListener,
+    },
     error::DeviceError,
 };
 
-///
-/// Структура, описывающая взаимодействие с "умным" термометром.
-///
-#[derive(Debug)]
-pub struct SmartThermometer {
-    ///
-    /// Идентификатор "умного" термометра.
-    ///
-    id: Uuid,
-
-    ///
-    /// Имя "умного" термометра.
-    ///
-    name: String,
-
-    ///
-    /// Текущее значение температуры.
-    ///
-    temperature: f64,
-}
-
-impl fmt::Display for SmartThermometer {
-    ///
-    /// Получить информацию об "умном" термометре с помощью форматирования.
-    ///
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "умный термометр \"{}\" ({}). Температура: {} °C.",
-            self.name, self.id, self.temperature
-        )
-    }
-}
-
-#[async_trait]
-impl AsyncDevice for SmartThermometer {
-    ///
-    /// Получить идентификатор "умного" термометра.
-    ///
-    #[inline]
-    fn id(&self) -> Uuid {
-        self.id
-    }
-
-    ///
-    /// Получить имя "умного" термометра.
-    ///
-    #[inline]
-    fn name(&self) -> &str {
-        self.name.as_str()
-    }
-
-    ///
-    /// Обработать событие устройством.
-    ///
-    async fn async_notify(&mut self, e: Pin<Box<dyn Event>>) -> Result<DeviceState, DeviceError> {
-        if e.id() == StateEvent::ID {
-            Ok(DeviceState::for_thermometer(
-                self.id,
-                e.id(),
-                self.temperature,
-            ))
-        } else {
-            Err(DeviceError::NotImplementedEvent(e.id()))
-        }
-    }
-}
-
-impl SmartThermometer {
-    ///
-    /// Создать термометр с заданным значением температуры.
-    ///
-    pub fn new(name: &str, temperature: f64) -> Self {
-        Self {
-            id: Uuid::new_v4(),
-            name: name.to_string(),
-            temperature,
-        }
-    }
-
-    ///
-    /// Получить текущее значение температуры.
-    ///
-    pub fn temperature(&self) -> f64 {
-        self.temperature
-    }
-}
-
 ///
 /// Структура, описывающая взаимодействие с автономным "умным" термометром.
 ///
@@ -169,7 +86,8 @@ impl AutonomousThermometer {
                 temperature += rng.sample(normal);
             }
 
-            let message = ThermometerMessage::new(id, temperature);
+            let message =
+                SensorMessage::new(id).with_measurement((Quantity::Temperature, temperature));
             let bytes = bincode::options().with_big_endian().serialize(&message)?;
 
             log::info!(
@@ -304,6 +222,11 @@ pub struct RemoteThermometer {
     ///
     data: Arc<RwLock<(Uuid, f64)>>,
 
+    ///
+    /// Обработчик получения новых показаний температуры.
+    ///
+    listener: Arc<StdRwLock<Option<StateListener>>>,
+
     ///
     /// Флаг для завершения связанного с удаленным "умным" термометром потока.
     ///
@@ -358,11 +281,20 @@ impl AsyncDevice for RemoteThermometer {
         self.name.as_str()
     }
 
+    ///
+    /// Получить описание возможностей удаленного "умного" термометра.
+    /// Удаленный термометр только сообщает показания температуры.
+    ///
+    #[inline]
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::new(DeviceKind::Thermometer).with_measurement(Quantity::Temperature)
+    }
+
     ///
     /// Обработать событие устройством.
     ///
     async fn async_notify(&mut self, e: Pin<Box<dyn Event>>) -> Result<DeviceState, DeviceError> {
-        if e.id() == StateEvent::ID {
+        if e.is::<StateEvent>() {
             let (id, temperature) = {
                 let guard = self.data.read().await;
                 *guard
@@ -373,6 +305,14 @@ impl AsyncDevice for RemoteThermometer {
             Err(DeviceError::NotImplementedEvent(e.id()))
         }
     }
+
+    ///
+    /// Установить обработчик получения новых показаний температуры.
+    ///
+    fn watch(&mut self, listener: StateListener) {
+        let mut guard = self.listener.write().unwrap();
+        *guard = Some(listener);
+    }
 }
 
 impl RemoteThermometer {
@@ -475,19 +415,33 @@ impl<BA: ToSocketAddrs + Send, RA: ToSocketAddrs + Send> RemoteThermometerBuilde
         let data = Arc::new(RwLock::new((Uuid::nil(), 0.0)));
         let cloned = data.clone();
 
+        let listener: Arc<StdRwLock<Option<StateListener>>> = Arc::default();
+        let cloned_listener = listener.clone();
+
         tokio::spawn(async move {
             if let Ok(socket) = UdpSocket::bind(addr).await {
                 if socket.connect(remote_addr).await.is_ok() {
                     let mut buf = [0u8; 512];
                     while (*working).load(Ordering::Relaxed) {
                         if let Ok(received) = socket.recv(&mut buf).await {
-                            if let Ok(message) =
-                                bincode::options()
-                                    .with_big_endian()
-                                    .deserialize::<ThermometerMessage>(&buf[..received])
-                            {
-                                let mut guard = cloned.write().await;
-                                *guard = (message.id(), message.temperature());
+                            let message = bincode::options()
+                                .with_big_endian()
+                                .deserialize::<SensorMessage>(&buf[..received]);
+                            if let Some((id, temperature)) = message.ok().and_then(|m| {
+                                Some((m.id(), m.value(Quantity::Temperature)?.as_f64()?))
+                            }) {
+                                {
+                                    let mut guard = cloned.write().await;
+                                    *guard = (id, temperature);
+                                }
+
+                                if let Some(ref listener) = *cloned_listener.read().unwrap() {
+                                    listener.call(DeviceState::for_thermometer(
+                                        id,
+                                        StateEvent::ID,
+                                        temperature,
+                                    ));
+                                }
                             } else {
                                 log::error!("Message deserialization error");
                             }
@@ -502,6 +456,7 @@ impl<BA: ToSocketAddrs + Send, RA: ToSocketAddrs + Send> RemoteThermometerBuilde
         RemoteThermometer {
             name: self.name,
             data,
+            listener,
             control,
         }
     }
@@ -537,13 +492,6 @@ impl RemoteThermometerBuilder<&str, &str> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn smart_thermometer_test() {
-        let thermometer1 = SmartThermometer::new("Thermometer1", 20.0);
-        assert_eq!(thermometer1.name.as_str(), "Thermometer1");
-        assert_eq!(thermometer1.temperature, 20.0);
-    }
-
     #[test]
     fn autonomous_thermometer_builder_test() {
         let builder = AutonomousThermometer::builder()
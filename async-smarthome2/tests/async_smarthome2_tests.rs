@@ -1,17 +1,55 @@
 #![allow(dead_code)]
 
+use std::{
+    io,
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    task::JoinHandle,
+};
 use uuid::Uuid;
 
 use async_smarthome2::{
+    control::{
+        client::ControlClient,
+        message::{ControlRequest, DeviceSpec, SensorMessage, TextMessage},
+        protocol::{
+            client::Client,
+            codec::Codec,
+            consts::USER_MESSAGE_ID_MIN,
+            envelope::{Envelope, Router},
+            handshake::Capabilities,
+            server::Server,
+            Message, ProtocolVersion,
+        },
+        retry::{ConnectionStatus, RetryPolicy, StatusListener},
+        server::{ControlServer, ShutdownHandle, SmartLampServer, SmartSocketServer},
+    },
     device::{
-        socket::{SmartSocket, SwitchOffEvent, SwitchOnEvent},
-        thermometer::SmartThermometer,
-        AsyncDevice, StateEvent,
+        lamp::{RemoteSmartLamp, SetBrightnessEvent, SetColorTemperatureEvent, SmartLamp},
+        sensor::{AutonomousSensor, RemoteSensor, SmartSensor},
+        socket::{SetLoadEvent, SmartSocket, SwitchOffEvent, SwitchOnEvent},
+        thermometer::{AutonomousThermometer, SmartThermometer},
+        AsyncDevice, DeviceKind, EventData, EventKind, Quantity, SensorKind, StateEvent, Value,
     },
+    error::{ConnectionError, DeviceError, RecvError, RequestError, SendError},
     house::{DeviceInfo, DeviceNotifier, RoomGetter, SmartHouse},
     room::SmartRoom,
 };
 
+// Общий ключ клиентов и серверов для аутентификации.
+const KEY: &[u8] = b"smarthome2 test key";
+
 #[tokio::test]
 async fn smart_home_test() {
     let socket1 = SmartSocket::new("Socket1");
@@ -233,3 +271,1127 @@ async fn smart_home_test() {
         .unwrap();
     assert_eq!(state.themperature().unwrap(), 20.0);
 }
+
+#[tokio::test]
+async fn handshake_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let handle = tokio::spawn(async move {
+        let connection = server.accept().await.unwrap();
+        assert_eq!(connection.version(), ProtocolVersion::CURRENT);
+        assert!(connection.capabilities().contains(Capabilities::BINCODE));
+
+        let request = connection.recv::<TextMessage>().await.unwrap();
+        connection
+            .send(TextMessage::new(request.to_string()))
+            .await
+            .unwrap();
+    });
+
+    let client = Client::connect(addr, KEY).await.unwrap();
+    assert_eq!(client.version(), ProtocolVersion::CURRENT);
+    assert_eq!(
+        client.capabilities(),
+        Capabilities::supported().without(Capabilities::CODECS) | Capabilities::BINCODE
+    );
+    assert_eq!(client.codec(), Codec::Bincode);
+
+    let response: Box<TextMessage> = client.request(TextMessage::new("ping")).await.unwrap();
+    assert_eq!(response.to_string(), "ping");
+
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn authentication_test() {
+    let server = Server::builder("server secret")
+        .bind("127.0.0.1:0")
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let handle = tokio::spawn(async move {
+        assert!(server.accept().await.is_ok());
+        assert!(server.accept().await.is_err());
+    });
+
+    assert!(Client::builder("server secret").connect(addr).await.is_ok());
+    assert!(matches!(
+        Client::builder("wrong secret").connect(addr).await,
+        Err(ConnectionError::AuthenticationFailed)
+    ));
+
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn pipelining_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let socket = SmartSocket::new("Socket1");
+    let socket_id = socket.id();
+    let server = SmartSocketServer::with_server(server, socket);
+    tokio::spawn(async move { server.run().await });
+
+    let client = ControlClient::connect(addr, KEY).await.unwrap();
+    let (name, switch, state) = tokio::join!(
+        client.request(ControlRequest::acquire_remote_device_name()),
+        client.request(ControlRequest::switch_on_remote_device()),
+        client.request(ControlRequest::acquire_remote_device_state()),
+    );
+    let (name, switch, state) = (name.unwrap(), switch.unwrap(), state.unwrap());
+
+    assert_eq!(name.name(), Some((socket_id, "Socket1")));
+    assert!(switch.state().unwrap().enabled().unwrap());
+    assert!(state.state().is_some());
+
+    assert_ne!(name.id(), switch.id());
+    assert_ne!(switch.id(), state.id());
+    assert_eq!(client.in_flight(), 0);
+}
+
+#[tokio::test]
+async fn in_flight_limit_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, control_house());
+    tokio::spawn(async move { server.run().await });
+
+    // Запросов больше, чем сервер обрабатывает одновременно для одного
+    // соединения: остальные ожидают своей очереди, но не теряются.
+    let client = ControlClient::connect(addr, KEY).await.unwrap();
+    let responses = futures::future::join_all(
+        (0..100).map(|_| client.request(ControlRequest::acquire_device_state("Room1/Socket1"))),
+    )
+    .await;
+
+    assert_eq!(responses.len(), 100);
+    assert!(responses
+        .iter()
+        .all(|r| r.as_ref().unwrap().state().is_some()));
+    assert_eq!(client.in_flight(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pipelined_mutations_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, control_house());
+    tokio::spawn(async move { server.run().await });
+
+    // Запросы отправляются подряд, не дожидаясь ответов: изменения
+    // состояния должны примениться в порядке отправки.
+    tokio::task::spawn_blocking(move || {
+        use smarthome2::control::{client::ControlClient, message::ControlRequest};
+
+        let mut client = ControlClient::connect(addr, KEY).unwrap();
+        let ids: Vec<_> = (0..50)
+            .map(|i| {
+                let request = match i % 3 {
+                    0 => ControlRequest::switch_on_device("Room1/Socket1"),
+                    1 => ControlRequest::acquire_device_state("Room1/Socket1"),
+                    _ => ControlRequest::switch_off_device("Room1/Socket1"),
+                };
+                (i, client.submit(request).unwrap())
+            })
+            .collect();
+
+        for (i, id) in ids {
+            let response = client.wait(id).unwrap();
+            let enabled = response.state().unwrap().enabled().unwrap();
+            assert_eq!(enabled, i % 3 != 2, "request {}", i);
+        }
+
+        let response = client
+            .request(ControlRequest::acquire_device_state("Room1/Socket1"))
+            .unwrap();
+        assert!(response.state().unwrap().enabled().unwrap());
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn subscription_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let socket = SmartSocket::new("Socket1");
+    let socket_id = socket.id();
+    let server = SmartSocketServer::with_server(server, socket);
+    tokio::spawn(async move { server.run().await });
+
+    // Ожидание уведомлений дольше времени ожидания данных не прерывает
+    // поток ошибкой.
+    let subscriber = ControlClient::from(
+        Client::builder(KEY)
+            .with_read_timeout(Some(Duration::from_millis(200)))
+            .connect(addr)
+            .await
+            .unwrap(),
+    );
+    subscriber
+        .request(ControlRequest::subscribe_all())
+        .await
+        .unwrap();
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        let client = ControlClient::connect(addr, KEY).await.unwrap();
+        client
+            .request(ControlRequest::switch_on_remote_device())
+            .await
+            .unwrap();
+    });
+
+    let notifications = subscriber.notifications();
+    futures::pin_mut!(notifications);
+    let (room_id, state) = notifications.next().await.unwrap().unwrap();
+    assert_eq!(room_id, Uuid::nil());
+    assert_eq!(state.device_id(), socket_id);
+    assert!(state.enabled().unwrap());
+}
+
+#[tokio::test]
+async fn shutdown_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let server = SmartSocketServer::with_server(server, SmartSocket::new("Socket1"));
+    let shutdown = server.shutdown_handle();
+    let handle = tokio::spawn(async move {
+        server.run().await;
+        server
+    });
+
+    let client = ControlClient::connect(addr, KEY).await.unwrap();
+    client
+        .request(ControlRequest::switch_on_remote_device())
+        .await
+        .unwrap();
+
+    shutdown.shutdown();
+    assert!(shutdown.is_shutdown());
+    let server = handle.await.unwrap();
+    let socket = server.into_socket().ok().unwrap();
+    assert!(socket.enabled());
+
+    assert!(client
+        .request(ControlRequest::acquire_remote_device_state())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn silent_client_test() {
+    let server = Server::builder(KEY)
+        .with_handshake_timeout(Some(Duration::from_millis(300)))
+        .bind("127.0.0.1:0")
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let server = SmartSocketServer::with_server(server, SmartSocket::new("Socket1"));
+    let shutdown = server.shutdown_handle();
+    let handle = tokio::spawn(async move { server.run().await });
+
+    // Клиент, не начинающий подтверждение связи, не задерживает прием
+    // других соединений.
+    let mut silent = TcpStream::connect(addr).await.unwrap();
+    let started = Instant::now();
+    let client = Client::builder(KEY)
+        .with_read_timeout(Some(Duration::from_secs(1)))
+        .connect(addr)
+        .await
+        .map(ControlClient::from)
+        .unwrap();
+    assert!(client
+        .request(ControlRequest::acquire_remote_device_state())
+        .await
+        .is_ok());
+    assert!(started.elapsed() < Duration::from_millis(300));
+
+    // По истечении времени на подтверждение связи соединение закрывается.
+    let read = tokio::time::timeout(Duration::from_secs(2), silent.read(&mut [0u8; 1])).await;
+    assert_eq!(read.unwrap().unwrap(), 0);
+
+    shutdown.shutdown();
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn reconnect_test() {
+    async fn start(
+        addr: SocketAddr,
+        socket: SmartSocket,
+    ) -> (ShutdownHandle, JoinHandle<SmartSocket>) {
+        let server = SmartSocketServer::bind(addr, KEY, socket).await.unwrap();
+        let shutdown = server.shutdown_handle();
+        let handle = tokio::spawn(async move {
+            server.run().await;
+            server.into_socket().ok().unwrap()
+        });
+
+        (shutdown, handle)
+    }
+
+    async fn restart(
+        addr: SocketAddr,
+        (shutdown, handle): (ShutdownHandle, JoinHandle<SmartSocket>),
+    ) -> (ShutdownHandle, JoinHandle<SmartSocket>) {
+        shutdown.shutdown();
+        start(addr, handle.await.unwrap()).await
+    }
+
+    let addr = Server::bind("127.0.0.1:0", KEY)
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let server = start(addr, SmartSocket::new("Socket1")).await;
+
+    let statuses = Arc::new(Mutex::new(Vec::new()));
+    let policy = RetryPolicy::new().with_initial_backoff(Duration::from_millis(10));
+    let client = ControlClient::reconnecting(addr, KEY, policy)
+        .await
+        .unwrap()
+        .with_status_listener(StatusListener::new({
+            let statuses = statuses.clone();
+            move |s| statuses.lock().unwrap().push(s)
+        }));
+    client
+        .request(ControlRequest::switch_on_remote_device())
+        .await
+        .unwrap();
+
+    let server = restart(addr, server).await;
+    let response = client
+        .request(ControlRequest::acquire_remote_device_state())
+        .await
+        .unwrap();
+    assert!(response.state().unwrap().enabled().unwrap());
+    assert_eq!(client.status(), ConnectionStatus::Connected);
+    assert_eq!(
+        *statuses.lock().unwrap(),
+        [
+            ConnectionStatus::Reconnecting(1),
+            ConnectionStatus::Connected
+        ]
+    );
+
+    let (shutdown, handle) = server;
+    shutdown.shutdown();
+    handle.await.unwrap();
+    assert!(client
+        .request(ControlRequest::acquire_remote_device_state())
+        .await
+        .is_err());
+    assert_eq!(client.status(), ConnectionStatus::Disconnected);
+}
+
+#[tokio::test]
+async fn reconnect_attempts_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = SmartSocketServer::with_server(server, SmartSocket::new("Socket1"));
+    let shutdown = server.shutdown_handle();
+    let handle = tokio::spawn(async move { server.run().await });
+
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let policy = RetryPolicy::new()
+        .with_max_attempts(3)
+        .with_initial_backoff(Duration::from_millis(10));
+    let client = ControlClient::reconnecting_with(
+        {
+            let attempts = attempts.clone();
+            move || {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Client::connect(addr, KEY)
+            }
+        },
+        policy,
+    )
+    .await
+    .unwrap();
+    client
+        .request(ControlRequest::acquire_remote_device_state())
+        .await
+        .unwrap();
+    assert_eq!(attempts.load(Ordering::Relaxed), 1);
+
+    // После остановки сервера порт закрыт, и восстановление соединения
+    // ограничено числом попыток политики.
+    shutdown.shutdown();
+    handle.await.unwrap();
+    assert!(client
+        .request(ControlRequest::acquire_remote_device_state())
+        .await
+        .is_err());
+    assert_eq!(attempts.load(Ordering::Relaxed), 1 + 3);
+    assert_eq!(client.status(), ConnectionStatus::Disconnected);
+}
+
+#[tokio::test]
+async fn stalled_frame_test() {
+    // Промежуточный узел, который по сигналу пересылает клиенту только
+    // начало очередного ответа сервера и больше ничего не передает.
+    async fn stalling_proxy(server: SocketAddr) -> (SocketAddr, Arc<AtomicBool>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stall = Arc::new(AtomicBool::new(false));
+        tokio::spawn({
+            let stall = stall.clone();
+            async move {
+                while let Ok((client, _)) = listener.accept().await {
+                    let upstream = TcpStream::connect(server).await.unwrap();
+                    let (mut client_reader, mut client_writer) = client.into_split();
+                    let (mut upstream_reader, mut upstream_writer) = upstream.into_split();
+                    tokio::spawn(async move {
+                        let _ = tokio::io::copy(&mut client_reader, &mut upstream_writer).await;
+                    });
+                    let stall = stall.clone();
+                    tokio::spawn(async move {
+                        let mut buf = [0u8; 1024];
+                        let mut stalled = false;
+                        while let Ok(n @ 1..) = upstream_reader.read(&mut buf).await {
+                            if !stalled && stall.swap(false, Ordering::Relaxed) {
+                                stalled = true;
+                                let _ = client_writer.write_all(&buf[..n.min(3)]).await;
+                            } else if !stalled && client_writer.write_all(&buf[..n]).await.is_err()
+                            {
+                                break;
+                            }
+                        }
+                    });
+                }
+            }
+        });
+
+        (addr, stall)
+    }
+
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let (proxy, stall) = stalling_proxy(server.local_addr().unwrap()).await;
+    let server = SmartSocketServer::with_server(server, SmartSocket::new("Socket1"));
+    let shutdown = server.shutdown_handle();
+    let handle = tokio::spawn(async move { server.run().await });
+    let connect = move || {
+        Client::builder(KEY)
+            .with_read_timeout(Some(Duration::from_millis(200)))
+            .connect(proxy)
+    };
+
+    // Без восстановления соединения оно закрывается, и следующие запросы
+    // завершаются ошибкой, а не получают остаток прерванного ответа.
+    let client = ControlClient::from(connect().await.unwrap());
+    client
+        .request(ControlRequest::switch_on_remote_device())
+        .await
+        .unwrap();
+    stall.store(true, Ordering::Relaxed);
+    match client
+        .request(ControlRequest::acquire_remote_device_state())
+        .await
+    {
+        Err(RequestError::Recv(RecvError::Io(e))) => {
+            assert_eq!(e.kind(), io::ErrorKind::TimedOut)
+        }
+        r => panic!("unexpected result: {:?}", r.map(|_| ())),
+    }
+    assert!(client
+        .request(ControlRequest::acquire_remote_device_state())
+        .await
+        .is_err());
+
+    // В режиме восстановления соединения запрос повторяется через новое
+    // соединение.
+    let policy = RetryPolicy::new().with_initial_backoff(Duration::from_millis(10));
+    let client = ControlClient::reconnecting_with(connect, policy)
+        .await
+        .unwrap();
+    stall.store(true, Ordering::Relaxed);
+    let response = client
+        .request(ControlRequest::acquire_remote_device_state())
+        .await
+        .unwrap();
+    assert!(response.state().unwrap().enabled().unwrap());
+    assert_eq!(client.status(), ConnectionStatus::Connected);
+
+    shutdown.shutdown();
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn interrupted_request_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+        let connection = server.accept().await.unwrap();
+        connection.recv::<ControlRequest>().await.unwrap();
+        drop(connection);
+
+        let connection = server.accept().await.unwrap();
+        assert!(connection.recv::<ControlRequest>().await.is_err());
+    });
+
+    let client = ControlClient::reconnecting(addr, KEY, RetryPolicy::new())
+        .await
+        .unwrap();
+    assert!(matches!(
+        client
+            .request(ControlRequest::switch_on_remote_device())
+            .await,
+        Err(RequestError::Interrupted(_))
+    ));
+    assert_eq!(client.status(), ConnectionStatus::Connected);
+    assert_eq!(client.in_flight(), 0);
+
+    drop(client);
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn codec_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let socket = SmartSocket::new("Socket1");
+    let socket_id = socket.id();
+    let server = SmartSocketServer::with_server(server, socket);
+    tokio::spawn(async move { server.run().await });
+
+    for &codec in Codec::all() {
+        let client = Client::builder(KEY)
+            .with_codec(codec)
+            .connect(addr)
+            .await
+            .unwrap();
+        assert_eq!(client.codec(), codec);
+        assert!(client
+            .capabilities()
+            .contains(Capabilities::PUSH_NOTIFICATIONS));
+
+        let client = ControlClient::from(client);
+        let response = client
+            .request(ControlRequest::acquire_remote_device_name())
+            .await
+            .unwrap();
+        assert_eq!(response.name(), Some((socket_id, "Socket1")));
+    }
+}
+
+#[tokio::test]
+async fn error_code_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = SmartSocketServer::with_server(server, SmartSocket::new("Socket1"));
+    tokio::spawn(async move { server.run().await });
+
+    let client = ControlClient::from(
+        Client::builder(KEY)
+            .with_codec(Codec::Json)
+            .connect(addr)
+            .await
+            .unwrap(),
+    );
+    match client.request(ControlRequest::acquire_rooms()).await {
+        Err(RequestError::ServerError(e)) => {
+            assert!(matches!(*e, DeviceError::UnexpectedMessage))
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Ping(u32);
+
+impl Message for Ping {
+    const TYPE: u16 = USER_MESSAGE_ID_MIN;
+}
+
+#[tokio::test]
+async fn envelope_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let handle = tokio::spawn(async move {
+        let router = Router::<(), Result<Envelope, SendError>>::new()
+            .with_route(|_, m: Box<TextMessage>| Envelope::seal(TextMessage::new(m.to_string())))
+            .with_route(|_, m: Box<SensorMessage>| {
+                Envelope::seal(TextMessage::new(
+                    m.value(Quantity::Temperature).unwrap().to_string(),
+                ))
+            })
+            .with_route(|_, m: Box<Ping>| Envelope::seal(Ping(m.0 + 1)));
+
+        let connection = server.accept().await.unwrap();
+        for _ in 0..3 {
+            let envelope = connection.recv_any().await.unwrap();
+            let response = router.route(&mut (), &envelope).unwrap().unwrap();
+            connection.send_any(&response).await.unwrap();
+        }
+
+        let envelope = connection.recv_any().await.unwrap();
+        assert!(envelope.is::<ControlRequest>());
+        assert!(matches!(
+            router.route(&mut (), &envelope),
+            Err(RecvError::BadType(_))
+        ));
+    });
+
+    let client = Client::connect(addr, KEY).await.unwrap();
+
+    client.send(TextMessage::new("hello")).await.unwrap();
+    let response = client.recv_any().await.unwrap();
+    assert!(response.is::<TextMessage>());
+    assert_eq!(response.open::<TextMessage>().unwrap().to_string(), "hello");
+
+    client
+        .send(SensorMessage::new(Uuid::new_v4()).with_measurement((Quantity::Temperature, 21.5)))
+        .await
+        .unwrap();
+    let response: Box<TextMessage> = client.recv().await.unwrap();
+    assert_eq!(response.to_string(), "21.5");
+
+    let response: Box<Ping> = client.request(Ping(41)).await.unwrap();
+    assert_eq!(response.0, 42);
+
+    client.send(ControlRequest::acquire_rooms()).await.unwrap();
+    handle.await.unwrap();
+}
+
+// Создать "умный" дом с одной комнатой из розетки и термометра.
+fn control_house() -> SmartHouse {
+    let mut room = SmartRoom::new("Room1");
+    room += SmartSocket::new("Socket1");
+    room += SmartThermometer::new("Thermometer1", 20.0);
+
+    let mut house = SmartHouse::new("House1");
+    house += room;
+    house
+}
+
+#[tokio::test]
+async fn control_server_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, control_house());
+    let shutdown = server.shutdown_handle();
+    let server = Arc::new(server);
+    let handle = tokio::spawn({
+        let server = server.clone();
+        async move { server.run().await }
+    });
+
+    let client = ControlClient::connect(addr, KEY).await.unwrap();
+    let rooms = client
+        .request(ControlRequest::acquire_rooms())
+        .await
+        .unwrap();
+    assert_eq!(rooms.list().unwrap().len(), 1);
+    assert_eq!(rooms.list().unwrap()[0].1, "Room1");
+
+    let devices = client
+        .request(ControlRequest::acquire_devices("Room1"))
+        .await
+        .unwrap();
+    assert_eq!(devices.list().unwrap().len(), 2);
+
+    let response = client
+        .request(ControlRequest::switch_on_device("Room1/Socket1"))
+        .await
+        .unwrap();
+    assert!(response.state().unwrap().enabled().unwrap());
+
+    let response = client
+        .request(ControlRequest::notify_device(
+            "Room1/Socket1",
+            EventData::SetLoad { watts: 500.0 },
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.state().unwrap().power(), Some(500.0));
+    assert_eq!(response.state().unwrap().event_id(), SetLoadEvent::ID);
+
+    let response = client
+        .request(ControlRequest::notify_device(
+            "Room1/Thermometer1",
+            EventData::SetTemperature { celsius: 23.5 },
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.state().unwrap().themperature(), Some(23.5));
+
+    // Недопустимые значения нагрузки и температуры отклоняются.
+    let invalid = [
+        ("Room1/Socket1", EventData::SetLoad { watts: -1.0 }),
+        ("Room1/Socket1", EventData::SetLoad { watts: f64::NAN }),
+        (
+            "Room1/Thermometer1",
+            EventData::SetTemperature {
+                celsius: f64::NEG_INFINITY,
+            },
+        ),
+    ];
+    for (device, event) in invalid {
+        match client
+            .request(ControlRequest::notify_device(device, event))
+            .await
+        {
+            Err(RequestError::ServerError(e)) => {
+                assert!(matches!(*e, DeviceError::InvalidArgument(_)))
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    let response = client
+        .request(ControlRequest::acquire_device_capabilities(
+            "Room1/Thermometer1",
+        ))
+        .await
+        .unwrap();
+    let capabilities = response.capabilities().unwrap();
+    assert_eq!(capabilities.kind(), DeviceKind::Thermometer);
+    assert!(!capabilities.accepts(EventKind::SwitchOn));
+
+    let response = client
+        .request(ControlRequest::acquire_device_info("Room1/Thermometer1"))
+        .await
+        .unwrap();
+    assert!(response.info().unwrap().contains("Thermometer1"));
+
+    let response = client
+        .request(ControlRequest::create_room("Room2"))
+        .await
+        .unwrap();
+    assert_eq!(response.name().unwrap().1, "Room2");
+    let room2_id = response.name().unwrap().0;
+
+    // Имена, которые невозможно выбрать в пути "комната/устройство",
+    // отклоняются.
+    let unselectable = ["Room/3".to_owned(), Uuid::new_v4().to_string()];
+    for name in &unselectable {
+        let requests = [
+            ControlRequest::create_room(name),
+            ControlRequest::rename_room(room2_id, name),
+            ControlRequest::attach_device(room2_id, DeviceSpec::Socket { name: name.clone() }),
+        ];
+        for request in requests {
+            match client.request(request).await {
+                Err(RequestError::ServerError(e)) => {
+                    assert!(matches!(*e, DeviceError::InvalidArgument(_)))
+                }
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    let response = client
+        .request(ControlRequest::transaction([
+            ControlRequest::switch_off_device("Room1/Socket1"),
+            ControlRequest::switch_off_device("Room1/Socket2"),
+        ]))
+        .await
+        .unwrap();
+    assert_eq!(response.results().unwrap().len(), 2);
+
+    let response = client
+        .request(ControlRequest::acquire_device_state("Room1/Socket1"))
+        .await
+        .unwrap();
+    assert!(response.state().unwrap().enabled().unwrap());
+
+    match client
+        .request(ControlRequest::acquire_devices("Room3"))
+        .await
+    {
+        Err(RequestError::ServerError(e)) => {
+            assert!(matches!(*e, DeviceError::IllegalRoomName(_)))
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    // Подключение к удаленному устройству из пакета не блокирует дом:
+    // запросы других клиентов выполняются, пока устройство не отвечает.
+    let silent = TcpListener::bind("127.0.0.1:0").unwrap();
+    let silent_addr = silent.local_addr().unwrap();
+    let batch_client = ControlClient::connect(addr, KEY).await.unwrap();
+    let batch = tokio::spawn(async move {
+        batch_client
+            .request(ControlRequest::batch([
+                ControlRequest::attach_device(
+                    room2_id,
+                    DeviceSpec::RemoteSocket {
+                        address: silent_addr.to_string(),
+                    },
+                ),
+                ControlRequest::attach_device(
+                    room2_id,
+                    DeviceSpec::Socket {
+                        name: "Socket3".to_owned(),
+                    },
+                ),
+            ]))
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let response = tokio::time::timeout(
+        Duration::from_secs(1),
+        client.request(ControlRequest::acquire_devices(room2_id)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert!(response.list().unwrap().is_empty());
+
+    drop(silent);
+    let response = batch.await.unwrap().unwrap();
+    let results = response.results().unwrap();
+    assert!(results[0].error().is_some());
+    assert_eq!(results[1].name().unwrap().1, "Socket3");
+
+    drop(client);
+    shutdown.shutdown();
+    handle.await.unwrap();
+
+    let server = Arc::try_unwrap(server).ok().unwrap();
+    let house = server.into_house().ok().unwrap();
+    assert!(house.get("Room2").is_some());
+}
+
+#[tokio::test]
+async fn lamp_test() {
+    let lamp_server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let lamp_addr = lamp_server.local_addr().unwrap();
+    let lamp_server = SmartLampServer::with_server(
+        lamp_server,
+        SmartLamp::new("Lamp2")
+            .with_color_temperature(4000)
+            .unwrap(),
+    );
+    let lamp_shutdown = lamp_server.shutdown_handle();
+    let lamp_handle = tokio::spawn(async move {
+        lamp_server.run().await;
+        lamp_server.into_lamp().ok().unwrap()
+    });
+
+    let mut lamp = RemoteSmartLamp::connect(lamp_addr, KEY).await.unwrap();
+    assert!(lamp.capabilities().accepts(EventKind::SetColorTemperature));
+    let state = lamp
+        .async_notify(Box::pin(SwitchOnEvent::new()))
+        .await
+        .unwrap();
+    assert_eq!(state.enabled(), Some(true));
+    assert!(matches!(
+        lamp.set_brightness(150).await,
+        Err(DeviceError::InvalidArgument(_))
+    ));
+    assert_eq!(
+        lamp.set_brightness(100).await.unwrap().brightness(),
+        Some(100)
+    );
+    let state = lamp.set_color_temperature(2700).await.unwrap();
+    assert_eq!(state.color_temperature(), Some(2700));
+    assert_eq!(state.event_id(), SetColorTemperatureEvent::ID);
+
+    let mut room = SmartRoom::new("Room1");
+    room += SmartLamp::new("Lamp1");
+    room += lamp;
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, house);
+    let shutdown = server.shutdown_handle();
+    let handle = tokio::spawn(async move { server.run().await });
+
+    let client = ControlClient::connect(addr, KEY).await.unwrap();
+    for device in ["Room1/Lamp1", "Room1/Lamp2"] {
+        let response = client
+            .request(ControlRequest::notify_device(
+                device,
+                EventData::SetBrightness { percent: 40 },
+            ))
+            .await
+            .unwrap();
+        let state = response.state().unwrap();
+        assert_eq!(state.brightness(), Some(40));
+        assert_eq!(state.event_id(), SetBrightnessEvent::ID);
+
+        match client
+            .request(ControlRequest::notify_device(
+                device,
+                EventData::SetBrightness { percent: 150 },
+            ))
+            .await
+        {
+            Err(RequestError::ServerError(e)) => {
+                assert!(matches!(*e, DeviceError::InvalidArgument(_)))
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        let response = client
+            .request(ControlRequest::acquire_device_state(device))
+            .await
+            .unwrap();
+        assert_eq!(response.state().unwrap().brightness(), Some(40));
+    }
+
+    match client
+        .request(ControlRequest::notify_device(
+            "Room1/Lamp1",
+            EventData::SetColorTemperature { kelvin: 2700 },
+        ))
+        .await
+    {
+        Err(RequestError::ServerError(e)) => {
+            assert!(matches!(*e, DeviceError::NotImplementedEvent(_)))
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    drop(client);
+    shutdown.shutdown();
+    handle.await.unwrap();
+    lamp_shutdown.shutdown();
+    let lamp = lamp_handle.await.unwrap();
+    assert!(lamp.enabled());
+    assert_eq!(lamp.brightness(), 40);
+    assert_eq!(lamp.color_temperature(), Some(2700));
+}
+
+#[tokio::test]
+async fn sensor_test() {
+    let sensor = RemoteSensor::builder(SensorKind::CarbonDioxide)
+        .with_name("Co2")
+        .bind("127.0.0.1:55411")
+        .connect("127.0.0.1:55412")
+        .build()
+        .await;
+    assert_eq!(sensor.value().await, None);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (autonomous, control) = AutonomousSensor::builder()
+        .bind("127.0.0.1:55412")
+        .connect("127.0.0.1:55411")
+        .build(SmartSensor::new("Co2", SensorKind::CarbonDioxide, 800.0))
+        .await
+        .unwrap();
+    let handle = tokio::spawn(async move { autonomous.run().await });
+
+    for _ in 0..40 {
+        if sensor.value().await.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(sensor.value().await, Some(Value::Number(800.0)));
+
+    let mut room = SmartRoom::new("Room1");
+    room += sensor;
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, house);
+    let shutdown = server.shutdown_handle();
+    let server_handle = tokio::spawn(async move { server.run().await });
+
+    let client = ControlClient::connect(addr, KEY).await.unwrap();
+    let response = client
+        .request(ControlRequest::acquire_device_state("Room1/Co2"))
+        .await
+        .unwrap();
+    let measurement = *response
+        .state()
+        .unwrap()
+        .measurement(Quantity::CarbonDioxide)
+        .unwrap();
+    assert_eq!(measurement.to_string(), "800 ppm");
+
+    let room_id = client
+        .request(ControlRequest::acquire_rooms())
+        .await
+        .unwrap()
+        .list()
+        .unwrap()[0]
+        .0;
+    client
+        .request(ControlRequest::attach_device(
+            room_id,
+            DeviceSpec::Sensor {
+                name: "Humidity1".to_owned(),
+                kind: SensorKind::Humidity,
+                value: Value::Number(45.0),
+            },
+        ))
+        .await
+        .unwrap();
+    let response = client
+        .request(ControlRequest::acquire_device_capabilities(
+            "Room1/Humidity1",
+        ))
+        .await
+        .unwrap();
+    let capabilities = response.capabilities().unwrap();
+    assert_eq!(capabilities.kind(), DeviceKind::HumiditySensor);
+    assert!(!capabilities.accepts(EventKind::SwitchOn));
+
+    drop(client);
+    shutdown.shutdown();
+    server_handle.await.unwrap();
+    control.upgrade().unwrap().store(false, Ordering::Relaxed);
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn remote_device_notification_test() {
+    let sensor = RemoteSensor::builder(SensorKind::CarbonDioxide)
+        .with_name("Co2")
+        .bind("127.0.0.1:55413")
+        .connect("127.0.0.1:55414")
+        .build()
+        .await;
+    let mut room = SmartRoom::new("Room1");
+    let room_id = room.id();
+    room += sensor;
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, house);
+    let shutdown = server.shutdown_handle();
+    let server_handle = tokio::spawn(async move { server.run().await });
+
+    let subscriber = ControlClient::connect(addr, KEY).await.unwrap();
+    subscriber
+        .request(ControlRequest::subscribe_all())
+        .await
+        .unwrap();
+    let client = ControlClient::connect(addr, KEY).await.unwrap();
+    client
+        .request(ControlRequest::attach_device(
+            room_id,
+            DeviceSpec::RemoteThermometer {
+                name: "Thermometer1".to_owned(),
+                bind: "127.0.0.1:55415".to_owned(),
+                address: "127.0.0.1:55416".to_owned(),
+            },
+        ))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let co2 = SmartSensor::new("Co2", SensorKind::CarbonDioxide, 800.0);
+    let co2_id = co2.id();
+    let (autonomous_sensor, sensor_control) = AutonomousSensor::builder()
+        .bind("127.0.0.1:55414")
+        .connect("127.0.0.1:55413")
+        .build(co2)
+        .await
+        .unwrap();
+    let sensor_handle = tokio::spawn(async move { autonomous_sensor.run().await });
+    let thermometer = SmartThermometer::new("Thermometer1", 21.0);
+    let thermometer_id = thermometer.id();
+    let (autonomous_thermometer, _) = AutonomousThermometer::builder()
+        .bind("127.0.0.1:55416")
+        .connect("127.0.0.1:55415")
+        .build(thermometer)
+        .await
+        .unwrap();
+
+    // Показания удаленных устройств рассылаются подписчикам как для
+    // устройств исходного дома, так и для добавленных по запросу.
+    let notifications = subscriber.notifications();
+    futures::pin_mut!(notifications);
+    let (mut co2_seen, mut thermometer_seen) = (false, false);
+    let received = tokio::time::timeout(Duration::from_secs(10), async {
+        while !(co2_seen && thermometer_seen) {
+            let Ok((notified_room_id, state)) = notifications.next().await.unwrap() else {
+                continue;
+            };
+            assert_eq!(notified_room_id, room_id);
+            if state.device_id() == co2_id {
+                assert_eq!(
+                    state.measurement(Quantity::CarbonDioxide),
+                    Some(&(Quantity::CarbonDioxide, 800.0).into())
+                );
+                co2_seen = true;
+            } else if state.device_id() == thermometer_id {
+                assert_eq!(state.themperature(), Some(21.0));
+                thermometer_seen = true;
+            }
+        }
+    });
+    tokio::select! {
+        r = autonomous_thermometer.run() => panic!("unexpected result {:?}", r),
+        r = received => r.unwrap(),
+    }
+
+    drop(client);
+    shutdown.shutdown();
+    server_handle.await.unwrap();
+    sensor_control
+        .upgrade()
+        .unwrap()
+        .store(false, Ordering::Relaxed);
+    sensor_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sync_client_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, control_house());
+    tokio::spawn(async move { server.run().await });
+
+    tokio::task::spawn_blocking(move || {
+        use smarthome2::{
+            control::{client::ControlClient, message::ControlRequest},
+            error::{DeviceError, RequestError},
+        };
+
+        let mut subscriber = ControlClient::connect(addr, KEY).unwrap();
+        subscriber
+            .request(ControlRequest::subscribe("Room1/Socket1"))
+            .unwrap();
+
+        let mut client = ControlClient::connect(addr, KEY).unwrap();
+        let rooms = client.request(ControlRequest::acquire_rooms()).unwrap();
+        assert_eq!(rooms.list().unwrap()[0].1, "Room1");
+
+        let devices = client
+            .request(ControlRequest::acquire_devices("Room1"))
+            .unwrap();
+        assert_eq!(devices.list().unwrap().len(), 2);
+
+        let response = client
+            .request(ControlRequest::switch_on_device(("Room1", "Socket1")))
+            .unwrap();
+        assert!(response.state().unwrap().enabled().unwrap());
+
+        let response = client
+            .request(ControlRequest::acquire_device_info("Room1/Socket1"))
+            .unwrap();
+        assert!(response.info().unwrap().contains("Socket1"));
+
+        let (_, state) = subscriber.notifications().next().unwrap().unwrap();
+        assert!(state.enabled().unwrap());
+
+        match client.request(ControlRequest::switch_off_device("Room1/Mixer")) {
+            Err(RequestError::ServerError(e)) => {
+                assert!(matches!(*e, DeviceError::IllegalDeviceName(_)))
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    })
+    .await
+    .unwrap();
+}
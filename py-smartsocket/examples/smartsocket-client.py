@@ -1,6 +1,8 @@
 #!/usr/bin/env python3
 # -*- coding: utf-8 -*-
 
+import os
+
 from pysmartsocket import SmartSocketClient
 
 
@@ -8,7 +10,7 @@ if __name__ == "__main__":
     client = SmartSocketClient()
     print(client)
 
-    client.connect("127.0.0.1:55333")
+    client.connect("127.0.0.1:55333", os.environ["SMARTHOME_KEY"])
     print(client)
 
     client.switch_on()
@@ -37,10 +37,11 @@ impl SmartSocketClient {
     }
 
     ///
-    /// Подключиться к серверу умной розетки.
+    /// Подключиться к серверу умной розетки с заданным общим с сервером
+    /// ключом для аутентификации.
     ///
-    fn connect(&mut self, addrs: &str) -> PyResult<()> {
-        match RemoteSmartSocket::connect(addrs) {
+    fn connect(&mut self, addrs: &str, key: &str) -> PyResult<()> {
+        match RemoteSmartSocket::connect(addrs, key) {
             Ok(mut socket) => match socket.notify(&StateEvent::new()) {
                 Ok(device_state) => {
                     self.socket = Some(socket);
@@ -124,7 +125,7 @@ impl SmartSocketClient {
             info.push("Соединение с умной розеткой не установлено".to_string());
         }
 
-        format!("{}", info.join(", "))
+        info.join(", ")
     }
 
     ///
@@ -0,0 +1,171 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+///
+/// Клиент командной строки подсистемы управления "умным" домом.
+///
+#[derive(Parser, Debug)]
+#[command(
+    name = "smarthome-ctl",
+    version,
+    after_help = "Коды завершения: 0 - успех, 2 - неверные аргументы, \
+                  3 - ошибка соединения, 4 - ошибка протокола, 5 - ошибка сервера, \
+                  6 - ошибка аутентификации."
+)]
+pub struct Cli {
+    ///
+    /// Адрес сервера управления "умным" домом.
+    ///
+    #[arg(short, long, env = "SMARTHOME_ADDR", default_value = "127.0.0.1:55333")]
+    pub addr: String,
+
+    ///
+    /// Общий с сервером ключ для аутентификации.
+    ///
+    #[arg(short, long, env = "SMARTHOME_KEY", hide_env_values = true)]
+    pub key: String,
+
+    ///
+    /// Файл PEM с корневыми сертификатами для проверки сертификата
+    /// сервера. Если задан, подключение выполняется по TLS.
+    ///
+    #[arg(long, env = "SMARTHOME_TLS_CA")]
+    pub tls_ca: Option<PathBuf>,
+
+    ///
+    /// Имя сервера, проверяемое по его сертификату. По умолчанию
+    /// используется имя узла из адреса сервера.
+    ///
+    #[arg(long, env = "SMARTHOME_TLS_SERVER_NAME", requires = "tls_ca")]
+    pub tls_server_name: Option<String>,
+
+    ///
+    /// Файл PEM с сертификатом клиента, предъявляемым серверу.
+    ///
+    #[arg(long, env = "SMARTHOME_TLS_CERT", requires_all = ["tls_ca", "tls_key"])]
+    pub tls_cert: Option<PathBuf>,
+
+    ///
+    /// Файл PEM с закрытым ключом сертификата клиента.
+    ///
+    #[arg(long, env = "SMARTHOME_TLS_KEY", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    ///
+    /// Формат вывода результатов.
+    ///
+    #[arg(short, long, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+///
+/// Формат вывода результатов.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    ///
+    /// Таблица с выровненными столбцами.
+    ///
+    Table,
+
+    ///
+    /// Документ JSON; при наблюдении - по одному документу в строке.
+    ///
+    Json,
+}
+
+///
+/// Команды управления "умным" домом. Комнаты и устройства задаются
+/// идентификаторами или именами, устройства - путем вида
+/// "комната/устройство".
+///
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    ///
+    /// Вывести список комнат.
+    ///
+    Rooms,
+
+    ///
+    /// Вывести список устройств комнаты.
+    ///
+    Devices {
+        ///
+        /// Идентификатор или имя комнаты.
+        ///
+        room: String,
+    },
+
+    ///
+    /// Вывести состояние устройства.
+    ///
+    State {
+        ///
+        /// Путь к устройству "комната/устройство".
+        ///
+        device: String,
+    },
+
+    ///
+    /// Вывести текстовую информацию об устройстве.
+    ///
+    Info {
+        ///
+        /// Путь к устройству "комната/устройство".
+        ///
+        device: String,
+    },
+
+    ///
+    /// Вывести вид устройства, обрабатываемые им события и сообщаемые
+    /// величины.
+    ///
+    Capabilities {
+        ///
+        /// Путь к устройству "комната/устройство".
+        ///
+        device: String,
+    },
+
+    ///
+    /// Включить устройство.
+    ///
+    On {
+        ///
+        /// Путь к устройству "комната/устройство".
+        ///
+        device: String,
+    },
+
+    ///
+    /// Выключить устройство.
+    ///
+    Off {
+        ///
+        /// Путь к устройству "комната/устройство".
+        ///
+        device: String,
+    },
+
+    ///
+    /// Наблюдать за изменениями состояния устройства или всех устройств.
+    ///
+    Watch {
+        ///
+        /// Путь к устройству "комната/устройство"; если не задан,
+        /// наблюдение ведется за всеми устройствами.
+        ///
+        device: Option<String>,
+
+        ///
+        /// Завершить работу после получения заданного количества
+        /// уведомлений.
+        ///
+        #[arg(short, long)]
+        count: Option<usize>,
+    },
+}
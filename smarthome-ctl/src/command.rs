@@ -0,0 +1,117 @@
+use std::io;
+
+use uuid::Uuid;
+
+use smarthome2::control::{
+    client::ControlClient,
+    message::{ControlRequest, ControlResponse, DeviceSelector, Selector},
+    protocol::{client::Client, tls::TlsClientConfig},
+};
+
+use crate::{
+    cli::{Cli, Command},
+    error::CtlError,
+    output::Printer,
+};
+
+///
+/// Выполнить команду, заданную аргументами командной строки, и вывести
+/// ее результат в стандартный поток вывода.
+///
+pub fn run(cli: &Cli) -> Result<(), CtlError> {
+    let mut client = connect(cli)?;
+    let mut printer = Printer::new(cli.format, io::stdout().lock());
+
+    match cli.command {
+        Command::Rooms => {
+            let response = client.request(ControlRequest::acquire_rooms())?;
+            printer.list(list(&response)?)?;
+        }
+
+        Command::Devices { ref room } => {
+            let response =
+                client.request(ControlRequest::acquire_devices(Selector::parse(room)))?;
+            printer.list(list(&response)?)?;
+        }
+
+        Command::State { ref device } => {
+            let response = client.request(ControlRequest::acquire_device_state(path(device)))?;
+            printer.state(&response.state().ok_or(CtlError::UnexpectedResponse)?)?;
+        }
+
+        Command::Info { ref device } => {
+            let response = client.request(ControlRequest::acquire_device_info(path(device)))?;
+            printer.info(response.info().ok_or(CtlError::UnexpectedResponse)?)?;
+        }
+
+        Command::Capabilities { ref device } => {
+            let response =
+                client.request(ControlRequest::acquire_device_capabilities(path(device)))?;
+            printer.capabilities(
+                response
+                    .capabilities()
+                    .ok_or(CtlError::UnexpectedResponse)?,
+            )?;
+        }
+
+        Command::On { ref device } => {
+            let response = client.request(ControlRequest::switch_on_device(path(device)))?;
+            printer.state(&response.state().ok_or(CtlError::UnexpectedResponse)?)?;
+        }
+
+        Command::Off { ref device } => {
+            let response = client.request(ControlRequest::switch_off_device(path(device)))?;
+            printer.state(&response.state().ok_or(CtlError::UnexpectedResponse)?)?;
+        }
+
+        Command::Watch { ref device, count } => {
+            let request = match device {
+                Some(device) => ControlRequest::subscribe(path(device)),
+                None => ControlRequest::subscribe_all(),
+            };
+            client.request(request)?;
+
+            for notification in client.notifications().take(count.unwrap_or(usize::MAX)) {
+                let (room_id, state) = notification?;
+                printer.notification(room_id, &state)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Подключиться к серверу, используя защищенное соединение TLS, если
+// заданы корневые сертификаты.
+fn connect(cli: &Cli) -> Result<ControlClient, CtlError> {
+    let mut builder = Client::builder(cli.key.as_str());
+    if let Some(ref ca_path) = cli.tls_ca {
+        let server_name = match cli.tls_server_name {
+            Some(ref name) => name.as_str(),
+            None => host(&cli.addr),
+        };
+        let mut tls = TlsClientConfig::from_pem(server_name, ca_path)?;
+        if let (Some(cert_path), Some(key_path)) = (&cli.tls_cert, &cli.tls_key) {
+            tls = tls.with_identity(cert_path, key_path)?;
+        }
+        builder = builder.with_tls(tls);
+    }
+
+    Ok(builder.connect(cli.addr.as_str())?.into())
+}
+
+// Получить имя узла из адреса вида "узел:порт".
+fn host(addr: &str) -> &str {
+    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+// Получить список идентификаторов и имен из ответа сервера.
+fn list(response: &ControlResponse) -> Result<&[(Uuid, String)], CtlError> {
+    response.list().ok_or(CtlError::UnexpectedResponse)
+}
+
+// Выбрать устройство по пути "комната/устройство".
+fn path(device: &str) -> DeviceSelector {
+    DeviceSelector::Path(device.to_owned())
+}
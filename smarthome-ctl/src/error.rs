@@ -0,0 +1,156 @@
+use std::io;
+
+use thiserror::Error;
+
+use smarthome2::error::{
+    ConnectionError, DeviceError, RecvError, RequestError, SendError, TlsError,
+};
+
+///
+/// Код завершения при неверных аргументах командной строки.
+///
+pub const EXIT_USAGE: u8 = 2;
+
+///
+/// Код завершения при ошибке подключения к серверу.
+///
+pub const EXIT_CONNECTION: u8 = 3;
+
+///
+/// Код завершения при нарушении протокола обмена.
+///
+pub const EXIT_PROTOCOL: u8 = 4;
+
+///
+/// Код завершения при ошибке, возвращенной сервером.
+///
+pub const EXIT_SERVER: u8 = 5;
+
+///
+/// Код завершения при отказе сервера в аутентификации.
+///
+pub const EXIT_AUTHENTICATION: u8 = 6;
+
+///
+/// Ошибка выполнения команды.
+///
+#[derive(Error, Debug)]
+pub enum CtlError {
+    #[error("connection error: {0}")]
+    Connection(RequestError),
+
+    #[error("protocol error: {0}")]
+    Protocol(RequestError),
+
+    #[error("authentication failed, check the key")]
+    Authentication,
+
+    #[error("TLS configuration error: {0}")]
+    Tls(#[from] TlsError),
+
+    #[error("unexpected response from the server")]
+    UnexpectedResponse,
+
+    #[error("server error: {0}")]
+    Server(Box<DeviceError>),
+
+    #[error("output error: {0}")]
+    Output(#[from] io::Error),
+}
+
+impl From<RequestError> for CtlError {
+    ///
+    /// Разделить ошибки обработки запроса на ошибки соединения,
+    /// протокола и сервера.
+    ///
+    fn from(error: RequestError) -> Self {
+        match error {
+            RequestError::ServerError(e) => Self::Server(e),
+            RequestError::Connection(ConnectionError::AuthenticationFailed) => Self::Authentication,
+            RequestError::Send(ref e) if is_protocol_send(e) => Self::Protocol(error),
+            RequestError::Recv(ref e) if is_protocol_recv(e) => Self::Protocol(error),
+            RequestError::Connection(ref e) if is_protocol_connection(e) => Self::Protocol(error),
+            RequestError::UnknownRequest(_) => Self::Protocol(error),
+            _ => Self::Connection(error),
+        }
+    }
+}
+
+impl From<ConnectionError> for CtlError {
+    ///
+    /// Преобразовать ошибку подключения к серверу.
+    ///
+    #[inline]
+    fn from(error: ConnectionError) -> Self {
+        RequestError::from(error).into()
+    }
+}
+
+impl CtlError {
+    ///
+    /// Получить код завершения программы для ошибки.
+    ///
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Connection(_) => EXIT_CONNECTION,
+            Self::Protocol(_) | Self::UnexpectedResponse => EXIT_PROTOCOL,
+            Self::Server(_) => EXIT_SERVER,
+            Self::Authentication => EXIT_AUTHENTICATION,
+            Self::Tls(_) => EXIT_USAGE,
+            Self::Output(_) => 1,
+        }
+    }
+}
+
+// Проверить, вызвана ли ошибка отправки нарушением протокола.
+fn is_protocol_send(e: &SendError) -> bool {
+    !matches!(e, SendError::Io(_) | SendError::Timeout)
+}
+
+// Проверить, вызвана ли ошибка получения нарушением протокола.
+fn is_protocol_recv(e: &RecvError) -> bool {
+    !matches!(e, RecvError::Io(_) | RecvError::Timeout)
+}
+
+// Проверить, вызвана ли ошибка подключения нарушением протокола.
+fn is_protocol_connection(e: &ConnectionError) -> bool {
+    match e {
+        ConnectionError::BadHandshake
+        | ConnectionError::UnsupportedVersion(_)
+        | ConnectionError::IncompatibleCapabilities(_) => true,
+        ConnectionError::Send(e) => is_protocol_send(e),
+        ConnectionError::Recv(e) => is_protocol_recv(e),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_code_test() {
+        let e = CtlError::from(ConnectionError::Io(io::ErrorKind::ConnectionRefused.into()));
+        assert_eq!(e.exit_code(), EXIT_CONNECTION);
+
+        let e = CtlError::from(ConnectionError::BadHandshake);
+        assert_eq!(e.exit_code(), EXIT_PROTOCOL);
+
+        let e = CtlError::from(ConnectionError::AuthenticationFailed);
+        assert_eq!(e.exit_code(), EXIT_AUTHENTICATION);
+
+        let e = CtlError::from(TlsError::NoCertificates);
+        assert_eq!(e.exit_code(), EXIT_USAGE);
+
+        let e = CtlError::from(RequestError::Recv(RecvError::BadType(0)));
+        assert_eq!(e.exit_code(), EXIT_PROTOCOL);
+
+        let e = CtlError::from(RequestError::Recv(RecvError::Timeout));
+        assert_eq!(e.exit_code(), EXIT_CONNECTION);
+
+        let e = CtlError::from(RequestError::ServerError(Box::new(
+            DeviceError::UnexpectedMessage,
+        )));
+        assert_eq!(e.exit_code(), EXIT_SERVER);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod cli;
+pub mod command;
+pub mod error;
+pub mod output;
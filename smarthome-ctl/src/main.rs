@@ -0,0 +1,17 @@
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use smarthome_ctl::{cli::Cli, command};
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match command::run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("smarthome-ctl: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
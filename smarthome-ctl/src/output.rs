@@ -0,0 +1,347 @@
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use smarthome2::device::{DeviceCapabilities, DeviceState, Measurement, Quantity, Unit, Value};
+
+use crate::cli::Format;
+
+// Заголовки столбцов таблицы состояния устройства.
+const STATE_HEADERS: [&str; 4] = ["DEVICE", "SEQUENCE", "TIMESTAMP", "MEASUREMENTS"];
+
+// Элемент списка комнат или устройств.
+#[derive(Serialize)]
+struct Entry<'a> {
+    id: Uuid,
+    name: &'a str,
+}
+
+// Состояние устройства и, для уведомлений, комната устройства.
+#[derive(Serialize)]
+struct State {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    room_id: Option<Uuid>,
+    device_id: Uuid,
+    sequence: u64,
+    timestamp: String,
+    measurements: Vec<Reading>,
+}
+
+impl State {
+    // Получить представление состояния устройства.
+    fn new(room_id: Option<Uuid>, state: &DeviceState) -> Self {
+        Self {
+            room_id,
+            device_id: state.device_id(),
+            sequence: state.sequence(),
+            timestamp: humantime::format_rfc3339_millis(state.timestamp()).to_string(),
+            measurements: state.measurements().iter().map(Reading::new).collect(),
+        }
+    }
+
+    // Получить строку таблицы для состояния устройства.
+    fn row(&self) -> Vec<String> {
+        let mut row: Vec<String> = self.room_id.iter().map(Uuid::to_string).collect();
+        row.push(self.device_id.to_string());
+        row.push(self.sequence.to_string());
+        row.push(self.timestamp.clone());
+        // Отсутствие результатов измерений обозначается прочерком.
+        let readings: Vec<_> = self.measurements.iter().map(Reading::cell).collect();
+        row.push(if readings.is_empty() {
+            "-".to_owned()
+        } else {
+            readings.join(", ")
+        });
+        row
+    }
+}
+
+// Результат измерения величины устройством.
+#[derive(Serialize)]
+struct Reading {
+    quantity: Quantity,
+    value: Number,
+    unit: Unit,
+    #[serde(skip)]
+    text: String,
+}
+
+// Значение измеряемой величины в виде, естественном для JSON.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Number {
+    Flag(bool),
+    Number(f64),
+}
+
+impl Reading {
+    // Получить представление результата измерения.
+    fn new(measurement: &Measurement) -> Self {
+        Self {
+            quantity: measurement.quantity(),
+            value: match measurement.value() {
+                Value::Flag(value) => Number::Flag(value),
+                Value::Number(value) => Number::Number(value),
+            },
+            unit: measurement.unit(),
+            text: measurement.to_string(),
+        }
+    }
+
+    // Получить значение ячейки таблицы для результата измерения.
+    fn cell(&self) -> String {
+        format!("{:?}: {}", self.quantity, self.text)
+    }
+}
+
+// Текстовая информация об устройстве.
+#[derive(Serialize)]
+struct Info<'a> {
+    info: &'a str,
+}
+
+///
+/// Вывод результатов команд в заданном формате.
+///
+pub struct Printer<W: Write> {
+    // Формат вывода.
+    format: Format,
+
+    // Поток вывода.
+    out: W,
+
+    // Заголовок таблицы уведомлений уже выведен.
+    header: bool,
+}
+
+impl<W: Write> Printer<W> {
+    ///
+    /// Создать вывод результатов в заданный поток.
+    ///
+    pub fn new(format: Format, out: W) -> Self {
+        Self {
+            format,
+            out,
+            header: false,
+        }
+    }
+
+    ///
+    /// Вывести список идентификаторов и имен комнат или устройств.
+    ///
+    pub fn list(&mut self, items: &[(Uuid, String)]) -> io::Result<()> {
+        match self.format {
+            Format::Table => {
+                let rows: Vec<_> = items
+                    .iter()
+                    .map(|(id, name)| vec![id.to_string(), name.clone()])
+                    .collect();
+                self.out
+                    .write_all(table(&["ID", "NAME"], &rows, true).as_bytes())
+            }
+            Format::Json => {
+                let items: Vec<_> = items
+                    .iter()
+                    .map(|(id, name)| Entry { id: *id, name })
+                    .collect();
+                self.json(&items)
+            }
+        }
+    }
+
+    ///
+    /// Вывести состояние устройства.
+    ///
+    pub fn state(&mut self, state: &DeviceState) -> io::Result<()> {
+        let state = State::new(None, state);
+        match self.format {
+            Format::Table => self
+                .out
+                .write_all(table(&STATE_HEADERS, &[state.row()], true).as_bytes()),
+            Format::Json => self.json(&state),
+        }
+    }
+
+    ///
+    /// Вывести текстовую информацию об устройстве.
+    ///
+    pub fn info(&mut self, info: &str) -> io::Result<()> {
+        match self.format {
+            Format::Table => writeln!(self.out, "{}", info),
+            Format::Json => self.json(&Info { info }),
+        }
+    }
+
+    ///
+    /// Вывести описание возможностей устройства.
+    ///
+    pub fn capabilities(&mut self, capabilities: &DeviceCapabilities) -> io::Result<()> {
+        match self.format {
+            Format::Table => {
+                let row = vec![
+                    format!("{:?}", capabilities.kind()),
+                    join(capabilities.events()),
+                    join(capabilities.measurements()),
+                ];
+                self.out
+                    .write_all(table(&["KIND", "EVENTS", "MEASUREMENTS"], &[row], true).as_bytes())
+            }
+            Format::Json => self.json(capabilities),
+        }
+    }
+
+    ///
+    /// Вывести уведомление об изменении состояния устройства. Уведомление
+    /// выводится сразу, без буферизации.
+    ///
+    pub fn notification(&mut self, room_id: Uuid, state: &DeviceState) -> io::Result<()> {
+        let state = State::new(Some(room_id), state);
+        match self.format {
+            Format::Table => {
+                let headers: Vec<_> = ["ROOM"].into_iter().chain(STATE_HEADERS).collect();
+                let rows = table(&headers, &[state.row()], !self.header);
+                self.header = true;
+                self.out.write_all(rows.as_bytes())?;
+            }
+            Format::Json => self.json(&state)?,
+        }
+
+        self.out.flush()
+    }
+
+    // Вывести документ JSON в отдельной строке.
+    fn json<T: Serialize + ?Sized>(&mut self, value: &T) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, value)?;
+        writeln!(self.out)
+    }
+}
+
+// Получить значение ячейки таблицы со списком значений через запятую.
+fn join<T: fmt::Debug>(values: &[T]) -> String {
+    let values: Vec<_> = values.iter().map(|v| format!("{:?}", v)).collect();
+    values.join(",")
+}
+
+// Сформировать таблицу с выровненными по левому краю столбцами.
+// Ширина столбцов определяется заголовками и значениями ячеек.
+fn table(headers: &[&str], rows: &[Vec<String>], with_header: bool) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| {
+        let last = cells.len() - 1;
+        let mut line = String::new();
+        for (idx, (value, width)) in cells.into_iter().zip(&widths).enumerate() {
+            line.push_str(value);
+            if idx < last {
+                let padding = width - value.chars().count() + 2;
+                line.push_str(&" ".repeat(padding));
+            }
+        }
+        line.push('\n');
+        line
+    };
+
+    let mut text = String::new();
+    if with_header {
+        text.push_str(&format_row(headers.to_vec()));
+    }
+    for row in rows {
+        text.push_str(&format_row(row.iter().map(String::as_str).collect()));
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_test() {
+        let rows = vec![
+            vec!["1".to_owned(), "Гостиная".to_owned()],
+            vec!["22".to_owned(), "Hall".to_owned()],
+        ];
+        assert_eq!(
+            table(&["ID", "NAME"], &rows, true),
+            "ID  NAME\n1   Гостиная\n22  Hall\n"
+        );
+        assert_eq!(table(&["ID", "NAME"], &rows[1..], false), "22  Hall\n");
+    }
+
+    #[test]
+    fn printer_test() {
+        let id = Uuid::nil();
+        let state = DeviceState::for_socket(id, Uuid::nil(), true, Some(1000.0));
+
+        let mut printer = Printer::new(Format::Json, Vec::new());
+        printer.list(&[(id, "Socket1".to_owned())]).unwrap();
+        printer.state(&state).unwrap();
+        let text = String::from_utf8(printer.out).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            format!("[{{\"id\":\"{}\",\"name\":\"Socket1\"}}]", id)
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            format!(
+                "{{\"device_id\":\"{}\",\"sequence\":{},\"timestamp\":\"{}\",\
+                 \"measurements\":[{{\"quantity\":\"Enabled\",\"value\":true,\"unit\":\"None\"}},\
+                 {{\"quantity\":\"Power\",\"value\":1000.0,\"unit\":\"Watt\"}}]}}",
+                id,
+                state.sequence(),
+                humantime::format_rfc3339_millis(state.timestamp())
+            )
+        );
+
+        let mut printer = Printer::new(Format::Table, Vec::new());
+        printer.notification(id, &state).unwrap();
+        printer.notification(id, &state).unwrap();
+        let text = String::from_utf8(printer.out).unwrap();
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.starts_with("ROOM"));
+        assert!(text.contains("Enabled: true, Power: 1000 Вт"));
+    }
+
+    #[test]
+    fn measurements_test() {
+        let id = Uuid::nil();
+        let lamp = DeviceState::for_lamp(id, Uuid::nil(), true, 70, Some(2700));
+        let sensor = DeviceState::new(id, Uuid::nil())
+            .with_measurement((Quantity::Humidity, 45.0))
+            .with_measurement((Quantity::Motion, false));
+
+        let mut printer = Printer::new(Format::Table, Vec::new());
+        printer.state(&lamp).unwrap();
+        printer.state(&sensor).unwrap();
+        let text = String::from_utf8(printer.out).unwrap();
+        let mut lines = text.lines();
+        assert!(lines.next().unwrap().starts_with("DEVICE"));
+        let row = lines.next().unwrap();
+        assert!(row.contains(&lamp.sequence().to_string()));
+        assert!(row.ends_with("Enabled: true, Brightness: 70 %, ColorTemperature: 2700 K"));
+        assert!(lines
+            .nth(1)
+            .unwrap()
+            .ends_with("Humidity: 45 %, Motion: false"));
+
+        let mut printer = Printer::new(Format::Json, Vec::new());
+        printer.state(&sensor).unwrap();
+        let state: serde_json::Value = serde_json::from_slice(&printer.out).unwrap();
+        assert_eq!(state["sequence"], sensor.sequence());
+        assert_eq!(state["measurements"][0]["quantity"], "Humidity");
+        assert_eq!(state["measurements"][0]["value"], 45.0);
+        assert_eq!(state["measurements"][0]["unit"], "Percent");
+        assert_eq!(state["measurements"][1]["value"], false);
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    env, fs,
+    net::{SocketAddr, TcpListener},
+    process::{Command, Output},
+    thread,
+    time::Duration,
+};
+
+use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
+use smarthome2::{
+    control::{
+        client::ControlClient,
+        message::{ControlRequest, TextMessage},
+        protocol::{server::Server, tls::TlsServerConfig},
+        server::ControlServer,
+    },
+    device::{socket::SmartSocket, thermometer::SmartThermometer, Device},
+    house::SmartHouse,
+    room::SmartRoom,
+};
+use uuid::Uuid;
+
+use smarthome_ctl::error::{
+    EXIT_AUTHENTICATION, EXIT_CONNECTION, EXIT_PROTOCOL, EXIT_SERVER, EXIT_USAGE,
+};
+
+// Общий с сервером ключ для аутентификации.
+const KEY: &str = "smarthome-ctl test key";
+
+// Запустить сервер с домом из одной комнаты с розеткой и термометром.
+fn start_server() -> (SocketAddr, Uuid, Uuid) {
+    let mut room = SmartRoom::new("Гостиная");
+    let room_id = room.id();
+    let socket = SmartSocket::new("Розетка");
+    let socket_id = socket.id();
+    room += socket;
+    room += SmartThermometer::new("Термометр", 21.5);
+
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, house);
+    thread::spawn(move || server.run());
+
+    (addr, room_id, socket_id)
+}
+
+// Выполнить программу с заданными аргументами.
+fn ctl(addr: SocketAddr, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_smarthome-ctl"))
+        .arg("--addr")
+        .arg(addr.to_string())
+        .env("SMARTHOME_KEY", KEY)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn query_test() {
+    let (addr, room_id, socket_id) = start_server();
+
+    let output = ctl(addr, &["rooms"]);
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with("ID"));
+    assert!(text.contains(&format!("{}  Гостиная", room_id)));
+
+    let output = ctl(addr, &["--format", "json", "devices", &room_id.to_string()]);
+    assert!(output.status.success());
+    let devices: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(devices.as_array().unwrap().len(), 2);
+    assert_eq!(devices[0]["id"], socket_id.to_string());
+    assert_eq!(devices[1]["name"], "Термометр");
+
+    let output = ctl(addr, &["-f", "json", "state", "Гостиная/Термометр"]);
+    let state: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(state["measurements"].as_array().unwrap().len(), 1);
+    assert_eq!(state["measurements"][0]["quantity"], "Temperature");
+    assert_eq!(state["measurements"][0]["value"], 21.5);
+    assert_eq!(state["measurements"][0]["unit"], "Celsius");
+
+    let output = ctl(addr, &["info", &format!("{}/Розетка", room_id)]);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("выключена"));
+
+    let output = ctl(addr, &["-f", "json", "capabilities", "Гостиная/Термометр"]);
+    let capabilities: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(capabilities["kind"], "Thermometer");
+    assert_eq!(capabilities["events"][1], "SetTemperature");
+    assert_eq!(capabilities["measurements"][0], "Temperature");
+
+    let output = ctl(addr, &["capabilities", "Гостиная/Розетка"]);
+    let text = String::from_utf8(output.stdout).unwrap();
+    let row = text.lines().nth(1).unwrap();
+    assert!(row.starts_with("Socket"));
+    assert!(row.contains("State,SwitchOn,SwitchOff,SetLoad"));
+}
+
+#[test]
+fn switch_test() {
+    let (addr, _, socket_id) = start_server();
+
+    let output = ctl(addr, &["-f", "json", "on", "Гостиная/Розетка"]);
+    assert!(output.status.success());
+    let state: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(state["device_id"], socket_id.to_string());
+    assert_eq!(state["measurements"][0]["quantity"], "Enabled");
+    assert_eq!(state["measurements"][0]["value"], true);
+
+    let output = ctl(addr, &["off", "Гостиная/Розетка"]);
+    let text = String::from_utf8(output.stdout).unwrap();
+    let row = text.lines().nth(1).unwrap();
+    assert!(row.starts_with(&socket_id.to_string()));
+    assert!(row.contains("Enabled: false"));
+}
+
+#[test]
+fn watch_test() {
+    let (addr, room_id, socket_id) = start_server();
+
+    let watcher = thread::spawn(move || {
+        ctl(
+            addr,
+            &["-f", "json", "watch", "Гостиная/Розетка", "--count", "2"],
+        )
+    });
+
+    // Состояние переключается, пока наблюдатель не получит уведомления.
+    let mut client = ControlClient::connect(addr, KEY).unwrap();
+    while !watcher.is_finished() {
+        client
+            .request(ControlRequest::switch_on_device((room_id, socket_id)))
+            .unwrap();
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let output = watcher.join().unwrap();
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(text.lines().count(), 2);
+    for line in text.lines() {
+        let state: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(state["room_id"], room_id.to_string());
+        assert_eq!(state["measurements"][0]["value"], true);
+    }
+}
+
+#[test]
+fn exit_code_test() {
+    let (addr, _, _) = start_server();
+
+    let output = ctl(addr, &["state", "Гостиная/Миксер"]);
+    assert_eq!(output.status.code(), Some(EXIT_SERVER.into()));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Миксер"));
+
+    let output = ctl(addr, &["state", "Гостиная"]);
+    assert_eq!(output.status.code(), Some(EXIT_SERVER.into()));
+
+    // Порт освобождается, и подключиться к нему невозможно.
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let output = ctl(addr, &["rooms"]);
+    assert_eq!(output.status.code(), Some(EXIT_CONNECTION.into()));
+
+    // Сервер отвечает на запрос сообщением другого типа.
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut connection = server.incoming().next().unwrap().unwrap();
+        connection.recv::<ControlRequest>().unwrap();
+        connection.send(TextMessage::new("hello")).unwrap();
+    });
+    let output = ctl(addr, &["rooms"]);
+    assert_eq!(output.status.code(), Some(EXIT_PROTOCOL.into()));
+
+    // Сервер не принимает ключ клиента.
+    let (addr, _, _) = start_server();
+    let output = ctl(addr, &["--key", "wrong key", "rooms"]);
+    assert_eq!(output.status.code(), Some(EXIT_AUTHENTICATION.into()));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("authentication"));
+}
+
+#[test]
+fn tls_test() {
+    let dir = env::temp_dir().join(format!("smarthome-ctl-tls-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut params = CertificateParams::new(vec![]);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = Certificate::from_params(params).unwrap();
+    let ca_path = dir.join("ca.pem");
+    fs::write(&ca_path, ca.serialize_pem().unwrap()).unwrap();
+
+    let cert = Certificate::from_params(CertificateParams::new(vec!["localhost".into()])).unwrap();
+    let cert_path = dir.join("server.pem");
+    fs::write(&cert_path, cert.serialize_pem_with_signer(&ca).unwrap()).unwrap();
+    let key_path = dir.join("server.key");
+    fs::write(&key_path, cert.serialize_private_key_pem()).unwrap();
+
+    let server = Server::builder(KEY)
+        .with_tls(TlsServerConfig::from_pem(&cert_path, &key_path).unwrap())
+        .bind("127.0.0.1:0")
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, SmartHouse::new("House1"));
+    thread::spawn(move || server.run());
+
+    let ca_path = ca_path.to_str().unwrap();
+    let output = ctl(
+        addr,
+        &[
+            "--tls-ca",
+            ca_path,
+            "--tls-server-name",
+            "localhost",
+            "rooms",
+        ],
+    );
+    assert!(output.status.success());
+
+    // Настройки TLS задаются и переменными окружения.
+    let output = Command::new(env!("CARGO_BIN_EXE_smarthome-ctl"))
+        .arg("--addr")
+        .arg(addr.to_string())
+        .env("SMARTHOME_KEY", KEY)
+        .env("SMARTHOME_TLS_CA", ca_path)
+        .env("SMARTHOME_TLS_SERVER_NAME", "localhost")
+        .arg("rooms")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // Сертификат сервера не подходит для имени узла из адреса.
+    let output = ctl(addr, &["--tls-ca", ca_path, "rooms"]);
+    assert_eq!(output.status.code(), Some(EXIT_CONNECTION.into()));
+
+    let output = ctl(addr, &["--tls-ca", "missing.pem", "rooms"]);
+    assert_eq!(output.status.code(), Some(EXIT_USAGE.into()));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
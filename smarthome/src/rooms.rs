@@ -340,26 +340,14 @@ impl SmartHouse {
     /// Получить ссылку на комнату "умного" дома по ее идентификатору.
     ///
     pub fn get(&self, id: Uuid) -> Option<&SmartRoom> {
-        for room_ref in self.rooms.iter() {
-            if room_ref.id == id {
-                return Some(room_ref);
-            }
-        }
-
-        None
+        self.rooms.iter().find(|room_ref| room_ref.id == id)
     }
 
     ///
     /// Получить изменяемую ссылку на комнату "умного" дома по ее идентификатору.
     ///
     pub fn get_mut(&mut self, id: Uuid) -> Option<&mut SmartRoom> {
-        for room_ref in self.rooms.iter_mut() {
-            if room_ref.id == id {
-                return Some(room_ref);
-            }
-        }
-
-        None
+        self.rooms.iter_mut().find(|room_ref| room_ref.id == id)
     }
 
     ///
@@ -0,0 +1,405 @@
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::{
+    device::{
+        socket::{SwitchOffEvent, SwitchOnEvent},
+        Component, DeviceCapabilities, DeviceState, Event, EventData, Model, StateEvent,
+    },
+    error::ModelError,
+};
+
+///
+/// Максимальная яркость свечения "умной" лампы, %.
+///
+pub const MAX_BRIGHTNESS: u8 = 100;
+
+///
+/// Минимальная цветовая температура свечения "умной" лампы, K.
+///
+pub const MIN_COLOR_TEMPERATURE: u16 = 1000;
+
+///
+/// Максимальная цветовая температура свечения "умной" лампы, K.
+///
+pub const MAX_COLOR_TEMPERATURE: u16 = 10000;
+
+///
+/// Структура, описывающая взаимодействие с "умной" лампой
+/// с регулируемой яркостью.
+///
+#[derive(Debug)]
+pub struct SmartLamp {
+    ///
+    /// Идентификатор "умной" лампы.
+    ///
+    id: Uuid,
+
+    ///
+    /// Имя "умной" лампы.
+    ///
+    name: String,
+
+    ///
+    /// Текущее состояние лампы.
+    ///
+    enabled: bool,
+
+    ///
+    /// Яркость свечения, %.
+    ///
+    brightness: u8,
+
+    ///
+    /// Цветовая температура свечения, K, если лампа позволяет
+    /// ее изменять.
+    ///
+    color_temperature: Option<u16>,
+}
+
+impl fmt::Display for SmartLamp {
+    ///
+    /// Получить информацию об "умной" лампе с помощью форматирования.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut v = vec![format!(
+            "умная лампа \"{}\" ({}). Состояние: ",
+            self.name, self.id
+        )];
+
+        if self.enabled {
+            v.push(format!("включена, яркость {} %", self.brightness));
+            if let Some(kelvin) = self.color_temperature {
+                v.push(format!(", цветовая температура {} K", kelvin));
+            }
+            v.push(".".to_string());
+        } else {
+            v.push("выключена.".to_string());
+        }
+
+        write!(f, "{}", v.join(""))
+    }
+}
+
+impl Component for SmartLamp {
+    ///
+    /// Получить идентификатор "умной" лампы.
+    ///
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    ///
+    /// Получить имя "умной" лампы.
+    ///
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+impl Model for SmartLamp {
+    ///
+    /// Обработать событие устройством.
+    ///
+    fn handle(&mut self, e: &dyn Event) -> Result<DeviceState, ModelError> {
+        if e.is::<SwitchOnEvent>() {
+            self.switch_on();
+        } else if e.is::<SwitchOffEvent>() {
+            self.switch_off();
+        } else if let Some(event) = e.downcast_ref::<SetBrightnessEvent>() {
+            self.set_brightness(event.percent())?;
+        } else if let Some(event) = e.downcast_ref::<SetColorTemperatureEvent>() {
+            self.set_color_temperature(event.kelvin())?;
+        } else if !e.is::<StateEvent>() {
+            return Err(ModelError::NotImplementedEvent(e.id()));
+        }
+
+        Ok(DeviceState::for_lamp(
+            self.id,
+            e.id(),
+            self.enabled,
+            self.brightness,
+            self.color_temperature,
+        ))
+    }
+
+    ///
+    /// Получить описание возможностей устройства.
+    ///
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::for_lamp(self.color_temperature.is_some())
+    }
+}
+
+impl SmartLamp {
+    ///
+    /// Создать "умную" лампу в выключенном состоянии с максимальной
+    /// яркостью. Цветовая температура такой лампы не изменяется.
+    ///
+    #[inline]
+    pub fn new(name: &str) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            enabled: false,
+            brightness: MAX_BRIGHTNESS,
+            color_temperature: None,
+        }
+    }
+
+    ///
+    /// Позволить изменять цветовую температуру лампы и задать ее
+    /// начальное значение. Для значений вне диапазона
+    /// `MIN_COLOR_TEMPERATURE..=MAX_COLOR_TEMPERATURE` возвращается ошибка.
+    ///
+    pub fn with_color_temperature(self, kelvin: u16) -> Result<Self, ModelError> {
+        Ok(Self {
+            color_temperature: Some(check_color_temperature(kelvin)?),
+            ..self
+        })
+    }
+
+    ///
+    /// Включить "умную" лампу.
+    ///
+    #[inline]
+    pub fn switch_on(&mut self) {
+        self.enabled = true;
+    }
+
+    ///
+    /// Выключить "умную" лампу.
+    ///
+    #[inline]
+    pub fn switch_off(&mut self) {
+        self.enabled = false;
+    }
+
+    ///
+    /// Проверить, включена ли "умная" лампа.
+    ///
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    ///
+    /// Получить яркость свечения.
+    ///
+    #[inline]
+    pub fn brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    ///
+    /// Установить яркость свечения. Для значений больше `MAX_BRIGHTNESS`
+    /// возвращается ошибка.
+    ///
+    pub fn set_brightness(&mut self, percent: u8) -> Result<(), ModelError> {
+        if percent > MAX_BRIGHTNESS {
+            return Err(ModelError::InvalidArgument(format!(
+                "brightness {} % must not exceed {} %",
+                percent, MAX_BRIGHTNESS
+            )));
+        }
+
+        self.brightness = percent;
+        Ok(())
+    }
+
+    ///
+    /// Получить цветовую температуру свечения, если лампа позволяет
+    /// ее изменять.
+    ///
+    #[inline]
+    pub fn color_temperature(&self) -> Option<u16> {
+        self.color_temperature
+    }
+
+    ///
+    /// Установить цветовую температуру свечения. Если лампа не позволяет
+    /// изменять цветовую температуру или значение выходит за пределы
+    /// диапазона `MIN_COLOR_TEMPERATURE..=MAX_COLOR_TEMPERATURE`,
+    /// возвращается ошибка.
+    ///
+    pub fn set_color_temperature(&mut self, kelvin: u16) -> Result<(), ModelError> {
+        match self.color_temperature {
+            Some(ref mut value) => {
+                *value = check_color_temperature(kelvin)?;
+                Ok(())
+            }
+            None => Err(ModelError::NotImplementedEvent(
+                SetColorTemperatureEvent::ID,
+            )),
+        }
+    }
+}
+
+// Проверить, что цветовая температура не выходит за пределы диапазона,
+// допустимого для лампы.
+fn check_color_temperature(kelvin: u16) -> Result<u16, ModelError> {
+    if !(MIN_COLOR_TEMPERATURE..=MAX_COLOR_TEMPERATURE).contains(&kelvin) {
+        return Err(ModelError::InvalidArgument(format!(
+            "color temperature {} K must be within {}..={} K",
+            kelvin, MIN_COLOR_TEMPERATURE, MAX_COLOR_TEMPERATURE
+        )));
+    }
+
+    Ok(kelvin)
+}
+
+///
+/// Событие, для установки яркости свечения "умной" лампы.
+///
+pub struct SetBrightnessEvent {
+    // Яркость свечения, %.
+    percent: u8,
+}
+
+impl Event for SetBrightnessEvent {
+    ///
+    /// Получить идентификатор класса события.
+    ///
+    fn id(&self) -> Uuid {
+        Self::ID
+    }
+
+    ///
+    /// Получить представление события для передачи по сети.
+    ///
+    fn data(&self) -> Option<EventData> {
+        Some(EventData::SetBrightness {
+            percent: self.percent,
+        })
+    }
+}
+
+impl SetBrightnessEvent {
+    ///
+    /// Идентификатор класса события.
+    ///
+    pub const ID: Uuid = uuid::uuid!("3b7e5f0a-91c4-4d6e-a2f8-5c1d7e9b0a64");
+
+    ///
+    /// Создать событие, для установки яркости свечения.
+    ///
+    #[inline]
+    pub fn new(percent: u8) -> Self {
+        Self { percent }
+    }
+
+    ///
+    /// Получить яркость свечения.
+    ///
+    #[inline]
+    pub fn percent(&self) -> u8 {
+        self.percent
+    }
+}
+
+///
+/// Событие, для установки цветовой температуры свечения "умной" лампы.
+///
+pub struct SetColorTemperatureEvent {
+    // Цветовая температура, K.
+    kelvin: u16,
+}
+
+impl Event for SetColorTemperatureEvent {
+    ///
+    /// Получить идентификатор класса события.
+    ///
+    fn id(&self) -> Uuid {
+        Self::ID
+    }
+
+    ///
+    /// Получить представление события для передачи по сети.
+    ///
+    fn data(&self) -> Option<EventData> {
+        Some(EventData::SetColorTemperature {
+            kelvin: self.kelvin,
+        })
+    }
+}
+
+impl SetColorTemperatureEvent {
+    ///
+    /// Идентификатор класса события.
+    ///
+    pub const ID: Uuid = uuid::uuid!("c8a1d4e2-6f3b-4a97-8e05-2b9f7d6c1e38");
+
+    ///
+    /// Создать событие, для установки цветовой температуры свечения.
+    ///
+    #[inline]
+    pub fn new(kelvin: u16) -> Self {
+        Self { kelvin }
+    }
+
+    ///
+    /// Получить цветовую температуру свечения.
+    ///
+    #[inline]
+    pub fn kelvin(&self) -> u16 {
+        self.kelvin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::device::EventKind;
+
+    use super::*;
+
+    #[test]
+    fn smart_lamp_test() {
+        let mut lamp1 = SmartLamp::new("Lamp1");
+        assert_eq!(lamp1.name.as_str(), "Lamp1");
+        assert!(!lamp1.enabled);
+        assert_eq!(lamp1.brightness, MAX_BRIGHTNESS);
+        assert!(!lamp1.capabilities().accepts(EventKind::SetColorTemperature));
+
+        let state = lamp1.handle(&SwitchOnEvent::new()).unwrap();
+        assert_eq!(state.enabled(), Some(true));
+        assert_eq!(state.brightness(), Some(100));
+        assert_eq!(state.color_temperature(), None);
+
+        assert!(matches!(
+            lamp1.handle(&SetBrightnessEvent::new(140)),
+            Err(ModelError::InvalidArgument(_))
+        ));
+        assert_eq!(lamp1.brightness, MAX_BRIGHTNESS);
+        lamp1.set_brightness(30).unwrap();
+        assert!(lamp1.to_string().contains("яркость 30 %"));
+
+        assert!(matches!(
+            lamp1.handle(&SetColorTemperatureEvent::new(2700)),
+            Err(ModelError::NotImplementedEvent(
+                SetColorTemperatureEvent::ID
+            ))
+        ));
+
+        assert!(matches!(
+            SmartLamp::new("Lamp2").with_color_temperature(MAX_COLOR_TEMPERATURE + 1),
+            Err(ModelError::InvalidArgument(_))
+        ));
+        let mut lamp2 = SmartLamp::new("Lamp2")
+            .with_color_temperature(4000)
+            .unwrap();
+        assert!(lamp2.capabilities().accepts(EventKind::SetColorTemperature));
+        let event: Box<dyn Event> = EventData::SetColorTemperature { kelvin: 2700 }.into();
+        let state = lamp2.handle(&*event).unwrap();
+        assert_eq!(state.enabled(), Some(false));
+        assert_eq!(state.color_temperature(), Some(2700));
+
+        for kelvin in [0, MIN_COLOR_TEMPERATURE - 1, MAX_COLOR_TEMPERATURE + 1] {
+            assert!(matches!(
+                lamp2.handle(&SetColorTemperatureEvent::new(kelvin)),
+                Err(ModelError::InvalidArgument(_))
+            ));
+        }
+        assert_eq!(lamp2.color_temperature(), Some(2700));
+    }
+}
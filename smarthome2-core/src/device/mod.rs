@@ -0,0 +1,168 @@
+use std::{any::Any, fmt};
+
+use uuid::Uuid;
+
+pub use smarthome2_protocol::{
+    capability::{DeviceCapabilities, DeviceKind, EventKind, SensorKind},
+    event::EventData,
+    measurement::{Measurement, Quantity, Unit, Value},
+    state::DeviceState,
+};
+
+use crate::error::ModelError;
+
+use self::{
+    lamp::{SetBrightnessEvent, SetColorTemperatureEvent},
+    socket::{SetLoadEvent, SwitchOffEvent, SwitchOnEvent},
+    thermometer::SetTemperatureEvent,
+};
+
+pub mod lamp;
+pub mod sensor;
+pub mod socket;
+pub mod thermometer;
+
+///
+/// Типаж, описывающий событие. Событие может нести данные; устройство
+/// получает их, приводя событие к конкретному типу.
+///
+pub trait Event: Any + Send + Sync {
+    ///
+    /// Получить идентификатор класса события.
+    ///
+    fn id(&self) -> Uuid;
+
+    ///
+    /// Получить представление события для передачи по сети, если
+    /// событие может быть передано.
+    ///
+    fn data(&self) -> Option<EventData> {
+        None
+    }
+}
+
+impl dyn Event {
+    ///
+    /// Проверить, является ли событие событием заданного типа.
+    ///
+    #[inline]
+    pub fn is<T: Event>(&self) -> bool {
+        (self as &dyn Any).is::<T>()
+    }
+
+    ///
+    /// Получить ссылку на событие заданного типа.
+    ///
+    #[inline]
+    pub fn downcast_ref<T: Event>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref::<T>()
+    }
+}
+
+impl From<EventData> for Box<dyn Event> {
+    ///
+    /// Восстановить событие из его представления для передачи по сети.
+    ///
+    fn from(data: EventData) -> Self {
+        match data {
+            EventData::State => Box::new(StateEvent::new()),
+            EventData::SwitchOn => Box::new(SwitchOnEvent::new()),
+            EventData::SwitchOff => Box::new(SwitchOffEvent::new()),
+            EventData::SetLoad { watts } => Box::new(SetLoadEvent::new(watts)),
+            EventData::SetTemperature { celsius } => Box::new(SetTemperatureEvent::new(celsius)),
+            EventData::SetBrightness { percent } => Box::new(SetBrightnessEvent::new(percent)),
+            EventData::SetColorTemperature { kelvin } => {
+                Box::new(SetColorTemperatureEvent::new(kelvin))
+            }
+        }
+    }
+}
+
+///
+/// Типаж, описывающий элемент "умного" дома, который может быть
+/// размещен в комнате.
+///
+pub trait Component: fmt::Display {
+    ///
+    /// Получить идентификатор устройства.
+    ///
+    fn id(&self) -> Uuid;
+
+    ///
+    /// Получить имя устройства.
+    ///
+    fn name(&self) -> &str;
+}
+
+///
+/// Типаж, описывающий модель устройства: его состояние и обработку
+/// событий без ввода-вывода. Синхронные и асинхронные устройства
+/// реализуются поверх модели адаптерами.
+///
+pub trait Model: Component + Send + Sync + 'static {
+    ///
+    /// Обработать событие устройством.
+    ///
+    fn handle(&mut self, e: &dyn Event) -> Result<DeviceState, ModelError>;
+
+    ///
+    /// Получить описание возможностей устройства.
+    ///
+    fn capabilities(&self) -> DeviceCapabilities;
+}
+
+impl<M: Model> From<M> for Box<dyn Model> {
+    ///
+    /// Упаковать модель устройства для размещения в комнате.
+    ///
+    #[inline]
+    fn from(model: M) -> Self {
+        Box::new(model)
+    }
+}
+
+///
+/// Событие, для получение текущего состояния устройства.
+///
+pub struct StateEvent {}
+
+impl Event for StateEvent {
+    ///
+    /// Получить идентификатор класса события.
+    ///
+    fn id(&self) -> Uuid {
+        Self::ID
+    }
+
+    ///
+    /// Получить представление события для передачи по сети.
+    ///
+    fn data(&self) -> Option<EventData> {
+        Some(EventData::State)
+    }
+}
+
+impl Default for StateEvent {
+    ///
+    /// Экземпляр события по умолчанию.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateEvent {
+    ///
+    /// Идентификатор класса события.
+    ///
+    pub const ID: Uuid = uuid::uuid!("c346ee2a-4cd1-4e46-8ca7-5b329721187e");
+
+    ///
+    /// Создать событие, для получения текущего состояния устройства.
+    ///
+    #[inline]
+    pub fn new() -> Self {
+        Self {}
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::{
+    device::{
+        Component, DeviceCapabilities, DeviceState, Event, Measurement, Model, SensorKind,
+        StateEvent, Value,
+    },
+    error::ModelError,
+};
+
+///
+/// Структура, описывающая взаимодействие с "умным" датчиком: датчиком
+/// влажности, углекислого газа, движения или открытия двери.
+///
+#[derive(Debug)]
+pub struct SmartSensor {
+    ///
+    /// Идентификатор "умного" датчика.
+    ///
+    id: Uuid,
+
+    ///
+    /// Имя "умного" датчика.
+    ///
+    name: String,
+
+    ///
+    /// Вид "умного" датчика.
+    ///
+    kind: SensorKind,
+
+    ///
+    /// Текущее показание датчика.
+    ///
+    value: Value,
+}
+
+impl fmt::Display for SmartSensor {
+    ///
+    /// Получить информацию об "умном" датчике с помощью форматирования.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} \"{}\" ({}). Показание: {}.",
+            title(self.kind),
+            self.name,
+            self.id,
+            self.measurement()
+        )
+    }
+}
+
+impl Component for SmartSensor {
+    ///
+    /// Получить идентификатор "умного" датчика.
+    ///
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    ///
+    /// Получить имя "умного" датчика.
+    ///
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+impl Model for SmartSensor {
+    ///
+    /// Обработать событие устройством. Датчик только сообщает свое
+    /// показание.
+    ///
+    fn handle(&mut self, e: &dyn Event) -> Result<DeviceState, ModelError> {
+        if !e.is::<StateEvent>() {
+            return Err(ModelError::NotImplementedEvent(e.id()));
+        }
+
+        Ok(DeviceState::new(self.id, e.id()).with_measurement(self.measurement()))
+    }
+
+    ///
+    /// Получить описание возможностей устройства.
+    ///
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::for_sensor(self.kind)
+    }
+}
+
+impl SmartSensor {
+    ///
+    /// Создать датчик заданного вида с заданным показанием.
+    ///
+    pub fn new<V: Into<Value>>(name: &str, kind: SensorKind, value: V) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            kind,
+            value: value.into(),
+        }
+    }
+
+    ///
+    /// Получить вид датчика.
+    ///
+    #[inline]
+    pub fn kind(&self) -> SensorKind {
+        self.kind
+    }
+
+    ///
+    /// Получить текущее показание датчика.
+    ///
+    #[inline]
+    pub fn value(&self) -> Value {
+        self.value
+    }
+
+    ///
+    /// Установить текущее показание датчика.
+    ///
+    #[inline]
+    pub fn set_value<V: Into<Value>>(&mut self, value: V) {
+        self.value = value.into();
+    }
+
+    ///
+    /// Получить текущее показание датчика как результат измерения.
+    ///
+    #[inline]
+    pub fn measurement(&self) -> Measurement {
+        (self.kind.quantity(), self.value).into()
+    }
+}
+
+///
+/// Получить название датчика заданного вида для вывода информации
+/// о датчике.
+///
+pub fn title(kind: SensorKind) -> &'static str {
+    match kind {
+        SensorKind::Temperature => "умный термометр",
+        SensorKind::Humidity => "умный датчик влажности",
+        SensorKind::CarbonDioxide => "умный датчик CO2",
+        SensorKind::Motion => "умный датчик движения",
+        SensorKind::Contact => "умный датчик открытия",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::device::{socket::SwitchOnEvent, EventKind, Quantity, Unit};
+
+    use super::*;
+
+    #[test]
+    fn smart_sensor_test() {
+        let mut sensor = SmartSensor::new("Humidity1", SensorKind::Humidity, 45.0);
+        let state = sensor.handle(&StateEvent::new()).unwrap();
+        let measurement = state.measurement(Quantity::Humidity).unwrap();
+        assert_eq!(measurement.value(), Value::Number(45.0));
+        assert_eq!(measurement.unit(), Unit::Percent);
+        assert_eq!(sensor.capabilities().events(), [EventKind::State]);
+        assert!(sensor.to_string().contains("Показание: 45 %"));
+
+        let mut door = SmartSensor::new("Door1", SensorKind::Contact, false);
+        door.set_value(true);
+        let state = door.handle(&StateEvent::new()).unwrap();
+        assert_eq!(
+            state.measurement(Quantity::Open).unwrap().value(),
+            Value::Flag(true)
+        );
+        assert!(matches!(
+            door.handle(&SwitchOnEvent::new()),
+            Err(ModelError::NotImplementedEvent(_))
+        ));
+    }
+}
@@ -0,0 +1,358 @@
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::{
+    device::{Component, DeviceCapabilities, DeviceState, Event, EventData, Model, StateEvent},
+    error::ModelError,
+};
+
+///
+/// Структура, описывающая взаимодействие с "умной" розеткой.
+///
+pub struct SmartSocket {
+    ///
+    /// Идентификатор "умной" розетки.
+    ///
+    id: Uuid,
+
+    ///
+    /// Имя "умной" розетки.
+    ///
+    name: String,
+
+    ///
+    /// Текущее состояние розетки.
+    ///
+    enabled: bool,
+
+    ///
+    /// Потребляемая мощность.
+    ///
+    power: f64,
+}
+
+impl fmt::Display for SmartSocket {
+    ///
+    /// Получить информацию об "умной" розетке с помощью форматирования.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut v = vec![format!(
+            "умная розетка \"{}\" ({}). Состояние: ",
+            self.name, self.id
+        )];
+
+        if self.enabled {
+            v.push(format!(
+                "включена, потребляемая мощность {} Вт.",
+                self.power
+            ));
+        } else {
+            v.push("выключена.".to_string());
+        }
+
+        write!(f, "{}", v.join(""))
+    }
+}
+
+impl Component for SmartSocket {
+    ///
+    /// Получить идентификатор "умной" розетки.
+    ///
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    ///
+    /// Получить имя "умной" розетки.
+    ///
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+impl Model for SmartSocket {
+    ///
+    /// Обработать событие устройством.
+    ///
+    fn handle(&mut self, e: &dyn Event) -> Result<DeviceState, ModelError> {
+        if e.is::<SwitchOnEvent>() {
+            self.switch_on();
+        } else if e.is::<SwitchOffEvent>() {
+            self.switch_off();
+        } else if let Some(event) = e.downcast_ref::<SetLoadEvent>() {
+            let watts = event.watts();
+            if !watts.is_finite() || watts < 0.0 {
+                return Err(ModelError::InvalidArgument(format!(
+                    "load {} W must be a finite non-negative number",
+                    watts
+                )));
+            }
+            self.plug(watts);
+        } else if !e.is::<StateEvent>() {
+            return Err(ModelError::NotImplementedEvent(e.id()));
+        }
+
+        Ok(DeviceState::for_socket(
+            self.id,
+            e.id(),
+            self.enabled,
+            self.power(),
+        ))
+    }
+
+    ///
+    /// Получить описание возможностей устройства.
+    ///
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::for_socket()
+    }
+}
+
+impl SmartSocket {
+    ///
+    /// Создать "умную" розетку в выключенном состоянии.
+    ///
+    #[inline]
+    pub fn new(name: &str) -> Self {
+        SmartSocket {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            enabled: false,
+            power: 0.0,
+        }
+    }
+
+    ///
+    /// Включить "умную" розетку.
+    ///
+    #[inline]
+    pub fn switch_on(&mut self) {
+        self.enabled = true;
+    }
+
+    ///
+    /// Выключить "умную" розетку.
+    ///
+    #[inline]
+    pub fn switch_off(&mut self) {
+        self.enabled = false;
+    }
+
+    ///
+    /// Проверить, включена ли "умная" розетка.
+    ///
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    ///
+    /// Получить текущее значение потребляемой мощности.
+    ///
+    pub fn power(&self) -> Option<f64> {
+        if self.enabled {
+            Some(self.power)
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// Подключить нагрузку с заданной мощностью.
+    ///
+    pub fn plug(&mut self, power: f64) {
+        self.power = power;
+    }
+}
+
+///
+/// Событие, для включения "умной" розетки.
+///
+pub struct SwitchOnEvent {}
+
+impl Event for SwitchOnEvent {
+    ///
+    /// Получить идентификатор класса события.
+    ///
+    fn id(&self) -> Uuid {
+        Self::ID
+    }
+
+    ///
+    /// Получить представление события для передачи по сети.
+    ///
+    fn data(&self) -> Option<EventData> {
+        Some(EventData::SwitchOn)
+    }
+}
+
+impl Default for SwitchOnEvent {
+    ///
+    /// Экземпляр события по умолчанию.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SwitchOnEvent {
+    ///
+    /// Идентификатор класса события.
+    ///
+    pub const ID: Uuid = uuid::uuid!("56848c21-6600-48d9-a50a-9a0f83486408");
+
+    ///
+    /// Создать событие, для для включения "умной" розетки.
+    ///
+    #[inline]
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+///
+/// Событие, для выключения "умной" розетки.
+///
+pub struct SwitchOffEvent {}
+
+impl Event for SwitchOffEvent {
+    ///
+    /// Получить идентификатор класса события.
+    ///
+    fn id(&self) -> Uuid {
+        Self::ID
+    }
+
+    ///
+    /// Получить представление события для передачи по сети.
+    ///
+    fn data(&self) -> Option<EventData> {
+        Some(EventData::SwitchOff)
+    }
+}
+
+impl Default for SwitchOffEvent {
+    ///
+    /// Экземпляр события по умолчанию.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SwitchOffEvent {
+    ///
+    /// Идентификатор класса события.
+    ///
+    pub const ID: Uuid = uuid::uuid!("4ca18a36-38e0-410a-9c71-ccf4f109ebd4");
+
+    ///
+    /// Создать событие, для для включения "умной" розетки.
+    ///
+    #[inline]
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+///
+/// Событие, для подключения к "умной" розетке нагрузки заданной мощности.
+///
+pub struct SetLoadEvent {
+    // Мощность нагрузки, Вт.
+    watts: f64,
+}
+
+impl Event for SetLoadEvent {
+    ///
+    /// Получить идентификатор класса события.
+    ///
+    fn id(&self) -> Uuid {
+        Self::ID
+    }
+
+    ///
+    /// Получить представление события для передачи по сети.
+    ///
+    fn data(&self) -> Option<EventData> {
+        Some(EventData::SetLoad { watts: self.watts })
+    }
+}
+
+impl SetLoadEvent {
+    ///
+    /// Идентификатор класса события.
+    ///
+    pub const ID: Uuid = uuid::uuid!("0e1f6f3c-2b8e-4d5a-9a43-6f1f2d7c8b90");
+
+    ///
+    /// Создать событие, для подключения нагрузки заданной мощности.
+    ///
+    #[inline]
+    pub fn new(watts: f64) -> Self {
+        Self { watts }
+    }
+
+    ///
+    /// Получить мощность нагрузки.
+    ///
+    #[inline]
+    pub fn watts(&self) -> f64 {
+        self.watts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smart_socket_test() {
+        let mut socket1 = SmartSocket::new("Socket1");
+        assert_eq!(socket1.name.as_str(), "Socket1");
+        assert!(!socket1.enabled);
+        assert_eq!(socket1.power, 0.0);
+
+        socket1.switch_on();
+        assert!(socket1.enabled);
+
+        socket1.plug(1000.0);
+        assert_eq!(socket1.power, 1000.0);
+
+        socket1.switch_off();
+        assert!(!socket1.enabled);
+
+        let state = socket1.handle(&SwitchOnEvent::new()).unwrap();
+        assert_eq!(state.enabled(), Some(true));
+        assert_eq!(state.power(), Some(1000.0));
+
+        let event: Box<dyn Event> = EventData::SetLoad { watts: 500.0 }.into();
+        assert!(event.is::<SetLoadEvent>());
+        assert_eq!(event.downcast_ref::<SetLoadEvent>().unwrap().watts(), 500.0);
+        let state = socket1.handle(&*event).unwrap();
+        assert_eq!(state.power(), Some(500.0));
+        assert_eq!(state.event_id(), SetLoadEvent::ID);
+        for watts in [f64::NAN, f64::INFINITY, -1.0] {
+            assert!(matches!(
+                socket1.handle(&SetLoadEvent::new(watts)),
+                Err(ModelError::InvalidArgument(_))
+            ));
+        }
+        assert_eq!(socket1.power, 500.0);
+        assert!(matches!(
+            socket1.handle(&TestEvent {}),
+            Err(ModelError::NotImplementedEvent(_))
+        ));
+    }
+
+    struct TestEvent {}
+
+    impl Event for TestEvent {
+        fn id(&self) -> Uuid {
+            Uuid::nil()
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::{
+    device::{Component, DeviceCapabilities, DeviceState, Event, EventData, Model, StateEvent},
+    error::ModelError,
+};
+
+///
+/// Структура, описывающая взаимодействие с "умным" термометром.
+///
+#[derive(Debug)]
+pub struct SmartThermometer {
+    ///
+    /// Идентификатор "умного" термометра.
+    ///
+    id: Uuid,
+
+    ///
+    /// Имя "умного" термометра.
+    ///
+    name: String,
+
+    ///
+    /// Текущее значение температуры.
+    ///
+    temperature: f64,
+}
+
+impl fmt::Display for SmartThermometer {
+    ///
+    /// Получить информацию об "умном" термометре с помощью форматирования.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "умный термометр \"{}\" ({}). Температура: {} °C.",
+            self.name, self.id, self.temperature
+        )
+    }
+}
+
+impl Component for SmartThermometer {
+    ///
+    /// Получить идентификатор "умного" термометра.
+    ///
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    ///
+    /// Получить имя "умного" термометра.
+    ///
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+impl Model for SmartThermometer {
+    ///
+    /// Обработать событие устройством.
+    ///
+    fn handle(&mut self, e: &dyn Event) -> Result<DeviceState, ModelError> {
+        if let Some(event) = e.downcast_ref::<SetTemperatureEvent>() {
+            let celsius = event.celsius();
+            if !celsius.is_finite() {
+                return Err(ModelError::InvalidArgument(format!(
+                    "temperature {} °C must be a finite number",
+                    celsius
+                )));
+            }
+            self.temperature = celsius;
+        } else if !e.is::<StateEvent>() {
+            return Err(ModelError::NotImplementedEvent(e.id()));
+        }
+
+        Ok(DeviceState::for_thermometer(
+            self.id,
+            e.id(),
+            self.temperature,
+        ))
+    }
+
+    ///
+    /// Получить описание возможностей устройства.
+    ///
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::for_thermometer()
+    }
+}
+
+impl SmartThermometer {
+    ///
+    /// Создать термометр с заданным значением температуры.
+    ///
+    pub fn new(name: &str, temperature: f64) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            temperature,
+        }
+    }
+
+    ///
+    /// Получить текущее значение температуры.
+    ///
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+}
+
+///
+/// Событие, для установки значения температуры "умного" термометра.
+///
+pub struct SetTemperatureEvent {
+    // Значение температуры, °C.
+    celsius: f64,
+}
+
+impl Event for SetTemperatureEvent {
+    ///
+    /// Получить идентификатор класса события.
+    ///
+    fn id(&self) -> Uuid {
+        Self::ID
+    }
+
+    ///
+    /// Получить представление события для передачи по сети.
+    ///
+    fn data(&self) -> Option<EventData> {
+        Some(EventData::SetTemperature {
+            celsius: self.celsius,
+        })
+    }
+}
+
+impl SetTemperatureEvent {
+    ///
+    /// Идентификатор класса события.
+    ///
+    pub const ID: Uuid = uuid::uuid!("9d4b2a61-5c7e-4f08-b3d2-8e6a1c0f4d27");
+
+    ///
+    /// Создать событие, для установки значения температуры.
+    ///
+    #[inline]
+    pub fn new(celsius: f64) -> Self {
+        Self { celsius }
+    }
+
+    ///
+    /// Получить значение температуры.
+    ///
+    #[inline]
+    pub fn celsius(&self) -> f64 {
+        self.celsius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smart_thermometer_test() {
+        let mut thermometer1 = SmartThermometer::new("Thermometer1", 20.0);
+        assert_eq!(thermometer1.name.as_str(), "Thermometer1");
+        assert_eq!(thermometer1.temperature, 20.0);
+
+        let state = thermometer1.handle(&StateEvent::new()).unwrap();
+        assert_eq!(state.device_id(), thermometer1.id);
+        assert_eq!(state.themperature(), Some(20.0));
+
+        let state = thermometer1
+            .handle(&SetTemperatureEvent::new(22.5))
+            .unwrap();
+        assert_eq!(state.themperature(), Some(22.5));
+        assert_eq!(thermometer1.temperature(), 22.5);
+
+        for celsius in [f64::NAN, f64::NEG_INFINITY] {
+            assert!(matches!(
+                thermometer1.handle(&SetTemperatureEvent::new(celsius)),
+                Err(ModelError::InvalidArgument(_))
+            ));
+        }
+        assert_eq!(thermometer1.temperature(), 22.5);
+    }
+}
@@ -0,0 +1,507 @@
+use uuid::Uuid;
+
+use smarthome2_protocol::{
+    capability::DeviceCapabilities,
+    event::EventData,
+    message::{ControlRequestData, ControlResponse, DeviceSelector, Selector},
+    state::DeviceState,
+};
+
+use crate::{
+    device::Component,
+    error::ModelError,
+    house::{DeviceInfo, RoomGetter, SmartHouse},
+    room::{DeviceGetter, SmartRoom},
+};
+
+///
+/// Типаж устройства, которым можно управлять запросами клиентов.
+///
+pub trait Managed: Component {
+    ///
+    /// Получить описание возможностей устройства.
+    ///
+    fn capabilities(&self) -> DeviceCapabilities;
+}
+
+///
+/// Действие, необходимое для завершения обработки запроса.
+///
+#[derive(Debug)]
+pub enum Action {
+    ///
+    /// Запрос выполнен, ответ готов к отправке клиенту.
+    ///
+    Respond(ControlResponse),
+
+    ///
+    /// Передать событие устройству с заданными идентификаторами комнаты
+    /// и устройства; ответом служит новое состояние устройства.
+    ///
+    Notify(Uuid, Uuid, EventData),
+}
+
+///
+/// Выполнить запрос над заблокированным экземпляром "умного" дома без
+/// обращения к устройствам. Запросы, требующие передачи события
+/// устройству, возвращают действие `Action::Notify`. Запросы на
+/// добавление устройств, пакеты и подписки выполняются сервером.
+///
+pub fn execute<D>(
+    house: &mut SmartHouse<D>,
+    data: &ControlRequestData,
+) -> Result<Action, ModelError>
+where
+    D: ?Sized + Managed,
+{
+    let response = match *data {
+        ControlRequestData::AcquireRooms => house.rooms().collect(),
+
+        ControlRequestData::AcquireDevices(ref room) => find_room(house, room)?.devices().collect(),
+
+        ControlRequestData::AcquireDeviceState(ref selector) => {
+            let (room_id, device_id) = locate(house, selector)?;
+            return Ok(Action::Notify(room_id, device_id, EventData::State));
+        }
+
+        ControlRequestData::AcquireDeviceInfo(ref selector) => {
+            let (room_id, device_id) = locate(house, selector)?;
+            ControlResponse::with_info(house.info(room_id, device_id)?)
+        }
+
+        ControlRequestData::AcquireDeviceCapabilities(ref selector) => {
+            let (room_id, device_id) = locate(house, selector)?;
+            ControlResponse::with_capabilities(capabilities(house, room_id, device_id)?)
+        }
+
+        ControlRequestData::SwitchOnDevice(ref selector) => {
+            let (room_id, device_id) = locate(house, selector)?;
+            return Ok(Action::Notify(room_id, device_id, EventData::SwitchOn));
+        }
+
+        ControlRequestData::SwitchOffDevice(ref selector) => {
+            let (room_id, device_id) = locate(house, selector)?;
+            return Ok(Action::Notify(room_id, device_id, EventData::SwitchOff));
+        }
+
+        ControlRequestData::NotifyDevice(ref selector, event) => {
+            let (room_id, device_id) = locate(house, selector)?;
+            return Ok(Action::Notify(room_id, device_id, event));
+        }
+
+        ControlRequestData::CreateRoom(ref name) => create_room(house, name)?,
+
+        ControlRequestData::DeleteRoom(room_id) => delete_room(house, room_id)?,
+
+        ControlRequestData::RenameRoom(room_id, ref name) => rename_room(house, room_id, name)?,
+
+        ControlRequestData::DetachDevice(room_id, device_id) => {
+            detach_device(house, room_id, device_id)?
+        }
+
+        _ => return Err(ModelError::UnexpectedMessage),
+    };
+
+    Ok(Action::Respond(response))
+}
+
+///
+/// Найти комнату по идентификатору или имени.
+///
+pub fn find_room<'a, D: ?Sized>(
+    house: &'a SmartHouse<D>,
+    room: &Selector,
+) -> Result<&'a SmartRoom<D>, ModelError> {
+    match room {
+        Selector::Id(room_id) => house
+            .get(*room_id)
+            .ok_or(ModelError::IllegalRoomId(*room_id)),
+        Selector::Name(room_name) => house
+            .get(room_name.as_str())
+            .ok_or_else(|| ModelError::IllegalRoomName(room_name.clone())),
+    }
+}
+
+///
+/// Получить идентификаторы комнаты и устройства, выбранного по
+/// идентификаторам, именам или пути.
+///
+pub fn locate<D: ?Sized + Component>(
+    house: &SmartHouse<D>,
+    selector: &DeviceSelector,
+) -> Result<(Uuid, Uuid), ModelError> {
+    let (room, device) = selector.parts()?;
+    let room_ref = find_room(house, &room)?;
+    match room_ref
+        .devices()
+        .find(|&(id, name)| device.matches(id, name))
+    {
+        Some((device_id, _)) => Ok((room_ref.id(), device_id)),
+        None => Err(match device {
+            Selector::Id(device_id) => ModelError::IllegalDeviceId(device_id),
+            Selector::Name(device_name) => ModelError::IllegalDeviceName(device_name),
+        }),
+    }
+}
+
+///
+/// Получить описание возможностей устройства в комнате.
+///
+pub fn capabilities<D: ?Sized + Managed>(
+    house: &SmartHouse<D>,
+    room_id: Uuid,
+    device_id: Uuid,
+) -> Result<DeviceCapabilities, ModelError> {
+    let room = house
+        .get(room_id)
+        .ok_or(ModelError::IllegalRoomId(room_id))?;
+    room.get(device_id)
+        .map(|device_ref| device_ref.capabilities())
+        .ok_or(ModelError::IllegalDeviceId(device_id))
+}
+
+///
+/// Создать комнату с заданным именем. Имя должно быть непустым и не
+/// совпадать с именами других комнат.
+///
+pub fn create_room<D: ?Sized>(
+    house: &mut SmartHouse<D>,
+    name: &str,
+) -> Result<ControlResponse, ModelError> {
+    check_name(name)?;
+    if name.is_empty() || house.get(name).is_some() {
+        return Err(ModelError::IllegalRoomName(name.to_owned()));
+    }
+
+    let room = SmartRoom::new(name);
+    let room_id = room.id();
+    *house += room;
+
+    Ok(ControlResponse::with_name(room_id, name))
+}
+
+///
+/// Удалить комнату вместе с ее устройствами.
+///
+pub fn delete_room<D: ?Sized>(
+    house: &mut SmartHouse<D>,
+    room_id: Uuid,
+) -> Result<ControlResponse, ModelError> {
+    if house.get(room_id).is_none() {
+        return Err(ModelError::IllegalRoomId(room_id));
+    }
+
+    *house -= room_id;
+
+    Ok(ControlResponse::done())
+}
+
+///
+/// Переименовать комнату. Новое имя должно быть непустым и не
+/// совпадать с именами других комнат.
+///
+pub fn rename_room<D: ?Sized>(
+    house: &mut SmartHouse<D>,
+    room_id: Uuid,
+    name: &str,
+) -> Result<ControlResponse, ModelError> {
+    if house.get(room_id).is_none() {
+        return Err(ModelError::IllegalRoomId(room_id));
+    }
+    check_name(name)?;
+    if name.is_empty() || house.get(name).is_some_and(|room| room.id() != room_id) {
+        return Err(ModelError::IllegalRoomName(name.to_owned()));
+    }
+
+    if let Some(room) = house.get_mut(room_id) {
+        room.rename(name);
+    }
+
+    Ok(ControlResponse::with_name(room_id, name))
+}
+
+///
+/// Добавить созданное устройство в комнату. Имя устройства должно
+/// быть непустым и не совпадать с именами других устройств комнаты.
+///
+pub fn insert_device<D: ?Sized + Component>(
+    house: &mut SmartHouse<D>,
+    room_id: Uuid,
+    device: Box<D>,
+) -> Result<ControlResponse, ModelError> {
+    let (device_id, name) = (device.id(), device.name().to_owned());
+    check_name(&name)?;
+    let room = house
+        .get_mut(room_id)
+        .ok_or(ModelError::IllegalRoomId(room_id))?;
+    if name.is_empty() || room.devices().any(|(_, n)| n == name) {
+        return Err(ModelError::IllegalDeviceName(name));
+    }
+
+    *room += device;
+
+    Ok(ControlResponse::with_name(device_id, name))
+}
+
+///
+/// Удалить устройство из комнаты.
+///
+pub fn detach_device<D: ?Sized + Component>(
+    house: &mut SmartHouse<D>,
+    room_id: Uuid,
+    device_id: Uuid,
+) -> Result<ControlResponse, ModelError> {
+    let room = house
+        .get_mut(room_id)
+        .ok_or(ModelError::IllegalRoomId(room_id))?;
+    if room.devices().all(|(id, _)| id != device_id) {
+        return Err(ModelError::IllegalDeviceId(device_id));
+    }
+
+    *room -= device_id;
+
+    Ok(ControlResponse::done())
+}
+
+///
+/// Проверить, что комнату или устройство с заданным именем можно будет
+/// выбрать по пути "комната/устройство".
+///
+pub fn check_name(name: &str) -> Result<(), ModelError> {
+    if Selector::is_selectable(name) {
+        Ok(())
+    } else {
+        Err(ModelError::InvalidArgument(format!(
+            "name \"{}\" must not contain '/' or be an identifier",
+            name
+        )))
+    }
+}
+
+///
+/// Пакет запросов, выполняемых по принципу "все или ничего". Пакет может
+/// содержать только запросы на получение сведений об устройствах и на их
+/// включение и выключение, так как откатить удается только переключение
+/// устройств.
+///
+/// Сервер получает запросы пакета методом [`Transaction::next_request`],
+/// перед переключением устройства сообщает его прежнее состояние методом
+/// [`Transaction::save`] и передает ответ на запрос методом
+/// [`Transaction::complete`]. При ошибке выполнение пакета прекращается,
+/// и сервер возвращает переключенные устройства в прежнее состояние,
+/// передавая им события из [`Transaction::rollback`].
+///
+#[derive(Debug)]
+pub struct Transaction<'a> {
+    items: &'a [ControlRequestData],
+    saved: Vec<(Uuid, Uuid, bool)>,
+    results: Vec<ControlResponse>,
+    failed: bool,
+}
+
+impl<'a> Transaction<'a> {
+    ///
+    /// Начать выполнение пакета запросов. Для пакета, содержащего запросы,
+    /// изменения от которых невозможно откатить, возвращает
+    /// `ModelError::UnexpectedMessage`.
+    ///
+    pub fn new(items: &'a [ControlRequestData]) -> Result<Self, ModelError> {
+        let reversible = items.iter().all(|item| {
+            matches!(
+                item,
+                ControlRequestData::AcquireDeviceState(..)
+                    | ControlRequestData::AcquireDeviceInfo(..)
+                    | ControlRequestData::SwitchOnDevice(..)
+                    | ControlRequestData::SwitchOffDevice(..)
+            )
+        });
+        if !reversible {
+            return Err(ModelError::UnexpectedMessage);
+        }
+
+        Ok(Self {
+            items,
+            saved: Vec::new(),
+            results: Vec::with_capacity(items.len()),
+            failed: false,
+        })
+    }
+
+    ///
+    /// Получить следующий запрос пакета. Возвращает `None` после
+    /// выполнения всех запросов или после ошибки.
+    ///
+    pub fn next_request(&self) -> Option<&'a ControlRequestData> {
+        if self.failed {
+            None
+        } else {
+            self.items.get(self.results.len())
+        }
+    }
+
+    ///
+    /// Проверить, требуется ли сохранить состояние устройства перед
+    /// выполнением действия.
+    ///
+    pub fn needs_state(&self, action: &Action) -> bool {
+        match *action {
+            Action::Notify(_, device_id, EventData::SwitchOn | EventData::SwitchOff) => {
+                self.saved.iter().all(|&(_, id, _)| id != device_id)
+            }
+            _ => false,
+        }
+    }
+
+    ///
+    /// Запомнить состояние устройства до его первого переключения пакетом.
+    ///
+    pub fn save(&mut self, room_id: Uuid, state: &DeviceState) {
+        let device_id = state.device_id();
+        if let Some(enabled) = state.enabled() {
+            if self.saved.iter().all(|&(_, id, _)| id != device_id) {
+                self.saved.push((room_id, device_id, enabled));
+            }
+        }
+    }
+
+    ///
+    /// Сохранить ответ на очередной запрос пакета. Возвращает `false`,
+    /// если запрос завершился ошибкой и пакет требуется откатить.
+    ///
+    pub fn complete(&mut self, response: ControlResponse) -> bool {
+        self.failed = response.error().is_some();
+        self.results.push(response);
+        !self.failed
+    }
+
+    ///
+    /// Получить события, возвращающие переключенные пакетом устройства
+    /// в прежнее состояние, в порядке, обратном переключению. Если
+    /// выполнение пакета не завершилось ошибкой, событий нет.
+    ///
+    pub fn rollback(&self) -> impl Iterator<Item = (Uuid, Uuid, EventData)> + '_ {
+        self.saved
+            .iter()
+            .rev()
+            .filter(|_| self.failed)
+            .map(|&(room_id, device_id, enabled)| {
+                let event = if enabled {
+                    EventData::SwitchOn
+                } else {
+                    EventData::SwitchOff
+                };
+                (room_id, device_id, event)
+            })
+    }
+
+    ///
+    /// Получить ответ со списком результатов выполненных запросов пакета.
+    ///
+    pub fn into_response(self) -> ControlResponse {
+        ControlResponse::with_results(self.results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use smarthome2_protocol::message::{ErrorCode, RemoteError};
+
+    use crate::device::{socket::SmartSocket, thermometer::SmartThermometer, Model};
+
+    use super::*;
+
+    impl Managed for dyn Model {
+        fn capabilities(&self) -> DeviceCapabilities {
+            Model::capabilities(self)
+        }
+    }
+
+    fn house() -> SmartHouse {
+        let mut room = SmartRoom::new("Room1");
+        room += SmartSocket::new("Socket1");
+        room += SmartThermometer::new("Thermometer1", 20.0);
+        let mut house = SmartHouse::new("House1");
+        house += room;
+        house
+    }
+
+    #[test]
+    fn execute_test() {
+        let mut house = house();
+        let (room_id, _) = house.rooms().next().unwrap();
+
+        let action = execute(&mut house, &ControlRequestData::AcquireRooms).unwrap();
+        assert!(matches!(action, Action::Respond(r) if r.list().unwrap().len() == 1));
+
+        let selector = DeviceSelector::from("Room1/Socket1");
+        let (_, socket_id) = locate(&house, &selector).unwrap();
+        let action = execute(&mut house, &ControlRequestData::SwitchOnDevice(selector)).unwrap();
+        assert!(matches!(
+            action,
+            Action::Notify(r, d, EventData::SwitchOn) if r == room_id && d == socket_id
+        ));
+
+        assert_eq!(
+            locate(&house, &DeviceSelector::from("Room1")),
+            Err(ModelError::IllegalDevicePath("Room1".to_owned()))
+        );
+        assert_eq!(
+            execute(&mut house, &ControlRequestData::SubscribeAll).unwrap_err(),
+            ModelError::UnexpectedMessage
+        );
+
+        assert!(matches!(
+            create_room(&mut house, "Room/2"),
+            Err(ModelError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            rename_room(&mut house, room_id, ""),
+            Err(ModelError::IllegalRoomName(_))
+        ));
+        assert!(matches!(
+            insert_device(&mut house, room_id, Box::new(SmartSocket::new("Socket1"))),
+            Err(ModelError::IllegalDeviceName(_))
+        ));
+    }
+
+    #[test]
+    fn transaction_test() {
+        let mut house = house();
+        let (room_id, _) = house.rooms().next().unwrap();
+        let items = [
+            ControlRequestData::SwitchOnDevice("Room1/Socket1".into()),
+            ControlRequestData::SwitchOnDevice("Room1/Socket2".into()),
+        ];
+        let mut transaction = Transaction::new(&items).unwrap();
+
+        let item = transaction.next_request().unwrap();
+        let action = execute(&mut house, item).unwrap();
+        assert!(transaction.needs_state(&action));
+        let Action::Notify(_, socket_id, _) = action else {
+            panic!("unexpected action {:?}", action);
+        };
+        transaction.save(
+            room_id,
+            &DeviceState::for_socket(socket_id, Uuid::nil(), false, None),
+        );
+        assert!(transaction.complete(ControlResponse::done()));
+        assert!(!transaction.needs_state(&action));
+
+        let item = transaction.next_request().unwrap();
+        let error = execute(&mut house, item).unwrap_err().to_string();
+        let error = RemoteError::new(ErrorCode::IllegalDeviceName("Socket2".to_owned()), error);
+        assert!(!transaction.complete(ControlResponse::with_error(error)));
+        assert!(transaction.next_request().is_none());
+        assert_eq!(
+            transaction.rollback().collect::<Vec<_>>(),
+            [(room_id, socket_id, EventData::SwitchOff)]
+        );
+        assert_eq!(transaction.into_response().results().unwrap().len(), 2);
+
+        let items = [ControlRequestData::CreateRoom("Room2".to_owned())];
+        assert_eq!(
+            Transaction::new(&items).unwrap_err(),
+            ModelError::UnexpectedMessage
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use thiserror::Error;
+use uuid::Uuid;
+
+use smarthome2_protocol::error::SelectorError;
+
+///
+/// Ошибка модели "умного" дома, не связанная с вводом-выводом.
+///
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ModelError {
+    #[error("illegal room name \"{0}\"")]
+    IllegalRoomName(String),
+
+    #[error("illegal device name \"{0}\"")]
+    IllegalDeviceName(String),
+
+    #[error("illegal room identifier {0}")]
+    IllegalRoomId(Uuid),
+
+    #[error("illegal device identifier {0}")]
+    IllegalDeviceId(Uuid),
+
+    #[error("illegal device path \"{0}\"")]
+    IllegalDevicePath(String),
+
+    #[error("the event {0} is not implemented")]
+    NotImplementedEvent(Uuid),
+
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("unexpected message")]
+    UnexpectedMessage,
+}
+
+impl From<SelectorError> for ModelError {
+    ///
+    /// Преобразовать ошибку разбора селектора устройства.
+    ///
+    fn from(error: SelectorError) -> Self {
+        match error {
+            SelectorError::IllegalDevicePath(path) => Self::IllegalDevicePath(path),
+        }
+    }
+}
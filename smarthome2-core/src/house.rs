@@ -0,0 +1,331 @@
+use std::collections::LinkedList;
+use std::{fmt, iter, ops};
+
+use uuid::Uuid;
+
+use crate::device::{Component, Model};
+use crate::error::ModelError;
+use crate::room::{DeviceGetter, SmartRoom};
+
+///
+/// Типаж, позволяющий получить комнату "умного" дома.
+///
+pub trait RoomGetter<T> {
+    type Output;
+
+    ///
+    /// Получить ссылку на комнату "умного" дома.
+    ///
+    fn get(&self, idx: T) -> Option<&Self::Output>;
+
+    ///
+    /// Получить изменяемую ссылку на комнату "умного" дома.
+    ///
+    fn get_mut(&mut self, idx: T) -> Option<&mut Self::Output>;
+}
+
+///
+/// Типаж, описывающий получение информации об устройстве.
+///
+pub trait DeviceInfo<U, V> {
+    ///
+    /// Получить текстовую информацию об устройстве.
+    ///
+    fn info(&self, idx1: U, idx2: V) -> Result<String, ModelError>;
+}
+
+///
+/// Структура, описывающая "умный" дом. Параметр `D` задает тип
+/// устройств в комнатах дома.
+///
+pub struct SmartHouse<D: ?Sized = dyn Model> {
+    ///
+    /// Идентификатор "умного" дома.
+    ///
+    id: Uuid,
+
+    ///
+    /// Наименование "умного" дома.
+    ///
+    name: String,
+
+    ///
+    /// Список комнат "умного" дома.
+    ///
+    rooms: LinkedList<SmartRoom<D>>,
+}
+
+impl<D: ?Sized + Component> fmt::Display for SmartHouse<D> {
+    ///
+    /// Получить информацию об "умном" доме и его устойствах
+    /// с помощью форматирования.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut v = vec![format!("Умный дом \"{}\" ({}):", self.name, self.id)];
+        for (idx, room) in self.rooms.iter().enumerate() {
+            v.push(format!("{}. {}", idx + 1, *room));
+        }
+
+        write!(f, "{}", v.join("\n\n"))
+    }
+}
+
+impl<D: ?Sized> ops::AddAssign<SmartRoom<D>> for SmartHouse<D> {
+    ///
+    /// Добавить комнату в "умный" дом.
+    ///
+    fn add_assign(&mut self, room: SmartRoom<D>) {
+        if self.rooms.iter().all(|item| item.name() != room.name()) {
+            self.rooms.push_back(room);
+        }
+    }
+}
+
+impl<D: ?Sized> ops::SubAssign<Uuid> for SmartHouse<D> {
+    ///
+    /// Удалить комнату с заданным идентификаторм из "умного" дома.
+    ///
+    fn sub_assign(&mut self, room_id: Uuid) {
+        let mut rooms: LinkedList<SmartRoom<D>> = LinkedList::new();
+        while let Some(room) = self.rooms.pop_back() {
+            if room.id() != room_id {
+                rooms.push_front(room);
+            }
+        }
+
+        self.rooms = rooms;
+    }
+}
+
+impl<D: ?Sized> ops::SubAssign<&str> for SmartHouse<D> {
+    ///
+    /// Удалить комнату с заданным именем из "умного" дома.
+    ///
+    fn sub_assign(&mut self, room_name: &str) {
+        let mut rooms: LinkedList<SmartRoom<D>> = LinkedList::new();
+        while let Some(room) = self.rooms.pop_back() {
+            if room.name() != room_name {
+                rooms.push_front(room);
+            }
+        }
+
+        self.rooms = rooms;
+    }
+}
+
+impl<D: ?Sized> RoomGetter<Uuid> for SmartHouse<D> {
+    type Output = SmartRoom<D>;
+
+    ///
+    /// Получить ссылку на комнату "умного" дома по ее идентификатору.
+    ///
+    fn get(&self, room_id: Uuid) -> Option<&Self::Output> {
+        self.rooms.iter().find(|room_ref| room_ref.id() == room_id)
+    }
+
+    ///
+    /// Получить изменяемую ссылку на комнату "умного" дома по ее идентификатору.
+    ///
+    fn get_mut(&mut self, room_id: Uuid) -> Option<&mut Self::Output> {
+        self.rooms
+            .iter_mut()
+            .find(|room_ref| room_ref.id() == room_id)
+    }
+}
+
+impl<D: ?Sized> RoomGetter<&str> for SmartHouse<D> {
+    type Output = SmartRoom<D>;
+
+    ///
+    /// Получить ссылку на комнату "умного" дома по ее имени.
+    ///
+    fn get(&self, room_name: &str) -> Option<&Self::Output> {
+        self.rooms
+            .iter()
+            .find(|room_ref| room_ref.name() == room_name)
+    }
+
+    ///
+    /// Получить изменяемую ссылку на комнату "умного" дома по ее имени.
+    ///
+    fn get_mut(&mut self, room_name: &str) -> Option<&mut Self::Output> {
+        self.rooms
+            .iter_mut()
+            .find(|room_ref| room_ref.name() == room_name)
+    }
+}
+
+impl<D: ?Sized + Component> DeviceInfo<Uuid, Uuid> for SmartHouse<D> {
+    ///
+    /// Получить информацию об устройстве по идентификатору комнаты
+    /// и идентификатору устройства.
+    ///
+    fn info(&self, room_id: Uuid, device_id: Uuid) -> Result<String, ModelError> {
+        let room = RoomGetter::get(self, room_id).ok_or(ModelError::IllegalRoomId(room_id))?;
+        room.get(device_id)
+            .map(|device_ref| device_ref.to_string())
+            .ok_or(ModelError::IllegalDeviceId(device_id))
+    }
+}
+
+impl<D: ?Sized + Component> DeviceInfo<Uuid, &str> for SmartHouse<D> {
+    ///
+    /// Получить информацию об устройстве по идентификатору комнаты
+    /// и имени устройства.
+    ///
+    fn info(&self, room_id: Uuid, device_name: &str) -> Result<String, ModelError> {
+        let room = RoomGetter::get(self, room_id).ok_or(ModelError::IllegalRoomId(room_id))?;
+        room.get(device_name)
+            .map(|device_ref| device_ref.to_string())
+            .ok_or_else(|| ModelError::IllegalDeviceName(device_name.to_owned()))
+    }
+}
+
+impl<D: ?Sized + Component> DeviceInfo<&str, Uuid> for SmartHouse<D> {
+    ///
+    /// Получить информацию об устройстве по имени комнаты
+    /// и идентификатору устройства.
+    ///
+    fn info(&self, room_name: &str, device_id: Uuid) -> Result<String, ModelError> {
+        let room = RoomGetter::get(self, room_name)
+            .ok_or_else(|| ModelError::IllegalRoomName(room_name.to_owned()))?;
+        room.get(device_id)
+            .map(|device_ref| device_ref.to_string())
+            .ok_or(ModelError::IllegalDeviceId(device_id))
+    }
+}
+
+impl<D: ?Sized + Component> DeviceInfo<&str, &str> for SmartHouse<D> {
+    ///
+    /// Получить информацию об устройстве по имени комнаты
+    /// и имени устройства.
+    ///
+    fn info(&self, room_name: &str, device_name: &str) -> Result<String, ModelError> {
+        let room = RoomGetter::get(self, room_name)
+            .ok_or_else(|| ModelError::IllegalRoomName(room_name.to_owned()))?;
+        room.get(device_name)
+            .map(|device_ref| device_ref.to_string())
+            .ok_or_else(|| ModelError::IllegalDeviceName(device_name.to_owned()))
+    }
+}
+
+impl<D: ?Sized> SmartHouse<D> {
+    ///
+    /// Создать "умный" дом с заданным именем.
+    ///
+    pub fn new(name: &str) -> Self {
+        SmartHouse {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            rooms: LinkedList::new(),
+        }
+    }
+
+    ///
+    /// Получить идентификатор "умного" дома.
+    ///
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    ///
+    /// Получить имя "умного" дома.
+    ///
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    ///
+    /// Запросить список идентификаторов и имен всех помещений.
+    ///
+    pub fn rooms(&self) -> impl iter::Iterator<Item = (Uuid, &str)> {
+        self.rooms.iter().map(|room| (room.id(), room.name()))
+    }
+
+    ///
+    /// Получить неизменяемый итератор для перебора всех комнат.
+    ///
+    pub fn iter(&self) -> impl iter::Iterator<Item = &SmartRoom<D>> {
+        self.rooms.iter()
+    }
+
+    ///
+    /// Получить изменяемый итератор для перебора всех комнат.
+    ///
+    pub fn iter_mut(&mut self) -> impl iter::Iterator<Item = &mut SmartRoom<D>> {
+        self.rooms.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::device::{socket::SmartSocket, thermometer::SmartThermometer};
+
+    use super::*;
+
+    #[test]
+    fn smart_house_test() {
+        let mut house1: SmartHouse = SmartHouse::new("House1");
+        assert_eq!(house1.name, "House1");
+        assert_eq!(house1.rooms.len(), 0);
+
+        let room1 = SmartRoom::new("Room1");
+        let room1_id = room1.id();
+        house1 += room1;
+        assert_eq!(house1.rooms.len(), 1);
+
+        let room2 = SmartRoom::new("Room2");
+        let room2_id = room2.id();
+        house1 += room2;
+        assert_eq!(house1.rooms.len(), 2);
+
+        let room2_ex = SmartRoom::new("Room2");
+        house1 += room2_ex;
+        assert_eq!(house1.rooms.len(), 2);
+
+        for ((id1, name1), (id2, name2)) in house1
+            .rooms()
+            .zip([(room1_id, "Room1"), (room2_id, "Room2")])
+        {
+            assert_eq!(id1, id2);
+            assert_eq!(name1, name2);
+        }
+
+        house1 -= room1_id;
+        assert_eq!(house1.rooms.len(), 1);
+        for ((id1, name1), (id2, name2)) in house1.rooms().zip([(room2_id, "Room2")]) {
+            assert_eq!(id1, id2);
+            assert_eq!(name1, name2);
+        }
+
+        house1 -= "Room2";
+        assert_eq!(house1.rooms.len(), 0);
+    }
+
+    #[test]
+    fn device_info_test() {
+        let mut room1: SmartRoom = SmartRoom::new("Room1");
+        let socket1 = SmartSocket::new("Socket1");
+        let socket1_id = socket1.id();
+        room1 += socket1;
+        room1 += SmartThermometer::new("Thermometer1", 20.0);
+        let room1_id = room1.id();
+
+        let mut house1 = SmartHouse::new("House1");
+        house1 += room1;
+
+        assert!(house1
+            .info(room1_id, socket1_id)
+            .unwrap()
+            .contains("Socket1"));
+        assert!(house1.info("Room1", "Thermometer1").unwrap().contains("20"));
+        assert_eq!(
+            house1.info("Room1", "Mixer"),
+            Err(ModelError::IllegalDeviceName("Mixer".to_owned()))
+        );
+        assert_eq!(
+            house1.info("Room2", socket1_id),
+            Err(ModelError::IllegalRoomName("Room2".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub mod device;
+pub mod dispatch;
+pub mod error;
+pub mod house;
+pub mod room;
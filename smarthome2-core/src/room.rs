@@ -0,0 +1,267 @@
+use std::collections::LinkedList;
+use std::{fmt, iter, ops};
+
+use uuid::Uuid;
+
+use crate::device::{Component, Model};
+
+///
+/// Типаж, позволяющий получить устройство комнаты "умного" дома.
+///
+pub trait DeviceGetter<T> {
+    type Output: ?Sized;
+
+    ///
+    /// Получить ссылку на устройство комнаты.
+    ///
+    fn get(&self, idx: T) -> Option<&Self::Output>;
+
+    ///
+    /// Получить изменяемую ссылку на устройство комнаты.
+    ///
+    fn get_mut(&mut self, idx: T) -> Option<&mut Self::Output>;
+}
+
+///
+/// Структура, описывающая комнату "умного" дома. Параметр `D` задает
+/// тип устройств комнаты: модели устройств, синхронные или асинхронные
+/// устройства.
+///
+pub struct SmartRoom<D: ?Sized = dyn Model> {
+    ///
+    /// Идентификатор комнаты "умного" дома.
+    ///
+    id: Uuid,
+
+    ///
+    /// Наименование комнаты "умного" дома.
+    ///
+    name: String,
+
+    ///
+    /// Список устройств комнаты "умного" дома.
+    ///
+    devices: LinkedList<Box<D>>,
+}
+
+impl<D: ?Sized + Component> fmt::Display for SmartRoom<D> {
+    ///
+    /// Получить информацию о комнате "умного" дома и ее устойствах
+    /// с помощью форматирования.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut v = vec![format!(
+            "Комната \"{}\" ({}). Устройства: ",
+            self.name, self.id
+        )];
+        for device_ref in self.devices.iter() {
+            v.push(format!("\t- {}", *device_ref));
+        }
+
+        write!(f, "{}", v.join("\n"))
+    }
+}
+
+impl<D: ?Sized + Component, T: Into<Box<D>>> ops::AddAssign<T> for SmartRoom<D> {
+    ///
+    /// Добавить устройство для комнаты "умного" дома.
+    ///
+    fn add_assign(&mut self, device: T) {
+        let device = device.into();
+        if self.devices.iter().all(|item| item.name() != device.name()) {
+            self.devices.push_back(device);
+        }
+    }
+}
+
+impl<D: ?Sized + Component> ops::SubAssign<Uuid> for SmartRoom<D> {
+    ///
+    /// Удалить устройство с заданным идентификатором.
+    ///
+    fn sub_assign(&mut self, device_id: Uuid) {
+        let mut devices: LinkedList<Box<D>> = LinkedList::new();
+        while let Some(device_ref) = self.devices.pop_back() {
+            if device_ref.id() != device_id {
+                devices.push_front(device_ref);
+            }
+        }
+
+        self.devices = devices;
+    }
+}
+
+impl<D: ?Sized + Component> ops::SubAssign<&str> for SmartRoom<D> {
+    ///
+    /// Удалить устройство с заданным именем.
+    ///
+    fn sub_assign(&mut self, device_name: &str) {
+        let mut devices: LinkedList<Box<D>> = LinkedList::new();
+        while let Some(device_ref) = self.devices.pop_back() {
+            if device_ref.name() != device_name {
+                devices.push_front(device_ref);
+            }
+        }
+
+        self.devices = devices;
+    }
+}
+
+impl<D: ?Sized + Component> DeviceGetter<Uuid> for SmartRoom<D> {
+    type Output = D;
+
+    ///
+    /// Получить ссылку на устройство по его идентификатору.
+    ///
+    fn get(&self, device_id: Uuid) -> Option<&D> {
+        self.devices
+            .iter()
+            .find(|device_ref| device_ref.id() == device_id)
+            .map(|device_ref| &**device_ref)
+    }
+
+    ///
+    /// Получить изменяемую ссылку на устройство по его идентификатору.
+    ///
+    fn get_mut(&mut self, device_id: Uuid) -> Option<&mut D> {
+        self.devices
+            .iter_mut()
+            .find(|device_ref| device_ref.id() == device_id)
+            .map(|device_ref| &mut **device_ref)
+    }
+}
+
+impl<D: ?Sized + Component> DeviceGetter<&str> for SmartRoom<D> {
+    type Output = D;
+
+    ///
+    /// Получить ссылку на устройство по его имени.
+    ///
+    fn get(&self, device_name: &str) -> Option<&D> {
+        self.devices
+            .iter()
+            .find(|device_ref| device_ref.name() == device_name)
+            .map(|device_ref| &**device_ref)
+    }
+
+    ///
+    /// Получить изменяемую ссылку на устройство по его имени.
+    ///
+    fn get_mut(&mut self, device_name: &str) -> Option<&mut D> {
+        self.devices
+            .iter_mut()
+            .find(|device_ref| device_ref.name() == device_name)
+            .map(|device_ref| &mut **device_ref)
+    }
+}
+
+impl<D: ?Sized> SmartRoom<D> {
+    ///
+    ///Создать комнату "умного" дома с заданным именем.
+    ///
+    pub fn new(name: &str) -> Self {
+        SmartRoom {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            devices: LinkedList::new(),
+        }
+    }
+
+    ///
+    /// Получить идентификатор комнаты "умного" дома.
+    ///
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    ///
+    /// Получить имя комнаты "умного" дома.
+    ///
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    ///
+    /// Переименовать комнату "умного" дома.
+    ///
+    pub fn rename(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    ///
+    /// Получить неизменяемый итератор для перебора всех устройств.
+    ///
+    pub fn iter(&self) -> impl iter::Iterator<Item = &D> {
+        self.devices.iter().map(|device_ref| &**device_ref)
+    }
+
+    ///
+    /// Получить изменяемый итератор для перебора всех устройств.
+    ///
+    pub fn iter_mut(&mut self) -> impl iter::Iterator<Item = &mut D> {
+        self.devices.iter_mut().map(|device_ref| &mut **device_ref)
+    }
+}
+
+impl<D: ?Sized + Component> SmartRoom<D> {
+    ///
+    /// Запросить список идентификаторов и имен всех устройств.
+    ///
+    pub fn devices(&self) -> impl iter::Iterator<Item = (Uuid, &str)> {
+        self.devices
+            .iter()
+            .map(|device| (device.id(), device.name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::device::socket::SmartSocket;
+    use crate::device::thermometer::SmartThermometer;
+
+    use super::*;
+
+    #[test]
+    fn smart_room_test() {
+        let mut room1: SmartRoom = SmartRoom::new("Room1");
+        assert_eq!(room1.name.as_str(), "Room1");
+        assert_eq!(room1.devices.len(), 0);
+
+        let socket1 = SmartSocket::new("Socket1");
+        let socket1_id = socket1.id();
+        room1 += socket1;
+        assert_eq!(room1.devices.len(), 1);
+
+        let thermometer1 = SmartThermometer::new("Thermometer1", 20.0);
+        let thermometer1_id = thermometer1.id();
+        room1 += thermometer1;
+        assert_eq!(room1.devices.len(), 2);
+
+        for ((id1, name1), (id2, name2)) in room1
+            .devices()
+            .zip([(socket1_id, "Socket1"), (thermometer1_id, "Thermometer1")])
+        {
+            assert_eq!(id1, id2);
+            assert_eq!(name1, name2);
+        }
+
+        assert_eq!(room1.get("Socket1").unwrap().id(), socket1_id);
+        assert_eq!(
+            room1.get_mut(thermometer1_id).unwrap().name(),
+            "Thermometer1"
+        );
+        assert!(room1.get(Uuid::new_v4()).is_none());
+
+        room1 -= thermometer1_id;
+        assert_eq!(room1.devices.len(), 1);
+        for ((id1, name1), (id2, name2)) in room1.devices().zip([(socket1_id, "Socket1")]) {
+            assert_eq!(id1, id2);
+            assert_eq!(name1, name2);
+        }
+
+        room1 -= "Socket1";
+        assert_eq!(room1.devices.len(), 0);
+
+        room1.rename("Room2");
+        assert_eq!(room1.name(), "Room2");
+    }
+}
@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+
+use crate::measurement::Quantity;
+
+///
+/// Вид устройства.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeviceKind {
+    ///
+    /// "Умная" розетка.
+    ///
+    Socket,
+
+    ///
+    /// "Умный" термометр.
+    ///
+    Thermometer,
+
+    ///
+    /// "Умная" лампа.
+    ///
+    Lamp,
+
+    ///
+    /// Датчик влажности.
+    ///
+    HumiditySensor,
+
+    ///
+    /// Датчик углекислого газа.
+    ///
+    CarbonDioxideSensor,
+
+    ///
+    /// Датчик движения.
+    ///
+    MotionSensor,
+
+    ///
+    /// Датчик открытия двери или окна.
+    ///
+    ContactSensor,
+}
+
+///
+/// Вид датчика, сообщающего показания по UDP.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SensorKind {
+    ///
+    /// Датчик температуры.
+    ///
+    Temperature,
+
+    ///
+    /// Датчик влажности.
+    ///
+    Humidity,
+
+    ///
+    /// Датчик углекислого газа.
+    ///
+    CarbonDioxide,
+
+    ///
+    /// Датчик движения.
+    ///
+    Motion,
+
+    ///
+    /// Датчик открытия двери или окна.
+    ///
+    Contact,
+}
+
+impl SensorKind {
+    ///
+    /// Получить величину, измеряемую датчиком.
+    ///
+    pub fn quantity(&self) -> Quantity {
+        match self {
+            Self::Temperature => Quantity::Temperature,
+            Self::Humidity => Quantity::Humidity,
+            Self::CarbonDioxide => Quantity::CarbonDioxide,
+            Self::Motion => Quantity::Motion,
+            Self::Contact => Quantity::Open,
+        }
+    }
+
+    ///
+    /// Получить вид устройства, которым является датчик.
+    ///
+    pub fn device_kind(&self) -> DeviceKind {
+        match self {
+            Self::Temperature => DeviceKind::Thermometer,
+            Self::Humidity => DeviceKind::HumiditySensor,
+            Self::CarbonDioxide => DeviceKind::CarbonDioxideSensor,
+            Self::Motion => DeviceKind::MotionSensor,
+            Self::Contact => DeviceKind::ContactSensor,
+        }
+    }
+
+    ///
+    /// Проверить, сообщает ли датчик логическое значение вместо
+    /// числового.
+    ///
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Self::Motion | Self::Contact)
+    }
+}
+
+///
+/// Вид события, обрабатываемого устройством.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventKind {
+    ///
+    /// Получение текущего состояния устройства.
+    ///
+    State,
+
+    ///
+    /// Включение устройства.
+    ///
+    SwitchOn,
+
+    ///
+    /// Выключение устройства.
+    ///
+    SwitchOff,
+
+    ///
+    /// Подключение нагрузки заданной мощности.
+    ///
+    SetLoad,
+
+    ///
+    /// Установка температуры.
+    ///
+    SetTemperature,
+
+    ///
+    /// Установка яркости свечения.
+    ///
+    SetBrightness,
+
+    ///
+    /// Установка цветовой температуры свечения.
+    ///
+    SetColorTemperature,
+}
+
+///
+/// Описание возможностей устройства: вид устройства, обрабатываемые
+/// события и сообщаемые величины.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceCapabilities {
+    // Вид устройства.
+    kind: DeviceKind,
+
+    // События, обрабатываемые устройством.
+    events: Vec<EventKind>,
+
+    // Величины, сообщаемые устройством.
+    measurements: Vec<Quantity>,
+}
+
+impl DeviceCapabilities {
+    ///
+    /// Создать описание устройства заданного вида, которое обрабатывает
+    /// только запрос текущего состояния.
+    ///
+    #[inline]
+    pub fn new(kind: DeviceKind) -> Self {
+        Self {
+            kind,
+            events: vec![EventKind::State],
+            measurements: Vec::new(),
+        }
+    }
+
+    ///
+    /// Получить описание возможностей "умной" розетки.
+    ///
+    pub fn for_socket() -> Self {
+        Self::new(DeviceKind::Socket)
+            .with_event(EventKind::SwitchOn)
+            .with_event(EventKind::SwitchOff)
+            .with_event(EventKind::SetLoad)
+            .with_measurement(Quantity::Enabled)
+            .with_measurement(Quantity::Power)
+    }
+
+    ///
+    /// Получить описание возможностей "умного" термометра.
+    ///
+    pub fn for_thermometer() -> Self {
+        Self::new(DeviceKind::Thermometer)
+            .with_event(EventKind::SetTemperature)
+            .with_measurement(Quantity::Temperature)
+    }
+
+    ///
+    /// Получить описание возможностей "умной" лампы. Установка цветовой
+    /// температуры поддерживается не всеми лампами.
+    ///
+    pub fn for_lamp(color_temperature: bool) -> Self {
+        let capabilities = Self::new(DeviceKind::Lamp)
+            .with_event(EventKind::SwitchOn)
+            .with_event(EventKind::SwitchOff)
+            .with_event(EventKind::SetBrightness)
+            .with_measurement(Quantity::Enabled)
+            .with_measurement(Quantity::Brightness);
+        if color_temperature {
+            capabilities
+                .with_event(EventKind::SetColorTemperature)
+                .with_measurement(Quantity::ColorTemperature)
+        } else {
+            capabilities
+        }
+    }
+
+    ///
+    /// Получить описание возможностей датчика заданного вида. Датчик
+    /// только сообщает свои показания.
+    ///
+    #[inline]
+    pub fn for_sensor(kind: SensorKind) -> Self {
+        Self::new(kind.device_kind()).with_measurement(kind.quantity())
+    }
+
+    ///
+    /// Добавить событие, обрабатываемое устройством.
+    ///
+    pub fn with_event(mut self, event: EventKind) -> Self {
+        if !self.events.contains(&event) {
+            self.events.push(event);
+        }
+        self
+    }
+
+    ///
+    /// Добавить величину, сообщаемую устройством.
+    ///
+    pub fn with_measurement(mut self, quantity: Quantity) -> Self {
+        if !self.measurements.contains(&quantity) {
+            self.measurements.push(quantity);
+        }
+        self
+    }
+
+    ///
+    /// Получить вид устройства.
+    ///
+    #[inline]
+    pub fn kind(&self) -> DeviceKind {
+        self.kind
+    }
+
+    ///
+    /// Получить события, обрабатываемые устройством.
+    ///
+    #[inline]
+    pub fn events(&self) -> &[EventKind] {
+        &self.events
+    }
+
+    ///
+    /// Получить величины, сообщаемые устройством.
+    ///
+    #[inline]
+    pub fn measurements(&self) -> &[Quantity] {
+        &self.measurements
+    }
+
+    ///
+    /// Проверить, обрабатывает ли устройство события заданного вида.
+    ///
+    #[inline]
+    pub fn accepts(&self, event: EventKind) -> bool {
+        self.events.contains(&event)
+    }
+
+    ///
+    /// Проверить, сообщает ли устройство заданную величину.
+    ///
+    #[inline]
+    pub fn reports(&self, quantity: Quantity) -> bool {
+        self.measurements.contains(&quantity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{event::EventData, measurement::Unit};
+
+    use super::*;
+
+    #[test]
+    fn capabilities_test() {
+        let socket = DeviceCapabilities::for_socket();
+        assert_eq!(socket.kind(), DeviceKind::Socket);
+        assert!(socket.accepts(EventKind::State));
+        assert!(socket.accepts(EventData::SetLoad { watts: 1.0 }.kind()));
+        assert!(!socket.accepts(EventKind::SetTemperature));
+        assert!(socket.reports(Quantity::Power));
+
+        let thermometer = DeviceCapabilities::for_thermometer().with_event(EventKind::State);
+        assert_eq!(
+            thermometer.events(),
+            [EventKind::State, EventKind::SetTemperature]
+        );
+        assert_eq!(thermometer.measurements(), [Quantity::Temperature]);
+        assert!(!thermometer.accepts(EventKind::SwitchOn));
+
+        let motion = DeviceCapabilities::for_sensor(SensorKind::Motion);
+        assert_eq!(motion.kind(), DeviceKind::MotionSensor);
+        assert_eq!(motion.events(), [EventKind::State]);
+        assert_eq!(motion.measurements(), [Quantity::Motion]);
+        assert!(SensorKind::Contact.is_binary());
+        assert_eq!(SensorKind::Humidity.quantity().unit(), Unit::Percent);
+    }
+}
@@ -0,0 +1,264 @@
+use std::fmt;
+
+use bincode::{self, Options};
+use serde::{de, Serialize};
+
+use crate::{
+    error::{RecvError, SendError},
+    handshake::Capabilities,
+};
+
+///
+/// Способ кодирования данных сообщений. Выбирается при установке
+/// соединения из кодеков, поддерживаемых обеими сторонами.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Codec {
+    ///
+    /// Двоичное кодирование bincode (используется по умолчанию).
+    ///
+    Bincode,
+
+    ///
+    /// Текстовое кодирование JSON.
+    ///
+    Json,
+
+    ///
+    /// Двоичное кодирование MessagePack.
+    ///
+    MessagePack,
+
+    ///
+    /// Двоичное кодирование CBOR.
+    ///
+    Cbor,
+}
+
+impl fmt::Display for Codec {
+    ///
+    /// Выполнить форматирование названия кодека.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Bincode => "bincode",
+            Self::Json => "json",
+            Self::MessagePack => "msgpack",
+            Self::Cbor => "cbor",
+        };
+
+        f.write_str(name)
+    }
+}
+
+impl Default for Codec {
+    ///
+    /// Кодек по умолчанию.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self::Bincode
+    }
+}
+
+impl Codec {
+    ///
+    /// Получить список всех кодеков в порядке предпочтения при
+    /// согласовании.
+    ///
+    #[inline]
+    pub fn all() -> &'static [Self] {
+        &[Self::Bincode, Self::MessagePack, Self::Cbor, Self::Json]
+    }
+
+    ///
+    /// Получить возможность протокола, соответствующую кодеку.
+    ///
+    #[inline]
+    pub const fn capability(&self) -> Capabilities {
+        match self {
+            Self::Bincode => Capabilities::BINCODE,
+            Self::Json => Capabilities::JSON,
+            Self::MessagePack => Capabilities::MESSAGE_PACK,
+            Self::Cbor => Capabilities::CBOR,
+        }
+    }
+
+    ///
+    /// Выбрать наиболее предпочтительный кодек из набора возможностей.
+    ///
+    pub fn select(capabilities: Capabilities) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|c| capabilities.contains(c.capability()))
+    }
+
+    ///
+    /// Закодировать значение.
+    ///
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, SendError> {
+        let codec_error = |e: &dyn fmt::Display| SendError::Codec(*self, e.to_string());
+
+        match self {
+            Self::Bincode => Ok(bincode::options().with_big_endian().serialize(value)?),
+            Self::Json => serde_json::to_vec(value).map_err(|e| codec_error(&e)),
+            Self::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| codec_error(&e)),
+            Self::Cbor => {
+                let mut data = Vec::new();
+                ciborium::ser::into_writer(value, &mut data).map_err(|e| codec_error(&e))?;
+                Ok(data)
+            }
+        }
+    }
+
+    ///
+    /// Декодировать значение.
+    ///
+    pub fn decode<T: de::DeserializeOwned>(&self, data: &[u8]) -> Result<T, RecvError> {
+        let codec_error = |e: &dyn fmt::Display| RecvError::Codec(*self, e.to_string());
+
+        match self {
+            Self::Bincode => Ok(bincode::options().with_big_endian().deserialize(data)?),
+            Self::Json => serde_json::from_slice(data).map_err(|e| codec_error(&e)),
+            Self::MessagePack => rmp_serde::from_slice(data).map_err(|e| codec_error(&e)),
+            Self::Cbor => ciborium::de::from_reader(data).map_err(|e| codec_error(&e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        capability::SensorKind,
+        envelope::Envelope,
+        measurement::Quantity,
+        message::{
+            ControlRequest, ControlResponse, DeviceSpec, ErrorCode, RemoteError, SensorMessage,
+            TextMessage,
+        },
+        state::DeviceState,
+        Message,
+    };
+
+    // Проверить, что сообщение не изменяется после кодирования и
+    // декодирования.
+    fn assert_roundtrip<M>(codec: Codec, message: M)
+    where
+        M: Message + Serialize + de::DeserializeOwned + Debug + Clone,
+    {
+        let envelope = Envelope::seal_with(codec, message.clone()).unwrap();
+        let decoded = envelope.open::<M>().unwrap();
+        assert_eq!(
+            format!("{:?}", decoded),
+            format!("{:?}", message),
+            "{}",
+            codec
+        );
+    }
+
+    #[test]
+    fn roundtrip_test() {
+        let (room_id, device_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let socket = DeviceState::for_socket(device_id, Uuid::new_v4(), true, Some(220.5));
+        let thermometer = DeviceState::for_thermometer(device_id, Uuid::new_v4(), -3.25);
+
+        for &codec in Codec::all() {
+            assert_roundtrip(codec, ControlRequest::acquire_rooms());
+            assert_roundtrip(codec, ControlRequest::acquire_devices(room_id));
+            assert_roundtrip(
+                codec,
+                ControlRequest::switch_on_device((room_id, device_id)),
+            );
+            assert_roundtrip(codec, ControlRequest::acquire_remote_device_state());
+            assert_roundtrip(codec, ControlRequest::subscribe((room_id, device_id)));
+            assert_roundtrip(codec, ControlRequest::subscribe_all());
+            assert_roundtrip(
+                codec,
+                ControlRequest::transaction([
+                    ControlRequest::switch_off_device("Room1/Socket1"),
+                    ControlRequest::switch_off_device(("Room1", "Socket2")),
+                ]),
+            );
+            assert_roundtrip(codec, ControlRequest::rename_room(room_id, "Room2"));
+            assert_roundtrip(
+                codec,
+                ControlRequest::attach_device(
+                    room_id,
+                    DeviceSpec::Thermometer {
+                        name: "Thermometer1".to_owned(),
+                        temperature: 18.5,
+                    },
+                ),
+            );
+            assert_roundtrip(
+                codec,
+                ControlRequest::attach_device(
+                    room_id,
+                    DeviceSpec::RemoteSensor {
+                        name: "Door1".to_owned(),
+                        kind: SensorKind::Contact,
+                        bind: "127.0.0.1:55336".to_owned(),
+                        address: "127.0.0.1:55337".to_owned(),
+                    },
+                ),
+            );
+
+            assert_roundtrip(
+                codec,
+                [(room_id, "Room1")]
+                    .into_iter()
+                    .collect::<ControlResponse>(),
+            );
+            assert_roundtrip(codec, ControlResponse::with_state(socket.clone()));
+            assert_roundtrip(codec, ControlResponse::with_state(thermometer.clone()));
+            assert_roundtrip(codec, ControlResponse::with_info("info"));
+            assert_roundtrip(codec, ControlResponse::with_name(device_id, "Socket1"));
+            assert_roundtrip(codec, ControlResponse::done());
+            assert_roundtrip(
+                codec,
+                ControlResponse::with_results(vec![
+                    ControlResponse::with_state(socket.clone()),
+                    ControlResponse::done(),
+                ]),
+            );
+            assert_roundtrip(
+                codec,
+                ControlResponse::with_notification(room_id, socket.clone()),
+            );
+            assert_roundtrip(
+                codec,
+                ControlResponse::with_error(RemoteError::new(
+                    ErrorCode::IllegalRoomId(room_id),
+                    "illegal room identifier",
+                )),
+            );
+
+            assert_roundtrip(codec, TextMessage::new("text"));
+            assert_roundtrip(
+                codec,
+                SensorMessage::new(device_id)
+                    .with_measurement((Quantity::Humidity, 45.0))
+                    .with_measurement((Quantity::Motion, true)),
+            );
+        }
+    }
+
+    #[test]
+    fn select_test() {
+        assert_eq!(
+            Codec::select(Capabilities::supported()),
+            Some(Codec::Bincode)
+        );
+        assert_eq!(
+            Codec::select(Capabilities::JSON | Capabilities::CBOR),
+            Some(Codec::Cbor)
+        );
+        assert_eq!(Codec::select(Capabilities::PUSH_NOTIFICATIONS), None);
+    }
+}
@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+pub const TEXT_MESSAGE_ID: u16 = 0xFFFF;
+pub const CONTROL_REQUEST_ID: u16 = 0x1;
+pub const CONTROL_RESPONSE_ID: u16 = 0x2;
+pub const SENSOR_MESSAGE_ID: u16 = 0x4;
+pub const HANDSHAKE_REQUEST_ID: u16 = 0x8;
+pub const HANDSHAKE_RESPONSE_ID: u16 = 0x10;
+
+pub const USER_MESSAGE_ID_MIN: u16 = 0x100;
+pub const USER_MESSAGE_ID_MAX: u16 = 0xFFFE;
+
+pub const NONCE_SIZE: usize = 32;
+pub const TAG_SIZE: usize = 32;
+
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = 1 << 20;
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const CLIENT_ROLE: &[u8] = b"smarthome2 client";
+pub const SERVER_ROLE: &[u8] = b"smarthome2 server";
@@ -0,0 +1,232 @@
+use std::{collections::HashMap, fmt};
+
+use serde::{de, Serialize};
+
+use crate::{
+    codec::Codec,
+    error::{RecvError, SendError},
+    Message,
+};
+
+///
+/// Конверт с сообщением произвольного типа. Хранит идентификатор типа
+/// и данные сообщения, позволяя получить сообщение, тип которого
+/// заранее неизвестен, и выбрать способ его обработки.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    message_type: u16,
+    codec: Codec,
+    data: Vec<u8>,
+}
+
+impl Envelope {
+    ///
+    /// Поместить в конверт сообщение, закодированное кодеком по умолчанию.
+    ///
+    #[inline]
+    pub fn seal<M: Message + Serialize>(message: M) -> Result<Self, SendError> {
+        Self::seal_with(Codec::default(), message)
+    }
+
+    ///
+    /// Поместить в конверт сообщение, закодированное заданным кодеком.
+    ///
+    pub fn seal_with<M: Message + Serialize>(codec: Codec, message: M) -> Result<Self, SendError> {
+        Ok(Self {
+            message_type: M::TYPE,
+            codec,
+            data: codec.encode(&message)?,
+        })
+    }
+
+    ///
+    /// Создать конверт из идентификатора типа и закодированных данных
+    /// сообщения.
+    ///
+    #[inline]
+    pub fn from_parts(message_type: u16, codec: Codec, data: Vec<u8>) -> Self {
+        Self {
+            message_type,
+            codec,
+            data,
+        }
+    }
+
+    ///
+    /// Получить идентификатор типа сообщения.
+    ///
+    #[inline]
+    pub fn message_type(&self) -> u16 {
+        self.message_type
+    }
+
+    ///
+    /// Получить кодек, которым закодированы данные сообщения.
+    ///
+    #[inline]
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    ///
+    /// Получить данные сообщения.
+    ///
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    ///
+    /// Проверить, содержит ли конверт сообщение заданного типа.
+    ///
+    #[inline]
+    pub fn is<M: Message>(&self) -> bool {
+        self.message_type == M::TYPE
+    }
+
+    ///
+    /// Извлечь сообщение заданного типа. Если в конверте сообщение
+    /// другого типа, возвращает `RecvError::BadType`.
+    ///
+    pub fn open<M: Message + de::DeserializeOwned>(&self) -> Result<Box<M>, RecvError> {
+        if !self.is::<M>() {
+            return Err(RecvError::BadType(self.message_type));
+        }
+
+        self.codec.decode(&self.data).map(Box::new)
+    }
+}
+
+// Обработчик сообщений одного типа.
+type Route<C, R> = Box<dyn Fn(&mut C, &Envelope) -> Result<R, RecvError> + Send + Sync>;
+
+///
+/// Таблица обработчиков сообщений, выбирающая обработчик по идентификатору
+/// типа сообщения в конверте. Обработчики получают изменяемый контекст
+/// (например, соединение для отправки ответа) и извлеченное сообщение.
+///
+/// Сторонние типы сообщений регистрируются так же, как и встроенные:
+/// достаточно реализовать для них `Message` с идентификатором из диапазона
+/// `USER_MESSAGE_ID_MIN..=USER_MESSAGE_ID_MAX` и добавить обработчик
+/// методом `with_route`.
+///
+pub struct Router<C, R = ()> {
+    routes: HashMap<u16, Route<C, R>>,
+}
+
+impl<C, R> fmt::Debug for Router<C, R> {
+    ///
+    /// Выполнить отладочное форматирование таблицы обработчиков.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut types: Vec<_> = self.routes.keys().collect();
+        types.sort();
+        f.debug_struct("Router").field("types", &types).finish()
+    }
+}
+
+impl<C, R> Default for Router<C, R> {
+    ///
+    /// Создать пустую таблицу обработчиков.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, R> Router<C, R> {
+    ///
+    /// Создать пустую таблицу обработчиков.
+    ///
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            routes: HashMap::new(),
+        }
+    }
+
+    ///
+    /// Зарегистрировать обработчик сообщений заданного типа.
+    ///
+    /// Паникует, если для типа с тем же идентификатором обработчик уже
+    /// зарегистрирован.
+    ///
+    pub fn with_route<M, F>(mut self, handler: F) -> Self
+    where
+        M: Message + de::DeserializeOwned + 'static,
+        F: Fn(&mut C, Box<M>) -> R + Send + Sync + 'static,
+    {
+        let route: Route<C, R> = Box::new(move |context, envelope| {
+            envelope
+                .open::<M>()
+                .map(|message| handler(context, message))
+        });
+        assert!(
+            self.routes.insert(M::TYPE, route).is_none(),
+            "message type {:#x} is already routed",
+            M::TYPE
+        );
+
+        self
+    }
+
+    ///
+    /// Проверить, зарегистрирован ли обработчик для сообщений с заданным
+    /// идентификатором типа.
+    ///
+    #[inline]
+    pub fn has_route(&self, message_type: u16) -> bool {
+        self.routes.contains_key(&message_type)
+    }
+
+    ///
+    /// Передать сообщение из конверта зарегистрированному для его типа
+    /// обработчику. Если обработчик не зарегистрирован, возвращает
+    /// `RecvError::BadType`.
+    ///
+    pub fn route(&self, context: &mut C, envelope: &Envelope) -> Result<R, RecvError> {
+        match self.routes.get(&envelope.message_type) {
+            Some(route) => route(context, envelope),
+            None => Err(RecvError::BadType(envelope.message_type)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        measurement::Quantity,
+        message::{SensorMessage, TextMessage},
+    };
+
+    #[test]
+    fn router_test() {
+        let router = Router::<Vec<String>>::new()
+            .with_route(|log, m: Box<TextMessage>| log.push(m.to_string()))
+            .with_route(|log, m: Box<SensorMessage>| {
+                log.push(format!("{:?}", m.value(Quantity::Temperature)))
+            });
+        assert!(router.has_route(TextMessage::TYPE));
+        assert!(router.has_route(SensorMessage::TYPE));
+
+        let mut log = Vec::new();
+        let text = Envelope::seal(TextMessage::new("hello")).unwrap();
+        assert!(text.is::<TextMessage>());
+        router.route(&mut log, &text).unwrap();
+        assert_eq!(log, ["hello"]);
+
+        assert!(matches!(
+            text.open::<SensorMessage>(),
+            Err(RecvError::BadType(TextMessage::TYPE))
+        ));
+
+        let router = Router::<Vec<String>>::new();
+        assert!(matches!(
+            router.route(&mut log, &text),
+            Err(RecvError::BadType(TextMessage::TYPE))
+        ));
+    }
+}
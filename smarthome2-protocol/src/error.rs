@@ -0,0 +1,84 @@
+use std::io;
+
+use bincode;
+use thiserror::Error;
+
+use crate::codec::Codec;
+
+///
+/// Ошибка разбора селектора устройства.
+///
+#[derive(Debug, Error)]
+pub enum SelectorError {
+    #[error("illegal device path \"{0}\"")]
+    IllegalDevicePath(String),
+}
+
+///
+/// Ошибка отпраки данных.
+///
+#[derive(Debug, Error)]
+pub enum SendError {
+    #[error("IO error: {0}")]
+    Io(io::Error),
+
+    #[error("binary error: {0}")]
+    Bin(#[from] bincode::Error),
+
+    #[error("{0} encoding error: {1}")]
+    Codec(Codec, String),
+
+    #[error("envelope encoded with {0} does not match the connection codec")]
+    CodecMismatch(Codec),
+
+    #[error("sending timed out")]
+    Timeout,
+}
+
+impl From<io::Error> for SendError {
+    ///
+    /// Преобразовать ошибку ввода-вывода, выделив истечение времени ожидания.
+    ///
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => Self::Timeout,
+            _ => Self::Io(e),
+        }
+    }
+}
+
+///
+/// Ошибка получения данных.
+///
+#[derive(Debug, Error)]
+pub enum RecvError {
+    #[error("IO error: {0}")]
+    Io(io::Error),
+
+    #[error("binary error: {0}")]
+    Bin(#[from] bincode::Error),
+
+    #[error("{0} decoding error: {1}")]
+    Codec(Codec, String),
+
+    #[error("bad message type {0}")]
+    BadType(u16),
+
+    #[error("frame size {0} exceeds the limit")]
+    FrameTooLarge(u32),
+
+    #[error("receiving timed out")]
+    Timeout,
+}
+
+impl From<io::Error> for RecvError {
+    ///
+    /// Преобразовать ошибку ввода-вывода, выделив истечение времени ожидания.
+    ///
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => Self::Timeout,
+            _ => Self::Io(e),
+        }
+    }
+}
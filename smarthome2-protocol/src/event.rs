@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+
+use crate::capability::EventKind;
+
+///
+/// Событие для устройства в виде, пригодном для передачи по сети.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EventData {
+    ///
+    /// Получение текущего состояния устройства.
+    ///
+    State,
+
+    ///
+    /// Включение устройства.
+    ///
+    SwitchOn,
+
+    ///
+    /// Выключение устройства.
+    ///
+    SwitchOff,
+
+    ///
+    /// Подключение нагрузки заданной мощности, Вт.
+    ///
+    SetLoad { watts: f64 },
+
+    ///
+    /// Установка температуры, °C.
+    ///
+    SetTemperature { celsius: f64 },
+
+    ///
+    /// Установка яркости свечения, %.
+    ///
+    SetBrightness { percent: u8 },
+
+    ///
+    /// Установка цветовой температуры свечения, K.
+    ///
+    SetColorTemperature { kelvin: u16 },
+}
+
+impl EventData {
+    ///
+    /// Проверить, изменяет ли событие состояние устройства.
+    ///
+    #[inline]
+    pub fn is_query(&self) -> bool {
+        matches!(self, Self::State)
+    }
+
+    ///
+    /// Получить вид события.
+    ///
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::State => EventKind::State,
+            Self::SwitchOn => EventKind::SwitchOn,
+            Self::SwitchOff => EventKind::SwitchOff,
+            Self::SetLoad { .. } => EventKind::SetLoad,
+            Self::SetTemperature { .. } => EventKind::SetTemperature,
+            Self::SetBrightness { .. } => EventKind::SetBrightness,
+            Self::SetColorTemperature { .. } => EventKind::SetColorTemperature,
+        }
+    }
+}
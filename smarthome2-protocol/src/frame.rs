@@ -0,0 +1,329 @@
+use serde::Serialize;
+
+use crate::{
+    codec::Codec,
+    consts::DEFAULT_MAX_FRAME_SIZE,
+    envelope::Envelope,
+    error::{RecvError, SendError},
+    Message,
+};
+
+///
+/// Размер заголовка кадра: идентификатор типа сообщения (2 байта) и
+/// размер данных (4 байта) в сетевом порядке байт.
+///
+pub const HEADER_SIZE: usize = 6;
+
+///
+/// Заголовок кадра, предшествующий данным сообщения.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    // Идентификатор типа сообщения.
+    message_type: u16,
+
+    // Размер данных сообщения.
+    size: u32,
+}
+
+impl FrameHeader {
+    ///
+    /// Создать заголовок кадра для сообщения заданного типа и размера.
+    ///
+    #[inline]
+    pub fn new(message_type: u16, size: u32) -> Self {
+        Self { message_type, size }
+    }
+
+    ///
+    /// Разобрать заголовок кадра. Если размер данных превышает заданный
+    /// предел, возвращает `RecvError::FrameTooLarge`.
+    ///
+    pub fn parse(bytes: &[u8; HEADER_SIZE], max_frame_size: u32) -> Result<Self, RecvError> {
+        let message_type = u16::from_be_bytes([bytes[0], bytes[1]]);
+        let size = u32::from_be_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]);
+        if size > max_frame_size {
+            return Err(RecvError::FrameTooLarge(size));
+        }
+
+        Ok(Self { message_type, size })
+    }
+
+    ///
+    /// Получить представление заголовка для передачи по сети.
+    ///
+    pub fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0u8; HEADER_SIZE];
+        bytes[..2].copy_from_slice(&self.message_type.to_be_bytes());
+        bytes[2..].copy_from_slice(&self.size.to_be_bytes());
+        bytes
+    }
+
+    ///
+    /// Получить идентификатор типа сообщения.
+    ///
+    #[inline]
+    pub fn message_type(&self) -> u16 {
+        self.message_type
+    }
+
+    ///
+    /// Получить размер данных сообщения.
+    ///
+    #[inline]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+}
+
+///
+/// Кодировщик и декодировщик кадров, не выполняющий ввода-вывода.
+/// Преобразует конверты с сообщениями в последовательность байт и
+/// обратно, оставляя чтение и запись данных вызывающей стороне.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameCodec {
+    // Кодек данных сообщений соединения.
+    codec: Codec,
+
+    // Максимальный размер данных одного сообщения.
+    max_frame_size: u32,
+}
+
+impl Default for FrameCodec {
+    ///
+    /// Кодировщик кадров с кодеком и ограничением размера по умолчанию.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self::new(Codec::default())
+    }
+}
+
+impl FrameCodec {
+    ///
+    /// Создать кодировщик кадров для сообщений, закодированных заданным
+    /// кодеком.
+    ///
+    #[inline]
+    pub fn new(codec: Codec) -> Self {
+        Self {
+            codec,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    ///
+    /// Ограничить размер данных одного получаемого сообщения.
+    ///
+    #[inline]
+    pub fn with_max_frame_size(self, max_frame_size: u32) -> Self {
+        Self {
+            max_frame_size,
+            ..self
+        }
+    }
+
+    ///
+    /// Получить кодек данных сообщений.
+    ///
+    #[inline]
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    ///
+    /// Получить максимальный размер данных одного сообщения.
+    ///
+    #[inline]
+    pub fn max_frame_size(&self) -> u32 {
+        self.max_frame_size
+    }
+
+    ///
+    /// Добавить в буфер кадр с сообщением из конверта. Конверт должен
+    /// быть закодирован кодеком соединения.
+    ///
+    pub fn encode(&self, envelope: &Envelope, dst: &mut Vec<u8>) -> Result<(), SendError> {
+        if envelope.codec() != self.codec {
+            return Err(SendError::CodecMismatch(envelope.codec()));
+        }
+
+        let data = envelope.data();
+        let header = FrameHeader::new(envelope.message_type(), data.len() as u32);
+        dst.reserve(HEADER_SIZE + data.len());
+        dst.extend_from_slice(&header.to_bytes());
+        dst.extend_from_slice(data);
+
+        Ok(())
+    }
+
+    ///
+    /// Закодировать сообщение и добавить в буфер кадр с ним.
+    ///
+    pub fn encode_message<M>(&self, message: M, dst: &mut Vec<u8>) -> Result<(), SendError>
+    where
+        M: Message + Serialize,
+    {
+        self.encode(&Envelope::seal_with(self.codec, message)?, dst)
+    }
+
+    ///
+    /// Извлечь из начала буфера очередной кадр. Если кадр получен не
+    /// полностью, возвращает `None`, оставляя буфер без изменений.
+    ///
+    pub fn decode(&self, src: &mut Vec<u8>) -> Result<Option<Envelope>, RecvError> {
+        Ok(self.decode_slice(src)?.map(|(envelope, consumed)| {
+            src.drain(..consumed);
+            envelope
+        }))
+    }
+
+    // Разобрать кадр в начале среза, вернув конверт и количество
+    // использованных байт.
+    fn decode_slice(&self, src: &[u8]) -> Result<Option<(Envelope, usize)>, RecvError> {
+        let header = match src.first_chunk::<HEADER_SIZE>() {
+            Some(bytes) => FrameHeader::parse(bytes, self.max_frame_size)?,
+            None => return Ok(None),
+        };
+
+        let end = HEADER_SIZE + header.size() as usize;
+        if src.len() < end {
+            return Ok(None);
+        }
+
+        let envelope = Envelope::from_parts(
+            header.message_type(),
+            self.codec,
+            src[HEADER_SIZE..end].to_vec(),
+        );
+
+        Ok(Some((envelope, end)))
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod framed {
+    use bytes::{Buf, BytesMut};
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use super::*;
+
+    impl Decoder for FrameCodec {
+        type Item = Envelope;
+        type Error = RecvError;
+
+        ///
+        /// Извлечь из буфера потока очередной кадр.
+        ///
+        fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Envelope>, RecvError> {
+            match self.decode_slice(src)? {
+                Some((envelope, consumed)) => {
+                    src.advance(consumed);
+                    Ok(Some(envelope))
+                }
+                None => {
+                    if let Some(bytes) = src.first_chunk::<HEADER_SIZE>() {
+                        let header = FrameHeader::parse(bytes, self.max_frame_size)?;
+                        src.reserve(HEADER_SIZE + header.size() as usize - src.len());
+                    }
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    impl Encoder<Envelope> for FrameCodec {
+        type Error = SendError;
+
+        ///
+        /// Добавить в буфер потока кадр с сообщением из конверта.
+        ///
+        #[inline]
+        fn encode(&mut self, envelope: Envelope, dst: &mut BytesMut) -> Result<(), SendError> {
+            Encoder::encode(self, &envelope, dst)
+        }
+    }
+
+    impl Encoder<&Envelope> for FrameCodec {
+        type Error = SendError;
+
+        ///
+        /// Добавить в буфер потока кадр с сообщением из конверта.
+        ///
+        fn encode(&mut self, envelope: &Envelope, dst: &mut BytesMut) -> Result<(), SendError> {
+            let mut frame = Vec::new();
+            FrameCodec::encode(self, envelope, &mut frame)?;
+            dst.extend_from_slice(&frame);
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        measurement::{Quantity, Value},
+        message::{SensorMessage, TextMessage},
+    };
+
+    #[test]
+    fn frame_codec_test() {
+        let codec = FrameCodec::new(Codec::Json);
+        let mut data = Vec::new();
+        codec
+            .encode_message(TextMessage::new("hello"), &mut data)
+            .unwrap();
+        codec
+            .encode_message(
+                SensorMessage::new(uuid::Uuid::nil())
+                    .with_measurement((Quantity::Temperature, 21.5)),
+                &mut data,
+            )
+            .unwrap();
+
+        // Данные поступают по одному байту.
+        let mut buf = Vec::new();
+        let mut envelopes = Vec::new();
+        for &byte in &data {
+            buf.push(byte);
+            if let Some(envelope) = codec.decode(&mut buf).unwrap() {
+                envelopes.push(envelope);
+            }
+        }
+        assert!(buf.is_empty());
+        assert_eq!(envelopes.len(), 2);
+        assert_eq!(
+            envelopes[0].open::<TextMessage>().unwrap().to_string(),
+            "hello"
+        );
+        assert_eq!(
+            envelopes[1]
+                .open::<SensorMessage>()
+                .unwrap()
+                .value(Quantity::Temperature),
+            Some(Value::Number(21.5))
+        );
+
+        let envelope = Envelope::seal(TextMessage::new("hello")).unwrap();
+        assert!(matches!(
+            codec.encode(&envelope, &mut Vec::new()),
+            Err(SendError::CodecMismatch(Codec::Bincode))
+        ));
+    }
+
+    #[test]
+    fn frame_size_test() {
+        let codec = FrameCodec::default().with_max_frame_size(32);
+        let header = FrameHeader::new(TextMessage::TYPE, 64);
+        assert_eq!(FrameHeader::parse(&header.to_bytes(), 64).unwrap(), header);
+
+        let mut buf = header.to_bytes().to_vec();
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(RecvError::FrameTooLarge(64))
+        ));
+    }
+}
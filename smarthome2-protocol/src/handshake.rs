@@ -0,0 +1,344 @@
+use std::{fmt, ops};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{
+    codec::Codec,
+    consts::{HANDSHAKE_REQUEST_ID, HANDSHAKE_RESPONSE_ID, TAG_SIZE},
+    Message, ProtocolVersion,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+///
+/// Набор возможностей протокола, согласуемых при установке соединения.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Capabilities(u32);
+
+impl fmt::Display for Capabilities {
+    ///
+    /// Выполнить форматирование набора возможностей.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = [
+            (Self::BINCODE, "bincode"),
+            (Self::JSON, "json"),
+            (Self::MESSAGE_PACK, "msgpack"),
+            (Self::CBOR, "cbor"),
+            (Self::PUSH_NOTIFICATIONS, "push"),
+            (Self::COMPRESSION, "compression"),
+        ]
+        .iter()
+        .filter(|(c, _)| self.contains(*c))
+        .map(|(_, name)| *name)
+        .collect();
+
+        write!(f, "[{}]", names.join(", "))
+    }
+}
+
+impl ops::BitOr for Capabilities {
+    type Output = Self;
+
+    ///
+    /// Объединить наборы возможностей.
+    ///
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for Capabilities {
+    ///
+    /// Добавить возможности к набору.
+    ///
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl ops::BitAnd for Capabilities {
+    type Output = Self;
+
+    ///
+    /// Получить пересечение наборов возможностей.
+    ///
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl Capabilities {
+    ///
+    /// Кодирование данных с помощью bincode.
+    ///
+    pub const BINCODE: Self = Self(0x0001);
+
+    ///
+    /// Кодирование данных в формате JSON.
+    ///
+    pub const JSON: Self = Self(0x0002);
+
+    ///
+    /// Кодирование данных в формате MessagePack.
+    ///
+    pub const MESSAGE_PACK: Self = Self(0x0004);
+
+    ///
+    /// Кодирование данных в формате CBOR.
+    ///
+    pub const CBOR: Self = Self(0x0008);
+
+    ///
+    /// Маска всех возможностей, отвечающих за кодирование данных.
+    ///
+    pub const CODECS: Self = Self(0x00FF);
+
+    ///
+    /// Отправка уведомлений сервером по собственной инициативе.
+    ///
+    pub const PUSH_NOTIFICATIONS: Self = Self(0x0100);
+
+    ///
+    /// Сжатие передаваемых данных.
+    ///
+    pub const COMPRESSION: Self = Self(0x0200);
+
+    ///
+    /// Создать пустой набор возможностей.
+    ///
+    #[inline]
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    ///
+    /// Получить набор возможностей, поддерживаемых данной реализацией
+    /// протокола.
+    ///
+    #[inline]
+    pub const fn supported() -> Self {
+        Self(
+            Self::BINCODE.0
+                | Self::JSON.0
+                | Self::MESSAGE_PACK.0
+                | Self::CBOR.0
+                | Self::PUSH_NOTIFICATIONS.0,
+        )
+    }
+
+    ///
+    /// Получить битовое представление набора возможностей.
+    ///
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    ///
+    /// Проверить, содержит ли набор все заданные возможности.
+    ///
+    #[inline]
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    ///
+    /// Получить набор без заданных возможностей.
+    ///
+    #[inline]
+    pub const fn without(&self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    ///
+    /// Проверить, является ли набор возможностей пустым.
+    ///
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+///
+/// Запрос клиента на установку соединения.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeRequest {
+    // Коды поддерживаемых клиентом версий протокола.
+    pub(crate) versions: Vec<u16>,
+
+    // Запрашиваемые клиентом возможности.
+    pub(crate) capabilities: Capabilities,
+}
+
+impl Message for HandshakeRequest {
+    ///
+    /// Идентификатор типа сообщения.
+    ///
+    const TYPE: u16 = HANDSHAKE_REQUEST_ID;
+}
+
+impl HandshakeRequest {
+    ///
+    /// Создать запрос со всеми поддерживаемыми версиями протокола.
+    ///
+    pub fn new(capabilities: Capabilities) -> Self {
+        Self {
+            versions: ProtocolVersion::supported()
+                .iter()
+                .map(|v| v.code())
+                .collect(),
+            capabilities,
+        }
+    }
+
+    ///
+    /// Согласовать версию протокола и набор возможностей с учетом
+    /// возможностей сервера.
+    ///
+    pub fn negotiate(&self, capabilities: Capabilities) -> HandshakeResponse {
+        let version = self
+            .versions
+            .iter()
+            .filter_map(|&code| ProtocolVersion::from_code(code))
+            .max();
+
+        let version = match version {
+            Some(v) => v,
+            None => return HandshakeResponse::UnsupportedVersion(self.versions.clone()),
+        };
+
+        // Из общих кодеков выбирается один, наиболее предпочтительный.
+        let capabilities = self.capabilities & capabilities;
+        let codec = match Codec::select(capabilities) {
+            Some(codec) => codec,
+            None => return HandshakeResponse::IncompatibleCapabilities(self.capabilities),
+        };
+        let capabilities = capabilities.without(Capabilities::CODECS) | codec.capability();
+
+        HandshakeResponse::Accepted(version.code(), capabilities)
+    }
+}
+
+///
+/// Ответ сервера на запрос установки соединения.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum HandshakeResponse {
+    ///
+    /// Соединение установлено с заданными версией и набором возможностей.
+    ///
+    Accepted(u16, Capabilities),
+
+    ///
+    /// Ни одна из предложенных версий протокола не поддерживается.
+    ///
+    UnsupportedVersion(Vec<u16>),
+
+    ///
+    /// Набор возможностей клиента несовместим с сервером.
+    ///
+    IncompatibleCapabilities(Capabilities),
+
+    ///
+    /// Клиент не подтвердил знание общего ключа.
+    ///
+    AuthenticationFailed,
+}
+
+impl Message for HandshakeResponse {
+    ///
+    /// Идентификатор типа сообщения.
+    ///
+    const TYPE: u16 = HANDSHAKE_RESPONSE_ID;
+}
+
+///
+/// Вычислить код аутентификации для пары одноразовых чисел.
+///
+pub fn sign(key: &[u8], role: &[u8], first: &[u8], second: &[u8]) -> [u8; TAG_SIZE] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(role);
+    mac.update(first);
+    mac.update(second);
+
+    mac.finalize().into_bytes().into()
+}
+
+///
+/// Проверить код аутентификации для пары одноразовых чисел.
+///
+pub fn verify(key: &[u8], role: &[u8], first: &[u8], second: &[u8], tag: &[u8]) -> bool {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(role);
+    mac.update(first);
+    mac.update(second);
+
+    mac.verify_slice(tag).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate_test() {
+        let request = HandshakeRequest::new(Capabilities::BINCODE | Capabilities::COMPRESSION);
+        match request.negotiate(Capabilities::supported()) {
+            HandshakeResponse::Accepted(code, capabilities) => {
+                assert_eq!(
+                    ProtocolVersion::from_code(code),
+                    Some(ProtocolVersion::V1_0)
+                );
+                assert_eq!(capabilities, Capabilities::BINCODE);
+            }
+            r => panic!("unexpected response {:?}", r),
+        }
+
+        let request = HandshakeRequest::new(Capabilities::JSON | Capabilities::CBOR);
+        assert!(matches!(
+            request.negotiate(Capabilities::supported()),
+            HandshakeResponse::Accepted(_, c) if c == Capabilities::CBOR
+        ));
+
+        let request = HandshakeRequest {
+            versions: vec![0x0900, 0x0100],
+            capabilities: Capabilities::BINCODE,
+        };
+        assert!(matches!(
+            request.negotiate(Capabilities::supported()),
+            HandshakeResponse::Accepted(0x0100, _)
+        ));
+
+        let request = HandshakeRequest {
+            versions: vec![0x0900],
+            capabilities: Capabilities::BINCODE,
+        };
+        assert!(matches!(
+            request.negotiate(Capabilities::supported()),
+            HandshakeResponse::UnsupportedVersion(_)
+        ));
+
+        let request = HandshakeRequest::new(Capabilities::PUSH_NOTIFICATIONS);
+        assert!(matches!(
+            request.negotiate(Capabilities::supported()),
+            HandshakeResponse::IncompatibleCapabilities(_)
+        ));
+    }
+
+    #[test]
+    fn sign_test() {
+        let tag = sign(b"key", b"role", b"first", b"second");
+        assert!(verify(b"key", b"role", b"first", b"second", &tag));
+        assert!(!verify(b"other key", b"role", b"first", b"second", &tag));
+        assert!(!verify(b"key", b"other role", b"first", b"second", &tag));
+        assert!(!verify(b"key", b"role", b"second", b"first", &tag));
+    }
+}
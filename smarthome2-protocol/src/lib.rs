@@ -0,0 +1,94 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+pub mod capability;
+pub mod codec;
+pub mod consts;
+pub mod envelope;
+pub mod error;
+pub mod event;
+pub mod frame;
+pub mod handshake;
+pub mod measurement;
+pub mod message;
+pub mod retry;
+pub mod state;
+
+///
+/// Типаж для отправки и получения сообщений по сети.
+///
+/// Идентификаторы типов до `USER_MESSAGE_ID_MIN` и `TEXT_MESSAGE_ID`
+/// зарезервированы для сообщений библиотеки. Сторонние типы сообщений
+/// должны использовать идентификаторы из диапазона
+/// `USER_MESSAGE_ID_MIN..=USER_MESSAGE_ID_MAX`.
+///
+pub trait Message {
+    ///
+    /// Идентификатор типа сообщения.
+    ///
+    const TYPE: u16;
+}
+
+///
+/// Версия протокола.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ProtocolVersion {
+    #[serde(rename = "1.0")]
+    V1_0,
+}
+
+impl fmt::Display for ProtocolVersion {
+    ///
+    /// Выполнить форматирование версии протокола.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code = self.code();
+        write!(f, "{}.{}", code >> 8, code & 0xFF)
+    }
+}
+
+impl Default for ProtocolVersion {
+    ///
+    /// Версия протокола по умолчанию.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self::CURRENT
+    }
+}
+
+impl ProtocolVersion {
+    ///
+    /// Текущая версия протокола.
+    ///
+    pub const CURRENT: Self = Self::V1_0;
+
+    ///
+    /// Получить список поддерживаемых версий протокола.
+    ///
+    #[inline]
+    pub fn supported() -> &'static [Self] {
+        &[Self::V1_0]
+    }
+
+    ///
+    /// Получить числовой код версии протокола, передаваемый при
+    /// установке соединения (старший байт - основная версия,
+    /// младший - дополнительная).
+    ///
+    #[inline]
+    pub const fn code(&self) -> u16 {
+        match self {
+            Self::V1_0 => 0x0100,
+        }
+    }
+
+    ///
+    /// Получить версию протокола по ее числовому коду.
+    ///
+    pub fn from_code(code: u16) -> Option<Self> {
+        Self::supported().iter().copied().find(|v| v.code() == code)
+    }
+}
@@ -0,0 +1,274 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+///
+/// Измеряемая величина.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Quantity {
+    ///
+    /// Признак включения устройства.
+    ///
+    Enabled,
+
+    ///
+    /// Потребляемая мощность.
+    ///
+    Power,
+
+    ///
+    /// Температура.
+    ///
+    Temperature,
+
+    ///
+    /// Яркость свечения.
+    ///
+    Brightness,
+
+    ///
+    /// Цветовая температура свечения.
+    ///
+    ColorTemperature,
+
+    ///
+    /// Относительная влажность воздуха.
+    ///
+    Humidity,
+
+    ///
+    /// Концентрация углекислого газа.
+    ///
+    CarbonDioxide,
+
+    ///
+    /// Признак обнаружения движения.
+    ///
+    Motion,
+
+    ///
+    /// Признак открытия двери или окна.
+    ///
+    Open,
+}
+
+impl Quantity {
+    ///
+    /// Получить единицу измерения, в которой величина сообщается
+    /// устройствами.
+    ///
+    pub fn unit(&self) -> Unit {
+        match self {
+            Self::Enabled => Unit::None,
+            Self::Power => Unit::Watt,
+            Self::Temperature => Unit::Celsius,
+            Self::Brightness => Unit::Percent,
+            Self::ColorTemperature => Unit::Kelvin,
+            Self::Humidity => Unit::Percent,
+            Self::CarbonDioxide => Unit::Ppm,
+            Self::Motion | Self::Open => Unit::None,
+        }
+    }
+}
+
+///
+/// Единица измерения величины.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Unit {
+    ///
+    /// Безразмерная величина.
+    ///
+    None,
+
+    ///
+    /// Ватт.
+    ///
+    Watt,
+
+    ///
+    /// Градус Цельсия.
+    ///
+    Celsius,
+
+    ///
+    /// Процент.
+    ///
+    Percent,
+
+    ///
+    /// Кельвин.
+    ///
+    Kelvin,
+
+    ///
+    /// Миллионная доля.
+    ///
+    Ppm,
+}
+
+impl fmt::Display for Unit {
+    ///
+    /// Получить обозначение единицы измерения.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "",
+            Self::Watt => "Вт",
+            Self::Celsius => "°C",
+            Self::Percent => "%",
+            Self::Kelvin => "K",
+            Self::Ppm => "ppm",
+        })
+    }
+}
+
+///
+/// Значение измеряемой величины.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    ///
+    /// Логическое значение.
+    ///
+    Flag(bool),
+
+    ///
+    /// Числовое значение.
+    ///
+    Number(f64),
+}
+
+impl fmt::Display for Value {
+    ///
+    /// Выполнить форматирование значения.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Flag(value) => write!(f, "{}", value),
+            Self::Number(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    ///
+    /// Получить логическое значение.
+    ///
+    #[inline]
+    fn from(value: bool) -> Self {
+        Self::Flag(value)
+    }
+}
+
+impl From<f64> for Value {
+    ///
+    /// Получить числовое значение.
+    ///
+    #[inline]
+    fn from(value: f64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl Value {
+    ///
+    /// Получить логическое значение, если значение логическое.
+    ///
+    #[inline]
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Self::Flag(value) => Some(value),
+            Self::Number(_) => None,
+        }
+    }
+
+    ///
+    /// Получить числовое значение, если значение числовое.
+    ///
+    #[inline]
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Self::Number(value) => Some(value),
+            Self::Flag(_) => None,
+        }
+    }
+}
+
+///
+/// Результат измерения величины устройством.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    // Измеряемая величина.
+    quantity: Quantity,
+
+    // Значение величины.
+    value: Value,
+
+    // Единица измерения.
+    unit: Unit,
+}
+
+impl fmt::Display for Measurement {
+    ///
+    /// Выполнить форматирование значения с единицей измерения.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.unit {
+            Unit::None => write!(f, "{}", self.value),
+            unit => write!(f, "{} {}", self.value, unit),
+        }
+    }
+}
+
+impl Measurement {
+    ///
+    /// Создать результат измерения с заданными величиной, значением
+    /// и единицей измерения.
+    ///
+    #[inline]
+    pub fn new<V: Into<Value>>(quantity: Quantity, value: V, unit: Unit) -> Self {
+        Self {
+            quantity,
+            value: value.into(),
+            unit,
+        }
+    }
+
+    ///
+    /// Получить измеряемую величину.
+    ///
+    #[inline]
+    pub fn quantity(&self) -> Quantity {
+        self.quantity
+    }
+
+    ///
+    /// Получить значение величины.
+    ///
+    #[inline]
+    pub fn value(&self) -> Value {
+        self.value
+    }
+
+    ///
+    /// Получить единицу измерения.
+    ///
+    #[inline]
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+}
+
+impl<V: Into<Value>> From<(Quantity, V)> for Measurement {
+    ///
+    /// Получить результат измерения величины в единицах, в которых
+    /// она сообщается устройствами.
+    ///
+    #[inline]
+    fn from((quantity, value): (Quantity, V)) -> Self {
+        Self::new(quantity, value, quantity.unit())
+    }
+}
@@ -0,0 +1,1311 @@
+use std::{fmt, iter};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    capability::{DeviceCapabilities, SensorKind},
+    consts::{CONTROL_REQUEST_ID, CONTROL_RESPONSE_ID, SENSOR_MESSAGE_ID, TEXT_MESSAGE_ID},
+    error::SelectorError,
+    event::EventData,
+    measurement::{Measurement, Quantity, Value},
+    state::DeviceState,
+    Message, ProtocolVersion,
+};
+
+///
+/// Сообщение для обмена тестовыми данными.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextMessage {
+    text: String,
+}
+
+impl fmt::Display for TextMessage {
+    ///
+    /// Выполнить форматирование сообщения.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl Message for TextMessage {
+    ///
+    /// Идентификатор типа сообщения.
+    ///
+    const TYPE: u16 = TEXT_MESSAGE_ID;
+}
+
+impl TextMessage {
+    ///
+    /// Создать сообщение с заданным текстом.
+    ///
+    pub fn new<D: AsRef<str>>(text: D) -> Self {
+        Self {
+            text: text.as_ref().to_owned(),
+        }
+    }
+}
+
+///
+/// Данные запроса управления "умным" домом.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ControlRequestData {
+    ///
+    /// Запрос на получение списка комнат.
+    ///
+    AcquireRooms,
+
+    ///
+    /// Запрос на получение списка устройств для комнаты.
+    ///
+    AcquireDevices(Selector),
+
+    ///
+    /// Запрос на получение состояния устройства.
+    ///
+    AcquireDeviceState(DeviceSelector),
+
+    ///
+    /// Запрос на получение состояния удаленного устройства.
+    ///
+    AcquireRemoteDeviceState,
+
+    ///
+    /// Запрос на получение информации об устройства.
+    ///
+    AcquireDeviceInfo(DeviceSelector),
+
+    ///
+    /// Запрос на получение описания возможностей устройства.
+    ///
+    AcquireDeviceCapabilities(DeviceSelector),
+
+    ///
+    /// Запрос на получение описания возможностей удаленного устройства.
+    ///
+    AcquireRemoteDeviceCapabilities,
+
+    ///
+    /// Запрос на получение идентификатора и имени удаленного устройства.
+    ///
+    AcquireRemoteDeviceName,
+
+    ///
+    /// Запрос на включение устройства.
+    ///
+    SwitchOnDevice(DeviceSelector),
+
+    ///
+    /// Запрос на включение удаоенного устройства.
+    ///
+    SwitchOnRemoteDevice,
+
+    ///
+    /// Запрос на выключение устройства.
+    ///
+    SwitchOffDevice(DeviceSelector),
+
+    ///
+    /// Запрос на выключение удаленного устройства.
+    ///
+    SwitchOffRemoteDevice,
+
+    ///
+    /// Запрос на обработку события устройством.
+    ///
+    NotifyDevice(DeviceSelector, EventData),
+
+    ///
+    /// Запрос на обработку события удаленным устройством.
+    ///
+    NotifyRemoteDevice(EventData),
+
+    ///
+    /// Запрос на подписку на изменения состояния устройства.
+    ///
+    Subscribe(DeviceSelector),
+
+    ///
+    /// Запрос на подписку на изменения состояния всех устройств.
+    ///
+    SubscribeAll,
+
+    ///
+    /// Запрос на создание комнаты с заданным именем.
+    ///
+    CreateRoom(String),
+
+    ///
+    /// Запрос на удаление комнаты.
+    ///
+    DeleteRoom(Uuid),
+
+    ///
+    /// Запрос на переименование комнаты.
+    ///
+    RenameRoom(Uuid, String),
+
+    ///
+    /// Запрос на добавление устройства в комнату.
+    ///
+    AttachDevice(Uuid, DeviceSpec),
+
+    ///
+    /// Запрос на удаление устройства из комнаты.
+    ///
+    DetachDevice(Uuid, Uuid),
+
+    ///
+    /// Пакет запросов, выполняемых сервером подряд без перерыва.
+    ///
+    Batch(Vec<ControlRequestData>),
+
+    ///
+    /// Пакет запросов, выполняемых по принципу "все или ничего".
+    ///
+    Transaction(Vec<ControlRequestData>),
+}
+
+impl ControlRequestData {
+    ///
+    /// Проверить, можно ли безопасно повторить запрос.
+    ///
+    fn is_idempotent(&self) -> bool {
+        match self {
+            Self::Batch(items) | Self::Transaction(items) => items.iter().all(Self::is_idempotent),
+            Self::NotifyDevice(_, event) | Self::NotifyRemoteDevice(event) => event.is_query(),
+            data => !matches!(
+                data,
+                Self::SwitchOnDevice(..)
+                    | Self::SwitchOnRemoteDevice
+                    | Self::SwitchOffDevice(..)
+                    | Self::SwitchOffRemoteDevice
+                    | Self::CreateRoom(..)
+                    | Self::DeleteRoom(..)
+                    | Self::RenameRoom(..)
+                    | Self::AttachDevice(..)
+                    | Self::DetachDevice(..)
+            ),
+        }
+    }
+}
+
+///
+/// Описание устройства, добавляемого в комнату "умного" дома по запросу
+/// клиента.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DeviceSpec {
+    ///
+    /// "Умная" розетка с заданным именем.
+    ///
+    Socket { name: String },
+
+    ///
+    /// "Умный" термометр с заданным именем и начальной температурой.
+    ///
+    Thermometer { name: String, temperature: f64 },
+
+    ///
+    /// "Умная" лампа с заданным именем.
+    ///
+    Lamp { name: String },
+
+    ///
+    /// Удаленная "умная" розетка, доступная по адресу ее сервера
+    /// управления. Имя розетки запрашивается у сервера.
+    ///
+    RemoteSocket { address: String },
+
+    ///
+    /// Удаленная "умная" лампа, доступная по адресу ее сервера
+    /// управления. Имя лампы запрашивается у сервера.
+    ///
+    RemoteLamp { address: String },
+
+    ///
+    /// Удаленный "умный" термометр с заданным именем, адресом привязки
+    /// UDP-сокета и адресом автономного термометра.
+    ///
+    RemoteThermometer {
+        name: String,
+        bind: String,
+        address: String,
+    },
+
+    ///
+    /// Датчик заданного вида с заданным именем и начальным показанием.
+    ///
+    Sensor {
+        name: String,
+        kind: SensorKind,
+        value: Value,
+    },
+
+    ///
+    /// Удаленный датчик заданного вида с заданным именем, адресом
+    /// привязки UDP-сокета и адресом автономного датчика.
+    ///
+    RemoteSensor {
+        name: String,
+        kind: SensorKind,
+        bind: String,
+        address: String,
+    },
+}
+
+///
+/// Способ выбора комнаты или устройства: по идентификатору или по имени.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Selector {
+    ///
+    /// Выбор по идентификатору.
+    ///
+    Id(Uuid),
+
+    ///
+    /// Выбор по имени.
+    ///
+    Name(String),
+}
+
+impl fmt::Display for Selector {
+    ///
+    /// Выполнить форматирование идентификатора или имени.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "{}", id),
+            Self::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+impl From<Uuid> for Selector {
+    ///
+    /// Выбрать комнату или устройство по идентификатору.
+    ///
+    #[inline]
+    fn from(id: Uuid) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl From<&str> for Selector {
+    ///
+    /// Выбрать комнату или устройство по имени.
+    ///
+    #[inline]
+    fn from(name: &str) -> Self {
+        Self::Name(name.to_owned())
+    }
+}
+
+impl From<String> for Selector {
+    ///
+    /// Выбрать комнату или устройство по имени.
+    ///
+    #[inline]
+    fn from(name: String) -> Self {
+        Self::Name(name)
+    }
+}
+
+impl Selector {
+    ///
+    /// Разобрать элемент пути к устройству. Элемент, являющийся
+    /// идентификатором, выбирает по идентификатору, иначе по имени.
+    ///
+    pub fn parse(s: &str) -> Self {
+        match Uuid::parse_str(s) {
+            Ok(id) => Self::Id(id),
+            Err(_) => Self::Name(s.to_owned()),
+        }
+    }
+
+    ///
+    /// Проверить, соответствует ли выбору комната или устройство с
+    /// заданными идентификатором и именем.
+    ///
+    pub fn matches(&self, id: Uuid, name: &str) -> bool {
+        match self {
+            Self::Id(selected) => *selected == id,
+            Self::Name(selected) => selected == name,
+        }
+    }
+
+    ///
+    /// Проверить, можно ли выбрать комнату или устройство с заданным
+    /// именем в пути "комната/устройство". Такое имя не содержит
+    /// разделителя пути и не разбирается как идентификатор.
+    ///
+    pub fn is_selectable(name: &str) -> bool {
+        !name.contains('/') && Uuid::parse_str(name).is_err()
+    }
+}
+
+///
+/// Способ выбора устройства "умного" дома: по идентификаторам или именам
+/// комнаты и устройства, либо по пути вида "комната/устройство".
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeviceSelector {
+    ///
+    /// Выбор по идентификаторам комнаты и устройства.
+    ///
+    Id(Uuid, Uuid),
+
+    ///
+    /// Выбор по именам комнаты и устройства.
+    ///
+    Name(String, String),
+
+    ///
+    /// Выбор по пути "комната/устройство", каждый элемент которого
+    /// является именем или идентификатором.
+    ///
+    Path(String),
+}
+
+impl fmt::Display for DeviceSelector {
+    ///
+    /// Выполнить форматирование пути к устройству.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(room_id, device_id) => write!(f, "{}/{}", room_id, device_id),
+            Self::Name(room_name, device_name) => write!(f, "{}/{}", room_name, device_name),
+            Self::Path(path) => f.write_str(path),
+        }
+    }
+}
+
+impl From<(Uuid, Uuid)> for DeviceSelector {
+    ///
+    /// Выбрать устройство по идентификаторам комнаты и устройства.
+    ///
+    #[inline]
+    fn from((room_id, device_id): (Uuid, Uuid)) -> Self {
+        Self::Id(room_id, device_id)
+    }
+}
+
+impl From<(&str, &str)> for DeviceSelector {
+    ///
+    /// Выбрать устройство по именам комнаты и устройства.
+    ///
+    #[inline]
+    fn from((room_name, device_name): (&str, &str)) -> Self {
+        Self::Name(room_name.to_owned(), device_name.to_owned())
+    }
+}
+
+impl From<&str> for DeviceSelector {
+    ///
+    /// Выбрать устройство по пути "комната/устройство".
+    ///
+    #[inline]
+    fn from(path: &str) -> Self {
+        Self::Path(path.to_owned())
+    }
+}
+
+impl From<String> for DeviceSelector {
+    ///
+    /// Выбрать устройство по пути "комната/устройство".
+    ///
+    #[inline]
+    fn from(path: String) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl DeviceSelector {
+    ///
+    /// Получить способы выбора комнаты и устройства. Для пути, не
+    /// содержащего разделителя или содержащего пустой элемент,
+    /// возвращает `SelectorError::IllegalDevicePath`.
+    ///
+    pub fn parts(&self) -> Result<(Selector, Selector), SelectorError> {
+        match self {
+            Self::Id(room_id, device_id) => Ok((Selector::Id(*room_id), Selector::Id(*device_id))),
+            Self::Name(room_name, device_name) => Ok((
+                Selector::Name(room_name.clone()),
+                Selector::Name(device_name.clone()),
+            )),
+            Self::Path(path) => match path.split_once('/') {
+                Some((room, device)) if !room.is_empty() && !device.is_empty() => {
+                    Ok((Selector::parse(room), Selector::parse(device)))
+                }
+                _ => Err(SelectorError::IllegalDevicePath(path.clone())),
+            },
+        }
+    }
+}
+
+///
+/// Запрос на управление "умным" домом.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlRequest {
+    // Версия протокола.
+    version: ProtocolVersion,
+
+    // Идентификатор запроса, назначаемый клиентом.
+    id: u64,
+
+    // Данные запроса.
+    data: ControlRequestData,
+}
+
+impl Message for ControlRequest {
+    ///
+    /// Идентификатор типа сообщения.
+    ///
+    const TYPE: u16 = CONTROL_REQUEST_ID;
+}
+
+impl ControlRequest {
+    ///
+    /// Получить версию протокола, которой соответствует запрос.
+    ///
+    #[inline]
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    ///
+    /// Получить идентификатор запроса.
+    ///
+    #[inline]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    ///
+    /// Получить данные запроса.
+    ///
+    #[inline]
+    pub fn data(&self) -> &ControlRequestData {
+        &self.data
+    }
+
+    ///
+    /// Назначить запросу версию протокола, согласованную для соединения.
+    ///
+    #[inline]
+    pub fn set_version(&mut self, version: ProtocolVersion) {
+        self.version = version;
+    }
+
+    ///
+    /// Назначить запросу идентификатор.
+    ///
+    #[inline]
+    pub fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+
+    ///
+    /// Проверить, можно ли безопасно повторить запрос, если ответ на
+    /// него не был получен. Повторять можно запросы, не изменяющие
+    /// состояние устройств и состав "умного" дома.
+    ///
+    pub fn is_idempotent(&self) -> bool {
+        self.data.is_idempotent()
+    }
+
+    ///
+    /// Проверить, является ли запрос запросом на подписку.
+    ///
+    pub fn is_subscription(&self) -> bool {
+        matches!(
+            self.data,
+            ControlRequestData::Subscribe(..) | ControlRequestData::SubscribeAll
+        )
+    }
+
+    ///
+    /// Создать запрос для получения списка комнат.
+    ///
+    #[inline]
+    pub fn acquire_rooms() -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::AcquireRooms,
+        }
+    }
+
+    ///
+    /// Создать запрос для получения списка устройств комнаты, выбранной
+    /// по идентификатору или имени.
+    ///
+    #[inline]
+    pub fn acquire_devices<R: Into<Selector>>(room: R) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::AcquireDevices(room.into()),
+        }
+    }
+
+    ///
+    /// Создать запрос для получения состояния устройства.
+    ///
+    #[inline]
+    pub fn acquire_device_state<S: Into<DeviceSelector>>(selector: S) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::AcquireDeviceState(selector.into()),
+        }
+    }
+
+    ///
+    /// Создать запрос для получения состояния удаленного устройства.
+    ///
+    #[inline]
+    pub fn acquire_remote_device_state() -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::AcquireRemoteDeviceState,
+        }
+    }
+
+    ///
+    /// Создать запрос для получения информации об устройстве.
+    ///
+    #[inline]
+    pub fn acquire_device_info<S: Into<DeviceSelector>>(selector: S) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::AcquireDeviceInfo(selector.into()),
+        }
+    }
+
+    ///
+    /// Создать запрос на получение описания возможностей устройства.
+    ///
+    #[inline]
+    pub fn acquire_device_capabilities<S: Into<DeviceSelector>>(selector: S) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::AcquireDeviceCapabilities(selector.into()),
+        }
+    }
+
+    ///
+    /// Создать запрос на получение описания возможностей удаленного устройства.
+    ///
+    #[inline]
+    pub fn acquire_remote_device_capabilities() -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::AcquireRemoteDeviceCapabilities,
+        }
+    }
+
+    ///
+    /// Создать запрос на получение идентификатора и имени удаленного устройства.
+    ///
+    #[inline]
+    pub fn acquire_remote_device_name() -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::AcquireRemoteDeviceName,
+        }
+    }
+
+    ///
+    /// Создать запрос для включения устройства.
+    ///
+    #[inline]
+    pub fn switch_on_device<S: Into<DeviceSelector>>(selector: S) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::SwitchOnDevice(selector.into()),
+        }
+    }
+
+    ///
+    /// Создать запрос для включения удаленного устройства.
+    ///
+    #[inline]
+    pub fn switch_on_remote_device() -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::SwitchOnRemoteDevice,
+        }
+    }
+
+    ///
+    /// Создать запрос для выключения устройства.
+    ///
+    #[inline]
+    pub fn switch_off_device<S: Into<DeviceSelector>>(selector: S) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::SwitchOffDevice(selector.into()),
+        }
+    }
+
+    ///
+    /// Создать запрос для выключения удаленного устройства.
+    ///
+    #[inline]
+    pub fn switch_off_remote_device() -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::SwitchOffRemoteDevice,
+        }
+    }
+
+    ///
+    /// Создать запрос для обработки события устройством.
+    ///
+    #[inline]
+    pub fn notify_device<S: Into<DeviceSelector>>(selector: S, event: EventData) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::NotifyDevice(selector.into(), event),
+        }
+    }
+
+    ///
+    /// Создать запрос для обработки события удаленным устройством.
+    ///
+    #[inline]
+    pub fn notify_remote_device(event: EventData) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::NotifyRemoteDevice(event),
+        }
+    }
+
+    ///
+    /// Создать запрос для подписки на изменения состояния устройства.
+    ///
+    #[inline]
+    pub fn subscribe<S: Into<DeviceSelector>>(selector: S) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::Subscribe(selector.into()),
+        }
+    }
+
+    ///
+    /// Создать запрос для подписки на изменения состояния всех устройств.
+    ///
+    #[inline]
+    pub fn subscribe_all() -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::SubscribeAll,
+        }
+    }
+
+    ///
+    /// Создать запрос для создания комнаты с заданным именем.
+    ///
+    #[inline]
+    pub fn create_room<D: AsRef<str>>(name: D) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::CreateRoom(name.as_ref().to_owned()),
+        }
+    }
+
+    ///
+    /// Создать запрос для удаления комнаты вместе с ее устройствами.
+    ///
+    #[inline]
+    pub fn delete_room(room_id: Uuid) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::DeleteRoom(room_id),
+        }
+    }
+
+    ///
+    /// Создать запрос для переименования комнаты.
+    ///
+    #[inline]
+    pub fn rename_room<D: AsRef<str>>(room_id: Uuid, name: D) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::RenameRoom(room_id, name.as_ref().to_owned()),
+        }
+    }
+
+    ///
+    /// Создать запрос для добавления в комнату устройства с заданным
+    /// описанием.
+    ///
+    #[inline]
+    pub fn attach_device(room_id: Uuid, spec: DeviceSpec) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::AttachDevice(room_id, spec),
+        }
+    }
+
+    ///
+    /// Создать запрос для удаления устройства из комнаты.
+    ///
+    #[inline]
+    pub fn detach_device(room_id: Uuid, device_id: Uuid) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::DetachDevice(room_id, device_id),
+        }
+    }
+
+    ///
+    /// Создать пакет запросов. Сервер выполняет запросы пакета подряд,
+    /// не прерываясь на обработку других соединений, и возвращает ответ
+    /// со списком результатов в порядке следования запросов.
+    ///
+    pub fn batch<I: IntoIterator<Item = ControlRequest>>(requests: I) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::Batch(requests.into_iter().map(|r| r.data).collect()),
+        }
+    }
+
+    ///
+    /// Создать пакет запросов, выполняемых по принципу "все или ничего".
+    /// Пакет может содержать только запросы на получение сведений об
+    /// устройствах и на их включение и выключение. При ошибке выполнение
+    /// пакета прекращается, переключенные им устройства возвращаются в
+    /// прежнее состояние, а последним результатом в ответе оказывается
+    /// ошибка.
+    ///
+    pub fn transaction<I: IntoIterator<Item = ControlRequest>>(requests: I) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlRequestData::Transaction(requests.into_iter().map(|r| r.data).collect()),
+        }
+    }
+}
+
+///
+/// Данные ответа на запрос управления "умным" домом.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) enum ControlResponseData {
+    // Вектор с данными о комнатах или устройствах.
+    List(Vec<(Uuid, String)>),
+
+    // Информация о состоянии устройства.
+    State(DeviceState),
+
+    // Информация об устройстве в текстовом виде.
+    Info(String),
+
+    // Идентификатор и имя устройства.
+    Name(Uuid, String),
+
+    // Описание возможностей устройства.
+    Capabilities(DeviceCapabilities),
+
+    // Запрос выполнен, данные в ответе не требуются.
+    Done,
+
+    // Уведомление об изменении состояния устройства в комнате.
+    Notification(Uuid, DeviceState),
+
+    // Код и описание ошибки.
+    Error(RemoteError),
+
+    // Результаты выполнения пакета запросов.
+    Batch(Vec<ControlResponse>),
+}
+
+///
+/// Ответ на запрос управления "умным" домом.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlResponse {
+    // Версия протокола.
+    version: ProtocolVersion,
+
+    // Идентификатор запроса, на который дан ответ.
+    id: u64,
+
+    // Данные ответа на запрос.
+    data: ControlResponseData,
+}
+
+impl<'a> iter::FromIterator<(Uuid, &'a str)> for ControlResponse {
+    ///
+    /// Сформировать ответ на запрос управления "умным" домом из
+    /// итератора.
+    ///
+    fn from_iter<T: IntoIterator<Item = (Uuid, &'a str)>>(iter: T) -> Self {
+        let v: Vec<(Uuid, String)> = iter
+            .into_iter()
+            .map(|(id, name)| (id, name.to_owned()))
+            .collect();
+
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlResponseData::List(v),
+        }
+    }
+}
+
+impl Message for ControlResponse {
+    ///
+    /// Идентификатор типа сообщения.
+    ///
+    const TYPE: u16 = CONTROL_RESPONSE_ID;
+}
+
+impl ControlResponse {
+    ///
+    /// Получить версию протокола, которой соответствует ответ.
+    ///
+    #[inline]
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    ///
+    /// Получить идентификатор запроса, на который дан ответ.
+    ///
+    #[inline]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    ///
+    /// Назначить ответу идентификатор запроса, на который он дан.
+    ///
+    #[inline]
+    pub fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+
+    ///
+    /// Создать ответ с состоянием устройства.
+    ///
+    #[inline]
+    pub fn with_state(state: DeviceState) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlResponseData::State(state),
+        }
+    }
+
+    ///
+    /// Создать ответ с информацией об устройстве.
+    ///
+    #[inline]
+    pub fn with_info<D: AsRef<str>>(info: D) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlResponseData::Info(info.as_ref().to_owned()),
+        }
+    }
+
+    ///
+    /// Создать ответ с идентификатором и именем устройства.
+    ///
+    #[inline]
+    pub fn with_name<D: AsRef<str>>(id: Uuid, name: D) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlResponseData::Name(id, name.as_ref().to_owned()),
+        }
+    }
+
+    ///
+    /// Создать ответ с описанием возможностей устройства.
+    ///
+    #[inline]
+    pub fn with_capabilities(capabilities: DeviceCapabilities) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlResponseData::Capabilities(capabilities),
+        }
+    }
+
+    ///
+    /// Создать ответ об успешном выполнении запроса без данных.
+    ///
+    #[inline]
+    pub fn done() -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlResponseData::Done,
+        }
+    }
+
+    ///
+    /// Создать уведомление об изменении состояния устройства в комнате.
+    /// Уведомления не относятся ни к одному запросу и имеют нулевой
+    /// идентификатор.
+    ///
+    #[inline]
+    pub fn with_notification(room_id: Uuid, state: DeviceState) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlResponseData::Notification(room_id, state),
+        }
+    }
+
+    ///
+    /// Создать ответ с результатами выполнения пакета запросов.
+    ///
+    #[inline]
+    pub fn with_results(results: Vec<ControlResponse>) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlResponseData::Batch(results),
+        }
+    }
+
+    ///
+    /// Создать ответ с информацией об ошибке.
+    ///
+    #[inline]
+    pub fn with_error<E: Into<RemoteError>>(error: E) -> Self {
+        Self {
+            version: ProtocolVersion::CURRENT,
+            id: 0,
+            data: ControlResponseData::Error(error.into()),
+        }
+    }
+
+    ///
+    /// Получить список идентификаторов и имен комнат или устройств.
+    ///
+    pub fn list(&self) -> Option<&[(Uuid, String)]> {
+        if let ControlResponseData::List(ref list) = self.data {
+            Some(list.as_slice())
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// Получить состояние устройства.
+    ///
+    pub fn state(&self) -> Option<DeviceState> {
+        if let ControlResponseData::State(ref state) = self.data {
+            Some(state.clone())
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// Получить информацию об устройстве.
+    ///
+    pub fn info(&self) -> Option<&str> {
+        if let ControlResponseData::Info(ref info) = self.data {
+            Some(info.as_str())
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// Получить идентификатор и имя устройства.
+    ///
+    pub fn name(&self) -> Option<(Uuid, &str)> {
+        if let ControlResponseData::Name(id, ref name) = self.data {
+            Some((id, name.as_str()))
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// Получить описание возможностей устройства.
+    ///
+    pub fn capabilities(&self) -> Option<&DeviceCapabilities> {
+        if let ControlResponseData::Capabilities(ref capabilities) = self.data {
+            Some(capabilities)
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// Получить идентификатор комнаты и состояние устройства из
+    /// уведомления.
+    ///
+    pub fn notification(&self) -> Option<(Uuid, DeviceState)> {
+        if let ControlResponseData::Notification(room_id, ref state) = self.data {
+            Some((room_id, state.clone()))
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// Получить результаты выполнения пакета запросов.
+    ///
+    pub fn results(&self) -> Option<&[ControlResponse]> {
+        if let ControlResponseData::Batch(ref results) = self.data {
+            Some(results.as_slice())
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// Получить ошибку, возвращенную сервером.
+    ///
+    pub fn error(&self) -> Option<&RemoteError> {
+        if let ControlResponseData::Error(ref error) = self.data {
+            Some(error)
+        } else {
+            None
+        }
+    }
+}
+
+///
+/// Код ошибки сервера с параметрами, позволяющими восстановить ошибку
+/// на стороне клиента.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCode {
+    ///
+    /// Недопустимое имя комнаты.
+    ///
+    IllegalRoomName(String),
+
+    ///
+    /// Недопустимое имя устройства.
+    ///
+    IllegalDeviceName(String),
+
+    ///
+    /// Комната с заданным идентификатором не найдена.
+    ///
+    IllegalRoomId(Uuid),
+
+    ///
+    /// Устройство с заданным идентификатором не найдено.
+    ///
+    IllegalDeviceId(Uuid),
+
+    ///
+    /// Недопустимый путь к устройству.
+    ///
+    IllegalDevicePath(String),
+
+    ///
+    /// Событие с заданным идентификатором не поддерживается устройством.
+    ///
+    NotImplementedEvent(Uuid),
+
+    ///
+    /// Сервер получил неожиданное сообщение.
+    ///
+    UnexpectedMessage,
+
+    ///
+    /// Версия протокола запроса не поддерживается сервером.
+    ///
+    UnsupportedVersion(ProtocolVersion),
+
+    ///
+    /// Уведомления не согласованы для соединения.
+    ///
+    NotificationsDisabled,
+
+    ///
+    /// Сервер перегружен.
+    ///
+    ServerBusy,
+
+    ///
+    /// Внутренняя ошибка сервера, подробности приведены в описании.
+    ///
+    Internal,
+
+    ///
+    /// Недопустимое значение аргумента запроса.
+    ///
+    InvalidArgument(String),
+}
+
+///
+/// Ошибка сервера, передаваемая в ответе на запрос.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteError {
+    // Код ошибки с параметрами.
+    code: ErrorCode,
+
+    // Описание ошибки.
+    message: String,
+}
+
+impl fmt::Display for RemoteError {
+    ///
+    /// Выполнить форматирование описания ошибки.
+    ///
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<SelectorError> for RemoteError {
+    ///
+    /// Преобразовать ошибку разбора селектора в ошибку для передачи
+    /// клиенту.
+    ///
+    fn from(error: SelectorError) -> Self {
+        let code = match error {
+            SelectorError::IllegalDevicePath(ref path) => {
+                ErrorCode::IllegalDevicePath(path.clone())
+            }
+        };
+
+        Self::new(code, error.to_string())
+    }
+}
+
+impl RemoteError {
+    ///
+    /// Создать ошибку для передачи клиенту с заданными кодом и
+    /// описанием.
+    ///
+    #[inline]
+    pub fn new<S: Into<String>>(code: ErrorCode, message: S) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    ///
+    /// Получить код ошибки.
+    ///
+    #[inline]
+    pub fn code(&self) -> &ErrorCode {
+        &self.code
+    }
+
+    ///
+    /// Получить описание ошибки.
+    ///
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+///
+/// Сообщение с показаниями автономного датчика.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensorMessage {
+    ///
+    /// Идентификатор автономного датчика.
+    ///
+    id: Uuid,
+
+    ///
+    /// Показания автономного датчика.
+    ///
+    measurements: Vec<Measurement>,
+}
+
+impl Message for SensorMessage {
+    ///
+    /// Идентификатор типа сообщения.
+    ///
+    const TYPE: u16 = SENSOR_MESSAGE_ID;
+}
+
+impl SensorMessage {
+    ///
+    /// Создать сообщение автономного датчика с заданным идентификатором
+    /// без показаний.
+    ///
+    #[inline]
+    pub fn new(id: Uuid) -> Self {
+        Self {
+            id,
+            measurements: Vec::new(),
+        }
+    }
+
+    ///
+    /// Добавить показание датчика. Предыдущее показание той же
+    /// величины заменяется.
+    ///
+    pub fn with_measurement<M: Into<Measurement>>(mut self, measurement: M) -> Self {
+        let measurement = measurement.into();
+        self.measurements
+            .retain(|m| m.quantity() != measurement.quantity());
+        self.measurements.push(measurement);
+        self
+    }
+
+    ///
+    /// Получить идентификатор автономного датчика.
+    ///
+    #[inline]
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    ///
+    /// Получить показания автономного датчика.
+    ///
+    #[inline]
+    pub fn measurements(&self) -> &[Measurement] {
+        &self.measurements
+    }
+
+    ///
+    /// Получить значение заданной величины, если датчик его сообщил.
+    ///
+    pub fn value(&self, quantity: Quantity) -> Option<Value> {
+        self.measurements
+            .iter()
+            .find(|m| m.quantity() == quantity)
+            .map(Measurement::value)
+    }
+}
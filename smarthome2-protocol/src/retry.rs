@@ -0,0 +1,195 @@
+use std::{fmt, time::Duration};
+
+///
+/// Политика повторного подключения к серверу с экспоненциально
+/// растущей паузой между попытками.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    ///
+    /// Создать политику повторного подключения по умолчанию.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetryPolicy {
+    ///
+    /// Создать политику повторного подключения с настройками по умолчанию:
+    /// не более 5 попыток с начальной паузой 100 мс, удваивающейся после
+    /// каждой неудачной попытки, но не превышающей 5 с.
+    ///
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+        }
+    }
+
+    ///
+    /// Выполнять не более заданного количества попыток подключения.
+    ///
+    #[inline]
+    pub fn with_max_attempts(self, max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            ..self
+        }
+    }
+
+    ///
+    /// Использовать заданную паузу перед второй попыткой подключения.
+    ///
+    #[inline]
+    pub fn with_initial_backoff(self, initial_backoff: Duration) -> Self {
+        Self {
+            initial_backoff,
+            ..self
+        }
+    }
+
+    ///
+    /// Ограничить паузу между попытками подключения заданным временем.
+    ///
+    #[inline]
+    pub fn with_max_backoff(self, max_backoff: Duration) -> Self {
+        Self {
+            max_backoff,
+            ..self
+        }
+    }
+
+    ///
+    /// Увеличивать паузу между попытками подключения в заданное
+    /// количество раз.
+    ///
+    #[inline]
+    pub fn with_multiplier(self, multiplier: f64) -> Self {
+        Self {
+            multiplier: multiplier.max(1.0),
+            ..self
+        }
+    }
+
+    ///
+    /// Получить максимальное количество попыток подключения.
+    ///
+    #[inline]
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    ///
+    /// Получить паузу перед попыткой подключения с заданным номером,
+    /// начиная с единицы. Первая попытка выполняется без паузы.
+    ///
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        if attempt <= 1 {
+            return Duration::ZERO;
+        }
+
+        let factor = self.multiplier.powi(attempt as i32 - 2);
+        self.initial_backoff
+            .mul_f64(factor.min(u32::MAX as f64))
+            .min(self.max_backoff)
+    }
+}
+
+///
+/// Состояние соединения клиента с сервером.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    ///
+    /// Соединение установлено.
+    ///
+    Connected,
+
+    ///
+    /// Соединение потеряно, выполняется попытка подключения с заданным
+    /// номером.
+    ///
+    Reconnecting(u32),
+
+    ///
+    /// Соединение потеряно, все попытки подключения исчерпаны.
+    ///
+    Disconnected,
+}
+
+impl fmt::Display for ConnectionStatus {
+    ///
+    /// Получить описание состояния соединения.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connected => write!(f, "connected"),
+            Self::Reconnecting(attempt) => write!(f, "reconnecting (attempt {})", attempt),
+            Self::Disconnected => write!(f, "disconnected"),
+        }
+    }
+}
+
+///
+/// Обработчик изменений состояния соединения клиента с сервером.
+///
+pub struct StatusListener(Box<dyn Fn(ConnectionStatus) + Send + Sync>);
+
+impl fmt::Debug for StatusListener {
+    ///
+    /// Выполнить отладочное форматирование обработчика.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StatusListener")
+    }
+}
+
+impl StatusListener {
+    ///
+    /// Создать обработчик изменений состояния соединения из функции.
+    ///
+    #[inline]
+    pub fn new<F: Fn(ConnectionStatus) + Send + Sync + 'static>(f: F) -> Self {
+        Self(Box::new(f))
+    }
+
+    ///
+    /// Сообщить обработчику новое состояние соединения.
+    ///
+    #[inline]
+    pub fn call(&self, status: ConnectionStatus) {
+        (self.0)(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_test() {
+        let policy = RetryPolicy::new()
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_max_backoff(Duration::from_millis(500))
+            .with_multiplier(2.0);
+
+        assert_eq!(policy.backoff(1), Duration::ZERO);
+        assert_eq!(policy.backoff(2), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(200));
+        assert_eq!(policy.backoff(4), Duration::from_millis(400));
+        assert_eq!(policy.backoff(5), Duration::from_millis(500));
+        assert_eq!(policy.backoff(100), Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::measurement::{Measurement, Quantity};
+
+// Счетчик для нумерации состояний устройств.
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+///
+/// Структура, содержащая состояние устройства после обработки
+/// события.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceState {
+    // Идентификатор устройства.
+    device_id: Uuid,
+    // Идентификатор события.
+    event_id: Uuid,
+
+    // Время получения состояния.
+    timestamp: SystemTime,
+    // Порядковый номер состояния.
+    sequence: u64,
+
+    // Результаты измерений устройства.
+    measurements: Vec<Measurement>,
+}
+
+impl DeviceState {
+    ///
+    /// Создать состояние устройства без результатов измерений. Состояние
+    /// получает текущее время и очередной порядковый номер, которые
+    /// позволяют упорядочить состояния и отбросить повторные.
+    ///
+    pub fn new(device_id: Uuid, event_id: Uuid) -> Self {
+        Self {
+            device_id,
+            event_id,
+            timestamp: SystemTime::now(),
+            sequence: SEQUENCE.fetch_add(1, Ordering::Relaxed) + 1,
+            measurements: Vec::new(),
+        }
+    }
+
+    ///
+    /// Получить состояние устройства для розетки.
+    ///
+    #[inline]
+    pub fn for_socket(device_id: Uuid, event_id: Uuid, enabled: bool, power: Option<f64>) -> Self {
+        let state = Self::new(device_id, event_id).with_measurement((Quantity::Enabled, enabled));
+        match power {
+            Some(power) => state.with_measurement((Quantity::Power, power)),
+            None => state,
+        }
+    }
+
+    ///
+    /// Получить состояние устройства для термометра.
+    ///
+    #[inline]
+    pub fn for_thermometer(device_id: Uuid, event_id: Uuid, themperature: f64) -> Self {
+        Self::new(device_id, event_id).with_measurement((Quantity::Temperature, themperature))
+    }
+
+    ///
+    /// Получить состояние устройства для лампы.
+    ///
+    pub fn for_lamp(
+        device_id: Uuid,
+        event_id: Uuid,
+        enabled: bool,
+        brightness: u8,
+        color_temperature: Option<u16>,
+    ) -> Self {
+        let state = Self::new(device_id, event_id)
+            .with_measurement((Quantity::Enabled, enabled))
+            .with_measurement((Quantity::Brightness, f64::from(brightness)));
+        match color_temperature {
+            Some(kelvin) => state.with_measurement((Quantity::ColorTemperature, f64::from(kelvin))),
+            None => state,
+        }
+    }
+
+    ///
+    /// Добавить результат измерения. Предыдущий результат измерения той же
+    /// величины заменяется.
+    ///
+    pub fn with_measurement<M: Into<Measurement>>(mut self, measurement: M) -> Self {
+        let measurement = measurement.into();
+        self.measurements
+            .retain(|m| m.quantity() != measurement.quantity());
+        self.measurements.push(measurement);
+        self
+    }
+
+    ///
+    /// Получить идентификатор устройства.
+    ///
+    #[inline]
+    pub fn device_id(&self) -> Uuid {
+        self.device_id
+    }
+
+    ///
+    /// Получить идентификатор класса события.
+    ///
+    #[inline]
+    pub fn event_id(&self) -> Uuid {
+        self.event_id
+    }
+
+    ///
+    /// Получить время получения состояния.
+    ///
+    #[inline]
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    ///
+    /// Получить порядковый номер состояния. Номера состояний, полученных
+    /// в одном процессе, возрастают.
+    ///
+    #[inline]
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    ///
+    /// Получить результаты измерений устройства.
+    ///
+    #[inline]
+    pub fn measurements(&self) -> &[Measurement] {
+        &self.measurements
+    }
+
+    ///
+    /// Получить результат измерения заданной величины.
+    ///
+    pub fn measurement(&self, quantity: Quantity) -> Option<&Measurement> {
+        self.measurements.iter().find(|m| m.quantity() == quantity)
+    }
+
+    ///
+    /// Получить измеряемую температуру устройства.
+    ///
+    #[inline]
+    pub fn themperature(&self) -> Option<f64> {
+        self.measurement(Quantity::Temperature)
+            .and_then(|m| m.value().as_f64())
+    }
+
+    ///
+    /// Определить, включено ли устройство.
+    ///
+    #[inline]
+    pub fn enabled(&self) -> Option<bool> {
+        self.measurement(Quantity::Enabled)
+            .and_then(|m| m.value().as_bool())
+    }
+
+    ///
+    /// Получить потребляемую мощность.
+    ///
+    #[inline]
+    pub fn power(&self) -> Option<f64> {
+        self.measurement(Quantity::Power)
+            .and_then(|m| m.value().as_f64())
+    }
+
+    ///
+    /// Получить яркость свечения в процентах.
+    ///
+    #[inline]
+    pub fn brightness(&self) -> Option<u8> {
+        self.measurement(Quantity::Brightness)
+            .and_then(|m| m.value().as_f64())
+            .map(|v| v as u8)
+    }
+
+    ///
+    /// Получить цветовую температуру свечения в кельвинах.
+    ///
+    #[inline]
+    pub fn color_temperature(&self) -> Option<u16> {
+        self.measurement(Quantity::ColorTemperature)
+            .and_then(|m| m.value().as_f64())
+            .map(|v| v as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::measurement::{Unit, Value};
+
+    use super::*;
+
+    #[test]
+    fn device_state_test() {
+        let state1 = DeviceState::for_socket(Uuid::nil(), Uuid::nil(), true, None);
+        assert_eq!(state1.enabled(), Some(true));
+        assert_eq!(state1.power(), None);
+        assert_eq!(state1.themperature(), None);
+
+        let state2 = DeviceState::new(Uuid::nil(), Uuid::nil())
+            .with_measurement(Measurement::new(Quantity::Power, 1.5, Unit::Watt))
+            .with_measurement((Quantity::Power, 2.5));
+        assert!(state2.sequence() > state1.sequence());
+        assert!(state2.timestamp() >= state1.timestamp());
+        assert_eq!(state2.measurements().len(), 1);
+        assert_eq!(state2.power(), Some(2.5));
+
+        let temperature = DeviceState::for_thermometer(Uuid::nil(), Uuid::nil(), -3.5);
+        let measurement = temperature.measurement(Quantity::Temperature).unwrap();
+        assert_eq!(measurement.value(), Value::Number(-3.5));
+        assert_eq!(measurement.unit(), Unit::Celsius);
+        assert_eq!(measurement.to_string(), "-3.5 °C");
+
+        let lamp = DeviceState::for_lamp(Uuid::nil(), Uuid::nil(), false, 40, Some(2700));
+        assert_eq!(lamp.enabled(), Some(false));
+        assert_eq!(lamp.brightness(), Some(40));
+        assert_eq!(lamp.color_temperature(), Some(2700));
+        assert_eq!(
+            lamp.measurement(Quantity::Brightness).unwrap().to_string(),
+            "40 %"
+        );
+    }
+}
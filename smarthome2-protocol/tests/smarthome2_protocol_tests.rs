@@ -0,0 +1,76 @@
+#![cfg(feature = "tokio")]
+
+use futures::{SinkExt, StreamExt};
+use tokio::io::AsyncWriteExt;
+use tokio_util::codec::{FramedRead, FramedWrite};
+use uuid::Uuid;
+
+use smarthome2_protocol::{
+    codec::Codec,
+    envelope::Envelope,
+    error::RecvError,
+    frame::FrameCodec,
+    message::{ControlRequest, ControlResponse, TextMessage},
+    state::DeviceState,
+};
+
+#[tokio::test]
+async fn framed_test() {
+    let (client, server) = tokio::io::duplex(64);
+    let codec = FrameCodec::new(Codec::MessagePack);
+    let mut writer = FramedWrite::new(client, codec);
+    let mut reader = FramedRead::new(server, codec);
+
+    let (room_id, device_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let state = DeviceState::for_socket(device_id, Uuid::new_v4(), true, Some(220.0));
+    let handle = tokio::spawn(async move {
+        let requests = [
+            ControlRequest::acquire_rooms(),
+            ControlRequest::switch_on_device((room_id, device_id)),
+        ];
+        for request in requests {
+            let envelope = Envelope::seal_with(Codec::MessagePack, request).unwrap();
+            writer.send(envelope).await.unwrap();
+        }
+        let envelope = Envelope::seal_with(
+            Codec::MessagePack,
+            ControlResponse::with_notification(room_id, state),
+        )
+        .unwrap();
+        writer.send(&envelope).await.unwrap();
+    });
+
+    let envelope = reader.next().await.unwrap().unwrap();
+    assert!(envelope.open::<ControlRequest>().unwrap().is_idempotent());
+
+    let envelope = reader.next().await.unwrap().unwrap();
+    assert!(!envelope.open::<ControlRequest>().unwrap().is_idempotent());
+
+    let envelope = reader.next().await.unwrap().unwrap();
+    let (id, notification) = envelope
+        .open::<ControlResponse>()
+        .unwrap()
+        .notification()
+        .unwrap();
+    assert_eq!(id, room_id);
+    assert_eq!(notification.device_id(), device_id);
+
+    handle.await.unwrap();
+    assert!(reader.next().await.is_none());
+}
+
+#[tokio::test]
+async fn truncated_frame_test() {
+    let (mut client, server) = tokio::io::duplex(64);
+    let codec = FrameCodec::new(Codec::Json);
+    let mut reader = FramedRead::new(server, codec);
+
+    let mut frame = Vec::new();
+    codec
+        .encode_message(TextMessage::new("hello"), &mut frame)
+        .unwrap();
+    client.write_all(&frame[..frame.len() - 1]).await.unwrap();
+    drop(client);
+
+    assert!(matches!(reader.next().await, Some(Err(RecvError::Io(_)))));
+}
@@ -1,4 +1,4 @@
-use std::error::Error;
+use std::{env, error::Error};
 
 use smarthome2::control::{
     message::TextMessage,
@@ -6,7 +6,8 @@ use smarthome2::control::{
 };
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let server = Server::bind("127.0.0.1:55332")?;
+    let key = env::var("SMARTHOME_KEY")?;
+    let server = Server::bind("127.0.0.1:55332", key)?;
     for connection in server.incoming() {
         process(connection?)?;
     }
@@ -1,15 +1,19 @@
-use std::fs;
+use std::{env, fs};
 
-use smarthome2::device::{
-    socket::{RemoteSmartSocket, SwitchOffEvent, SwitchOnEvent},
-    Device,
+use smarthome2::{
+    control::retry::RetryPolicy,
+    device::{
+        socket::{RemoteSmartSocket, SwitchOffEvent, SwitchOnEvent},
+        Device,
+    },
 };
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let addr =
         fs::read_to_string("settings/addr").unwrap_or_else(|_| String::from("127.0.0.1:55333"));
 
-    let mut remote_socket = RemoteSmartSocket::connect(addr)?;
+    let key = env::var("SMARTHOME_KEY")?;
+    let mut remote_socket = RemoteSmartSocket::reconnecting(addr, key, RetryPolicy::default())?;
     println!("Удаленная розетка: {}", remote_socket);
 
     let _ = remote_socket.notify(&SwitchOnEvent::new())?;
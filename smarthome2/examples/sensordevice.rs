@@ -0,0 +1,50 @@
+use std::{
+    env, fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread, time,
+};
+
+use smarthome2::device::{sensor::RemoteSensor, SensorKind};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let kind = match env::args().nth(1).as_deref() {
+        Some("co2") => SensorKind::CarbonDioxide,
+        Some("motion") => SensorKind::Motion,
+        Some("contact") => SensorKind::Contact,
+        _ => SensorKind::Humidity,
+    };
+
+    let sensor = RemoteSensor::builder(kind)
+        .with_name("Удаленный датчик")
+        .bind(
+            fs::read_to_string("settings/sensor_remote_addr")
+                .unwrap_or_else(|_| String::from("127.0.0.1:55337")),
+        )
+        .connect(
+            fs::read_to_string("settings/sensor_auto_addr")
+                .unwrap_or_else(|_| String::from("127.0.0.1:55336")),
+        )
+        .build();
+
+    let duration = time::Duration::from_secs(1);
+
+    let working = Arc::new(AtomicBool::new(true));
+    let control = Arc::downgrade(&working);
+    ctrlc::set_handler(move || {
+        if let Some(w) = control.upgrade() {
+            (*w).store(false, Ordering::Relaxed);
+        }
+    })?;
+
+    while (*working).load(Ordering::Relaxed) {
+        println!("Состояние датчика: {}", sensor);
+        thread::sleep(duration);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::{env, fs, sync::atomic::Ordering};
+
+use smarthome2::{
+    device::{
+        sensor::{AutonomousSensor, SmartSensor},
+        SensorKind, Value,
+    },
+    error::DeviceError,
+};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let (kind, value) = match env::args().nth(1).as_deref() {
+        Some("co2") => (SensorKind::CarbonDioxide, Value::Number(600.0)),
+        Some("motion") => (SensorKind::Motion, Value::Flag(false)),
+        Some("contact") => (SensorKind::Contact, Value::Flag(false)),
+        _ => (SensorKind::Humidity, Value::Number(45.0)),
+    };
+
+    let sensor = SmartSensor::new("Автономный датчик", kind, value);
+    let sensor = AutonomousSensor::builder()
+        .bind(
+            fs::read_to_string("settings/sensor_auto_addr")
+                .unwrap_or_else(|_| String::from("127.0.0.1:55336")),
+        )
+        .connect(
+            fs::read_to_string("settings/sensor_remote_addr")
+                .unwrap_or_else(|_| String::from("127.0.0.1:55337")),
+        )
+        .with_noise()
+        .build(sensor)?;
+
+    let (handle, control) = sensor.run()?;
+    ctrlc::set_handler(move || {
+        if let Some(w) = control.upgrade() {
+            log::info!("Terminating process ...");
+            (*w).store(false, Ordering::Relaxed);
+        }
+    })?;
+    if handle.join().is_err() {
+        return Err(Box::new(DeviceError::UnexpectedMessage));
+    }
+
+    Ok(())
+}
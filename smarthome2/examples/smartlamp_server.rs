@@ -0,0 +1,25 @@
+use std::{env, fs};
+
+use smarthome2::{control::server::SmartLampServer, device::lamp::SmartLamp};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let mut lamp = SmartLamp::new("Удаленная лампа").with_color_temperature(4000)?;
+    lamp.set_brightness(70)?;
+
+    let addr =
+        fs::read_to_string("settings/addr").unwrap_or_else(|_| String::from("127.0.0.1:55333"));
+    let key = env::var("SMARTHOME_KEY")?;
+    let server = SmartLampServer::bind(addr, key, lamp)?;
+
+    let shutdown = server.shutdown_handle();
+    ctrlc::set_handler(move || shutdown.shutdown())?;
+    server.run();
+
+    if let Ok(lamp) = server.into_lamp() {
+        println!("Состояние лампы: {}", lamp);
+    }
+
+    Ok(())
+}
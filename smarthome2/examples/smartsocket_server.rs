@@ -1,4 +1,4 @@
-use std::fs;
+use std::{env, fs};
 
 use smarthome2::{control::server::SmartSocketServer, device::socket::SmartSocket};
 
@@ -10,8 +10,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let addr =
         fs::read_to_string("settings/addr").unwrap_or_else(|_| String::from("127.0.0.1:55333"));
-    let server = SmartSocketServer::bind(addr, socket)?;
+    let key = env::var("SMARTHOME_KEY")?;
+    let server = SmartSocketServer::bind(addr, key, socket)?;
+
+    let shutdown = server.shutdown_handle();
+    ctrlc::set_handler(move || shutdown.shutdown())?;
     server.run();
 
+    if let Ok(socket) = server.into_socket() {
+        println!("Состояние розетки: {}", socket);
+    }
+
     Ok(())
 }
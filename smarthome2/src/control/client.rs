@@ -1,43 +1,465 @@
-use std::net::ToSocketAddrs;
+use std::{
+    collections::{HashMap, VecDeque},
+    net::{SocketAddr, ToSocketAddrs},
+    thread,
+};
+
+use uuid::Uuid;
 
 use crate::{
     control::{
-        message::{ControlRequest, ControlResponse, ControlResponseData},
+        message::{ControlRequest, ControlResponse},
         protocol::client::Client,
+        retry::{ConnectionStatus, RetryPolicy, StatusListener},
     },
-    error::{ConnectionError, RequestError},
+    device::DeviceState,
+    error::{ConnectionError, RecvError, RequestError, SendError},
 };
 
+// Способ повторного подключения к серверу.
+type Connector = Box<dyn FnMut() -> Result<Client, ConnectionError> + Send>;
+
+// Состояние запроса, отправленного серверу.
+enum Slot {
+    // Ответ еще не получен.
+    Waiting(ControlRequest),
+
+    // Ответ получен, но еще не забран.
+    Ready(Box<ControlResponse>),
+
+    // Соединение потеряно до получения ответа на запрос, который
+    // нельзя безопасно повторить.
+    Interrupted,
+
+    // Ответ не нужен: запрос отправлен клиентом самостоятельно при
+    // восстановлении соединения.
+    Detached,
+}
+
 ///
 /// Клиент подсистемы управления "умного" дома.
 ///
 pub struct ControlClient {
     client: Client,
+
+    // Идентификатор следующего запроса.
+    next_id: u64,
+
+    // Отправленные запросы и полученные, но еще не забранные ответы на них.
+    pending: HashMap<u64, Slot>,
+
+    // Полученные, но еще не забранные уведомления сервера.
+    notifications: VecDeque<(Uuid, DeviceState)>,
+
+    // Способ повторного подключения и политика повторных попыток.
+    // Отсутствует, если клиент не восстанавливает соединение.
+    reconnect: Option<(Connector, RetryPolicy)>,
+
+    // Запросы на подписку, повторяемые после восстановления соединения.
+    subscriptions: Vec<ControlRequest>,
+
+    // Текущее состояние соединения.
+    status: ConnectionStatus,
+
+    // Обработчик изменений состояния соединения.
+    listener: Option<StatusListener>,
+}
+
+impl From<Client> for ControlClient {
+    ///
+    /// Создать клиент подсистемы управления из настроенного и
+    /// подключенного клиента для обмена сообщениями.
+    ///
+    #[inline]
+    fn from(client: Client) -> Self {
+        Self {
+            client,
+            next_id: 1,
+            pending: HashMap::new(),
+            notifications: VecDeque::new(),
+            reconnect: None,
+            subscriptions: Vec::new(),
+            status: ConnectionStatus::Connected,
+            listener: None,
+        }
+    }
 }
 
 impl ControlClient {
     ///
-    /// Подключиться к серверу с заданным адресом.
+    /// Подключиться к серверу с заданным адресом и общим с сервером ключом
+    /// для аутентификации.
     ///
-    pub fn connect<A>(addrs: A) -> Result<Self, ConnectionError>
+    pub fn connect<A, K>(addrs: A, key: K) -> Result<Self, ConnectionError>
     where
         A: ToSocketAddrs,
+        K: AsRef<[u8]>,
+    {
+        Ok(Self::from(Client::connect(addrs, key)?))
+    }
+
+    ///
+    /// Подключиться к серверу с заданным адресом в режиме восстановления
+    /// соединения. При потере соединения клиент подключается повторно
+    /// в соответствии с заданной политикой.
+    ///
+    pub fn reconnecting<A, K>(
+        addrs: A,
+        key: K,
+        policy: RetryPolicy,
+    ) -> Result<Self, ConnectionError>
+    where
+        A: ToSocketAddrs,
+        K: AsRef<[u8]>,
+    {
+        let key = key.as_ref().to_vec();
+        let addrs: Vec<SocketAddr> = addrs.to_socket_addrs()?.collect();
+        Self::reconnecting_with(move || Client::connect(&addrs[..], &key), policy)
+    }
+
+    ///
+    /// Подключиться к серверу с помощью заданной функции в режиме
+    /// восстановления соединения. Функция вызывается повторно при каждой
+    /// попытке подключения, что позволяет использовать клиент для обмена
+    /// сообщениями с произвольными настройками.
+    ///
+    pub fn reconnecting_with<F>(connector: F, policy: RetryPolicy) -> Result<Self, ConnectionError>
+    where
+        F: FnMut() -> Result<Client, ConnectionError> + Send + 'static,
     {
+        let mut connector: Connector = Box::new(connector);
+        let client = establish(&mut connector, &policy, None)?;
+
         Ok(Self {
-            client: Client::connect(addrs)?,
+            reconnect: Some((connector, policy)),
+            ..Self::from(client)
         })
     }
 
+    ///
+    /// Сообщать заданному обработчику об изменениях состояния соединения.
+    ///
+    #[inline]
+    pub fn with_status_listener(self, listener: StatusListener) -> Self {
+        Self {
+            listener: Some(listener),
+            ..self
+        }
+    }
+
+    ///
+    /// Получить текущее состояние соединения.
+    ///
+    #[inline]
+    pub fn status(&self) -> ConnectionStatus {
+        self.status
+    }
+
     ///
     /// Отправить запрос серверу и получить ответ от него.
     ///
     pub fn request(&mut self, req: ControlRequest) -> Result<Box<ControlResponse>, RequestError> {
-        let response: Box<ControlResponse> = self.client.request(req)?;
+        let id = self.submit(req)?;
+        self.wait(id)
+    }
 
-        if let ControlResponseData::Error(message) = response.data {
-            Err(RequestError::ServerError(message))
+    ///
+    /// Отправить запрос серверу, не дожидаясь ответа. Возвращает
+    /// идентификатор запроса для последующего получения ответа.
+    ///
+    pub fn submit(&mut self, mut req: ControlRequest) -> Result<u64, RequestError> {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+
+        req.set_id(id);
+        self.deliver(&mut req)?;
+        if self.reconnect.is_some() && req.is_subscription() {
+            self.subscriptions.push(req.clone());
+        }
+        self.pending.insert(id, Slot::Waiting(req));
+
+        Ok(id)
+    }
+
+    ///
+    /// Дождаться ответа на ранее отправленный запрос с заданным
+    /// идентификатором. Ответы на другие запросы сохраняются до их
+    /// востребования.
+    ///
+    pub fn wait(&mut self, id: u64) -> Result<Box<ControlResponse>, RequestError> {
+        loop {
+            match self.pending.get(&id) {
+                Some(Slot::Ready(_)) => {
+                    if let Some(Slot::Ready(response)) = self.pending.remove(&id) {
+                        return Self::check(response);
+                    }
+                }
+                Some(Slot::Interrupted) => {
+                    self.pending.remove(&id);
+                    return Err(RequestError::Interrupted(id));
+                }
+                Some(Slot::Waiting(_)) => {}
+                Some(Slot::Detached) | None => return Err(RequestError::UnknownRequest(id)),
+            }
+
+            self.receive()?;
+        }
+    }
+
+    ///
+    /// Получить итератор по уведомлениям сервера об изменении состояния
+    /// устройств, на которые оформлена подписка. Итератор ожидает
+    /// очередного уведомления и завершается при разрыве соединения.
+    ///
+    #[inline]
+    pub fn notifications(&mut self) -> Notifications<'_> {
+        Notifications {
+            client: self,
+            closed: false,
+        }
+    }
+
+    ///
+    /// Получить количество запросов, ожидающих выдачи ответа.
+    ///
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.pending
+            .values()
+            .filter(|s| !matches!(s, Slot::Detached))
+            .count()
+    }
+
+    // Отправить запрос серверу. Запрос, который не удалось отправить,
+    // сервер не получил, поэтому в режиме восстановления соединения он
+    // отправляется повторно после подключения независимо от его вида.
+    fn deliver(&mut self, req: &mut ControlRequest) -> Result<(), RequestError> {
+        loop {
+            req.set_version(self.client.version());
+            let e = match self.client.send(req.clone()) {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+
+            if let SendError::Io(_) = e {
+                // Сервер мог закрыть соединение, предварительно сообщив причину.
+                if let Ok(response) = self.client.recv() {
+                    self.store(response)?;
+                }
+
+                if self.reconnect.is_some() {
+                    log::warn!("Connection lost when sending data: {}", e);
+                    self.reconnect()?;
+                    continue;
+                }
+            }
+
+            return Err(e.into());
+        }
+    }
+
+    // Получить очередное сообщение сервера и сохранить его до востребования.
+    // В режиме восстановления соединения потерянное соединение
+    // восстанавливается.
+    fn receive(&mut self) -> Result<(), RequestError> {
+        match self.client.recv() {
+            Ok(response) => self.store(response),
+            Err(RecvError::Timeout) => Err(RecvError::Timeout.into()),
+            Err(e) if self.reconnect.is_some() => {
+                log::warn!("Connection lost when receiving data: {}", e);
+                self.reconnect()
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Сохранить сообщение сервера до востребования. Ошибка, не относящаяся
+    // ни к одному запросу (например, отказ перегруженного сервера
+    // в обслуживании), возвращается сразу.
+    fn store(&mut self, response: Box<ControlResponse>) -> Result<(), RequestError> {
+        if let Some(notification) = response.notification() {
+            self.notifications.push_back(notification);
+            return Ok(());
+        }
+
+        if response.id() == 0 {
+            return Self::check(response).map(|_| ());
+        }
+
+        match self.pending.get_mut(&response.id()) {
+            Some(Slot::Detached) => {
+                self.pending.remove(&response.id());
+                if let Err(e) = Self::check(response) {
+                    log::warn!("Cannot restore subscription: {}", e);
+                }
+            }
+            Some(slot) => *slot = Slot::Ready(response),
+            None => log::warn!("Unexpected response to request {}", response.id()),
+        }
+
+        Ok(())
+    }
+
+    // Восстановить соединение с сервером. Запросы, которые можно безопасно
+    // повторить, и запросы на подписку отправляются повторно, остальные
+    // запросы, ожидающие ответа, завершаются ошибкой.
+    // Все попытки, включая повторные после неудачной отправки запросов
+    // через новое соединение, учитываются единым счетчиком политики.
+    fn reconnect(&mut self) -> Result<(), RequestError> {
+        let max_attempts = match self.reconnect {
+            Some((_, ref policy)) => policy.max_attempts(),
+            None => return Ok(()),
+        };
+
+        self.pending
+            .retain(|_, slot| !matches!(slot, Slot::Detached));
+        for slot in self.pending.values_mut() {
+            if matches!(slot, Slot::Waiting(ref req) if !req.is_idempotent()) {
+                *slot = Slot::Interrupted;
+            }
+        }
+
+        let mut result = Ok(());
+        for attempt in 1..=max_attempts {
+            let client = match self.reconnect {
+                Some((ref mut connector, ref policy)) => {
+                    try_connect(connector, policy, self.listener.as_ref(), attempt)
+                }
+                None => return Ok(()),
+            };
+            self.client = match client {
+                Ok(client) => client,
+                Err(e) if !e.is_transient() || attempt >= max_attempts => {
+                    self.set_status(ConnectionStatus::Disconnected);
+                    return Err(e.into());
+                }
+                Err(e) => {
+                    log::warn!("Connection attempt {} failed: {}", attempt, e);
+                    continue;
+                }
+            };
+            self.set_status(ConnectionStatus::Connected);
+
+            let mut resend: Vec<_> = self
+                .pending
+                .values()
+                .filter_map(|slot| match slot {
+                    Slot::Waiting(req) => Some(req.clone()),
+                    _ => None,
+                })
+                .collect();
+            for subscription in self.subscriptions.iter() {
+                let mut req = subscription.clone();
+                req.set_id(self.next_id);
+                self.next_id = self.next_id.wrapping_add(1).max(1);
+                self.pending.insert(req.id(), Slot::Detached);
+                resend.push(req);
+            }
+
+            let version = self.client.version();
+            result = resend.into_iter().try_for_each(|mut req| {
+                req.set_version(version);
+                self.client.send(req)
+            });
+            match result {
+                Ok(()) => return Ok(()),
+                Err(ref e) => log::warn!("Connection lost when resending requests: {}", e),
+            }
+
+            self.pending
+                .retain(|_, slot| !matches!(slot, Slot::Detached));
+        }
+
+        self.set_status(ConnectionStatus::Disconnected);
+        Ok(result?)
+    }
+
+    // Изменить состояние соединения и сообщить об этом обработчику.
+    fn set_status(&mut self, status: ConnectionStatus) {
+        self.status = status;
+        if let Some(ref listener) = self.listener {
+            listener.call(status);
+        }
+    }
+
+    // Преобразовать ответ с ошибкой сервера в ошибку запроса.
+    fn check(response: Box<ControlResponse>) -> Result<Box<ControlResponse>, RequestError> {
+        if let Some(error) = response.error() {
+            Err(RequestError::ServerError(Box::new(error.clone().into())))
         } else {
             Ok(response)
         }
     }
 }
+
+// Подключиться к серверу с помощью заданной функции, выполняя повторные
+// попытки в соответствии с политикой.
+fn establish(
+    connector: &mut Connector,
+    policy: &RetryPolicy,
+    listener: Option<&StatusListener>,
+) -> Result<Client, ConnectionError> {
+    let mut attempt = 1;
+    loop {
+        match try_connect(connector, policy, listener, attempt) {
+            Ok(client) => return Ok(client),
+            Err(e) if !e.is_transient() || attempt >= policy.max_attempts() => return Err(e),
+            Err(e) => log::warn!("Connection attempt {} failed: {}", attempt, e),
+        }
+
+        attempt += 1;
+    }
+}
+
+// Выполнить одну попытку подключения к серверу после задержки,
+// определяемой политикой для попытки с заданным номером.
+fn try_connect(
+    connector: &mut Connector,
+    policy: &RetryPolicy,
+    listener: Option<&StatusListener>,
+    attempt: u32,
+) -> Result<Client, ConnectionError> {
+    if let Some(listener) = listener {
+        listener.call(ConnectionStatus::Reconnecting(attempt));
+    }
+
+    thread::sleep(policy.backoff(attempt));
+    connector()
+}
+
+///
+/// Итератор по уведомлениям сервера об изменении состояния устройств.
+/// Возвращает идентификатор комнаты и новое состояние устройства.
+///
+pub struct Notifications<'a> {
+    client: &'a mut ControlClient,
+    closed: bool,
+}
+
+impl<'a> Iterator for Notifications<'a> {
+    type Item = Result<(Uuid, DeviceState), RequestError>;
+
+    ///
+    /// Дождаться очередного уведомления. Ответы на запросы, полученные
+    /// во время ожидания, сохраняются до их востребования. Уведомления
+    /// могут не поступать сколь угодно долго, поэтому истечение времени
+    /// ожидания данных ошибкой не считается.
+    ///
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.closed {
+            if let Some(notification) = self.client.notifications.pop_front() {
+                return Some(Ok(notification));
+            }
+
+            match self.client.receive() {
+                Ok(()) | Err(RequestError::Recv(RecvError::Timeout)) => {}
+                Err(e) => {
+                    self.closed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        None
+    }
+}
@@ -1,4 +1,7 @@
 pub mod client;
 pub mod message;
+pub mod pool;
 pub mod protocol;
+pub mod retry;
 pub mod server;
+pub(crate) mod subscription;
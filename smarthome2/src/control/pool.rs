@@ -0,0 +1,406 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use log;
+
+// Период проверки завершения рабочих потоков при остановке пула.
+const JOIN_INTERVAL: Duration = Duration::from_millis(100);
+
+///
+/// Статистика рабочего потока пула.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkerStats {
+    connections: u64,
+    requests: u64,
+    panics: u64,
+    busy: bool,
+}
+
+impl WorkerStats {
+    ///
+    /// Получить количество соединений, обслуживание которых начал
+    /// рабочий поток.
+    ///
+    #[inline]
+    pub fn connections(&self) -> u64 {
+        self.connections
+    }
+
+    ///
+    /// Получить количество запросов, обработанных рабочим потоком.
+    ///
+    #[inline]
+    pub fn requests(&self) -> u64 {
+        self.requests
+    }
+
+    ///
+    /// Получить количество соединений, закрытых из-за паники в
+    /// обработчике. Рабочий поток при этом продолжает работу.
+    ///
+    #[inline]
+    pub fn panics(&self) -> u64 {
+        self.panics
+    }
+
+    ///
+    /// Проверить, обслуживает ли рабочий поток соединение в данный момент.
+    ///
+    #[inline]
+    pub fn busy(&self) -> bool {
+        self.busy
+    }
+}
+
+///
+/// Статистика пула рабочих потоков.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    workers: Vec<WorkerStats>,
+    active: usize,
+    queued: usize,
+    rejected: u64,
+}
+
+impl PoolStats {
+    ///
+    /// Получить статистику рабочих потоков.
+    ///
+    #[inline]
+    pub fn workers(&self) -> &[WorkerStats] {
+        &self.workers
+    }
+
+    ///
+    /// Получить количество открытых соединений, принятых пулом.
+    ///
+    #[inline]
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    ///
+    /// Получить количество соединений, ожидающих в очереди очередного
+    /// цикла обслуживания.
+    ///
+    #[inline]
+    pub fn queued(&self) -> usize {
+        self.queued
+    }
+
+    ///
+    /// Получить количество соединений, отклоненных из-за переполнения
+    /// очереди.
+    ///
+    #[inline]
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+}
+
+///
+/// Счетчики рабочего потока пула.
+///
+#[derive(Default)]
+pub(crate) struct Worker {
+    connections: AtomicU64,
+    requests: AtomicU64,
+    panics: AtomicU64,
+    busy: AtomicBool,
+}
+
+impl Worker {
+    ///
+    /// Учесть обработанный запрос.
+    ///
+    #[inline]
+    pub(crate) fn count_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    ///
+    /// Получить статистику рабочего потока.
+    ///
+    fn stats(&self) -> WorkerStats {
+        WorkerStats {
+            connections: self.connections.load(Ordering::Relaxed),
+            requests: self.requests.load(Ordering::Relaxed),
+            panics: self.panics.load(Ordering::Relaxed),
+            busy: self.busy.load(Ordering::Relaxed),
+        }
+    }
+
+    ///
+    /// Обслуживать соединения из очереди до ее закрытия. Паника в
+    /// обработчике закрывает только обслуживаемое соединение, после
+    /// чего рабочий поток продолжает работу.
+    ///
+    fn run<T, F>(&self, queue: &Queue<T>, handler: &F)
+    where
+        F: Fn(&Worker, &mut T) -> bool,
+    {
+        while let Some(mut entry) = queue.pop() {
+            if !entry.started {
+                entry.started = true;
+                self.connections.fetch_add(1, Ordering::Relaxed);
+            }
+
+            self.busy.store(true, Ordering::Relaxed);
+            let open = panic::catch_unwind(AssertUnwindSafe(|| handler(self, &mut entry.item)));
+            self.busy.store(false, Ordering::Relaxed);
+
+            match open {
+                Ok(true) => queue.push(entry),
+                Ok(false) => queue.close_entry(entry),
+                Err(_) => {
+                    log::error!("Connection handler panicked, closing the connection");
+                    self.panics.fetch_add(1, Ordering::Relaxed);
+                    queue.close_entry(entry);
+                }
+            }
+        }
+    }
+}
+
+///
+/// Пул рабочих потоков, обслуживающих соединения по очереди. Рабочий
+/// поток выполняет один цикл обслуживания соединения и возвращает его
+/// в конец очереди, поэтому долгоживущие соединения не занимают потоки
+/// и не мешают обслуживанию остальных клиентов.
+///
+pub(crate) struct WorkerPool {
+    workers: Vec<Arc<Worker>>,
+    queue_capacity: usize,
+    active: Arc<AtomicUsize>,
+    queued: Arc<AtomicUsize>,
+    rejected: AtomicU64,
+}
+
+impl WorkerPool {
+    ///
+    /// Создать пул с заданными количеством рабочих потоков и количеством
+    /// соединений, которые могут ожидать обслуживания сверх количества
+    /// рабочих потоков.
+    ///
+    pub(crate) fn new(workers: usize, queue_capacity: usize) -> Self {
+        Self {
+            workers: (0..workers.max(1))
+                .map(|_| Arc::new(Worker::default()))
+                .collect(),
+            queue_capacity,
+            active: Arc::new(AtomicUsize::new(0)),
+            queued: Arc::new(AtomicUsize::new(0)),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    ///
+    /// Получить количество рабочих потоков.
+    ///
+    #[inline]
+    pub(crate) fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    ///
+    /// Получить размер очереди соединений.
+    ///
+    #[inline]
+    pub(crate) fn queue_capacity(&self) -> usize {
+        self.queue_capacity
+    }
+
+    ///
+    /// Получить статистику пула.
+    ///
+    pub(crate) fn stats(&self) -> PoolStats {
+        PoolStats {
+            workers: self.workers.iter().map(|w| w.stats()).collect(),
+            active: self.active.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+
+    ///
+    /// Запустить рабочие потоки, обслуживающие соединения из очереди
+    /// с помощью заданного обработчика. Обработчик выполняет один цикл
+    /// обслуживания соединения и возвращает `false`, если соединение
+    /// закрыто; открытое соединение возвращается в конец очереди.
+    ///
+    pub(crate) fn start<T, F>(&self, handler: F) -> Dispatcher<'_, T>
+    where
+        T: Send + 'static,
+        F: Fn(&Worker, &mut T) -> bool + Send + Sync + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let queue = Arc::new(Queue {
+            sender,
+            receiver: Mutex::new(receiver),
+            active: self.active.clone(),
+            queued: self.queued.clone(),
+            closed: AtomicBool::new(false),
+        });
+        let handler = Arc::new(handler);
+
+        let threads = self
+            .workers
+            .iter()
+            .map(|worker| {
+                let worker = worker.clone();
+                let queue = queue.clone();
+                let handler = handler.clone();
+                thread::spawn(move || worker.run(&queue, &*handler))
+            })
+            .collect();
+
+        Dispatcher {
+            pool: self,
+            queue,
+            threads,
+        }
+    }
+}
+
+///
+/// Распределяет соединения между рабочими потоками запущенного пула.
+///
+pub(crate) struct Dispatcher<'a, T> {
+    pool: &'a WorkerPool,
+    queue: Arc<Queue<T>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl<'a, T> Dispatcher<'a, T> {
+    ///
+    /// Поставить соединение в очередь на обслуживание. Если количество
+    /// открытых соединений превышает количество рабочих потоков более
+    /// чем на размер очереди, соединение возвращается вызывающей стороне.
+    ///
+    pub(crate) fn dispatch(&self, item: T) -> Result<(), T> {
+        let limit = self.pool.workers() + self.pool.queue_capacity;
+        let active = self.queue.active.fetch_add(1, Ordering::Relaxed);
+        if active >= limit {
+            self.queue.active.fetch_sub(1, Ordering::Relaxed);
+            self.pool.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(item);
+        }
+
+        self.queue.push(Entry {
+            item,
+            started: false,
+        });
+
+        Ok(())
+    }
+
+    ///
+    /// Закрыть очередь и дождаться завершения рабочих потоков не дольше
+    /// заданного времени.
+    ///
+    pub(crate) fn join(self, timeout: Duration) {
+        self.queue.closed.store(true, Ordering::Relaxed);
+
+        let deadline = Instant::now() + timeout;
+        let mut threads = self.threads;
+        loop {
+            let (finished, running): (Vec<_>, Vec<_>) =
+                threads.into_iter().partition(|t| t.is_finished());
+            for thread in finished {
+                if thread.join().is_err() {
+                    log::error!("Worker thread panicked");
+                }
+            }
+
+            threads = running;
+            if threads.is_empty() {
+                break;
+            }
+
+            if Instant::now() >= deadline {
+                log::warn!(
+                    "{} worker(s) did not finish before the shutdown deadline",
+                    threads.len()
+                );
+                break;
+            }
+
+            thread::sleep(JOIN_INTERVAL);
+        }
+    }
+}
+
+///
+/// Соединение в очереди на обслуживание.
+///
+struct Entry<T> {
+    item: T,
+    started: bool,
+}
+
+///
+/// Очередь соединений, общая для распределителя и рабочих потоков.
+///
+struct Queue<T> {
+    sender: Sender<Entry<T>>,
+    receiver: Mutex<Receiver<Entry<T>>>,
+    active: Arc<AtomicUsize>,
+    queued: Arc<AtomicUsize>,
+    closed: AtomicBool,
+}
+
+impl<T> Queue<T> {
+    ///
+    /// Поставить соединение в конец очереди. После закрытия очереди
+    /// соединение закрывается.
+    ///
+    fn push(&self, entry: Entry<T>) {
+        if self.closed.load(Ordering::Relaxed) {
+            return self.close_entry(entry);
+        }
+
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        if let Err(e) = self.sender.send(entry) {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            self.close_entry(e.0);
+        }
+    }
+
+    ///
+    /// Получить очередное соединение. Возвращает `None` после закрытия
+    /// очереди; оставшиеся в ней соединения при этом закрываются.
+    ///
+    fn pop(&self) -> Option<Entry<T>> {
+        loop {
+            let entry = match self.receiver.lock().ok()?.recv_timeout(JOIN_INTERVAL) {
+                Ok(entry) => entry,
+                Err(RecvTimeoutError::Timeout) if !self.closed.load(Ordering::Relaxed) => continue,
+                Err(_) => return None,
+            };
+
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            if !self.closed.load(Ordering::Relaxed) {
+                return Some(entry);
+            }
+            self.close_entry(entry);
+        }
+    }
+
+    ///
+    /// Закрыть соединение, исключив его из числа открытых.
+    ///
+    fn close_entry(&self, entry: Entry<T>) {
+        drop(entry);
+        self.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
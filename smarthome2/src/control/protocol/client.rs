@@ -1,33 +1,63 @@
 use std::{
-    io::{Read, Write},
+    io::{self, Read, Write},
     net::{TcpStream, ToSocketAddrs},
+    time::Duration,
 };
 
+#[cfg(unix)]
+use std::{os::unix::net::UnixStream, path::Path};
+
 use rand::{self, Rng};
 use serde::{de, Serialize};
 
 use crate::{
-    control::protocol::{consts::MASK, mask, recv_message, send_message, Message},
-    error::{ConnectionError, RequestError},
+    control::protocol::{
+        codec::Codec,
+        consts::{CLIENT_ROLE, NONCE_SIZE, SERVER_ROLE, TAG_SIZE},
+        envelope::Envelope,
+        handshake::{self, Capabilities, HandshakeRequest, HandshakeResponse},
+        recv_envelope, recv_message, send_envelope, send_message,
+        stream::{CountingStream, Stream},
+        transport::Transport,
+        Limits, Message, ProtocolVersion,
+    },
+    error::{ConnectionError, RecvError, RequestError, SendError},
 };
 
+#[cfg(feature = "tls")]
+use crate::control::protocol::tls::TlsClientConfig;
+
 ///
 /// Представляет клиент для обмена сообщениями.
 ///
 pub struct Client {
-    stream: TcpStream,
+    stream: Stream,
+    version: ProtocolVersion,
+    capabilities: Capabilities,
+    codec: Codec,
+    limits: Limits,
 }
 
 impl Client {
     ///
-    /// Подключиться к серверу с заданным адресом.
+    /// Подключиться к серверу с заданным адресом и общим с сервером ключом
+    /// для аутентификации, используя остальные настройки по умолчанию.
     ///
-    pub fn connect<A>(addrs: A) -> Result<Self, ConnectionError>
+    pub fn connect<A, K>(addrs: A, key: K) -> Result<Self, ConnectionError>
     where
         A: ToSocketAddrs,
+        K: AsRef<[u8]>,
     {
-        let stream = TcpStream::connect(addrs)?;
-        Self::try_handshake(stream)
+        Self::builder(key).connect(addrs)
+    }
+
+    ///
+    /// Создать объект для построения клиента с заданным общим с сервером
+    /// ключом для аутентификации и остальными настройками по умолчанию.
+    ///
+    #[inline]
+    pub fn builder<K: AsRef<[u8]>>(key: K) -> ClientBuilder {
+        ClientBuilder::new(key)
     }
 
     ///
@@ -38,25 +68,310 @@ impl Client {
         R: Message + Serialize,
         S: Message + de::DeserializeOwned,
     {
-        send_message(req, &mut self.stream)?;
-        let response = recv_message(&mut self.stream)?;
+        self.send(req)?;
+        let response = self.recv()?;
 
         Ok(response)
     }
 
+    ///
+    /// Отправить сообщение серверу, не дожидаясь ответа.
+    ///
+    #[inline]
+    pub fn send<M: Message + Serialize>(&mut self, message: M) -> Result<(), SendError> {
+        self.send_any(&Envelope::seal_with(self.codec, message)?)
+    }
+
+    ///
+    /// Отправить серверу сообщение из конверта, не дожидаясь ответа.
+    ///
+    /// Если время ожидания истекло посреди кадра, соединение закрывается:
+    /// продолжить обмен сообщениями по нему уже нельзя.
+    ///
+    pub fn send_any(&mut self, envelope: &Envelope) -> Result<(), SendError> {
+        let mut stream = CountingStream::new(&mut self.stream);
+        match send_envelope(envelope, self.codec, &mut stream) {
+            Err(e) if stream.count() > 0 => {
+                self.shutdown();
+                match e {
+                    SendError::Timeout => Err(SendError::Io(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "sending timed out in the middle of a frame",
+                    ))),
+                    e => Err(e),
+                }
+            }
+            result => result,
+        }
+    }
+
+    ///
+    /// Получить очередное сообщение от сервера.
+    ///
+    #[inline]
+    pub fn recv<M: Message + de::DeserializeOwned>(&mut self) -> Result<Box<M>, RecvError> {
+        self.recv_any()?.open()
+    }
+
+    ///
+    /// Получить от сервера сообщение произвольного типа в конверте.
+    ///
+    /// Если время ожидания истекло посреди кадра, соединение закрывается:
+    /// продолжить обмен сообщениями по нему уже нельзя.
+    ///
+    pub fn recv_any(&mut self) -> Result<Envelope, RecvError> {
+        let mut stream = CountingStream::new(&mut self.stream);
+        match recv_envelope(&mut stream, self.codec, self.limits.max_frame_size) {
+            Err(e) if stream.count() > 0 => {
+                self.shutdown();
+                match e {
+                    RecvError::Timeout => Err(RecvError::Io(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "receiving timed out in the middle of a frame",
+                    ))),
+                    e => Err(e),
+                }
+            }
+            result => result,
+        }
+    }
+
+    ///
+    /// Получить согласованную с сервером версию протокола.
+    ///
+    #[inline]
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    ///
+    /// Получить согласованный с сервером набор возможностей.
+    ///
+    #[inline]
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    ///
+    /// Получить согласованный с сервером кодек данных сообщений.
+    ///
+    #[inline]
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    // Закрыть соединение в обоих направлениях.
+    fn shutdown(&self) {
+        if let Err(e) = self.stream.shutdown() {
+            log::debug!("Failed to shut down connection: {}", e);
+        }
+    }
+
     // Подтвердить handshake.
-    fn try_handshake(mut stream: TcpStream) -> Result<Self, ConnectionError> {
-        let data = rand::thread_rng().gen::<[u8; 32]>();
-        stream.write_all(&data)?;
+    fn try_handshake(
+        mut stream: Stream,
+        key: &[u8],
+        capabilities: Capabilities,
+        limits: Limits,
+    ) -> Result<Self, ConnectionError> {
+        let client_nonce = rand::thread_rng().gen::<[u8; NONCE_SIZE]>();
+        stream.write_all(&client_nonce)?;
 
-        let mut bytes = [0u8; 32];
-        stream.read_exact(&mut bytes)?;
+        let mut server_nonce = [0u8; NONCE_SIZE];
+        stream.read_exact(&mut server_nonce)?;
+        let mut server_tag = [0u8; TAG_SIZE];
+        stream.read_exact(&mut server_tag)?;
 
-        let bytes = mask(bytes, MASK);
-        if bytes != data {
-            return Err(ConnectionError::BadHandshake);
+        if !handshake::verify(key, SERVER_ROLE, &client_nonce, &server_nonce, &server_tag) {
+            return Err(ConnectionError::AuthenticationFailed);
         }
 
-        Ok(Self { stream })
+        let client_tag = handshake::sign(key, CLIENT_ROLE, &server_nonce, &client_nonce);
+        stream.write_all(&client_tag)?;
+
+        send_message(
+            HandshakeRequest::new(capabilities),
+            Codec::Bincode,
+            &mut stream,
+        )?;
+        match *recv_message::<HandshakeResponse, _>(
+            &mut stream,
+            Codec::Bincode,
+            limits.max_frame_size,
+        )? {
+            HandshakeResponse::Accepted(code, capabilities) => {
+                let version =
+                    ProtocolVersion::from_code(code).ok_or(ConnectionError::BadHandshake)?;
+
+                let codec = Codec::select(capabilities).ok_or(ConnectionError::BadHandshake)?;
+
+                Ok(Self {
+                    stream,
+                    version,
+                    capabilities,
+                    codec,
+                    limits,
+                })
+            }
+
+            HandshakeResponse::UnsupportedVersion(versions) => {
+                Err(ConnectionError::UnsupportedVersion(versions))
+            }
+
+            HandshakeResponse::IncompatibleCapabilities(capabilities) => {
+                Err(ConnectionError::IncompatibleCapabilities(capabilities))
+            }
+
+            HandshakeResponse::AuthenticationFailed => Err(ConnectionError::AuthenticationFailed),
+        }
+    }
+}
+
+///
+/// Структура для построения экземпляра клиента.
+///
+pub struct ClientBuilder {
+    ///
+    /// Общий с сервером ключ для аутентификации.
+    ///
+    key: Vec<u8>,
+
+    ///
+    /// Запрашиваемый у сервера набор возможностей.
+    ///
+    capabilities: Capabilities,
+
+    ///
+    /// Ограничения, накладываемые на соединение.
+    ///
+    limits: Limits,
+
+    ///
+    /// Настройки защищенного соединения.
+    ///
+    #[cfg(feature = "tls")]
+    tls: Option<TlsClientConfig>,
+}
+
+impl ClientBuilder {
+    ///
+    /// Создать экземпляр построителя клиента с заданным общим с сервером
+    /// ключом для аутентификации и остальными настройками по умолчанию.
+    ///
+    #[inline]
+    pub fn new<K: AsRef<[u8]>>(key: K) -> Self {
+        Self {
+            key: key.as_ref().to_vec(),
+            capabilities: Capabilities::supported(),
+            limits: Limits::default(),
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    ///
+    /// Запросить у сервера заданный набор возможностей.
+    ///
+    #[inline]
+    pub fn with_capabilities(self, capabilities: Capabilities) -> Self {
+        Self {
+            capabilities,
+            ..self
+        }
+    }
+
+    ///
+    /// Кодировать данные сообщений только заданным кодеком, заменив
+    /// остальные кодеки в запрашиваемом наборе возможностей.
+    ///
+    #[inline]
+    pub fn with_codec(self, codec: Codec) -> Self {
+        Self {
+            capabilities: self.capabilities.without(Capabilities::CODECS) | codec.capability(),
+            ..self
+        }
+    }
+
+    ///
+    /// Принимать от сервера сообщения с размером данных не более заданного.
+    ///
+    #[inline]
+    pub fn with_max_frame_size(mut self, max_frame_size: u32) -> Self {
+        self.limits.max_frame_size = max_frame_size;
+        self
+    }
+
+    ///
+    /// Ожидать данные от сервера не дольше заданного времени
+    /// (`None` снимает ограничение).
+    ///
+    #[inline]
+    pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.read_timeout = timeout.filter(|t| !t.is_zero());
+        self
+    }
+
+    ///
+    /// Ожидать отправки данных серверу не дольше заданного времени
+    /// (`None` снимает ограничение).
+    ///
+    #[inline]
+    pub fn with_write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.write_timeout = timeout.filter(|t| !t.is_zero());
+        self
+    }
+
+    ///
+    /// Использовать защищенное соединение TLS с заданными настройками.
+    ///
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub fn with_tls(self, tls: TlsClientConfig) -> Self {
+        Self {
+            tls: Some(tls),
+            ..self
+        }
+    }
+
+    ///
+    /// Подключиться к серверу с заданным адресом.
+    ///
+    #[inline]
+    pub fn connect<A>(self, addrs: A) -> Result<Client, ConnectionError>
+    where
+        A: ToSocketAddrs,
+    {
+        self.connect_with(TcpStream::connect(addrs)?)
+    }
+
+    ///
+    /// Подключиться к серверу через сокет Unix с заданным путем.
+    ///
+    #[cfg(unix)]
+    #[inline]
+    pub fn connect_unix<P: AsRef<Path>>(self, path: P) -> Result<Client, ConnectionError> {
+        self.connect_with(UnixStream::connect(path)?)
+    }
+
+    ///
+    /// Подключиться к серверу через заданный транспорт.
+    ///
+    pub fn connect_with<T: Transport + 'static>(
+        self,
+        transport: T,
+    ) -> Result<Client, ConnectionError> {
+        transport.set_read_timeout(self.limits.read_timeout)?;
+        transport.set_write_timeout(self.limits.write_timeout)?;
+        let transport: Box<dyn Transport> = Box::new(transport);
+
+        #[cfg(feature = "tls")]
+        let stream = match self.tls {
+            Some(ref tls) => tls.connect(transport)?,
+            None => Stream::Plain(transport),
+        };
+        #[cfg(not(feature = "tls"))]
+        let stream = Stream::Plain(transport);
+
+        Client::try_handshake(stream, &self.key, self.capabilities, self.limits)
     }
 }
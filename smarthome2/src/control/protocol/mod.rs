@@ -1,80 +1,140 @@
-use std::io::{Read, Write};
+use std::{
+    io::{Read, Write},
+    time::Duration,
+};
 
-use bincode::{self, Options};
-use serde::{de, Deserialize, Serialize};
+use serde::{de, Serialize};
 
-use crate::error::{RecvError, SendError};
+use crate::{
+    control::protocol::{
+        codec::Codec,
+        consts::{DEFAULT_HANDSHAKE_TIMEOUT, DEFAULT_MAX_FRAME_SIZE, DEFAULT_TIMEOUT},
+        envelope::Envelope,
+        frame::{FrameCodec, FrameHeader, HEADER_SIZE},
+    },
+    error::{RecvError, SendError},
+};
 
 pub mod client;
-pub mod consts;
+pub mod pipe;
 pub mod server;
+pub(crate) mod stream;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod transport;
+
+pub use smarthome2_protocol::{
+    codec, consts, envelope, frame, handshake, Message, ProtocolVersion,
+};
 
 ///
-/// Типаж для отправки и получения сообщений по сети.
+/// Ограничения, накладываемые на соединение.
 ///
-pub trait Message {
-    ///
-    /// Идентификатор типа сообщения.
-    ///
-    const TYPE: u16;
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limits {
+    // Максимальный размер данных одного сообщения.
+    pub(crate) max_frame_size: u32,
+
+    // Время ожидания данных при чтении сообщения.
+    pub(crate) read_timeout: Option<Duration>,
+
+    // Время ожидания при записи сообщения.
+    pub(crate) write_timeout: Option<Duration>,
+
+    // Время ожидания начала следующего сообщения.
+    pub(crate) idle_timeout: Option<Duration>,
+
+    // Время, за которое должно завершиться подтверждение связи.
+    pub(crate) handshake_timeout: Option<Duration>,
 }
 
-///
-/// Версия протокола.
-///
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ProtocolVersion {
-    #[serde(rename = "1.0")]
-    V1_0,
+impl Default for Limits {
+    ///
+    /// Ограничения по умолчанию.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            read_timeout: Some(DEFAULT_TIMEOUT),
+            write_timeout: Some(DEFAULT_TIMEOUT),
+            idle_timeout: None,
+            handshake_timeout: Some(DEFAULT_HANDSHAKE_TIMEOUT),
+        }
+    }
 }
 
-// Отправить сообщение.
+// Отправить сообщение, закодированное заданным кодеком.
 pub(crate) fn send_message<M: Message + Serialize, W: Write>(
     message: M,
-    mut writer: W,
+    codec: Codec,
+    writer: W,
 ) -> Result<(), SendError> {
-    let bytes = M::TYPE.to_be_bytes();
-    writer.write_all(&bytes)?;
+    send_envelope(&Envelope::seal_with(codec, message)?, codec, writer)
+}
 
-    let data = bincode::options().with_big_endian().serialize(&message)?;
-    let size = data.len() as u32;
-    let bytes = size.to_be_bytes();
-    writer.write_all(&bytes)?;
-    writer.write_all(data.as_ref())?;
+// Отправить сообщение из конверта, если оно закодировано заданным кодеком.
+pub(crate) fn send_envelope<W: Write>(
+    envelope: &Envelope,
+    codec: Codec,
+    mut writer: W,
+) -> Result<(), SendError> {
+    let mut frame = Vec::new();
+    FrameCodec::new(codec).encode(envelope, &mut frame)?;
+    // Заголовок и данные записываются отдельно: запись в соединение,
+    // закрытое другой стороной, завершится ошибкой еще до отправки
+    // данных, и запрос не будет считаться доставленным.
+    let (header, data) = frame.split_at(HEADER_SIZE);
+    writer.write_all(header)?;
+    writer.write_all(data)?;
 
     Ok(())
 }
 
-// Получить сообщение.
+// Получить сообщение, закодированное заданным кодеком.
 pub(crate) fn recv_message<M: Message + de::DeserializeOwned, R: Read>(
-    mut reader: R,
+    reader: R,
+    codec: Codec,
+    max_frame_size: u32,
 ) -> Result<Box<M>, RecvError> {
-    let mut bytes = [0u8; 2];
-    reader.read_exact(&mut bytes)?;
-    let message_type = u16::from_be_bytes(bytes);
-    if message_type != M::TYPE {
-        return Err(RecvError::BadType(message_type));
-    }
+    recv_envelope(reader, codec, max_frame_size)?.open()
+}
 
-    let mut bytes = [0u8; 4];
+// Получить сообщение произвольного типа в конверте.
+pub(crate) fn recv_envelope<R: Read>(
+    mut reader: R,
+    codec: Codec,
+    max_frame_size: u32,
+) -> Result<Envelope, RecvError> {
+    let mut bytes = [0u8; HEADER_SIZE];
     reader.read_exact(&mut bytes)?;
-    let len = u32::from_be_bytes(bytes);
+    let header = FrameHeader::parse(&bytes, max_frame_size)?;
 
-    let mut data = vec![0u8; len as _];
+    let mut data = vec![0u8; header.size() as _];
     reader.read_exact(&mut data)?;
-    let message = bincode::options()
-        .with_big_endian()
-        .deserialize(&data[..])?;
 
-    Ok(Box::new(message))
+    Ok(Envelope::from_parts(header.message_type(), codec, data))
 }
 
-// Маскировать бинарные данные.
-pub(crate) fn mask<const N: usize>(data: [u8; N], mask: &[u8]) -> [u8; N] {
-    let mut result = [0u8; N];
-    for (idx, (&v1, &v2)) in data.iter().zip(mask.iter().cycle()).enumerate() {
-        result[idx] = v1 ^ v2;
-    }
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::control::message::TextMessage;
 
-    result
+    #[test]
+    fn frame_size_test() {
+        let mut data = Vec::new();
+        send_message(TextMessage::new("x".repeat(64)), Codec::Bincode, &mut data).unwrap();
+
+        let message =
+            recv_message::<TextMessage, _>(Cursor::new(&data), Codec::Bincode, 128).unwrap();
+        assert_eq!(message.to_string(), "x".repeat(64));
+
+        assert!(matches!(
+            recv_message::<TextMessage, _>(Cursor::new(&data), Codec::Bincode, 32),
+            Err(RecvError::FrameTooLarge(_))
+        ));
+    }
 }
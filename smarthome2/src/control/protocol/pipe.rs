@@ -0,0 +1,310 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::control::protocol::transport::{Address, Listener, Transport};
+
+///
+/// Создать пару связанных двунаправленных каналов в памяти процесса.
+/// Данные, записанные в один канал, читаются из другого.
+///
+pub fn pipe() -> (PipeStream, PipeStream) {
+    let forward = Arc::new(Buffer::default());
+    let backward = Arc::new(Buffer::default());
+
+    (
+        PipeStream::new(backward.clone(), forward.clone()),
+        PipeStream::new(forward, backward),
+    )
+}
+
+///
+/// Буфер данных, передаваемых по каналу в одном направлении.
+///
+#[derive(Default)]
+struct Buffer {
+    state: Mutex<BufferState>,
+    ready: Condvar,
+}
+
+// Состояние буфера канала.
+#[derive(Default)]
+struct BufferState {
+    // Записанные, но еще не прочитанные данные.
+    data: VecDeque<u8>,
+
+    // Одна из сторон канала закрыта.
+    closed: bool,
+}
+
+impl Buffer {
+    ///
+    /// Закрыть буфер и разбудить ожидающих чтения.
+    ///
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.ready.notify_all();
+    }
+}
+
+///
+/// Двунаправленный канал в памяти процесса.
+///
+pub struct PipeStream {
+    incoming: Arc<Buffer>,
+    outgoing: Arc<Buffer>,
+    read_timeout: Mutex<Option<Duration>>,
+}
+
+impl PipeStream {
+    ///
+    /// Создать канал, читающий из одного буфера и пишущий в другой.
+    ///
+    fn new(incoming: Arc<Buffer>, outgoing: Arc<Buffer>) -> Self {
+        Self {
+            incoming,
+            outgoing,
+            read_timeout: Mutex::new(None),
+        }
+    }
+}
+
+impl Read for PipeStream {
+    ///
+    /// Прочитать данные из канала. Если данных нет, ожидает их
+    /// поступления не дольше времени ожидания чтения. После закрытия
+    /// другой стороны канала возвращает конец потока.
+    ///
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let deadline = self
+            .read_timeout
+            .lock()
+            .unwrap()
+            .map(|t| Instant::now() + t);
+
+        let mut state = self.incoming.state.lock().unwrap();
+        while state.data.is_empty() && !state.closed && !buf.is_empty() {
+            state = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(io::ErrorKind::WouldBlock.into());
+                    }
+                    self.incoming
+                        .ready
+                        .wait_timeout(state, deadline - now)
+                        .unwrap()
+                        .0
+                }
+                None => self.incoming.ready.wait(state).unwrap(),
+            };
+        }
+
+        let n = buf.len().min(state.data.len());
+        for (dst, src) in buf.iter_mut().zip(state.data.drain(..n)) {
+            *dst = src;
+        }
+
+        Ok(n)
+    }
+}
+
+impl Write for PipeStream {
+    ///
+    /// Записать данные в канал. После закрытия другой стороны канала
+    /// возвращает ошибку.
+    ///
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.outgoing.state.lock().unwrap();
+        if state.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+
+        state.data.extend(buf);
+        self.outgoing.ready.notify_all();
+
+        Ok(buf.len())
+    }
+
+    ///
+    /// Отправить буферизованные данные.
+    ///
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for PipeStream {
+    ///
+    /// Закрыть канал в обоих направлениях.
+    ///
+    fn drop(&mut self) {
+        self.incoming.close();
+        self.outgoing.close();
+    }
+}
+
+impl Transport for PipeStream {
+    ///
+    /// Ожидать данные не дольше заданного времени.
+    ///
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        *self.read_timeout.lock().unwrap() = timeout;
+        Ok(())
+    }
+
+    ///
+    /// Запись в канал не блокируется, поэтому время ожидания отправки
+    /// не используется.
+    ///
+    #[inline]
+    fn set_write_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+
+    ///
+    /// Получить адрес удаленной стороны соединения.
+    ///
+    #[inline]
+    fn peer_addr(&self) -> io::Result<Address> {
+        Ok(Address::Pipe)
+    }
+
+    ///
+    /// Закрыть канал в обоих направлениях.
+    ///
+    #[inline]
+    fn shutdown(&self) -> io::Result<()> {
+        self.incoming.close();
+        self.outgoing.close();
+        Ok(())
+    }
+}
+
+///
+/// Источник входящих соединений по каналам в памяти процесса.
+///
+pub struct PipeListener {
+    receiver: Mutex<Receiver<PipeStream>>,
+    sender: Sender<PipeStream>,
+}
+
+impl Default for PipeListener {
+    ///
+    /// Создать источник входящих соединений по каналам.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PipeListener {
+    ///
+    /// Создать источник входящих соединений по каналам.
+    ///
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            receiver: Mutex::new(receiver),
+            sender,
+        }
+    }
+
+    ///
+    /// Получить объект для подключения к источнику соединений.
+    ///
+    #[inline]
+    pub fn connector(&self) -> PipeConnector {
+        PipeConnector(self.sender.clone())
+    }
+}
+
+impl Listener for PipeListener {
+    ///
+    /// Дождаться входящего соединения.
+    ///
+    fn accept(&self) -> io::Result<Box<dyn Transport>> {
+        match self.receiver.lock().unwrap().recv() {
+            Ok(stream) => Ok(Box::new(stream)),
+            Err(_) => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    ///
+    /// Принять входящее соединение, если оно поступит не позднее
+    /// заданного времени.
+    ///
+    fn poll(&self, wait: Duration) -> io::Result<Option<Box<dyn Transport>>> {
+        match self.receiver.lock().unwrap().recv_timeout(wait) {
+            Ok(stream) => Ok(Some(Box::new(stream))),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    ///
+    /// Получить адрес, по которому принимаются соединения.
+    ///
+    #[inline]
+    fn local_addr(&self) -> io::Result<Address> {
+        Ok(Address::Pipe)
+    }
+}
+
+///
+/// Объект для подключения к источнику соединений по каналам.
+///
+#[derive(Clone)]
+pub struct PipeConnector(Sender<PipeStream>);
+
+impl PipeConnector {
+    ///
+    /// Подключиться к источнику соединений. Возвращает сторону канала,
+    /// принадлежащую клиенту.
+    ///
+    pub fn connect(&self) -> io::Result<PipeStream> {
+        let (client, server) = pipe();
+        self.0
+            .send(server)
+            .map_err(|_| io::Error::from(io::ErrorKind::ConnectionRefused))?;
+
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pipe_test() {
+        let (mut a, mut b) = pipe();
+        a.write_all(b"ping").unwrap();
+
+        let mut buf = [0u8; 4];
+        b.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        b.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        assert_eq!(
+            b.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+
+        drop(a);
+        assert_eq!(b.read(&mut buf).unwrap(), 0);
+        assert_eq!(
+            b.write(b"pong").unwrap_err().kind(),
+            io::ErrorKind::BrokenPipe
+        );
+    }
+}
@@ -1,52 +1,433 @@
 use std::{
     io::{self, Read, Write},
-    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    iter,
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
+#[cfg(unix)]
+use std::{os::unix::net::UnixListener, path::Path};
+
+use rand::{self, Rng};
 use serde::{de, Serialize};
 
 use crate::{
-    control::protocol::{consts::MASK, mask, recv_message, send_message, Message},
+    control::protocol::{
+        codec::Codec,
+        consts::{CLIENT_ROLE, NONCE_SIZE, SERVER_ROLE, TAG_SIZE},
+        envelope::Envelope,
+        handshake::{self, Capabilities, HandshakeRequest, HandshakeResponse},
+        recv_envelope, recv_message, send_envelope, send_message,
+        stream::{DeadlineStream, IdleReader, Stream},
+        transport::{Address, Listener, Transport},
+        Limits, Message, ProtocolVersion,
+    },
     error::{BindError, ConnectionError, RecvError, SendError},
 };
 
+#[cfg(feature = "tls")]
+use crate::control::protocol::tls::{self, TlsServerConfig};
+
 ///
 /// Представляет сервер для обмена сообщениями.
 ///
 pub struct Server {
-    listener: TcpListener,
+    listener: Box<dyn Listener>,
+    settings: Arc<Settings>,
+    max_connections: Option<usize>,
+    active_connections: Arc<AtomicUsize>,
+}
+
+///
+/// Настройки подтверждения связи, общие для всех соединений сервера.
+///
+struct Settings {
+    key: Vec<u8>,
+    capabilities: Capabilities,
+    limits: Limits,
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<rustls::ServerConfig>>,
 }
 
 impl Server {
     ///
-    /// Выполнить привязку сервера к сокету.
+    /// Выполнить привязку сервера к сокету с заданным общим с клиентами
+    /// ключом для аутентификации, используя остальные настройки по умолчанию.
     ///
-    pub fn bind<A>(addrs: A) -> Result<Self, BindError>
+    pub fn bind<A, K>(addrs: A, key: K) -> Result<Self, BindError>
     where
         A: ToSocketAddrs,
+        K: AsRef<[u8]>,
     {
-        let listener = TcpListener::bind(addrs)?;
-        Ok(Self { listener })
+        Self::builder(key).bind(addrs)
+    }
+
+    ///
+    /// Создать объект для построения сервера с заданным общим с клиентами
+    /// ключом для аутентификации и остальными настройками по умолчанию.
+    ///
+    #[inline]
+    pub fn builder<K: AsRef<[u8]>>(key: K) -> ServerBuilder {
+        ServerBuilder::new(key)
+    }
+
+    ///
+    /// Получить адрес сокета TCP, к которому привязан сервер.
+    ///
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self.listener.local_addr()? {
+            Address::Tcp(addr) => Ok(addr),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the server is not bound to a TCP socket",
+            )),
+        }
+    }
+
+    ///
+    /// Получить адрес, по которому сервер принимает соединения.
+    ///
+    #[inline]
+    pub fn address(&self) -> io::Result<Address> {
+        self.listener.local_addr()
+    }
+
+    ///
+    /// Получить общий с клиентами ключ для аутентификации.
+    ///
+    #[inline]
+    pub(crate) fn key(&self) -> &[u8] {
+        &self.settings.key
     }
 
     ///
-    /// Блокирующий итератор для входящих соединений.
+    /// Блокирующий итератор для входящих соединений. Подтверждение связи
+    /// с клиентом выполняется перед возвратом соединения.
     ///
     pub fn incoming(&self) -> impl Iterator<Item = Result<Connection, ConnectionError>> + '_ {
-        self.listener.incoming().map(|s| match s {
-            Ok(s) => Self::try_handshake(s),
+        iter::repeat_with(|| self.listener.accept()).map(|s| match s {
+            Ok(s) => self.admit(s)?.handshake(),
             Err(e) => Err(ConnectionError::Io(e)),
         })
     }
 
-    // Подтвердить handshake.
-    fn try_handshake(mut stream: TcpStream) -> Result<Connection, ConnectionError> {
-        let mut bytes = [0u8; 32];
-        stream.read_exact(&mut bytes)?;
-        let bytes = mask(bytes, MASK);
-        stream.write_all(&bytes)?;
+    ///
+    /// Принять входящее соединение, если оно поступит не позднее заданного
+    /// времени. Позволяет прерывать ожидание соединений, например, для
+    /// остановки сервера. Подтверждение связи с клиентом выполняется
+    /// перед возвратом соединения.
+    ///
+    pub fn poll(&self, wait: Duration) -> Result<Option<Connection>, ConnectionError> {
+        self.poll_incoming(wait)?
+            .map(Incoming::handshake)
+            .transpose()
+    }
+
+    ///
+    /// Принять входящее соединение, если оно поступит не позднее заданного
+    /// времени, не подтверждая связь с клиентом. Связь подтверждается
+    /// отдельно, поэтому медленный или молчащий клиент не задерживает
+    /// прием остальных соединений.
+    ///
+    pub fn poll_incoming(&self, wait: Duration) -> Result<Option<Incoming>, ConnectionError> {
+        match self.listener.poll(wait)? {
+            Some(stream) => self.admit(stream).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // Учесть принятое соединение в счетчике активных соединений.
+    fn admit(&self, stream: Box<dyn Transport>) -> Result<Incoming, ConnectionError> {
+        let guard = ConnectionGuard::acquire(&self.active_connections, self.max_connections)
+            .ok_or(ConnectionError::TooManyConnections)?;
 
-        Ok(Connection { stream })
+        Ok(Incoming {
+            stream,
+            settings: self.settings.clone(),
+            guard,
+        })
+    }
+}
+
+///
+/// Принятое сервером соединение, связь с клиентом по которому еще не
+/// подтверждена.
+///
+pub struct Incoming {
+    stream: Box<dyn Transport>,
+    settings: Arc<Settings>,
+    guard: ConnectionGuard,
+}
+
+impl Incoming {
+    ///
+    /// Получить адрес подключенного клиента.
+    ///
+    #[inline]
+    pub fn peer_addr(&self) -> io::Result<Address> {
+        self.stream.peer_addr()
+    }
+
+    ///
+    /// Подтвердить связь с клиентом: аутентифицировать его и согласовать
+    /// версию протокола и набор возможностей. Подтверждение должно
+    /// завершиться за время, заданное при построении сервера.
+    ///
+    pub fn handshake(self) -> Result<Connection, ConnectionError> {
+        let settings = self.settings;
+        let limits = settings.limits;
+
+        #[cfg(feature = "tls")]
+        let mut stream = match settings.tls {
+            Some(ref config) => tls::accept(config, self.stream)?,
+            None => Stream::Plain(self.stream),
+        };
+        #[cfg(not(feature = "tls"))]
+        let mut stream = Stream::Plain(self.stream);
+
+        stream.transport().set_read_timeout(limits.read_timeout)?;
+        stream.transport().set_write_timeout(limits.write_timeout)?;
+        let response = handshake_with(
+            &mut DeadlineStream::new(&mut stream, limits.handshake_timeout),
+            &settings,
+        )?;
+        stream.transport().set_read_timeout(limits.read_timeout)?;
+        stream.transport().set_write_timeout(limits.write_timeout)?;
+
+        match response {
+            HandshakeResponse::Accepted(code, capabilities) => Ok(Connection {
+                stream,
+                version: ProtocolVersion::from_code(code).ok_or(ConnectionError::BadHandshake)?,
+                capabilities,
+                codec: Codec::select(capabilities).unwrap_or_default(),
+                limits,
+                idle_since: Instant::now(),
+                _guard: self.guard,
+            }),
+
+            HandshakeResponse::UnsupportedVersion(versions) => {
+                Err(ConnectionError::UnsupportedVersion(versions))
+            }
+
+            HandshakeResponse::IncompatibleCapabilities(capabilities) => {
+                Err(ConnectionError::IncompatibleCapabilities(capabilities))
+            }
+
+            HandshakeResponse::AuthenticationFailed => Err(ConnectionError::AuthenticationFailed),
+        }
+    }
+}
+
+// Обменяться с клиентом сообщениями подтверждения связи и получить
+// отправленный клиенту ответ.
+fn handshake_with<S: Read + Write>(
+    stream: &mut S,
+    settings: &Settings,
+) -> Result<HandshakeResponse, ConnectionError> {
+    let mut client_nonce = [0u8; NONCE_SIZE];
+    stream.read_exact(&mut client_nonce)?;
+
+    let server_nonce = rand::thread_rng().gen::<[u8; NONCE_SIZE]>();
+    let server_tag = handshake::sign(&settings.key, SERVER_ROLE, &client_nonce, &server_nonce);
+    stream.write_all(&server_nonce)?;
+    stream.write_all(&server_tag)?;
+
+    let mut client_tag = [0u8; TAG_SIZE];
+    stream.read_exact(&mut client_tag)?;
+    let authenticated = handshake::verify(
+        &settings.key,
+        CLIENT_ROLE,
+        &server_nonce,
+        &client_nonce,
+        &client_tag,
+    );
+
+    let request = recv_message::<HandshakeRequest, _>(
+        &mut *stream,
+        Codec::Bincode,
+        settings.limits.max_frame_size,
+    )?;
+    let response = if authenticated {
+        request.negotiate(settings.capabilities)
+    } else {
+        HandshakeResponse::AuthenticationFailed
+    };
+    send_message(response.clone(), Codec::Bincode, stream)?;
+
+    Ok(response)
+}
+
+///
+/// Структура для построения экземпляра сервера.
+///
+pub struct ServerBuilder {
+    ///
+    /// Общий с клиентами ключ для аутентификации.
+    ///
+    key: Vec<u8>,
+
+    ///
+    /// Предоставляемый клиентам набор возможностей.
+    ///
+    capabilities: Capabilities,
+
+    ///
+    /// Ограничения, накладываемые на соединения.
+    ///
+    limits: Limits,
+
+    ///
+    /// Максимальное количество одновременных соединений.
+    ///
+    max_connections: Option<usize>,
+
+    ///
+    /// Настройки защищенного соединения.
+    ///
+    #[cfg(feature = "tls")]
+    tls: Option<TlsServerConfig>,
+}
+
+impl ServerBuilder {
+    ///
+    /// Создать экземпляр построителя сервера с заданным общим с клиентами
+    /// ключом для аутентификации и остальными настройками по умолчанию.
+    ///
+    #[inline]
+    pub fn new<K: AsRef<[u8]>>(key: K) -> Self {
+        Self {
+            key: key.as_ref().to_vec(),
+            capabilities: Capabilities::supported(),
+            limits: Limits::default(),
+            max_connections: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    ///
+    /// Предоставлять клиентам только заданный набор возможностей.
+    ///
+    #[inline]
+    pub fn with_capabilities(self, capabilities: Capabilities) -> Self {
+        Self {
+            capabilities: capabilities & Capabilities::supported(),
+            ..self
+        }
+    }
+
+    ///
+    /// Принимать от клиентов сообщения с размером данных не более заданного.
+    ///
+    #[inline]
+    pub fn with_max_frame_size(mut self, max_frame_size: u32) -> Self {
+        self.limits.max_frame_size = max_frame_size;
+        self
+    }
+
+    ///
+    /// Ожидать продолжения начатого клиентом сообщения не дольше заданного
+    /// времени (`None` снимает ограничение).
+    ///
+    #[inline]
+    pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.read_timeout = timeout.filter(|t| !t.is_zero());
+        self
+    }
+
+    ///
+    /// Ожидать отправки данных клиенту не дольше заданного времени
+    /// (`None` снимает ограничение).
+    ///
+    #[inline]
+    pub fn with_write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.write_timeout = timeout.filter(|t| !t.is_zero());
+        self
+    }
+
+    ///
+    /// Ожидать следующего запроса клиента не дольше заданного времени
+    /// (`None` снимает ограничение).
+    ///
+    #[inline]
+    pub fn with_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.idle_timeout = timeout.filter(|t| !t.is_zero());
+        self
+    }
+
+    ///
+    /// Завершать подтверждение связи с клиентом не позднее заданного
+    /// времени (`None` снимает ограничение).
+    ///
+    #[inline]
+    pub fn with_handshake_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.limits.handshake_timeout = timeout.filter(|t| !t.is_zero());
+        self
+    }
+
+    ///
+    /// Обслуживать одновременно не более заданного количества клиентов.
+    ///
+    #[inline]
+    pub fn with_max_connections(self, max_connections: usize) -> Self {
+        Self {
+            max_connections: Some(max_connections),
+            ..self
+        }
+    }
+
+    ///
+    /// Принимать только защищенные соединения TLS с заданными настройками.
+    ///
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub fn with_tls(self, tls: TlsServerConfig) -> Self {
+        Self {
+            tls: Some(tls),
+            ..self
+        }
+    }
+
+    ///
+    /// Выполнить привязку сервера к сокету TCP.
+    ///
+    #[inline]
+    pub fn bind<A>(self, addrs: A) -> Result<Server, BindError>
+    where
+        A: ToSocketAddrs,
+    {
+        self.listen(TcpListener::bind(addrs)?)
+    }
+
+    ///
+    /// Выполнить привязку сервера к сокету Unix с заданным путем.
+    ///
+    #[cfg(unix)]
+    #[inline]
+    pub fn bind_unix<P: AsRef<Path>>(self, path: P) -> Result<Server, BindError> {
+        self.listen(UnixListener::bind(path)?)
+    }
+
+    ///
+    /// Принимать соединения из заданного источника.
+    ///
+    pub fn listen<L: Listener + 'static>(self, listener: L) -> Result<Server, BindError> {
+        Ok(Server {
+            listener: Box::new(listener),
+            settings: Arc::new(Settings {
+                key: self.key,
+                capabilities: self.capabilities,
+                limits: self.limits,
+                #[cfg(feature = "tls")]
+                tls: self.tls.as_ref().map(TlsServerConfig::build).transpose()?,
+            }),
+            max_connections: self.max_connections,
+            active_connections: Arc::new(AtomicUsize::new(0)),
+        })
     }
 }
 
@@ -54,7 +435,13 @@ impl Server {
 /// Представляет соединение с клиентом.
 ///
 pub struct Connection {
-    stream: TcpStream,
+    stream: Stream,
+    version: ProtocolVersion,
+    capabilities: Capabilities,
+    codec: Codec,
+    limits: Limits,
+    idle_since: Instant,
+    _guard: ConnectionGuard,
 }
 
 impl Connection {
@@ -63,7 +450,15 @@ impl Connection {
     ///
     #[inline]
     pub fn send<M: Message + Serialize>(&mut self, response: M) -> Result<(), SendError> {
-        send_message(response, &mut self.stream)
+        send_message(response, self.codec, &mut self.stream)
+    }
+
+    ///
+    /// Отправить клиенту сообщение из конверта.
+    ///
+    #[inline]
+    pub fn send_any(&mut self, envelope: &Envelope) -> Result<(), SendError> {
+        send_envelope(envelope, self.codec, &mut self.stream)
     }
 
     ///
@@ -71,14 +466,130 @@ impl Connection {
     ///
     #[inline]
     pub fn recv<M: Message + de::DeserializeOwned>(&mut self) -> Result<Box<M>, RecvError> {
-        recv_message(&mut self.stream)
+        self.recv_any()?.open()
+    }
+
+    ///
+    /// Получить от клиента сообщение произвольного типа в конверте.
+    ///
+    pub fn recv_any(&mut self) -> Result<Envelope, RecvError> {
+        let reader = IdleReader::new(&mut self.stream, &self.limits)?;
+        let envelope = recv_envelope(reader, self.codec, self.limits.max_frame_size);
+        self.idle_since = Instant::now();
+
+        envelope
+    }
+
+    ///
+    /// Получить запрос от клиента, если он начнет поступать не позднее
+    /// заданного времени. Позволяет обслуживать соединение в паузах
+    /// между запросами, не превышая времени простоя соединения.
+    ///
+    pub fn poll<M: Message + de::DeserializeOwned>(
+        &mut self,
+        wait: Duration,
+    ) -> Result<Option<Box<M>>, RecvError> {
+        self.poll_any(wait)?.map(|e| e.open()).transpose()
+    }
+
+    ///
+    /// Получить от клиента сообщение произвольного типа в конверте, если
+    /// оно начнет поступать не позднее заданного времени.
+    ///
+    pub fn poll_any(&mut self, wait: Duration) -> Result<Option<Envelope>, RecvError> {
+        let mut first = [0u8; 1];
+        self.stream
+            .transport()
+            .set_read_timeout(Some(wait).filter(|w| !w.is_zero()))?;
+        match self.stream.read(&mut first) {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            Ok(_) => {}
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return match self.limits.idle_timeout {
+                    Some(idle) if self.idle_since.elapsed() >= idle => Err(RecvError::Timeout),
+                    _ => Ok(None),
+                };
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        self.stream
+            .transport()
+            .set_read_timeout(self.limits.read_timeout)?;
+        let envelope = recv_envelope(
+            (&first[..]).chain(&mut self.stream),
+            self.codec,
+            self.limits.max_frame_size,
+        );
+        self.idle_since = Instant::now();
+
+        envelope.map(Some)
     }
 
     ///
     /// Получить адрес подключенного клиента.
     ///
     #[inline]
-    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+    pub fn peer_addr(&self) -> io::Result<Address> {
         self.stream.peer_addr()
     }
+
+    ///
+    /// Получить согласованную с клиентом версию протокола.
+    ///
+    #[inline]
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    ///
+    /// Получить согласованный с клиентом набор возможностей.
+    ///
+    #[inline]
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    ///
+    /// Получить согласованный с клиентом кодек данных сообщений.
+    ///
+    #[inline]
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+}
+
+///
+/// Учитывает соединение в счетчике активных соединений сервера.
+///
+struct ConnectionGuard(Arc<AtomicUsize>);
+
+impl ConnectionGuard {
+    ///
+    /// Учесть новое соединение, если не превышено максимальное количество
+    /// одновременных соединений.
+    ///
+    fn acquire(counter: &Arc<AtomicUsize>, max_connections: Option<usize>) -> Option<Self> {
+        let active = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        let guard = Self(counter.clone());
+
+        match max_connections {
+            Some(max) if active > max => None,
+            _ => Some(guard),
+        }
+    }
+}
+
+impl Drop for ConnectionGuard {
+    ///
+    /// Исключить соединение из счетчика активных соединений.
+    ///
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
@@ -0,0 +1,275 @@
+use std::{
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "tls")]
+use rustls::{ClientConnection, ServerConnection, StreamOwned};
+
+use crate::control::protocol::{
+    transport::{Address, Transport},
+    Limits,
+};
+
+///
+/// Поток для обмена данными между клиентом и сервером.
+///
+pub(crate) enum Stream {
+    // Незащищенное соединение.
+    Plain(Box<dyn Transport>),
+
+    // Защищенное соединение TLS на стороне клиента.
+    #[cfg(feature = "tls")]
+    TlsClient(Box<StreamOwned<ClientConnection, Box<dyn Transport>>>),
+
+    // Защищенное соединение TLS на стороне сервера.
+    #[cfg(feature = "tls")]
+    TlsServer(Box<StreamOwned<ServerConnection, Box<dyn Transport>>>),
+}
+
+impl Read for Stream {
+    ///
+    /// Прочитать данные из потока.
+    ///
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(s) => s.read(buf),
+            #[cfg(feature = "tls")]
+            Self::TlsClient(s) => s.read(buf),
+            #[cfg(feature = "tls")]
+            Self::TlsServer(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    ///
+    /// Записать данные в поток.
+    ///
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(s) => s.write(buf),
+            #[cfg(feature = "tls")]
+            Self::TlsClient(s) => s.write(buf),
+            #[cfg(feature = "tls")]
+            Self::TlsServer(s) => s.write(buf),
+        }
+    }
+
+    ///
+    /// Отправить буферизованные данные.
+    ///
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(s) => s.flush(),
+            #[cfg(feature = "tls")]
+            Self::TlsClient(s) => s.flush(),
+            #[cfg(feature = "tls")]
+            Self::TlsServer(s) => s.flush(),
+        }
+    }
+}
+
+impl Stream {
+    ///
+    /// Получить ссылку на нижележащий транспорт.
+    ///
+    pub(crate) fn transport(&self) -> &dyn Transport {
+        match self {
+            Self::Plain(s) => s.as_ref(),
+            #[cfg(feature = "tls")]
+            Self::TlsClient(s) => s.get_ref().as_ref(),
+            #[cfg(feature = "tls")]
+            Self::TlsServer(s) => s.get_ref().as_ref(),
+        }
+    }
+
+    ///
+    /// Получить адрес удаленной стороны соединения.
+    ///
+    #[inline]
+    pub(crate) fn peer_addr(&self) -> io::Result<Address> {
+        self.transport().peer_addr()
+    }
+
+    ///
+    /// Закрыть соединение в обоих направлениях.
+    ///
+    #[inline]
+    pub(crate) fn shutdown(&self) -> io::Result<()> {
+        self.transport().shutdown()
+    }
+}
+
+///
+/// Поток, подсчитывающий переданные байты: по их количеству можно понять,
+/// прервалась ли операция посреди кадра.
+///
+pub(crate) struct CountingStream<'a> {
+    stream: &'a mut Stream,
+    count: usize,
+}
+
+impl<'a> CountingStream<'a> {
+    ///
+    /// Создать поток с нулевым счетчиком.
+    ///
+    #[inline]
+    pub(crate) fn new(stream: &'a mut Stream) -> Self {
+        Self { stream, count: 0 }
+    }
+
+    ///
+    /// Получить количество прочитанных или записанных байт.
+    ///
+    #[inline]
+    pub(crate) fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<'a> Read for CountingStream<'a> {
+    ///
+    /// Прочитать данные из потока.
+    ///
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stream.read(buf)?;
+        self.count += n;
+
+        Ok(n)
+    }
+}
+
+impl<'a> Write for CountingStream<'a> {
+    ///
+    /// Записать данные в поток.
+    ///
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.stream.write(buf)?;
+        self.count += n;
+
+        Ok(n)
+    }
+
+    ///
+    /// Отправить буферизованные данные.
+    ///
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+///
+/// Поток чтения, ожидающий начала сообщения не дольше времени простоя
+/// соединения, а его продолжения - не дольше времени ожидания чтения.
+///
+pub(crate) struct IdleReader<'a> {
+    stream: &'a mut Stream,
+    read_timeout: Option<Duration>,
+    started: bool,
+}
+
+impl<'a> IdleReader<'a> {
+    ///
+    /// Создать поток чтения с заданными ограничениями.
+    ///
+    pub(crate) fn new(stream: &'a mut Stream, limits: &Limits) -> io::Result<Self> {
+        stream.transport().set_read_timeout(limits.idle_timeout)?;
+
+        Ok(Self {
+            stream,
+            read_timeout: limits.read_timeout,
+            started: false,
+        })
+    }
+}
+
+impl<'a> Read for IdleReader<'a> {
+    ///
+    /// Прочитать данные из потока.
+    ///
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stream.read(buf)?;
+        if !self.started && n > 0 {
+            self.started = true;
+            self.stream
+                .transport()
+                .set_read_timeout(self.read_timeout)?;
+        }
+
+        Ok(n)
+    }
+}
+
+///
+/// Поток, операции чтения и записи которого должны завершиться не позднее
+/// заданного момента времени, сколько бы их ни потребовалось.
+///
+pub(crate) struct DeadlineStream<'a> {
+    stream: &'a mut Stream,
+    deadline: Option<Instant>,
+}
+
+impl<'a> DeadlineStream<'a> {
+    ///
+    /// Создать поток, операции которого должны завершиться не позднее
+    /// заданного времени (`None` снимает ограничение).
+    ///
+    pub(crate) fn new(stream: &'a mut Stream, timeout: Option<Duration>) -> Self {
+        Self {
+            stream,
+            deadline: timeout.map(|t| Instant::now() + t),
+        }
+    }
+
+    ///
+    /// Получить время, оставшееся до истечения срока.
+    ///
+    fn remaining(&self) -> io::Result<Option<Duration>> {
+        match self.deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(remaining) if !remaining.is_zero() => Ok(Some(remaining)),
+                _ => Err(io::ErrorKind::TimedOut.into()),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'a> Read for DeadlineStream<'a> {
+    ///
+    /// Прочитать данные из потока.
+    ///
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(remaining) = self.remaining()? {
+            self.stream.transport().set_read_timeout(Some(remaining))?;
+        }
+
+        self.stream.read(buf)
+    }
+}
+
+impl<'a> Write for DeadlineStream<'a> {
+    ///
+    /// Записать данные в поток.
+    ///
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(remaining) = self.remaining()? {
+            self.stream.transport().set_write_timeout(Some(remaining))?;
+        }
+
+        self.stream.write(buf)
+    }
+
+    ///
+    /// Отправить буферизованные данные.
+    ///
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(remaining) = self.remaining()? {
+            self.stream.transport().set_write_timeout(Some(remaining))?;
+        }
+
+        self.stream.flush()
+    }
+}
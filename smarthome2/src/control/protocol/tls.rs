@@ -0,0 +1,201 @@
+use std::{fs, io::BufReader, path::Path, sync::Arc};
+
+use rustls::{
+    server::AllowAnyAuthenticatedClient, Certificate, ClientConfig, ClientConnection, PrivateKey,
+    RootCertStore, ServerConfig, ServerConnection, ServerName, StreamOwned,
+};
+
+use crate::{
+    control::protocol::{stream::Stream, transport::Transport},
+    error::TlsError,
+};
+
+///
+/// Настройки защищенного соединения TLS на стороне клиента.
+///
+#[derive(Clone)]
+pub struct TlsClientConfig {
+    ///
+    /// Имя сервера, проверяемое по его сертификату.
+    ///
+    server_name: ServerName,
+
+    ///
+    /// Доверенные корневые сертификаты.
+    ///
+    roots: RootCertStore,
+
+    ///
+    /// Сертификат и закрытый ключ клиента.
+    ///
+    identity: Option<(Vec<Certificate>, PrivateKey)>,
+}
+
+impl TlsClientConfig {
+    ///
+    /// Создать настройки для подключения к серверу с заданным именем,
+    /// сертификат которого подписан сертификатами из заданного файла PEM.
+    ///
+    pub fn from_pem<D, P>(server_name: D, ca_path: P) -> Result<Self, TlsError>
+    where
+        D: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        let server_name = ServerName::try_from(server_name.as_ref())
+            .map_err(|_| TlsError::InvalidServerName(server_name.as_ref().to_owned()))?;
+
+        Ok(Self {
+            server_name,
+            roots: load_roots(ca_path)?,
+            identity: None,
+        })
+    }
+
+    ///
+    /// Предъявлять серверу сертификат и закрытый ключ клиента из
+    /// заданных файлов PEM.
+    ///
+    pub fn with_identity<P1, P2>(self, cert_path: P1, key_path: P2) -> Result<Self, TlsError>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        Ok(Self {
+            identity: Some((load_certs(cert_path)?, load_key(key_path)?)),
+            ..self
+        })
+    }
+
+    // Установить защищенное соединение поверх заданного транспорта.
+    pub(crate) fn connect(&self, stream: Box<dyn Transport>) -> Result<Stream, TlsError> {
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(self.roots.clone());
+        let config = match self.identity {
+            Some((ref certs, ref key)) => {
+                builder.with_client_auth_cert(certs.clone(), key.clone())?
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let connection = ClientConnection::new(Arc::new(config), self.server_name.clone())?;
+        Ok(Stream::TlsClient(Box::new(StreamOwned::new(
+            connection, stream,
+        ))))
+    }
+}
+
+///
+/// Настройки защищенного соединения TLS на стороне сервера.
+///
+#[derive(Clone)]
+pub struct TlsServerConfig {
+    ///
+    /// Цепочка сертификатов сервера.
+    ///
+    certs: Vec<Certificate>,
+
+    ///
+    /// Закрытый ключ сервера.
+    ///
+    key: PrivateKey,
+
+    ///
+    /// Корневые сертификаты для проверки сертификатов клиентов.
+    ///
+    client_roots: Option<RootCertStore>,
+}
+
+impl TlsServerConfig {
+    ///
+    /// Создать настройки с сертификатом и закрытым ключом сервера из
+    /// заданных файлов PEM.
+    ///
+    pub fn from_pem<P1, P2>(cert_path: P1, key_path: P2) -> Result<Self, TlsError>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        Ok(Self {
+            certs: load_certs(cert_path)?,
+            key: load_key(key_path)?,
+            client_roots: None,
+        })
+    }
+
+    ///
+    /// Требовать от клиентов сертификат, подписанный сертификатами из
+    /// заданного файла PEM.
+    ///
+    pub fn with_client_verification<P: AsRef<Path>>(self, ca_path: P) -> Result<Self, TlsError> {
+        Ok(Self {
+            client_roots: Some(load_roots(ca_path)?),
+            ..self
+        })
+    }
+
+    // Построить настройки библиотеки rustls.
+    pub(crate) fn build(&self) -> Result<Arc<ServerConfig>, TlsError> {
+        let builder = ServerConfig::builder().with_safe_defaults();
+        let builder = match self.client_roots {
+            Some(ref roots) => builder
+                .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots.clone()).boxed()),
+            None => builder.with_no_client_auth(),
+        };
+
+        Ok(Arc::new(
+            builder.with_single_cert(self.certs.clone(), self.key.clone())?,
+        ))
+    }
+}
+
+// Принять защищенное соединение поверх заданного транспорта.
+pub(crate) fn accept(
+    config: &Arc<ServerConfig>,
+    stream: Box<dyn Transport>,
+) -> Result<Stream, TlsError> {
+    let connection = ServerConnection::new(config.clone())?;
+    Ok(Stream::TlsServer(Box::new(StreamOwned::new(
+        connection, stream,
+    ))))
+}
+
+// Загрузить сертификаты из файла PEM.
+fn load_certs<P: AsRef<Path>>(path: P) -> Result<Vec<Certificate>, TlsError> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let certs: Vec<Certificate> = rustls_pemfile::certs(&mut reader)?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+
+    if certs.is_empty() {
+        Err(TlsError::NoCertificates)
+    } else {
+        Ok(certs)
+    }
+}
+
+// Загрузить корневые сертификаты из файла PEM.
+fn load_roots<P: AsRef<Path>>(path: P) -> Result<RootCertStore, TlsError> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots.add(&cert)?;
+    }
+
+    Ok(roots)
+}
+
+// Загрузить закрытый ключ из файла PEM.
+fn load_key<P: AsRef<Path>>(path: P) -> Result<PrivateKey, TlsError> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    while let Some(item) = rustls_pemfile::read_one(&mut reader)? {
+        match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => {}
+        }
+    }
+
+    Err(TlsError::NoPrivateKey)
+}
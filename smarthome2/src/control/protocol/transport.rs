@@ -0,0 +1,269 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::{
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+};
+
+// Период проверки наличия входящих соединений при ожидании с ограничением
+// по времени.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(20);
+
+///
+/// Адрес стороны соединения.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    ///
+    /// Адрес сокета TCP.
+    ///
+    Tcp(SocketAddr),
+
+    ///
+    /// Путь к сокету Unix (отсутствует для неименованных сокетов).
+    ///
+    #[cfg(unix)]
+    Unix(Option<PathBuf>),
+
+    ///
+    /// Канал в памяти процесса.
+    ///
+    Pipe,
+}
+
+impl fmt::Display for Address {
+    ///
+    /// Выполнить форматирование адреса.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{}", addr),
+            #[cfg(unix)]
+            Self::Unix(Some(path)) => write!(f, "unix:{}", path.display()),
+            #[cfg(unix)]
+            Self::Unix(None) => write!(f, "unix:(unnamed)"),
+            Self::Pipe => write!(f, "pipe"),
+        }
+    }
+}
+
+///
+/// Типаж транспорта, по которому клиент и сервер обмениваются данными.
+///
+pub trait Transport: Read + Write + Send {
+    ///
+    /// Ожидать данные не дольше заданного времени (`None` снимает
+    /// ограничение).
+    ///
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+
+    ///
+    /// Ожидать отправки данных не дольше заданного времени (`None`
+    /// снимает ограничение).
+    ///
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+
+    ///
+    /// Получить адрес удаленной стороны соединения.
+    ///
+    fn peer_addr(&self) -> io::Result<Address>;
+
+    ///
+    /// Закрыть соединение в обоих направлениях.
+    ///
+    fn shutdown(&self) -> io::Result<()>;
+}
+
+///
+/// Типаж источника входящих соединений сервера.
+///
+pub trait Listener: Send + Sync {
+    ///
+    /// Дождаться входящего соединения.
+    ///
+    fn accept(&self) -> io::Result<Box<dyn Transport>>;
+
+    ///
+    /// Принять входящее соединение, если оно поступит не позднее
+    /// заданного времени.
+    ///
+    fn poll(&self, wait: Duration) -> io::Result<Option<Box<dyn Transport>>>;
+
+    ///
+    /// Получить адрес, по которому принимаются соединения.
+    ///
+    fn local_addr(&self) -> io::Result<Address>;
+}
+
+impl Transport for TcpStream {
+    ///
+    /// Ожидать данные не дольше заданного времени.
+    ///
+    #[inline]
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    ///
+    /// Ожидать отправки данных не дольше заданного времени.
+    ///
+    #[inline]
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+
+    ///
+    /// Получить адрес удаленной стороны соединения.
+    ///
+    #[inline]
+    fn peer_addr(&self) -> io::Result<Address> {
+        TcpStream::peer_addr(self).map(Address::Tcp)
+    }
+
+    ///
+    /// Закрыть соединение в обоих направлениях.
+    ///
+    #[inline]
+    fn shutdown(&self) -> io::Result<()> {
+        TcpStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+impl Listener for TcpListener {
+    ///
+    /// Дождаться входящего соединения.
+    ///
+    fn accept(&self) -> io::Result<Box<dyn Transport>> {
+        let (stream, _) = TcpListener::accept(self)?;
+        Ok(Box::new(stream))
+    }
+
+    ///
+    /// Принять входящее соединение, если оно поступит не позднее
+    /// заданного времени.
+    ///
+    fn poll(&self, wait: Duration) -> io::Result<Option<Box<dyn Transport>>> {
+        self.set_nonblocking(true)?;
+        let accepted = poll_accept(wait, || TcpListener::accept(self).map(|(s, _)| s));
+        self.set_nonblocking(false)?;
+
+        match accepted? {
+            Some(stream) => {
+                stream.set_nonblocking(false)?;
+                Ok(Some(Box::new(stream)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    ///
+    /// Получить адрес, по которому принимаются соединения.
+    ///
+    #[inline]
+    fn local_addr(&self) -> io::Result<Address> {
+        TcpListener::local_addr(self).map(Address::Tcp)
+    }
+}
+
+#[cfg(unix)]
+impl Transport for UnixStream {
+    ///
+    /// Ожидать данные не дольше заданного времени.
+    ///
+    #[inline]
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+
+    ///
+    /// Ожидать отправки данных не дольше заданного времени.
+    ///
+    #[inline]
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_write_timeout(self, timeout)
+    }
+
+    ///
+    /// Получить адрес удаленной стороны соединения.
+    ///
+    #[inline]
+    fn peer_addr(&self) -> io::Result<Address> {
+        let addr = UnixStream::peer_addr(self)?;
+        Ok(Address::Unix(addr.as_pathname().map(|p| p.to_owned())))
+    }
+
+    ///
+    /// Закрыть соединение в обоих направлениях.
+    ///
+    #[inline]
+    fn shutdown(&self) -> io::Result<()> {
+        UnixStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+#[cfg(unix)]
+impl Listener for UnixListener {
+    ///
+    /// Дождаться входящего соединения.
+    ///
+    fn accept(&self) -> io::Result<Box<dyn Transport>> {
+        let (stream, _) = UnixListener::accept(self)?;
+        Ok(Box::new(stream))
+    }
+
+    ///
+    /// Принять входящее соединение, если оно поступит не позднее
+    /// заданного времени.
+    ///
+    fn poll(&self, wait: Duration) -> io::Result<Option<Box<dyn Transport>>> {
+        self.set_nonblocking(true)?;
+        let accepted = poll_accept(wait, || UnixListener::accept(self).map(|(s, _)| s));
+        self.set_nonblocking(false)?;
+
+        match accepted? {
+            Some(stream) => {
+                stream.set_nonblocking(false)?;
+                Ok(Some(Box::new(stream)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    ///
+    /// Получить адрес, по которому принимаются соединения.
+    ///
+    #[inline]
+    fn local_addr(&self) -> io::Result<Address> {
+        let addr = UnixListener::local_addr(self)?;
+        Ok(Address::Unix(addr.as_pathname().map(|p| p.to_owned())))
+    }
+}
+
+// Повторять неблокирующий прием соединения до его поступления или
+// истечения заданного времени.
+fn poll_accept<S, F>(wait: Duration, mut accept: F) -> io::Result<Option<S>>
+where
+    F: FnMut() -> io::Result<S>,
+{
+    let deadline = Instant::now() + wait;
+    loop {
+        match accept() {
+            Ok(stream) => return Ok(Some(stream)),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Ok(None);
+                }
+                thread::sleep(ACCEPT_INTERVAL.min(deadline - now));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
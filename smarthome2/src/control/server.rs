@@ -1,210 +1,745 @@
 use std::{
+    mem,
     net::ToSocketAddrs,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
     thread,
+    time::Duration,
 };
 
 use log;
+use uuid::Uuid;
+
+use smarthome2_core::dispatch::{self, Action, Transaction};
 
 use crate::{
     control::{
-        message::{ControlRequest, ControlRequestData, ControlResponse},
-        protocol::server::Server,
+        message::{ControlRequest, ControlRequestData, ControlResponse, DeviceSpec},
+        pool::{Dispatcher, PoolStats, WorkerPool},
+        protocol::{
+            handshake::Capabilities,
+            server::{Connection, Incoming, Server},
+        },
+        subscription::{Subscriptions, Topic},
     },
     device::{
-        socket::{SmartSocket, SwitchOffEvent, SwitchOnEvent},
-        Device, StateEvent,
+        lamp::{RemoteSmartLamp, SmartLamp},
+        sensor::{RemoteSensor, SmartSensor},
+        socket::{RemoteSmartSocket, SmartSocket, SwitchOffEvent, SwitchOnEvent},
+        thermometer::{RemoteThermometer, SmartThermometer},
+        Device, DeviceState, Event, EventData, StateEvent, StateListener,
     },
     error::{BindError, DeviceError},
-    house::{DeviceInfo, DeviceNotifier, RoomGetter, SmartHouse},
+    house::{DeviceNotifier, RoomGetter, SmartHouse},
 };
 
+// Период проверки запроса на остановку сервера в ожидании входящих
+// соединений.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Время ожидания запроса клиента в одном цикле обслуживания соединения.
+// По истечении этого времени соединение уступает рабочий поток
+// остальным соединениям, а в следующем цикле клиенту отправляются
+// накопившиеся уведомления.
+const TURN_INTERVAL: Duration = Duration::from_millis(10);
+
+// Время ожидания завершения обработки соединений по умолчанию при
+// остановке сервера.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Количество рабочих потоков для обслуживания соединений по умолчанию.
+const WORKERS: usize = 4;
+
+// Количество соединений сверх количества рабочих потоков, ожидающих
+// обслуживания, по умолчанию.
+const QUEUE_CAPACITY: usize = 16;
+
+// Количество отклоненных соединений, ожидающих отправки ответа с ошибкой.
+// Соединения сверх этого количества закрываются без ответа.
+const REJECT_CAPACITY: usize = 16;
+
+///
+/// Дескриптор для остановки сервера из другого потока.
+///
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownHandle(Arc<AtomicBool>);
+
+impl ShutdownHandle {
+    ///
+    /// Запросить остановку сервера. Сервер перестает принимать новые
+    /// соединения, а обслуживаемые соединения закрываются после
+    /// обработки текущего запроса.
+    ///
+    #[inline]
+    pub fn shutdown(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    ///
+    /// Проверить, запрошена ли остановка сервера.
+    ///
+    #[inline]
+    pub fn is_shutdown(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
 ///
 /// Сервер подсистемы управления "умного" дома.
 ///
 pub struct ControlServer {
     server: Server,
     house: Arc<Mutex<SmartHouse>>,
+    subscriptions: Arc<Subscriptions>,
+    device_key: Arc<[u8]>,
+    pool: WorkerPool,
+    shutdown: ShutdownHandle,
+    shutdown_timeout: Duration,
 }
 
 impl ControlServer {
     ///
-    /// Выполнить привязку сервера к сокету и экземпляру "умного" дома.
+    /// Выполнить привязку сервера к сокету и экземпляру "умного" дома с
+    /// заданным общим с клиентами ключом для аутентификации.
     ///
     #[inline]
-    pub fn bind<A>(addrs: A, house: SmartHouse) -> Result<Self, BindError>
+    pub fn bind<A, K>(addrs: A, key: K, house: SmartHouse) -> Result<Self, BindError>
     where
         A: ToSocketAddrs,
+        K: AsRef<[u8]>,
     {
-        Ok(Self {
-            server: Server::bind(addrs)?,
+        Ok(Self::with_server(Server::bind(addrs, key)?, house))
+    }
+
+    ///
+    /// Создать сервер на основе настроенного сервера обмена сообщениями
+    /// и экземпляра "умного" дома.
+    ///
+    pub fn with_server(server: Server, mut house: SmartHouse) -> Self {
+        let subscriptions = Arc::new(Subscriptions::default());
+        for room in house.iter_mut() {
+            let room_id = room.id();
+            for device_ref in room.iter_mut() {
+                watch(&subscriptions, room_id, device_ref);
+            }
+        }
+
+        Self {
+            device_key: server.key().into(),
+            server,
             house: Arc::new(Mutex::new(house)),
-        })
+            subscriptions,
+            pool: WorkerPool::new(WORKERS, QUEUE_CAPACITY),
+            shutdown: ShutdownHandle::default(),
+            shutdown_timeout: SHUTDOWN_TIMEOUT,
+        }
     }
 
     ///
-    /// Запустить сервер для обработки сообщений.
+    /// Подключаться к удаленным устройствам, добавляемым по запросам
+    /// клиентов, с заданным общим ключом для аутентификации. По умолчанию
+    /// используется ключ самого сервера.
     ///
-    pub fn run(&self) {
-        for connection in self.server.incoming() {
-            let mut connection = match connection {
-                Ok(c) => c,
-                Err(e) => {
-                    log::error!("Cannot establish connection {}", e);
-                    continue;
-                }
-            };
+    #[inline]
+    pub fn with_device_key<K: AsRef<[u8]>>(self, key: K) -> Self {
+        Self {
+            device_key: key.as_ref().into(),
+            ..self
+        }
+    }
 
-            let addr = match connection.peer_addr() {
-                Ok(addr) => addr.to_string(),
-                Err(_) => "unknown".to_owned(),
-            };
+    ///
+    /// Обслуживать соединения заданным количеством рабочих потоков.
+    ///
+    #[inline]
+    pub fn with_workers(self, workers: usize) -> Self {
+        Self {
+            pool: WorkerPool::new(workers, self.pool.queue_capacity()),
+            ..self
+        }
+    }
+
+    ///
+    /// Обслуживать сверх количества рабочих потоков не более заданного
+    /// количества соединений, которые ожидают своей очереди на обслуживание.
+    /// Соединения сверх этого количества отклоняются.
+    ///
+    #[inline]
+    pub fn with_queue_capacity(self, queue_capacity: usize) -> Self {
+        Self {
+            pool: WorkerPool::new(self.pool.workers(), queue_capacity),
+            ..self
+        }
+    }
+
+    ///
+    /// Ожидать завершения обработки соединений при остановке сервера
+    /// не дольше заданного времени.
+    ///
+    #[inline]
+    pub fn with_shutdown_timeout(self, shutdown_timeout: Duration) -> Self {
+        Self {
+            shutdown_timeout,
+            ..self
+        }
+    }
+
+    ///
+    /// Получить дескриптор для остановки сервера.
+    ///
+    #[inline]
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    ///
+    /// Получить статистику пула рабочих потоков.
+    ///
+    #[inline]
+    pub fn stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+
+    ///
+    /// Вернуть экземпляр "умного" дома после остановки сервера. Если дом
+    /// еще используется незавершенными соединениями, возвращается сервер.
+    ///
+    #[allow(clippy::result_large_err)]
+    pub fn into_house(self) -> Result<SmartHouse, Self> {
+        match Arc::try_unwrap(self.house) {
+            Ok(house) => Ok(house.into_inner().unwrap_or_else(PoisonError::into_inner)),
+            Err(house) => Err(Self { house, ..self }),
+        }
+    }
 
-            log::info!("New client connected: {}", addr);
+    ///
+    /// Запустить сервер для обработки сообщений. Возвращает управление
+    /// после остановки сервера с помощью дескриптора остановки.
+    ///
+    pub fn run(&self) {
+        let house = self.house.clone();
+        let subscriptions = self.subscriptions.clone();
+        let device_key = self.device_key.clone();
+        let shutdown = self.shutdown.clone();
+        let next_id = AtomicU64::new(1);
+        let dispatcher = self
+            .pool
+            .start(move |worker, session: &mut Session<Subscriber>| {
+                if shutdown.is_shutdown() {
+                    return false;
+                }
+                let Some(connection) = session.link.establish() else {
+                    return false;
+                };
+                let subscriber = &session.state;
+
+                if subscriber
+                    .notifications
+                    .try_iter()
+                    .try_for_each(|n| connection.send(n))
+                    .is_err()
+                {
+                    log::warn!("Connection lost when sending notifications");
+                    return false;
+                }
 
-            let house = self.house.clone();
-            thread::spawn(move || loop {
-                let request = connection.recv::<ControlRequest>();
-                let request = match request {
-                    Ok(r) => r,
-                    Err(_) => {
-                        log::warn!("Connection lost when receiving data");
-                        break;
+                let request = match connection.poll::<ControlRequest>(TURN_INTERVAL) {
+                    Ok(Some(r)) => r,
+                    Ok(None) => return true,
+                    Err(e) => {
+                        log::warn!("Connection lost when receiving data: {}", e);
+                        return false;
                     }
                 };
 
-                let response = Self::dispatch(house.clone(), request.as_ref());
+                worker.count_request();
+                let mut response = if request.version() > connection.version() {
+                    ControlResponse::with_error(DeviceError::UnsupportedVersion(request.version()))
+                } else if request.is_subscription() {
+                    if connection
+                        .capabilities()
+                        .contains(Capabilities::PUSH_NOTIFICATIONS)
+                    {
+                        Self::subscribe(&house, subscriber, request.as_ref())
+                    } else {
+                        ControlResponse::with_error(DeviceError::NotificationsDisabled)
+                    }
+                } else {
+                    Self::dispatch(house.clone(), &subscriptions, &device_key, request.as_ref())
+                };
+                response.set_id(request.id());
                 if connection.send(response).is_err() {
                     log::warn!("Connection lost when sending data");
-                    break;
+                    return false;
                 }
+
+                true
             });
-        }
+
+        serve(&self.server, &self.shutdown, &dispatcher, || {
+            Subscriber::new(next_id.fetch_add(1, Ordering::Relaxed), &self.subscriptions)
+        });
+        dispatcher.join(self.shutdown_timeout);
     }
 
     ///
-    /// Выполнить диспетчеризацию запроса.
+    /// Подписать соединение на изменения состояния устройств.
     ///
-    fn dispatch(house: Arc<Mutex<SmartHouse>>, req: &ControlRequest) -> ControlResponse {
-        match req.data {
-            ControlRequestData::AcquireRooms => house.lock().unwrap().rooms().collect(),
-
-            ControlRequestData::AcquireDevices(room_id) => {
-                let lock = house.lock().unwrap();
-                if let Some(room_ref) = lock.get(room_id) {
-                    room_ref.devices().collect()
-                } else {
-                    ControlResponse::with_error(DeviceError::IllegalRoomId(room_id))
+    fn subscribe(
+        house: &Mutex<SmartHouse>,
+        subscriber: &Subscriber,
+        req: &ControlRequest,
+    ) -> ControlResponse {
+        let topic = match *req.data() {
+            ControlRequestData::Subscribe(ref selector) => {
+                match dispatch::locate(&locked(house), selector) {
+                    Ok((room_id, device_id)) => Topic::Device(room_id, device_id),
+                    Err(e) => return ControlResponse::with_error(DeviceError::from(e)),
                 }
             }
+            _ => Topic::All,
+        };
 
-            ControlRequestData::AcquireDeviceState(room_id, device_id) => {
-                let mut lock = house.lock().unwrap();
-                match lock.notify(room_id, device_id, &StateEvent::new()) {
-                    Ok(s) => ControlResponse::with_state(s),
-                    Err(e) => ControlResponse::with_error(e),
-                }
+        log::info!("Subscribing connection {} to {:?}", subscriber.id, topic);
+        subscriber
+            .subscriptions
+            .subscribe(subscriber.id, topic, &subscriber.sender);
+
+        ControlResponse::done()
+    }
+
+    ///
+    /// Выполнить диспетчеризацию запроса.
+    ///
+    fn dispatch(
+        house: Arc<Mutex<SmartHouse>>,
+        subscriptions: &Arc<Subscriptions>,
+        key: &[u8],
+        req: &ControlRequest,
+    ) -> ControlResponse {
+        match *req.data() {
+            ControlRequestData::AttachDevice(room_id, ref spec) => {
+                Self::attach_device(&house, subscriptions, key, room_id, spec)
+                    .unwrap_or_else(ControlResponse::with_error)
             }
 
-            ControlRequestData::AcquireDeviceInfo(room_id, device_id) => {
-                let lock = house.lock().unwrap();
-                match lock.info(room_id, device_id) {
-                    Ok(s) => ControlResponse::with_info(s),
-                    Err(e) => ControlResponse::with_error(e),
-                }
+            ControlRequestData::Batch(ref items) => {
+                // Устройства, добавляемые пакетом, создаются до блокировки
+                // дома, так как подключение к удаленному устройству может
+                // занять длительное время.
+                let devices: Vec<_> = items
+                    .iter()
+                    .map(|item| match *item {
+                        ControlRequestData::AttachDevice(_, ref spec) => {
+                            Some(build_device(spec, key))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+
+                let mut lock = locked(&house);
+                let results = items
+                    .iter()
+                    .zip(devices)
+                    .map(|(item, device)| match (item, device) {
+                        (&ControlRequestData::AttachDevice(room_id, _), Some(device)) => device
+                            .and_then(|device| {
+                                Self::insert_device(&mut lock, subscriptions, room_id, device)
+                            })
+                            .unwrap_or_else(ControlResponse::with_error),
+                        (item, _) => Self::execute(&mut lock, subscriptions, item),
+                    })
+                    .collect();
+                ControlResponse::with_results(results)
+            }
+
+            ControlRequestData::Transaction(ref items) => {
+                Self::transaction(&mut locked(&house), subscriptions, items)
             }
 
-            ControlRequestData::SwitchOnDevice(room_id, device_id) => {
-                let mut lock = house.lock().unwrap();
-                match lock.notify(room_id, device_id, &SwitchOnEvent::new()) {
+            ref data => Self::execute(&mut locked(&house), subscriptions, data),
+        }
+    }
+
+    ///
+    /// Выполнить запрос над заблокированным экземпляром "умного" дома.
+    /// Запросы на добавление устройств выполняются без блокировки дома
+    /// и здесь не обрабатываются.
+    ///
+    fn execute(
+        house: &mut SmartHouse,
+        subscriptions: &Subscriptions,
+        data: &ControlRequestData,
+    ) -> ControlResponse {
+        trace(data);
+        match dispatch::execute(house, data) {
+            Ok(Action::Respond(response)) => response,
+            Ok(Action::Notify(room_id, device_id, event)) => {
+                match notify(house, subscriptions, room_id, device_id, event) {
                     Ok(s) => ControlResponse::with_state(s),
                     Err(e) => ControlResponse::with_error(e),
                 }
             }
+            Err(e) => ControlResponse::with_error(DeviceError::from(e)),
+        }
+    }
 
-            ControlRequestData::SwitchOffDevice(room_id, device_id) => {
-                let mut lock = house.lock().unwrap();
-                match lock.notify(room_id, device_id, &SwitchOffEvent::new()) {
-                    Ok(s) => ControlResponse::with_state(s),
-                    Err(e) => ControlResponse::with_error(e),
+    ///
+    /// Выполнить пакет запросов по принципу "все или ничего". Пакет может
+    /// содержать только запросы на получение сведений об устройствах и на
+    /// их включение и выключение, так как откатить удается только
+    /// переключение устройств. При ошибке выполнение прекращается, а
+    /// переключенные пакетом устройства возвращаются в прежнее состояние,
+    /// о чем подписчики получают уведомления.
+    ///
+    fn transaction(
+        house: &mut SmartHouse,
+        subscriptions: &Subscriptions,
+        items: &[ControlRequestData],
+    ) -> ControlResponse {
+        let mut transaction = match Transaction::new(items) {
+            Ok(transaction) => transaction,
+            Err(e) => return ControlResponse::with_error(DeviceError::from(e)),
+        };
+
+        while let Some(item) = transaction.next_request() {
+            let response = match dispatch::execute(house, item) {
+                Ok(Action::Respond(response)) => response,
+                Ok(action @ Action::Notify(room_id, device_id, event)) => {
+                    if transaction.needs_state(&action) {
+                        if let Ok(state) = house.notify(room_id, device_id, &StateEvent::new()) {
+                            transaction.save(room_id, &state);
+                        }
+                    }
+                    match notify(house, subscriptions, room_id, device_id, event) {
+                        Ok(s) => ControlResponse::with_state(s),
+                        Err(e) => ControlResponse::with_error(e),
+                    }
                 }
+                Err(e) => ControlResponse::with_error(DeviceError::from(e)),
+            };
+
+            if !transaction.complete(response) {
+                log::info!("Rolling back transaction of {} request(s)", items.len());
             }
+        }
 
-            _ => ControlResponse::with_error(DeviceError::UnexpectedMessage),
+        for (room_id, device_id, event) in transaction.rollback() {
+            if let Err(e) = notify(house, subscriptions, room_id, device_id, event) {
+                log::error!("Cannot restore device {} state: {}", device_id, e);
+            }
+        }
+
+        transaction.into_response()
+    }
+
+    ///
+    /// Добавить в комнату устройство с заданным описанием. Подключение к
+    /// удаленному устройству выполняется без блокировки "умного" дома.
+    ///
+    fn attach_device(
+        house: &Mutex<SmartHouse>,
+        subscriptions: &Arc<Subscriptions>,
+        key: &[u8],
+        room_id: Uuid,
+        spec: &DeviceSpec,
+    ) -> Result<ControlResponse, DeviceError> {
+        if locked(house).get(room_id).is_none() {
+            return Err(DeviceError::IllegalRoomId(room_id));
         }
+
+        let device = build_device(spec, key)?;
+        Self::insert_device(&mut locked(house), subscriptions, room_id, device)
     }
+
+    ///
+    /// Добавить созданное устройство в комнату и публиковать изменения
+    /// его состояния подписчикам.
+    ///
+    fn insert_device(
+        house: &mut SmartHouse,
+        subscriptions: &Arc<Subscriptions>,
+        room_id: Uuid,
+        mut device: Box<dyn Device + Send + Sync>,
+    ) -> Result<ControlResponse, DeviceError> {
+        log::info!(
+            "Attaching device {} \"{}\" to room {}",
+            device.id(),
+            device.name(),
+            room_id
+        );
+        watch(subscriptions, room_id, device.as_mut());
+
+        Ok(dispatch::insert_device(house, room_id, device)?)
+    }
+}
+
+// Записать в журнал запрос на изменение состава "умного" дома.
+fn trace(data: &ControlRequestData) {
+    match *data {
+        ControlRequestData::CreateRoom(ref name) => log::info!("Creating room \"{}\"", name),
+        ControlRequestData::DeleteRoom(room_id) => log::info!("Deleting room {}", room_id),
+        ControlRequestData::RenameRoom(room_id, ref name) => {
+            log::info!("Renaming room {} to \"{}\"", room_id, name)
+        }
+        ControlRequestData::DetachDevice(room_id, device_id) => {
+            log::info!("Detaching device {} from room {}", device_id, room_id)
+        }
+        _ => {}
+    }
+}
+
+// Передать событие устройству и, если событие изменяет состояние
+// устройства, сообщить подписчикам о его новом состоянии.
+fn notify(
+    house: &mut SmartHouse,
+    subscriptions: &Subscriptions,
+    room_id: Uuid,
+    device_id: Uuid,
+    event: EventData,
+) -> Result<DeviceState, DeviceError> {
+    let state = house.notify(room_id, device_id, &*Box::<dyn Event>::from(event))?;
+    if !event.is_query() {
+        subscriptions.publish(room_id, &state);
+    }
+
+    Ok(state)
+}
+
+// Установить для устройства обработчик, публикующий изменения его
+// состояния подписчикам.
+fn watch(subscriptions: &Arc<Subscriptions>, room_id: Uuid, device: &mut dyn Device) {
+    let subscriptions = subscriptions.clone();
+    device.watch(StateListener::new(move |state| {
+        subscriptions.publish(room_id, &state)
+    }));
+}
+
+// Создать устройство по описанию из запроса клиента. Адреса удаленных
+// термометра и датчика проверяются заранее, так как привязка их сокетов
+// выполняется в отдельном потоке.
+fn build_device(
+    spec: &DeviceSpec,
+    key: &[u8],
+) -> Result<Box<dyn Device + Send + Sync>, DeviceError> {
+    Ok(match spec {
+        DeviceSpec::Socket { name } => Box::new(SmartSocket::new(name)),
+        DeviceSpec::Thermometer { name, temperature } => {
+            Box::new(SmartThermometer::new(name, *temperature))
+        }
+        DeviceSpec::Lamp { name } => Box::new(SmartLamp::new(name)),
+        DeviceSpec::Sensor { name, kind, value } => Box::new(SmartSensor::new(name, *kind, *value)),
+        DeviceSpec::RemoteSocket { address } => {
+            Box::new(RemoteSmartSocket::connect(address.as_str(), key)?)
+        }
+        DeviceSpec::RemoteLamp { address } => {
+            Box::new(RemoteSmartLamp::connect(address.as_str(), key)?)
+        }
+        DeviceSpec::RemoteThermometer {
+            name,
+            bind,
+            address,
+        } => {
+            bind.to_socket_addrs()?;
+            address.to_socket_addrs()?;
+            Box::new(
+                RemoteThermometer::builder()
+                    .with_name(name)
+                    .bind(bind.clone())
+                    .connect(address.clone())
+                    .build(),
+            )
+        }
+        DeviceSpec::RemoteSensor {
+            name,
+            kind,
+            bind,
+            address,
+        } => {
+            bind.to_socket_addrs()?;
+            address.to_socket_addrs()?;
+            Box::new(
+                RemoteSensor::builder(*kind)
+                    .with_name(name)
+                    .bind(bind.clone())
+                    .connect(address.clone())
+                    .build(),
+            )
+        }
+    })
 }
 
 ///
-/// Сервер управления "умной" розеткой.
+/// Сервер управления отдельным устройством.
 ///
-pub struct SmartSocketServer {
+pub struct SmartDeviceServer<D> {
     server: Server,
-    socket: Arc<Mutex<SmartSocket>>,
+    device: Arc<Mutex<D>>,
+    pool: WorkerPool,
+    shutdown: ShutdownHandle,
+    shutdown_timeout: Duration,
 }
 
-impl SmartSocketServer {
+///
+/// Сервер управления "умной" розеткой.
+///
+pub type SmartSocketServer = SmartDeviceServer<SmartSocket>;
+
+///
+/// Сервер управления "умной" лампой.
+///
+pub type SmartLampServer = SmartDeviceServer<SmartLamp>;
+
+impl<D> SmartDeviceServer<D>
+where
+    D: Device + Send + 'static,
+{
     ///
-    /// Выполнить привязку сервера к сокету и экземпляру "умной" розетки.
+    /// Выполнить привязку сервера к сокету и экземпляру устройства с
+    /// заданным общим с клиентами ключом для аутентификации.
     ///
     #[inline]
-    pub fn bind<A>(addrs: A, socket: SmartSocket) -> Result<Self, BindError>
+    pub fn bind<A, K>(addrs: A, key: K, device: D) -> Result<Self, BindError>
     where
         A: ToSocketAddrs,
+        K: AsRef<[u8]>,
     {
-        Ok(Self {
-            server: Server::bind(addrs)?,
-            socket: Arc::new(Mutex::new(socket)),
-        })
+        Ok(Self::with_server(Server::bind(addrs, key)?, device))
+    }
+
+    ///
+    /// Создать сервер на основе настроенного сервера обмена сообщениями
+    /// и экземпляра устройства.
+    ///
+    #[inline]
+    pub fn with_server(server: Server, device: D) -> Self {
+        Self {
+            server,
+            device: Arc::new(Mutex::new(device)),
+            pool: WorkerPool::new(WORKERS, QUEUE_CAPACITY),
+            shutdown: ShutdownHandle::default(),
+            shutdown_timeout: SHUTDOWN_TIMEOUT,
+        }
+    }
+
+    ///
+    /// Обслуживать соединения заданным количеством рабочих потоков.
+    ///
+    #[inline]
+    pub fn with_workers(self, workers: usize) -> Self {
+        Self {
+            pool: WorkerPool::new(workers, self.pool.queue_capacity()),
+            ..self
+        }
+    }
+
+    ///
+    /// Обслуживать сверх количества рабочих потоков не более заданного
+    /// количества соединений, которые ожидают своей очереди на обслуживание.
+    /// Соединения сверх этого количества отклоняются.
+    ///
+    #[inline]
+    pub fn with_queue_capacity(self, queue_capacity: usize) -> Self {
+        Self {
+            pool: WorkerPool::new(self.pool.workers(), queue_capacity),
+            ..self
+        }
     }
 
     ///
-    /// Запустить сервер для обработки сообщений.
+    /// Ожидать завершения обработки соединений при остановке сервера
+    /// не дольше заданного времени.
+    ///
+    #[inline]
+    pub fn with_shutdown_timeout(self, shutdown_timeout: Duration) -> Self {
+        Self {
+            shutdown_timeout,
+            ..self
+        }
+    }
+
+    ///
+    /// Получить дескриптор для остановки сервера.
+    ///
+    #[inline]
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    ///
+    /// Получить статистику пула рабочих потоков.
+    ///
+    #[inline]
+    pub fn stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+
+    ///
+    /// Вернуть экземпляр устройства после остановки сервера. Если
+    /// устройство еще используется незавершенными соединениями,
+    /// возвращается сервер.
+    ///
+    #[allow(clippy::result_large_err)]
+    pub fn into_device(self) -> Result<D, Self> {
+        match Arc::try_unwrap(self.device) {
+            Ok(device) => Ok(device.into_inner().unwrap_or_else(PoisonError::into_inner)),
+            Err(device) => Err(Self { device, ..self }),
+        }
+    }
+
+    ///
+    /// Запустить сервер для обработки сообщений. Возвращает управление
+    /// после остановки сервера с помощью дескриптора остановки.
     ///
     pub fn run(&self) {
-        for connection in self.server.incoming() {
-            let mut connection = match connection {
-                Ok(c) => c,
+        let device = self.device.clone();
+        let shutdown = self.shutdown.clone();
+        let dispatcher = self.pool.start(move |worker, session: &mut Session<()>| {
+            if shutdown.is_shutdown() {
+                return false;
+            }
+            let Some(connection) = session.link.establish() else {
+                return false;
+            };
+
+            let request = match connection.poll::<ControlRequest>(TURN_INTERVAL) {
+                Ok(Some(r)) => r,
+                Ok(None) => return true,
                 Err(e) => {
-                    log::error!("Cannot establish connection {}", e);
-                    continue;
+                    log::warn!("Connection lost when receiving data: {}", e);
+                    return false;
                 }
             };
 
-            let addr = match connection.peer_addr() {
-                Ok(addr) => addr.to_string(),
-                Err(_) => "unknown".to_owned(),
+            worker.count_request();
+            let mut response = if request.version() > connection.version() {
+                ControlResponse::with_error(DeviceError::UnsupportedVersion(request.version()))
+            } else {
+                Self::dispatch(device.clone(), request.as_ref())
             };
+            response.set_id(request.id());
+            if connection.send(response).is_err() {
+                log::warn!("Connection lost when sending data");
+                return false;
+            }
 
-            log::info!("New client connected: {}", addr);
-
-            let socket = self.socket.clone();
-            thread::spawn(move || loop {
-                let request = connection.recv::<ControlRequest>();
-                let request = match request {
-                    Ok(r) => r,
-                    Err(_) => {
-                        log::warn!("Connection lost when receiving data");
-                        break;
-                    }
-                };
+            true
+        });
 
-                let response = Self::dispatch(socket.clone(), request.as_ref());
-                if connection.send(response).is_err() {
-                    log::warn!("Connection lost when sending data");
-                    break;
-                }
-            });
-        }
+        serve(&self.server, &self.shutdown, &dispatcher, || ());
+        dispatcher.join(self.shutdown_timeout);
     }
 
     ///
     /// Выполнить диспетчеризацию запроса.
     ///
-    fn dispatch(socket: Arc<Mutex<SmartSocket>>, req: &ControlRequest) -> ControlResponse {
-        match req.data {
+    fn dispatch(device: Arc<Mutex<D>>, req: &ControlRequest) -> ControlResponse {
+        match *req.data() {
             ControlRequestData::AcquireRemoteDeviceState => {
-                let mut lock = socket.lock().unwrap();
+                let mut lock = locked(&device);
                 log::info!("Requesting device {} state", lock.id());
 
                 match lock.notify(&StateEvent::new()) {
@@ -214,14 +749,21 @@ impl SmartSocketServer {
             }
 
             ControlRequestData::AcquireRemoteDeviceName => {
-                let lock = socket.lock().unwrap();
+                let lock = locked(&device);
                 log::info!("Obtaining device {} name \"{}\"", lock.id(), lock.name());
 
                 ControlResponse::with_name(lock.id(), lock.name())
             }
 
+            ControlRequestData::AcquireRemoteDeviceCapabilities => {
+                let lock = locked(&device);
+                log::info!("Obtaining device {} capabilities", lock.id());
+
+                ControlResponse::with_capabilities(lock.capabilities())
+            }
+
             ControlRequestData::SwitchOnRemoteDevice => {
-                let mut lock = socket.lock().unwrap();
+                let mut lock = locked(&device);
                 log::info!("Switching on device {}", lock.id());
 
                 match lock.notify(&SwitchOnEvent::new()) {
@@ -231,7 +773,7 @@ impl SmartSocketServer {
             }
 
             ControlRequestData::SwitchOffRemoteDevice => {
-                let mut lock = socket.lock().unwrap();
+                let mut lock = locked(&device);
                 log::info!("Switching off device {}", lock.id());
 
                 match lock.notify(&SwitchOffEvent::new()) {
@@ -240,7 +782,202 @@ impl SmartSocketServer {
                 }
             }
 
+            ControlRequestData::NotifyRemoteDevice(event) => {
+                let mut lock = locked(&device);
+                log::info!("Notifying device {} with {:?}", lock.id(), event);
+
+                match lock.notify(&*Box::<dyn Event>::from(event)) {
+                    Ok(s) => ControlResponse::with_state(s),
+                    Err(e) => ControlResponse::with_error(e),
+                }
+            }
+
             _ => ControlResponse::with_error(DeviceError::UnexpectedMessage),
         }
     }
 }
+
+impl SmartSocketServer {
+    ///
+    /// Вернуть экземпляр "умной" розетки после остановки сервера. Если
+    /// розетка еще используется незавершенными соединениями, возвращается
+    /// сервер.
+    ///
+    #[allow(clippy::result_large_err)]
+    #[inline]
+    pub fn into_socket(self) -> Result<SmartSocket, Self> {
+        self.into_device()
+    }
+}
+
+impl SmartLampServer {
+    ///
+    /// Вернуть экземпляр "умной" лампы после остановки сервера. Если
+    /// лампа еще используется незавершенными соединениями, возвращается
+    /// сервер.
+    ///
+    #[allow(clippy::result_large_err)]
+    #[inline]
+    pub fn into_lamp(self) -> Result<SmartLamp, Self> {
+        self.into_device()
+    }
+}
+
+// Принимать входящие соединения и передавать их рабочим потокам до
+// запроса на остановку сервера. Связь с клиентом подтверждается рабочим
+// потоком при первом обслуживании соединения. Если очередь соединений
+// заполнена, отдельный поток подтверждает связь, отправляет клиенту ответ
+// с ошибкой и закрывает соединение.
+fn serve<S, F>(
+    server: &Server,
+    shutdown: &ShutdownHandle,
+    dispatcher: &Dispatcher<Session<S>>,
+    state: F,
+) where
+    S: Send,
+    F: Fn() -> S,
+{
+    let (rejects, rejected) = mpsc::sync_channel::<Session<S>>(REJECT_CAPACITY);
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            for mut session in rejected {
+                if shutdown.is_shutdown() {
+                    continue;
+                }
+                if let Some(connection) = session.link.establish() {
+                    if connection
+                        .send(ControlResponse::with_error(DeviceError::ServerBusy))
+                        .is_err()
+                    {
+                        log::warn!("Connection lost when sending data");
+                    }
+                }
+            }
+        });
+
+        while let Some(incoming) = accept(server, shutdown) {
+            let session = Session {
+                link: Link::Incoming(incoming),
+                state: state(),
+            };
+            if let Err(session) = dispatcher.dispatch(session) {
+                log::warn!("Connection queue is full, rejecting the client");
+                if rejects.try_send(session).is_err() {
+                    log::warn!("Too many rejected clients, dropping the connection");
+                }
+            }
+        }
+
+        drop(rejects);
+    });
+}
+
+// Дождаться очередного входящего соединения. Возвращает `None` после
+// запроса на остановку сервера.
+fn accept(server: &Server, shutdown: &ShutdownHandle) -> Option<Incoming> {
+    while !shutdown.is_shutdown() {
+        match server.poll_incoming(POLL_INTERVAL) {
+            Ok(Some(incoming)) => return Some(incoming),
+            Ok(None) => continue,
+            Err(e) => log::error!("Cannot establish connection {}", e),
+        }
+    }
+
+    None
+}
+
+// Соединение, обслуживаемое рабочими потоками пула, вместе с состоянием
+// его обработчика, которое сохраняется между циклами обслуживания.
+struct Session<S> {
+    link: Link,
+    state: S,
+}
+
+// Состояние соединения с клиентом.
+enum Link {
+    // Принятое соединение, связь по которому еще не подтверждена.
+    Incoming(Incoming),
+
+    // Соединение с подтвержденной связью.
+    Established(Connection),
+
+    // Соединение, связь по которому подтвердить не удалось.
+    Failed,
+}
+
+impl Link {
+    // Получить соединение, подтвердив связь с клиентом при первом
+    // обращении. Возвращает `None`, если подтвердить связь не удалось.
+    fn establish(&mut self) -> Option<&mut Connection> {
+        if let Link::Incoming(_) = self {
+            if let Link::Incoming(incoming) = mem::replace(self, Link::Failed) {
+                *self = handshake(incoming).map_or(Link::Failed, Link::Established);
+            }
+        }
+
+        match self {
+            Link::Established(connection) => Some(connection),
+            _ => None,
+        }
+    }
+}
+
+// Подтвердить связь с клиентом по принятому соединению.
+fn handshake(incoming: Incoming) -> Option<Connection> {
+    let addr = match incoming.peer_addr() {
+        Ok(addr) => addr.to_string(),
+        Err(_) => "unknown".to_owned(),
+    };
+
+    match incoming.handshake() {
+        Ok(connection) => {
+            log::info!(
+                "New client connected: {} (protocol {}, capabilities {})",
+                addr,
+                connection.version(),
+                connection.capabilities()
+            );
+            Some(connection)
+        }
+        Err(e) => {
+            log::warn!("Cannot establish connection with {}: {}", addr, e);
+            None
+        }
+    }
+}
+
+// Подписчик на изменения состояния устройств, связанный с соединением.
+// Подписки соединения отменяются при его закрытии.
+struct Subscriber {
+    id: u64,
+    subscriptions: Arc<Subscriptions>,
+    sender: Sender<ControlResponse>,
+    notifications: Receiver<ControlResponse>,
+}
+
+impl Subscriber {
+    // Создать подписчика для соединения с заданным идентификатором.
+    fn new(id: u64, subscriptions: &Arc<Subscriptions>) -> Self {
+        let (sender, notifications) = mpsc::channel();
+        Self {
+            id,
+            subscriptions: subscriptions.clone(),
+            sender,
+            notifications,
+        }
+    }
+}
+
+impl Drop for Subscriber {
+    // Отменить подписки соединения.
+    fn drop(&mut self) {
+        self.subscriptions.unsubscribe(self.id);
+    }
+}
+
+// Заблокировать мьютекс, не обращая внимания на его отравление: паника
+// при обработке запроса одного клиента не должна делать дом или
+// устройство недоступными для остальных клиентов.
+fn locked<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,102 @@
+use std::{
+    collections::HashMap,
+    sync::{mpsc::Sender, Mutex},
+};
+
+use uuid::Uuid;
+
+use crate::{control::message::ControlResponse, device::DeviceState};
+
+///
+/// Предмет подписки на изменения состояния устройств.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Topic {
+    // Изменения состояния заданного устройства в заданной комнате.
+    Device(Uuid, Uuid),
+
+    // Изменения состояния всех устройств.
+    All,
+}
+
+impl Topic {
+    ///
+    /// Проверить, относится ли изменение состояния устройства к
+    /// предмету подписки.
+    ///
+    fn matches(&self, room_id: Uuid, device_id: Uuid) -> bool {
+        match *self {
+            Self::Device(r, d) => r == room_id && d == device_id,
+            Self::All => true,
+        }
+    }
+}
+
+///
+/// Подписчик на изменения состояния устройств.
+///
+struct Subscriber {
+    topics: Vec<Topic>,
+    sender: Sender<ControlResponse>,
+}
+
+///
+/// Реестр подписок соединений на изменения состояния устройств.
+///
+#[derive(Default)]
+pub(crate) struct Subscriptions {
+    subscribers: Mutex<HashMap<u64, Subscriber>>,
+}
+
+impl Subscriptions {
+    ///
+    /// Подписать соединение с заданным идентификатором на уведомления.
+    ///
+    pub(crate) fn subscribe(
+        &self,
+        connection_id: u64,
+        topic: Topic,
+        sender: &Sender<ControlResponse>,
+    ) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let subscriber = subscribers
+            .entry(connection_id)
+            .or_insert_with(|| Subscriber {
+                topics: Vec::new(),
+                sender: sender.clone(),
+            });
+
+        if !subscriber.topics.contains(&topic) {
+            subscriber.topics.push(topic);
+        }
+    }
+
+    ///
+    /// Отменить все подписки соединения с заданным идентификатором.
+    ///
+    pub(crate) fn unsubscribe(&self, connection_id: u64) {
+        self.subscribers.lock().unwrap().remove(&connection_id);
+    }
+
+    ///
+    /// Разослать уведомление об изменении состояния устройства всем
+    /// заинтересованным подписчикам. Подписчики, соединения которых
+    /// закрыты, исключаются из реестра.
+    ///
+    pub(crate) fn publish(&self, room_id: Uuid, state: &DeviceState) {
+        self.subscribers.lock().unwrap().retain(|_, subscriber| {
+            if subscriber
+                .topics
+                .iter()
+                .any(|t| t.matches(room_id, state.device_id()))
+            {
+                subscriber
+                    .sender
+                    .send(ControlResponse::with_notification(room_id, state.clone()))
+                    .is_ok()
+            } else {
+                true
+            }
+        });
+    }
+}
@@ -0,0 +1,215 @@
+use std::{fmt, net::ToSocketAddrs, sync::Mutex};
+
+use uuid::Uuid;
+
+pub use smarthome2_core::device::lamp::{
+    SetBrightnessEvent, SetColorTemperatureEvent, SmartLamp, MAX_BRIGHTNESS, MAX_COLOR_TEMPERATURE,
+    MIN_COLOR_TEMPERATURE,
+};
+
+use crate::{
+    control::{client::ControlClient, message::ControlRequest, retry::RetryPolicy},
+    device::{Device, DeviceCapabilities, DeviceKind, DeviceState, Event, EventData, StateEvent},
+    error::DeviceError,
+};
+
+///
+/// Структура, описывающая взаимодействие с удаленной "умной" лампой
+/// по протоколу TCP.
+///
+pub struct RemoteSmartLamp {
+    ///
+    /// Идентификатор "умной" лампы.
+    ///
+    id: Uuid,
+
+    ///
+    /// Имя "умной" лампы.
+    ///
+    name: String,
+
+    ///
+    /// Возможности "умной" лампы, полученные при подключении.
+    ///
+    capabilities: DeviceCapabilities,
+
+    ///
+    /// Клиент для взаимодействия с удаленной умной лампой.
+    ///
+    client: Mutex<ControlClient>,
+}
+
+impl fmt::Display for RemoteSmartLamp {
+    ///
+    /// Получить информацию об "умной" лампе с помощью форматирования.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self
+            .client
+            .lock()
+            .unwrap()
+            .request(ControlRequest::acquire_remote_device_state())
+            .ok()
+            .and_then(|response| response.state())
+            .filter(|state| state.device_id() == self.id)
+            .ok_or(fmt::Error)?;
+        let (enabled, brightness) = state.enabled().zip(state.brightness()).ok_or(fmt::Error)?;
+
+        let mut v = vec![format!(
+            "умная лампа \"{}\" ({}). Состояние: ",
+            self.name, self.id
+        )];
+
+        if enabled {
+            v.push(format!("включена, яркость {} %", brightness));
+            if let Some(kelvin) = state.color_temperature() {
+                v.push(format!(", цветовая температура {} K", kelvin));
+            }
+            v.push(".".to_string());
+        } else {
+            v.push("выключена.".to_string());
+        }
+
+        write!(f, "{}", v.join(""))
+    }
+}
+
+impl Device for RemoteSmartLamp {
+    ///
+    /// Идентификатор удаленной "умной" лампы.
+    ///
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    ///
+    /// Получить имя удаленной "умной" лампы.
+    ///
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    ///
+    /// Получить описание возможностей удаленной "умной" лампы.
+    ///
+    fn capabilities(&self) -> DeviceCapabilities {
+        self.capabilities.clone()
+    }
+
+    ///
+    /// Обработать событие устройством.
+    ///
+    fn notify(&mut self, e: &dyn Event) -> Result<DeviceState, DeviceError> {
+        if e.is::<StateEvent>() {
+            self.state()
+        } else if let Some(data) = e.data() {
+            self.send(data)
+        } else {
+            Err(DeviceError::NotImplementedEvent(e.id()))
+        }
+    }
+}
+
+impl RemoteSmartLamp {
+    ///
+    /// Подключиться к серверу с заданным адресом и общим с сервером ключом
+    /// для аутентификации.
+    ///
+    pub fn connect<A, K>(addrs: A, key: K) -> Result<Self, DeviceError>
+    where
+        A: ToSocketAddrs,
+        K: AsRef<[u8]>,
+    {
+        Self::with_client(ControlClient::connect(addrs, key)?)
+    }
+
+    ///
+    /// Подключиться к серверу с заданным адресом в режиме восстановления
+    /// соединения с заданной политикой повторных попыток и общим с сервером
+    /// ключом для аутентификации.
+    ///
+    pub fn reconnecting<A, K>(addrs: A, key: K, policy: RetryPolicy) -> Result<Self, DeviceError>
+    where
+        A: ToSocketAddrs,
+        K: AsRef<[u8]>,
+    {
+        Self::with_client(ControlClient::reconnecting(addrs, key, policy)?)
+    }
+
+    ///
+    /// Создать удаленную "умную" лампу на основе подключенного клиента
+    /// подсистемы управления. Если сервер управляет устройством другого
+    /// вида, возвращается ошибка.
+    ///
+    pub fn with_client(mut client: ControlClient) -> Result<Self, DeviceError> {
+        let response = client.request(ControlRequest::acquire_remote_device_name())?;
+        let (id, name) = response.name().ok_or(DeviceError::UnexpectedMessage)?;
+
+        let capabilities = client
+            .request(ControlRequest::acquire_remote_device_capabilities())?
+            .capabilities()
+            .filter(|capabilities| capabilities.kind() == DeviceKind::Lamp)
+            .cloned()
+            .ok_or(DeviceError::UnexpectedMessage)?;
+
+        Ok(Self {
+            id,
+            name: name.to_owned(),
+            capabilities,
+            client: Mutex::new(client),
+        })
+    }
+
+    ///
+    /// Включить удаленную "умную" лампу.
+    ///
+    pub fn switch_on(&mut self) -> Result<DeviceState, DeviceError> {
+        self.send(EventData::SwitchOn)
+    }
+
+    ///
+    /// Выключить удаленную "умную" лампу.
+    ///
+    pub fn switch_off(&mut self) -> Result<DeviceState, DeviceError> {
+        self.send(EventData::SwitchOff)
+    }
+
+    ///
+    /// Установить яркость свечения удаленной "умной" лампы.
+    ///
+    pub fn set_brightness(&mut self, percent: u8) -> Result<DeviceState, DeviceError> {
+        self.send(EventData::SetBrightness { percent })
+    }
+
+    ///
+    /// Установить цветовую температуру свечения удаленной "умной" лампы.
+    ///
+    pub fn set_color_temperature(&mut self, kelvin: u16) -> Result<DeviceState, DeviceError> {
+        self.send(EventData::SetColorTemperature { kelvin })
+    }
+
+    ///
+    /// Получить состояние удаленной "умной" лампы.
+    ///
+    pub fn state(&mut self) -> Result<DeviceState, DeviceError> {
+        self.request(ControlRequest::acquire_remote_device_state())
+    }
+
+    // Передать событие удаленной "умной" лампе для обработки.
+    fn send(&mut self, event: EventData) -> Result<DeviceState, DeviceError> {
+        self.request(ControlRequest::notify_remote_device(event))
+    }
+
+    // Выполнить запрос и получить из ответа состояние лампы.
+    fn request(&mut self, request: ControlRequest) -> Result<DeviceState, DeviceError> {
+        let response = self.client.get_mut().unwrap().request(request)?;
+
+        if let Some(state) = response.state() {
+            if state.device_id() == self.id {
+                return Ok(state);
+            }
+        }
+
+        Err(DeviceError::UnexpectedMessage)
+    }
+}
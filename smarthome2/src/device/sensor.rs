@@ -0,0 +1,562 @@
+#![allow(clippy::type_complexity)]
+
+use std::{
+    fmt,
+    net::{ToSocketAddrs, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock, Weak,
+    },
+    thread, time,
+};
+
+use bincode::{self, Options};
+use log;
+use rand::{thread_rng, Rng};
+use statrs::distribution::Normal;
+use uuid::Uuid;
+
+use smarthome2_core::device::sensor::title;
+pub use smarthome2_core::device::sensor::SmartSensor;
+
+use crate::{
+    control::message::SensorMessage,
+    device::{
+        Device, DeviceCapabilities, DeviceState, Event, Measurement, SensorKind, StateEvent,
+        StateListener, Value,
+    },
+    error::DeviceError,
+};
+
+///
+/// Структура, описывающая взаимодействие с автономным "умным" датчиком.
+/// Автономный датчик имитирует показания датчика заданного вида.
+///
+#[derive(Debug)]
+pub struct AutonomousSensor {
+    ///
+    /// Сокет для отправки показаний датчика.
+    ///
+    socket: UdpSocket,
+
+    ///
+    /// Экземпляр "умного" датчика.
+    ///
+    sensor: Arc<RwLock<SmartSensor>>,
+
+    ///
+    /// Имитировать изменение показаний датчика.
+    ///
+    noisy: bool,
+}
+
+impl AutonomousSensor {
+    ///
+    /// Создать объект по умолчанию для построения экземпляра автономного
+    /// "умного" датчика.
+    ///
+    #[inline]
+    pub fn builder() -> AutonomousSensorBuilder<&'static str, &'static str> {
+        AutonomousSensorBuilder::<&str, &str>::new()
+    }
+
+    ///
+    /// Установить текущее показание датчика.
+    ///
+    pub fn set_value<V: Into<Value>>(&self, value: V) {
+        let mut guard = self.sensor.write().unwrap();
+        guard.set_value(value);
+    }
+
+    ///
+    /// Запустить отдельный поток для отправки дейтаграмм с показаниями датчика.
+    ///
+    pub fn run(
+        &self,
+    ) -> Result<
+        (
+            thread::JoinHandle<Result<(), DeviceError>>,
+            Weak<AtomicBool>,
+        ),
+        DeviceError,
+    > {
+        let working = Arc::new(AtomicBool::new(true));
+        let control = Arc::downgrade(&working);
+
+        let socket = self.socket.try_clone()?;
+        let sensor = self.sensor.clone();
+        let noisy = self.noisy;
+
+        Ok((
+            thread::spawn(move || {
+                let duration = time::Duration::from_secs(3);
+
+                let mut rng = thread_rng();
+
+                while (*working).load(Ordering::Relaxed) {
+                    let (measurement, id) = {
+                        let mut guard = sensor.write().unwrap();
+                        if noisy {
+                            let value = simulate(guard.kind(), guard.value(), &mut rng);
+                            guard.set_value(value);
+                        }
+                        (guard.measurement(), guard.id())
+                    };
+
+                    let message = SensorMessage::new(id).with_measurement(measurement);
+                    let bytes = bincode::options().with_big_endian().serialize(&message)?;
+
+                    log::info!("Sending value {} of the device {} ...", measurement, id);
+                    socket.send(&bytes[..])?;
+
+                    thread::sleep(duration);
+                }
+
+                Ok(())
+            }),
+            control,
+        ))
+    }
+}
+
+// Получить очередное показание датчика заданного вида. Числовые показания
+// получают нормальный шум, логические случайно переключаются.
+fn simulate<R: Rng>(kind: SensorKind, value: Value, rng: &mut R) -> Value {
+    match (kind, value) {
+        (SensorKind::Temperature, Value::Number(v)) => {
+            Value::Number(v + rng.sample(Normal::new(0.0, 1.0).unwrap()))
+        }
+        (SensorKind::Humidity, Value::Number(v)) => {
+            Value::Number((v + rng.sample(Normal::new(0.0, 2.0).unwrap())).clamp(0.0, 100.0))
+        }
+        (SensorKind::CarbonDioxide, Value::Number(v)) => {
+            Value::Number((v + rng.sample(Normal::new(0.0, 25.0).unwrap())).max(0.0))
+        }
+        (SensorKind::Motion, Value::Flag(_)) => Value::Flag(rng.gen_bool(0.2)),
+        (SensorKind::Contact, Value::Flag(v)) => Value::Flag(v ^ rng.gen_bool(0.1)),
+        (_, value) => value,
+    }
+}
+
+///
+/// Структура для построения экзкмпляра автономного "умного" датчика.
+///
+pub struct AutonomousSensorBuilder<BA: ToSocketAddrs, RA: ToSocketAddrs> {
+    ///
+    /// Адес привязки UDP-сокета.
+    ///
+    addr: BA,
+
+    ///
+    /// Адрес подключения удаленного датчика.
+    ///
+    remote_addr: RA,
+
+    ///
+    /// Имитировать изменение показаний датчика.
+    ///
+    noisy: bool,
+}
+
+impl<BA: ToSocketAddrs, RA: ToSocketAddrs> AutonomousSensorBuilder<BA, RA> {
+    ///
+    /// Установить адрес привязки сокета автономного "умного" датчика.
+    ///
+    #[inline]
+    pub fn bind<BA2: ToSocketAddrs>(self, addr: BA2) -> AutonomousSensorBuilder<BA2, RA> {
+        AutonomousSensorBuilder::<BA2, RA> {
+            addr,
+            remote_addr: self.remote_addr,
+            noisy: self.noisy,
+        }
+    }
+
+    ///
+    /// Установить адрес удаленного "умного" датчика.
+    ///
+    #[inline]
+    pub fn connect<RA2: ToSocketAddrs>(self, addr: RA2) -> AutonomousSensorBuilder<BA, RA2> {
+        AutonomousSensorBuilder::<BA, RA2> {
+            addr: self.addr,
+            remote_addr: addr,
+            noisy: self.noisy,
+        }
+    }
+
+    ///
+    /// Имитировать изменение показаний датчика в соответствии с его видом.
+    ///
+    #[inline]
+    pub fn with_noise(self) -> Self {
+        Self {
+            addr: self.addr,
+            remote_addr: self.remote_addr,
+            noisy: true,
+        }
+    }
+
+    ///
+    /// Выполнить построение экзкмпляра автономного "умного" датчика.
+    ///
+    pub fn build(self, sensor: SmartSensor) -> Result<AutonomousSensor, DeviceError> {
+        let s = AutonomousSensor {
+            socket: UdpSocket::bind(self.addr)?,
+            sensor: Arc::new(RwLock::new(sensor)),
+            noisy: self.noisy,
+        };
+        s.socket.connect(self.remote_addr)?;
+
+        Ok(s)
+    }
+}
+
+impl Default for AutonomousSensorBuilder<&str, &str> {
+    ///
+    /// Создать экземпляр по умолчанию построителя автономного
+    /// "умного" датчика.
+    ///
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AutonomousSensorBuilder<&str, &str> {
+    ///
+    /// Создать экземпляр с настройками по умолчанию построителя
+    /// автономного "умного" датчика.
+    ///
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            addr: "127.0.0.1:8001",
+            remote_addr: "127.0.0.1:8889",
+            noisy: false,
+        }
+    }
+}
+
+///
+/// Структура, описывающая взаимодействие с удаленным "умным" датчиком.
+///
+#[derive(Debug)]
+pub struct RemoteSensor {
+    ///
+    /// Имя удаленного "умного" датчика.
+    ///
+    name: String,
+
+    ///
+    /// Вид удаленного "умного" датчика.
+    ///
+    kind: SensorKind,
+
+    ///
+    /// Идентификатор и последнее полученное показание удаленного
+    /// "умного" датчика.
+    ///
+    data: Arc<RwLock<(Uuid, Option<Value>)>>,
+
+    ///
+    /// Обработчик получения новых показаний датчика.
+    ///
+    listener: Arc<RwLock<Option<StateListener>>>,
+
+    ///
+    /// Флаг для завершения связанного с удаленным "умным" датчиком потока.
+    ///
+    control: Weak<AtomicBool>,
+}
+
+impl Drop for RemoteSensor {
+    ///
+    /// Выполнить остановку потока при удалении экземпляра удаленного
+    /// "умного" датчика.
+    ///
+    fn drop(&mut self) {
+        if let Some(w) = self.control.upgrade() {
+            (*w).store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+impl fmt::Display for RemoteSensor {
+    ///
+    /// Получить информацию об удаленном "умном" датчике с помощью форматирования.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (id, value) = {
+            let guard = self.data.read().unwrap();
+            *guard
+        };
+
+        write!(f, "{} \"{}\" ({}). ", title(self.kind), self.name, id)?;
+        match value {
+            Some(value) => write!(
+                f,
+                "Показание: {}.",
+                Measurement::from((self.kind.quantity(), value))
+            ),
+            None => write!(f, "Показания не получены."),
+        }
+    }
+}
+
+impl Device for RemoteSensor {
+    ///
+    /// Получить идентификатор удаленного "умного" датчика.
+    ///
+    fn id(&self) -> Uuid {
+        let guard = self.data.read().unwrap();
+        let (id, _) = *guard;
+
+        id
+    }
+
+    ///
+    /// Получить имя удаленного "умного" датчика.
+    ///
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    ///
+    /// Получить описание возможностей удаленного "умного" датчика.
+    ///
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::for_sensor(self.kind)
+    }
+
+    ///
+    /// Обработать событие устройством.
+    ///
+    fn notify(&mut self, e: &dyn Event) -> Result<DeviceState, DeviceError> {
+        if e.is::<StateEvent>() {
+            let (id, value) = {
+                let guard = self.data.read().unwrap();
+                *guard
+            };
+
+            Ok(state(self.kind, id, e.id(), value))
+        } else {
+            Err(DeviceError::NotImplementedEvent(e.id()))
+        }
+    }
+
+    ///
+    /// Установить обработчик получения новых показаний датчика.
+    ///
+    fn watch(&mut self, listener: StateListener) {
+        let mut guard = self.listener.write().unwrap();
+        *guard = Some(listener);
+    }
+}
+
+impl RemoteSensor {
+    ///
+    /// Создать объект по умолчанию для построения экземпляра удаленного
+    /// "умного" датчика заданного вида.
+    ///
+    #[inline]
+    pub fn builder(kind: SensorKind) -> RemoteSensorBuilder<&'static str, &'static str> {
+        RemoteSensorBuilder::<&str, &str>::new(kind)
+    }
+
+    ///
+    /// Получить вид удаленного "умного" датчика.
+    ///
+    #[inline]
+    pub fn kind(&self) -> SensorKind {
+        self.kind
+    }
+
+    ///
+    /// Получить последнее показание удаленного "умного" датчика, если
+    /// оно уже получено.
+    ///
+    pub fn value(&self) -> Option<Value> {
+        let guard = self.data.read().unwrap();
+        let (_, value) = *guard;
+
+        value
+    }
+}
+
+// Получить состояние датчика заданного вида с заданным показанием.
+fn state(kind: SensorKind, device_id: Uuid, event_id: Uuid, value: Option<Value>) -> DeviceState {
+    let state = DeviceState::new(device_id, event_id);
+    match value {
+        Some(value) => state.with_measurement((kind.quantity(), value)),
+        None => state,
+    }
+}
+
+///
+/// Структура для построения экзкмпляра удаленного "умного" датчика.
+///
+pub struct RemoteSensorBuilder<BA, RA>
+where
+    BA: 'static + ToSocketAddrs + Send,
+    RA: 'static + ToSocketAddrs + Send,
+{
+    ///
+    /// Имя удаленного "умного" датчика.
+    ///
+    name: String,
+
+    ///
+    /// Вид удаленного "умного" датчика.
+    ///
+    kind: SensorKind,
+
+    ///
+    /// Адес привязки UDP-сокета.
+    ///
+    addr: BA,
+
+    ///
+    /// Адрес подключения автономного датчика.
+    ///
+    remote_addr: RA,
+}
+
+impl<BA: ToSocketAddrs + Send, RA: ToSocketAddrs + Send> RemoteSensorBuilder<BA, RA> {
+    ///
+    /// Использовать имя удаленного "умного" датчика.
+    ///
+    #[inline]
+    pub fn with_name<D: AsRef<str>>(self, name: D) -> Self {
+        Self {
+            name: name.as_ref().to_string(),
+            ..self
+        }
+    }
+
+    ///
+    /// Установить адрес привязки сокета удаленного "умного" датчика.
+    ///
+    #[inline]
+    pub fn bind<BA2: 'static + ToSocketAddrs + Send>(
+        self,
+        addr: BA2,
+    ) -> RemoteSensorBuilder<BA2, RA> {
+        RemoteSensorBuilder::<BA2, RA> {
+            name: self.name,
+            kind: self.kind,
+            addr,
+            remote_addr: self.remote_addr,
+        }
+    }
+
+    ///
+    /// Установить адрес автономного "умного" датчика.
+    ///
+    #[inline]
+    pub fn connect<RA2: 'static + ToSocketAddrs + Send>(
+        self,
+        addr: RA2,
+    ) -> RemoteSensorBuilder<BA, RA2> {
+        RemoteSensorBuilder::<BA, RA2> {
+            name: self.name,
+            kind: self.kind,
+            addr: self.addr,
+            remote_addr: addr,
+        }
+    }
+
+    ///
+    /// Выполнить построение экзкмпляра удаленного "умного" датчика.
+    ///
+    pub fn build(self) -> RemoteSensor {
+        let kind = self.kind;
+        let addr = self.addr;
+        let remote_addr = self.remote_addr;
+        let duration = time::Duration::from_millis(50);
+
+        let working = Arc::new(AtomicBool::new(true));
+        let control = Arc::downgrade(&working);
+
+        let data = Arc::new(RwLock::new((Uuid::nil(), None)));
+        let cloned = data.clone();
+
+        let listener: Arc<RwLock<Option<StateListener>>> = Arc::new(RwLock::new(None));
+        let cloned_listener = listener.clone();
+
+        thread::spawn(move || -> Result<(), DeviceError> {
+            let socket = UdpSocket::bind(addr)?;
+            socket.connect(remote_addr)?;
+
+            let mut buf = [0u8; 512];
+            while (*working).load(Ordering::Relaxed) {
+                if let Ok(received) = socket.recv(&mut buf) {
+                    let message = bincode::options()
+                        .with_big_endian()
+                        .deserialize::<SensorMessage>(&buf[..received]);
+                    if let Some((id, value)) = message
+                        .ok()
+                        .and_then(|m| Some((m.id(), m.value(kind.quantity())?)))
+                    {
+                        {
+                            let mut guard = cloned.write().unwrap();
+                            *guard = (id, Some(value));
+                        }
+
+                        if let Some(ref listener) = *cloned_listener.read().unwrap() {
+                            listener.call(state(kind, id, StateEvent::ID, Some(value)));
+                        }
+                    } else {
+                        log::error!("Message deserialization error");
+                    }
+                }
+
+                thread::sleep(duration);
+            }
+
+            Ok(())
+        });
+
+        RemoteSensor {
+            name: self.name,
+            kind,
+            data,
+            listener,
+            control,
+        }
+    }
+}
+
+impl RemoteSensorBuilder<&str, &str> {
+    ///
+    /// Создать экземпляр с настройками по умолчанию построителя
+    /// удаленного "умного" датчика заданного вида.
+    ///
+    #[inline]
+    pub fn new(kind: SensorKind) -> Self {
+        Self {
+            name: "Untitled".to_owned(),
+            kind,
+            addr: "127.0.0.1:8889",
+            remote_addr: "127.0.0.1:8001",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulate_test() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let humidity = simulate(SensorKind::Humidity, Value::Number(99.0), &mut rng);
+            assert!((0.0..=100.0).contains(&humidity.as_f64().unwrap()));
+            let motion = simulate(SensorKind::Motion, Value::Flag(false), &mut rng);
+            assert!(motion.as_bool().is_some());
+        }
+        assert_eq!(
+            simulate(SensorKind::Humidity, Value::Flag(true), &mut rng),
+            Value::Flag(true)
+        );
+    }
+}
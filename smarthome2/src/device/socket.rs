@@ -1,170 +1,17 @@
-use std::{cell::RefCell, fmt, net::ToSocketAddrs};
+use std::{fmt, net::ToSocketAddrs, sync::Mutex};
 
 use uuid::Uuid;
 
+pub use smarthome2_core::device::socket::{
+    SetLoadEvent, SmartSocket, SwitchOffEvent, SwitchOnEvent,
+};
+
 use crate::{
-    control::{client::ControlClient, message::ControlRequest},
-    device::{Device, DeviceState, Event, StateEvent},
+    control::{client::ControlClient, message::ControlRequest, retry::RetryPolicy},
+    device::{Device, DeviceCapabilities, DeviceState, Event, EventData, StateEvent},
     error::DeviceError,
 };
 
-///
-/// Структура, описывающая взаимодействие с "умной" розеткой.
-///
-pub struct SmartSocket {
-    ///
-    /// Идентификатор "умной" розетки.
-    ///
-    id: Uuid,
-
-    ///
-    /// Имя "умной" розетки.
-    ///
-    name: String,
-
-    ///
-    /// Текущее состояние розетки.
-    ///
-    enabled: bool,
-
-    ///
-    /// Потребляемая мощность.
-    ///
-    power: f64,
-}
-
-impl fmt::Display for SmartSocket {
-    ///
-    /// Получить информацию об "умной" розетке с помощью форматирования.
-    ///
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut v = vec![format!(
-            "умная розетка \"{}\" ({}). Состояние: ",
-            self.name, self.id
-        )];
-
-        if self.enabled {
-            v.push(format!(
-                "включена, потребляемая мощность {} Вт.",
-                self.power
-            ));
-        } else {
-            v.push("выключена.".to_string());
-        }
-
-        write!(f, "{}", v.join(""))
-    }
-}
-
-impl Device for SmartSocket {
-    ///
-    /// Получить идентификатор "умной" розетки.
-    ///
-    fn id(&self) -> Uuid {
-        self.id
-    }
-
-    ///
-    /// Получить имя "умной" розетки.
-    ///
-    fn name(&self) -> &str {
-        self.name.as_str()
-    }
-
-    ///
-    /// Обработать событие устройством.
-    ///
-    fn notify(&mut self, e: &dyn Event) -> Result<DeviceState, DeviceError> {
-        match e.id() {
-            StateEvent::ID => Ok(DeviceState::for_socket(
-                self.id,
-                e.id(),
-                self.enabled,
-                self.power(),
-            )),
-
-            SwitchOnEvent::ID => {
-                self.switch_on();
-                Ok(DeviceState::for_socket(
-                    self.id,
-                    e.id(),
-                    self.enabled,
-                    self.power(),
-                ))
-            }
-
-            SwitchOffEvent::ID => {
-                self.switch_off();
-                Ok(DeviceState::for_socket(
-                    self.id,
-                    e.id(),
-                    self.enabled,
-                    self.power(),
-                ))
-            }
-
-            id => Err(DeviceError::NotImplementedEvent(id)),
-        }
-    }
-}
-
-impl SmartSocket {
-    ///
-    /// Создать "умную" розетку в выключенном состоянии.
-    ///
-    #[inline]
-    pub fn new(name: &str) -> Self {
-        SmartSocket {
-            id: Uuid::new_v4(),
-            name: name.to_string(),
-            enabled: false,
-            power: 0.0,
-        }
-    }
-
-    ///
-    /// Включить "умную" розетку.
-    ///
-    #[inline]
-    pub fn switch_on(&mut self) {
-        self.enabled = true;
-    }
-
-    ///
-    /// Выключить "умную" розетку.
-    ///
-    #[inline]
-    pub fn switch_off(&mut self) {
-        self.enabled = false;
-    }
-
-    ///
-    /// Проверить, включена ли "умная" розетка.
-    ///
-    #[inline]
-    pub fn enabled(&self) -> bool {
-        self.enabled
-    }
-
-    ///
-    /// Получить текущее значение потребляемой мощности.
-    ///
-    pub fn power(&self) -> Option<f64> {
-        if self.enabled {
-            Some(self.power)
-        } else {
-            None
-        }
-    }
-
-    ///
-    /// Подключить нагрузку с заданной мощностью.
-    ///
-    pub fn plug(&mut self, power: f64) {
-        self.power = power;
-    }
-}
-
 ///
 /// Структура, описывающая взаимодействие с удаленной "умной" розеткой
 /// по протоколу TCP.
@@ -180,10 +27,15 @@ pub struct RemoteSmartSocket {
     ///
     name: String,
 
+    ///
+    /// Возможности "умной" розетки, полученные при подключении.
+    ///
+    capabilities: DeviceCapabilities,
+
     ///
     /// Клиент для взаимодействия с удаленной умной розеткой.
     ///
-    client: RefCell<ControlClient>,
+    client: Mutex<ControlClient>,
 }
 
 impl fmt::Display for RemoteSmartSocket {
@@ -193,7 +45,8 @@ impl fmt::Display for RemoteSmartSocket {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if let Ok(response) = self
             .client
-            .borrow_mut()
+            .lock()
+            .unwrap()
             .request(ControlRequest::acquire_remote_device_state())
         {
             if let Some(state) = response.state() {
@@ -237,39 +90,77 @@ impl Device for RemoteSmartSocket {
         self.name.as_str()
     }
 
+    ///
+    /// Получить описание возможностей удаленной "умной" розетки.
+    ///
+    fn capabilities(&self) -> DeviceCapabilities {
+        self.capabilities.clone()
+    }
+
     ///
     /// Обработать событие устройством.
     ///
     fn notify(&mut self, e: &dyn Event) -> Result<DeviceState, DeviceError> {
-        match e.id() {
-            StateEvent::ID => self.state(),
-            SwitchOnEvent::ID => self.switch_on(),
-            SwitchOffEvent::ID => self.switch_off(),
-            id => Err(DeviceError::NotImplementedEvent(id)),
+        if e.is::<StateEvent>() {
+            self.state()
+        } else if e.is::<SwitchOnEvent>() {
+            self.switch_on()
+        } else if e.is::<SwitchOffEvent>() {
+            self.switch_off()
+        } else if let Some(data) = e.data() {
+            self.send(data)
+        } else {
+            Err(DeviceError::NotImplementedEvent(e.id()))
         }
     }
 }
 
 impl RemoteSmartSocket {
     ///
-    /// Подключиться к серверу с заданным адресом.
+    /// Подключиться к серверу с заданным адресом и общим с сервером ключом
+    /// для аутентификации.
     ///
-    pub fn connect<A>(addrs: A) -> Result<Self, DeviceError>
+    pub fn connect<A, K>(addrs: A, key: K) -> Result<Self, DeviceError>
     where
         A: ToSocketAddrs,
+        K: AsRef<[u8]>,
     {
-        let mut client = ControlClient::connect(addrs)?;
+        Self::with_client(ControlClient::connect(addrs, key)?)
+    }
+
+    ///
+    /// Подключиться к серверу с заданным адресом в режиме восстановления
+    /// соединения с заданной политикой повторных попыток и общим с сервером
+    /// ключом для аутентификации.
+    ///
+    pub fn reconnecting<A, K>(addrs: A, key: K, policy: RetryPolicy) -> Result<Self, DeviceError>
+    where
+        A: ToSocketAddrs,
+        K: AsRef<[u8]>,
+    {
+        Self::with_client(ControlClient::reconnecting(addrs, key, policy)?)
+    }
 
+    ///
+    /// Создать удаленную "умную" розетку на основе подключенного
+    /// клиента подсистемы управления.
+    ///
+    pub fn with_client(mut client: ControlClient) -> Result<Self, DeviceError> {
         let response = client.request(ControlRequest::acquire_remote_device_name())?;
-        if let Some((id, name)) = response.name() {
-            Ok(Self {
-                id,
-                name: name.to_owned(),
-                client: RefCell::new(client),
-            })
-        } else {
-            Err(DeviceError::UnexpectedMessage)
-        }
+        let (id, name) = response.name().ok_or(DeviceError::UnexpectedMessage)?;
+
+        let capabilities = client
+            .request(ControlRequest::acquire_remote_device_capabilities())?
+            .capabilities()
+            .cloned()
+            .ok_or(DeviceError::UnexpectedMessage)?;
+
+        Ok(Self {
+            id,
+            name: name.to_owned(),
+            capabilities,
+            client: Mutex::new(client),
+        })
     }
 
     ///
@@ -279,6 +170,7 @@ impl RemoteSmartSocket {
         let response = self
             .client
             .get_mut()
+            .unwrap()
             .request(ControlRequest::switch_on_remote_device())?;
 
         if let Some(state) = response.state() {
@@ -297,6 +189,7 @@ impl RemoteSmartSocket {
         let response = self
             .client
             .get_mut()
+            .unwrap()
             .request(ControlRequest::switch_off_remote_device())?;
 
         if let Some(state) = response.state() {
@@ -308,6 +201,13 @@ impl RemoteSmartSocket {
         Err(DeviceError::UnexpectedMessage)
     }
 
+    ///
+    /// Подключить к удаленной "умной" розетке нагрузку с заданной мощностью.
+    ///
+    pub fn plug(&mut self, power: f64) -> Result<DeviceState, DeviceError> {
+        self.send(EventData::SetLoad { watts: power })
+    }
+
     ///
     /// Получить состояние удаленной "умной" розетки.
     ///
@@ -315,6 +215,7 @@ impl RemoteSmartSocket {
         let response = self
             .client
             .get_mut()
+            .unwrap()
             .request(ControlRequest::acquire_remote_device_state())?;
 
         if let Some(state) = response.state() {
@@ -325,100 +226,21 @@ impl RemoteSmartSocket {
 
         Err(DeviceError::UnexpectedMessage)
     }
-}
-
-///
-/// Событие, для включения "умной" розетки.
-///
-pub struct SwitchOnEvent {}
-
-impl Event for SwitchOnEvent {
-    ///
-    /// Получить идентификатор класса события.
-    ///
-    fn id(&self) -> Uuid {
-        Self::ID
-    }
-}
-
-impl Default for SwitchOnEvent {
-    ///
-    /// Экземпляр события по умолчанию.
-    ///
-    #[inline]
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl SwitchOnEvent {
-    // Идентификатор класса события.
-    pub(crate) const ID: Uuid = uuid::uuid!("56848c21-6600-48d9-a50a-9a0f83486408");
-
-    ///
-    /// Создать событие, для для включения "умной" розетки.
-    ///
-    #[inline]
-    pub fn new() -> Self {
-        Self {}
-    }
-}
-
-///
-/// Событие, для выключения "умной" розетки.
-///
-pub struct SwitchOffEvent {}
-
-impl Event for SwitchOffEvent {
-    ///
-    /// Получить идентификатор класса события.
-    ///
-    fn id(&self) -> Uuid {
-        Self::ID
-    }
-}
-
-impl Default for SwitchOffEvent {
-    ///
-    /// Экземпляр события по умолчанию.
-    ///
-    #[inline]
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl SwitchOffEvent {
-    // Идентификатор класса события.
-    pub(crate) const ID: Uuid = uuid::uuid!("4ca18a36-38e0-410a-9c71-ccf4f109ebd4");
-
-    ///
-    /// Создать событие, для для включения "умной" розетки.
-    ///
-    #[inline]
-    pub fn new() -> Self {
-        Self {}
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    #[test]
-    fn smart_socket_test() {
-        let mut socket1 = SmartSocket::new("Socket1");
-        assert_eq!(socket1.name.as_str(), "Socket1");
-        assert!(!socket1.enabled);
-        assert_eq!(socket1.power, 0.0);
-
-        socket1.switch_on();
-        assert!(socket1.enabled);
+    // Передать событие удаленной "умной" розетке для обработки.
+    fn send(&mut self, event: EventData) -> Result<DeviceState, DeviceError> {
+        let response = self
+            .client
+            .get_mut()
+            .unwrap()
+            .request(ControlRequest::notify_remote_device(event))?;
 
-        socket1.plug(1000.0);
-        assert_eq!(socket1.power, 1000.0);
+        if let Some(state) = response.state() {
+            if state.device_id() == self.id {
+                return Ok(state);
+            }
+        }
 
-        socket1.switch_off();
-        assert!(!socket1.enabled);
+        Err(DeviceError::UnexpectedMessage)
     }
 }
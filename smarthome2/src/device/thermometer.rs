@@ -16,97 +16,17 @@ use rand::{thread_rng, Rng};
 use statrs::distribution::Normal;
 use uuid::Uuid;
 
+pub use smarthome2_core::device::thermometer::{SetTemperatureEvent, SmartThermometer};
+
 use crate::{
-    control::message::ThermometerMessage,
-    device::{Device, DeviceState, Event, StateEvent},
+    control::message::SensorMessage,
+    device::{
+        Device, DeviceCapabilities, DeviceKind, DeviceState, Event, Quantity, StateEvent,
+        StateListener,
+    },
     error::DeviceError,
 };
 
-///
-/// Структура, описывающая взаимодействие с "умным" термометром.
-///
-#[derive(Debug)]
-pub struct SmartThermometer {
-    ///
-    /// Идентификатор "умного" термометра.
-    ///
-    id: Uuid,
-
-    ///
-    /// Имя "умного" термометра.
-    ///
-    name: String,
-
-    ///
-    /// Текущее значение температуры.
-    ///
-    temperature: f64,
-}
-
-impl fmt::Display for SmartThermometer {
-    ///
-    /// Получить информацию об "умном" термометре с помощью форматирования.
-    ///
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "умный термометр \"{}\" ({}). Температура: {} °C.",
-            self.name, self.id, self.temperature
-        )
-    }
-}
-
-impl Device for SmartThermometer {
-    ///
-    /// Получить идентификатор "умного" термометра.
-    ///
-    fn id(&self) -> Uuid {
-        self.id
-    }
-
-    ///
-    /// Получить имя "умного" термометра.
-    ///
-    fn name(&self) -> &str {
-        self.name.as_str()
-    }
-
-    ///
-    /// Обработать событие устройством.
-    ///
-    fn notify(&mut self, e: &dyn Event) -> Result<DeviceState, DeviceError> {
-        if e.id() == StateEvent::ID {
-            Ok(DeviceState::for_thermometer(
-                self.id(),
-                e.id(),
-                self.temperature(),
-            ))
-        } else {
-            Err(DeviceError::NotImplementedEvent(e.id()))
-        }
-    }
-}
-
-impl SmartThermometer {
-    ///
-    /// Создать термометр с заданным значением температуры.
-    ///
-    pub fn new(name: &str, temperature: f64) -> Self {
-        Self {
-            id: Uuid::new_v4(),
-            name: name.to_string(),
-            temperature,
-        }
-    }
-
-    ///
-    /// Получить текущее значение температуры.
-    ///
-    pub fn temperature(&self) -> f64 {
-        self.temperature
-    }
-}
-
 ///
 /// Структура, описывающая взаимодействие с автономным "умным" термометром.
 ///
@@ -173,7 +93,8 @@ impl AutonomousThermometer {
                         temperature += rng.sample(normal);
                     }
 
-                    let message = ThermometerMessage::new(id, temperature);
+                    let message = SensorMessage::new(id)
+                        .with_measurement((Quantity::Temperature, temperature));
                     let bytes = bincode::options().with_big_endian().serialize(&message)?;
 
                     log::info!(
@@ -309,6 +230,11 @@ pub struct RemoteThermometer {
     ///
     data: Arc<RwLock<(Uuid, f64)>>,
 
+    ///
+    /// Обработчик получения новых показаний температуры.
+    ///
+    listener: Arc<RwLock<Option<StateListener>>>,
+
     ///
     /// Флаг для завершения связанного с удаленным "умным" термометром потока.
     ///
@@ -363,11 +289,19 @@ impl Device for RemoteThermometer {
         self.name.as_str()
     }
 
+    ///
+    /// Получить описание возможностей удаленного "умного" термометра.
+    /// Удаленный термометр только сообщает показания температуры.
+    ///
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::new(DeviceKind::Thermometer).with_measurement(Quantity::Temperature)
+    }
+
     ///
     /// Обработать событие устройством.
     ///
     fn notify(&mut self, e: &dyn Event) -> Result<DeviceState, DeviceError> {
-        if e.id() == StateEvent::ID {
+        if e.is::<StateEvent>() {
             let (id, temperature) = {
                 let guard = self.data.read().unwrap();
                 *guard
@@ -378,6 +312,14 @@ impl Device for RemoteThermometer {
             Err(DeviceError::NotImplementedEvent(e.id()))
         }
     }
+
+    ///
+    /// Установить обработчик получения новых показаний температуры.
+    ///
+    fn watch(&mut self, listener: StateListener) {
+        let mut guard = self.listener.write().unwrap();
+        *guard = Some(listener);
+    }
 }
 
 impl RemoteThermometer {
@@ -482,6 +424,9 @@ impl<BA: ToSocketAddrs + Send, RA: ToSocketAddrs + Send> RemoteThermometerBuilde
         let data = Arc::new(RwLock::new((Uuid::nil(), 0.0)));
         let cloned = data.clone();
 
+        let listener: Arc<RwLock<Option<StateListener>>> = Arc::new(RwLock::new(None));
+        let cloned_listener = listener.clone();
+
         thread::spawn(move || -> Result<(), DeviceError> {
             let socket = UdpSocket::bind(addr)?;
             socket.connect(remote_addr)?;
@@ -489,12 +434,25 @@ impl<BA: ToSocketAddrs + Send, RA: ToSocketAddrs + Send> RemoteThermometerBuilde
             let mut buf = [0u8; 512];
             while (*working).load(Ordering::Relaxed) {
                 if let Ok(received) = socket.recv(&mut buf) {
-                    if let Ok(message) = bincode::options()
+                    let message = bincode::options()
                         .with_big_endian()
-                        .deserialize::<ThermometerMessage>(&buf[..received])
+                        .deserialize::<SensorMessage>(&buf[..received]);
+                    if let Some((id, temperature)) = message
+                        .ok()
+                        .and_then(|m| Some((m.id(), m.value(Quantity::Temperature)?.as_f64()?)))
                     {
-                        let mut guard = cloned.write().unwrap();
-                        *guard = (message.id(), message.temperature());
+                        {
+                            let mut guard = cloned.write().unwrap();
+                            *guard = (id, temperature);
+                        }
+
+                        if let Some(ref listener) = *cloned_listener.read().unwrap() {
+                            listener.call(DeviceState::for_thermometer(
+                                id,
+                                StateEvent::ID,
+                                temperature,
+                            ));
+                        }
                     } else {
                         log::error!("Message deserialization error");
                     }
@@ -509,6 +467,7 @@ impl<BA: ToSocketAddrs + Send, RA: ToSocketAddrs + Send> RemoteThermometerBuilde
         RemoteThermometer {
             name: self.name,
             data,
+            listener,
             control,
         }
     }
@@ -544,13 +503,6 @@ impl RemoteThermometerBuilder<&str, &str> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn smart_thermometer_test() {
-        let thermometer1 = SmartThermometer::new("Thermometer1", 20.0);
-        assert_eq!(thermometer1.name.as_str(), "Thermometer1");
-        assert_eq!(thermometer1.temperature, 20.0);
-    }
-
     #[test]
     fn autonomous_thermometer_builder_test() {
         let builder = AutonomousThermometer::builder()
@@ -4,6 +4,14 @@ use bincode;
 use thiserror::Error;
 use uuid::Uuid;
 
+use smarthome2_core::error::ModelError;
+pub use smarthome2_protocol::error::{RecvError, SelectorError, SendError};
+use smarthome2_protocol::{
+    handshake::Capabilities,
+    message::{ErrorCode, RemoteError},
+    ProtocolVersion,
+};
+
 ///
 /// Ошибка при работе с устройствами.
 ///
@@ -21,17 +29,35 @@ pub enum DeviceError {
     #[error("illegal device identifier {0}")]
     IllegalDeviceId(Uuid),
 
+    #[error("illegal device path \"{0}\"")]
+    IllegalDevicePath(String),
+
     #[error("the event {0} is not implemented")]
     NotImplementedEvent(Uuid),
 
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+
     #[error("unexpected message")]
     UnexpectedMessage,
 
+    #[error("unsupported protocol version {0}")]
+    UnsupportedVersion(ProtocolVersion),
+
+    #[error("push notifications are not negotiated for the connection")]
+    NotificationsDisabled,
+
+    #[error("the server is busy, try again later")]
+    ServerBusy,
+
+    #[error("internal server error: {0}")]
+    Internal(String),
+
     #[error(transparent)]
     ConnectionError(#[from] ConnectionError),
 
     #[error(transparent)]
-    RequestError(#[from] RequestError),
+    RequestError(RequestError),
 
     #[error("IO error: {0}")]
     IoError(#[from] io::Error),
@@ -40,31 +66,95 @@ pub enum DeviceError {
     Bin(#[from] bincode::Error),
 }
 
-///
-/// Ошибка отпраки данных.
-///
-#[derive(Debug, Error)]
-pub enum SendError {
-    #[error("IO error: {0}")]
-    Io(#[from] io::Error),
+impl From<ModelError> for DeviceError {
+    ///
+    /// Преобразовать ошибку модели "умного" дома.
+    ///
+    fn from(error: ModelError) -> Self {
+        match error {
+            ModelError::IllegalRoomName(name) => Self::IllegalRoomName(name),
+            ModelError::IllegalDeviceName(name) => Self::IllegalDeviceName(name),
+            ModelError::IllegalRoomId(id) => Self::IllegalRoomId(id),
+            ModelError::IllegalDeviceId(id) => Self::IllegalDeviceId(id),
+            ModelError::IllegalDevicePath(path) => Self::IllegalDevicePath(path),
+            ModelError::NotImplementedEvent(id) => Self::NotImplementedEvent(id),
+            ModelError::InvalidArgument(message) => Self::InvalidArgument(message),
+            ModelError::UnexpectedMessage => Self::UnexpectedMessage,
+        }
+    }
+}
 
-    #[error("binary error: {0}")]
-    Bin(#[from] bincode::Error),
+impl From<RequestError> for DeviceError {
+    ///
+    /// Преобразовать ошибку запроса. Ошибка, о которой сообщил сервер,
+    /// передается без изменений.
+    ///
+    fn from(error: RequestError) -> Self {
+        match error {
+            RequestError::ServerError(error) => *error,
+            error => Self::RequestError(error),
+        }
+    }
 }
 
-///
-/// Ошибка получения данных.
-///
-#[derive(Debug, Error)]
-pub enum RecvError {
-    #[error("IO error: {0}")]
-    Io(#[from] io::Error),
+impl From<SelectorError> for DeviceError {
+    ///
+    /// Преобразовать ошибку разбора селектора устройства.
+    ///
+    fn from(error: SelectorError) -> Self {
+        match error {
+            SelectorError::IllegalDevicePath(path) => Self::IllegalDevicePath(path),
+        }
+    }
+}
 
-    #[error("binary error: {0}")]
-    Bin(#[from] bincode::Error),
+impl From<RemoteError> for DeviceError {
+    ///
+    /// Восстановить ошибку по коду, полученному от сервера.
+    ///
+    fn from(error: RemoteError) -> Self {
+        match error.code().clone() {
+            ErrorCode::IllegalRoomName(name) => Self::IllegalRoomName(name),
+            ErrorCode::IllegalDeviceName(name) => Self::IllegalDeviceName(name),
+            ErrorCode::IllegalRoomId(id) => Self::IllegalRoomId(id),
+            ErrorCode::IllegalDeviceId(id) => Self::IllegalDeviceId(id),
+            ErrorCode::IllegalDevicePath(path) => Self::IllegalDevicePath(path),
+            ErrorCode::NotImplementedEvent(id) => Self::NotImplementedEvent(id),
+            ErrorCode::InvalidArgument(message) => Self::InvalidArgument(message),
+            ErrorCode::UnexpectedMessage => Self::UnexpectedMessage,
+            ErrorCode::UnsupportedVersion(version) => Self::UnsupportedVersion(version),
+            ErrorCode::NotificationsDisabled => Self::NotificationsDisabled,
+            ErrorCode::ServerBusy => Self::ServerBusy,
+            ErrorCode::Internal => Self::Internal(error.message().to_owned()),
+        }
+    }
+}
 
-    #[error("bad message type {0}")]
-    BadType(u16),
+impl From<DeviceError> for RemoteError {
+    ///
+    /// Создать ошибку для передачи клиенту. Ошибки работы с устройствами
+    /// получают соответствующий код, остальные считаются внутренними.
+    ///
+    fn from(error: DeviceError) -> Self {
+        let code = match error {
+            DeviceError::IllegalRoomName(ref name) => ErrorCode::IllegalRoomName(name.clone()),
+            DeviceError::IllegalDeviceName(ref name) => ErrorCode::IllegalDeviceName(name.clone()),
+            DeviceError::IllegalRoomId(id) => ErrorCode::IllegalRoomId(id),
+            DeviceError::IllegalDeviceId(id) => ErrorCode::IllegalDeviceId(id),
+            DeviceError::IllegalDevicePath(ref path) => ErrorCode::IllegalDevicePath(path.clone()),
+            DeviceError::NotImplementedEvent(id) => ErrorCode::NotImplementedEvent(id),
+            DeviceError::InvalidArgument(ref message) => {
+                ErrorCode::InvalidArgument(message.clone())
+            }
+            DeviceError::UnexpectedMessage => ErrorCode::UnexpectedMessage,
+            DeviceError::UnsupportedVersion(version) => ErrorCode::UnsupportedVersion(version),
+            DeviceError::NotificationsDisabled => ErrorCode::NotificationsDisabled,
+            DeviceError::ServerBusy => ErrorCode::ServerBusy,
+            _ => ErrorCode::Internal,
+        };
+
+        RemoteError::new(code, error.to_string())
+    }
 }
 
 ///
@@ -75,8 +165,46 @@ pub enum ConnectionError {
     #[error("unexpected handshake response")]
     BadHandshake,
 
+    #[error("authentication failed")]
+    AuthenticationFailed,
+
+    #[error("no common protocol version, offered versions {0:?}")]
+    UnsupportedVersion(Vec<u16>),
+
+    #[error("incompatible protocol capabilities {0}")]
+    IncompatibleCapabilities(Capabilities),
+
+    #[error("too many connections")]
+    TooManyConnections,
+
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
+
+    #[error("handshake sending error: {0}")]
+    Send(#[from] SendError),
+
+    #[error("handshake receiving error: {0}")]
+    Recv(#[from] RecvError),
+
+    #[cfg(feature = "tls")]
+    #[error(transparent)]
+    Tls(#[from] TlsError),
+}
+
+impl ConnectionError {
+    ///
+    /// Проверить, может ли повторная попытка подключения оказаться
+    /// успешной. Ошибки аутентификации и согласования протокола
+    /// повторением не устраняются.
+    ///
+    pub fn is_transient(&self) -> bool {
+        !matches!(
+            self,
+            Self::AuthenticationFailed
+                | Self::UnsupportedVersion(_)
+                | Self::IncompatibleCapabilities(_)
+        )
+    }
 }
 
 ///
@@ -86,6 +214,32 @@ pub enum ConnectionError {
 pub enum BindError {
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
+
+    #[cfg(feature = "tls")]
+    #[error(transparent)]
+    Tls(#[from] TlsError),
+}
+
+///
+/// Ошибка настройки защищенного соединения.
+///
+#[cfg(feature = "tls")]
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("TLS error: {0}")]
+    Rustls(#[from] rustls::Error),
+
+    #[error("invalid server name \"{0}\"")]
+    InvalidServerName(String),
+
+    #[error("no certificates found")]
+    NoCertificates,
+
+    #[error("no private key found")]
+    NoPrivateKey,
 }
 
 ///
@@ -99,6 +253,15 @@ pub enum RequestError {
     #[error(transparent)]
     Recv(#[from] RecvError),
 
-    #[error("server side error {0}")]
-    ServerError(String),
+    #[error("server side error: {0}")]
+    ServerError(Box<DeviceError>),
+
+    #[error("unknown request identifier {0}")]
+    UnknownRequest(u64),
+
+    #[error("connection lost before the response to request {0} was received")]
+    Interrupted(u64),
+
+    #[error(transparent)]
+    Connection(#[from] ConnectionError),
 }
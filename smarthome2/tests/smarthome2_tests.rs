@@ -1,19 +1,57 @@
 #![allow(dead_code)]
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
 
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use smarthome2::{
+    control::{
+        client::ControlClient,
+        message::{ControlRequest, DeviceSpec, SensorMessage, TextMessage},
+        protocol::{
+            client::Client,
+            codec::Codec,
+            consts::USER_MESSAGE_ID_MIN,
+            envelope::Router,
+            handshake::Capabilities,
+            pipe::PipeListener,
+            server::{Connection, Server},
+            transport::Address,
+            Message, ProtocolVersion,
+        },
+        retry::{ConnectionStatus, RetryPolicy, StatusListener},
+        server::{
+            ControlServer, ShutdownHandle, SmartDeviceServer, SmartLampServer, SmartSocketServer,
+        },
+    },
     device::{
-        socket::{SmartSocket, SwitchOffEvent, SwitchOnEvent},
+        lamp::{RemoteSmartLamp, SetBrightnessEvent, SetColorTemperatureEvent, SmartLamp},
+        sensor::{AutonomousSensor, RemoteSensor, SmartSensor},
+        socket::{RemoteSmartSocket, SetLoadEvent, SmartSocket, SwitchOffEvent, SwitchOnEvent},
         thermometer::SmartThermometer,
-        Device, StateEvent,
+        Device, DeviceCapabilities, DeviceKind, DeviceState, Event, EventData, EventKind, Quantity,
+        SensorKind, StateEvent, StateListener, Unit, Value,
     },
-    house::{DeviceInfo, DeviceNotifier, RoomGetter, SmartHouse},
+    error::{ConnectionError, DeviceError, RecvError, RequestError, SendError},
+    house::{DeviceInfo, DeviceNotifier, EventBroadcaster, RoomGetter, SmartHouse},
     room::SmartRoom,
 };
 
+// Общий ключ клиентов и серверов для аутентификации.
+const KEY: &[u8] = b"smarthome2 test key";
+
 #[test]
 fn smart_home_test() {
     let socket1 = SmartSocket::new("Socket1");
@@ -192,3 +230,1580 @@ fn smart_home_test() {
     assert_eq!(themperature_info[&thermometer2_id], 25.0);
     assert_eq!(themperature_info[&thermometer3_id], 30.0);
 }
+
+#[test]
+fn handshake_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let handle = thread::spawn(move || {
+        let mut connection = server.incoming().next().unwrap().unwrap();
+        assert_eq!(connection.version(), ProtocolVersion::CURRENT);
+        assert!(connection.capabilities().contains(Capabilities::BINCODE));
+
+        let request = connection.recv::<TextMessage>().unwrap();
+        connection
+            .send(TextMessage::new(request.to_string()))
+            .unwrap();
+    });
+
+    let mut client = Client::connect(addr, KEY).unwrap();
+    assert_eq!(client.version(), ProtocolVersion::CURRENT);
+    assert_eq!(
+        client.capabilities(),
+        Capabilities::supported().without(Capabilities::CODECS) | Capabilities::BINCODE
+    );
+    assert_eq!(client.codec(), Codec::Bincode);
+
+    let response: Box<TextMessage> = client.request(TextMessage::new("ping")).unwrap();
+    assert_eq!(response.to_string(), "ping");
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn authentication_test() {
+    let server = Server::builder("server secret")
+        .bind("127.0.0.1:0")
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let handle = thread::spawn(move || {
+        let mut incoming = server.incoming();
+        assert!(incoming.next().unwrap().is_ok());
+        assert!(incoming.next().unwrap().is_err());
+    });
+
+    assert!(Client::builder("server secret").connect(addr).is_ok());
+    assert!(matches!(
+        Client::builder("wrong secret").connect(addr),
+        Err(ConnectionError::AuthenticationFailed)
+    ));
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn pipelining_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let socket = SmartSocket::new("Socket1");
+    let socket_id = socket.id();
+    let server = SmartSocketServer::with_server(server, socket);
+    thread::spawn(move || server.run());
+
+    let mut client = ControlClient::connect(addr, KEY).unwrap();
+    let name_id = client
+        .submit(ControlRequest::acquire_remote_device_name())
+        .unwrap();
+    let switch_id = client
+        .submit(ControlRequest::switch_on_remote_device())
+        .unwrap();
+    let state_id = client
+        .submit(ControlRequest::acquire_remote_device_state())
+        .unwrap();
+    assert_eq!(client.in_flight(), 3);
+
+    let response = client.wait(state_id).unwrap();
+    assert_eq!(response.id(), state_id);
+    assert!(response.state().unwrap().enabled().unwrap());
+
+    let response = client.wait(name_id).unwrap();
+    assert_eq!(response.id(), name_id);
+    assert_eq!(response.name(), Some((socket_id, "Socket1")));
+
+    let response = client.wait(switch_id).unwrap();
+    assert_eq!(response.id(), switch_id);
+    assert_eq!(client.in_flight(), 0);
+
+    assert!(matches!(
+        client.wait(switch_id),
+        Err(RequestError::UnknownRequest(_))
+    ));
+}
+
+#[test]
+fn subscription_test() {
+    let socket = SmartSocket::new("Socket1");
+    let socket_id = socket.id();
+    let mut room = SmartRoom::new("Room1");
+    let room_id = room.id();
+    room += socket;
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, house);
+    thread::spawn(move || server.run());
+
+    // Ожидание уведомлений дольше времени ожидания данных не прерывает
+    // итератор ошибкой.
+    let mut subscriber = ControlClient::from(
+        Client::builder(KEY)
+            .with_read_timeout(Some(Duration::from_millis(200)))
+            .connect(addr)
+            .unwrap(),
+    );
+    assert!(matches!(
+        subscriber.request(ControlRequest::subscribe((room_id, Uuid::new_v4()))),
+        Err(RequestError::ServerError(_))
+    ));
+    subscriber
+        .request(ControlRequest::subscribe((room_id, socket_id)))
+        .unwrap();
+
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(600));
+        let mut client = ControlClient::connect(addr, KEY).unwrap();
+        client
+            .request(ControlRequest::switch_on_device((room_id, socket_id)))
+            .unwrap();
+        client
+            .request(ControlRequest::switch_off_device((room_id, socket_id)))
+            .unwrap();
+    });
+
+    let states: Vec<_> = subscriber
+        .notifications()
+        .take(2)
+        .map(|n| n.unwrap())
+        .collect();
+    assert!(states.iter().all(|(id, _)| *id == room_id));
+    assert!(states
+        .iter()
+        .all(|(_, state)| state.device_id() == socket_id));
+    assert!(states[0].1.enabled().unwrap());
+    assert!(!states[1].1.enabled().unwrap());
+}
+
+#[test]
+fn shutdown_test() {
+    let mut room = SmartRoom::new("Room1");
+    room += SmartSocket::new("Socket1");
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, house);
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || {
+        server.run();
+        server
+    });
+
+    let mut client = ControlClient::connect(addr, KEY).unwrap();
+    client.request(ControlRequest::acquire_rooms()).unwrap();
+
+    shutdown.shutdown();
+    assert!(shutdown.is_shutdown());
+    let server = handle.join().unwrap();
+    let house = server.into_house().ok().unwrap();
+    assert_eq!(house.name(), "House1");
+
+    assert!(client.request(ControlRequest::acquire_rooms()).is_err());
+}
+
+#[test]
+fn worker_pool_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let server = SmartSocketServer::with_server(server, SmartSocket::new("Socket1"))
+        .with_workers(1)
+        .with_queue_capacity(1);
+    let server = Arc::new(server);
+    thread::spawn({
+        let server = server.clone();
+        move || server.run()
+    });
+
+    let mut client1 = ControlClient::connect(addr, KEY).unwrap();
+    client1
+        .request(ControlRequest::switch_on_remote_device())
+        .unwrap();
+
+    let mut client2 = ControlClient::connect(addr, KEY).unwrap();
+    let mut client3 = ControlClient::connect(addr, KEY).unwrap();
+    assert!(matches!(
+        client3.request(ControlRequest::acquire_remote_device_state()),
+        Err(RequestError::ServerError(_))
+    ));
+
+    let stats = server.stats();
+    assert_eq!(stats.workers().len(), 1);
+    assert_eq!(stats.active(), 2);
+    assert_eq!(stats.rejected(), 1);
+
+    // Единственный рабочий поток обслуживает оба соединения по очереди.
+    let response = client2
+        .request(ControlRequest::acquire_remote_device_state())
+        .unwrap();
+    assert!(response.state().unwrap().enabled().unwrap());
+
+    let stats = server.stats();
+    assert_eq!(stats.workers()[0].connections(), 2);
+    assert_eq!(stats.workers()[0].requests(), 2);
+
+    drop(client1);
+    let mut client4 = ControlClient::connect(addr, KEY).unwrap();
+    assert!(client4
+        .request(ControlRequest::acquire_remote_device_state())
+        .is_ok());
+}
+
+#[test]
+fn long_lived_clients_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let server = SmartSocketServer::with_server(server, SmartSocket::new("Socket1"))
+        .with_workers(2)
+        .with_queue_capacity(8);
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    // Клиентов больше, чем рабочих потоков, и ни один из них не
+    // отключается, пока остальные выполняют запросы.
+    let mut clients: Vec<_> = (0..6)
+        .map(|_| {
+            Client::builder(KEY)
+                .with_read_timeout(Some(Duration::from_secs(2)))
+                .connect(addr)
+                .map(ControlClient::from)
+                .unwrap()
+        })
+        .collect();
+    for _ in 0..3 {
+        for client in clients.iter_mut() {
+            assert!(client
+                .request(ControlRequest::acquire_remote_device_state())
+                .is_ok());
+        }
+    }
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+}
+
+#[test]
+fn silent_client_test() {
+    let server = Server::builder(KEY)
+        .with_handshake_timeout(Some(Duration::from_millis(300)))
+        .bind("127.0.0.1:0")
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let server = SmartSocketServer::with_server(server, SmartSocket::new("Socket1"));
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    // Клиент, не начинающий подтверждение связи, не задерживает прием
+    // других соединений.
+    let mut silent = TcpStream::connect(addr).unwrap();
+    let started = Instant::now();
+    let mut client = Client::builder(KEY)
+        .with_read_timeout(Some(Duration::from_secs(1)))
+        .connect(addr)
+        .map(ControlClient::from)
+        .unwrap();
+    assert!(client
+        .request(ControlRequest::acquire_remote_device_state())
+        .is_ok());
+    assert!(started.elapsed() < Duration::from_millis(300));
+
+    // По истечении времени на подтверждение связи соединение закрывается.
+    silent
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    assert_eq!(silent.read(&mut [0u8; 1]).unwrap(), 0);
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+}
+
+#[test]
+fn worker_panic_test() {
+    // Устройство, обработка включения которого завершается паникой.
+    struct FaultyDevice(SmartSocket);
+
+    impl fmt::Display for FaultyDevice {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
+    impl Device for FaultyDevice {
+        fn id(&self) -> Uuid {
+            self.0.id()
+        }
+
+        fn name(&self) -> &str {
+            self.0.name()
+        }
+
+        fn capabilities(&self) -> DeviceCapabilities {
+            self.0.capabilities()
+        }
+
+        fn notify(&mut self, e: &dyn Event) -> Result<DeviceState, DeviceError> {
+            if e.is::<SwitchOnEvent>() {
+                panic!("faulty device");
+            }
+            self.0.notify(e)
+        }
+    }
+
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let server = SmartDeviceServer::with_server(server, FaultyDevice(SmartSocket::new("Socket1")))
+        .with_workers(1);
+    let server = Arc::new(server);
+    thread::spawn({
+        let server = server.clone();
+        move || server.run()
+    });
+
+    let mut client = ControlClient::connect(addr, KEY).unwrap();
+    assert!(client
+        .request(ControlRequest::switch_on_remote_device())
+        .is_err());
+
+    // Рабочий поток переживает панику и обслуживает новые соединения.
+    let mut client = ControlClient::connect(addr, KEY).unwrap();
+    let response = client
+        .request(ControlRequest::acquire_remote_device_state())
+        .unwrap();
+    assert_eq!(response.state().unwrap().enabled(), Some(false));
+
+    let stats = server.stats();
+    assert_eq!(stats.workers()[0].panics(), 1);
+    assert_eq!(stats.workers()[0].connections(), 2);
+    assert_eq!(stats.active(), 1);
+}
+
+#[test]
+fn reconnect_test() {
+    fn start(addr: SocketAddr, socket: SmartSocket) -> (ShutdownHandle, JoinHandle<SmartSocket>) {
+        let server = SmartSocketServer::bind(addr, KEY, socket).unwrap();
+        let shutdown = server.shutdown_handle();
+        let handle = thread::spawn(move || {
+            server.run();
+            server.into_socket().ok().unwrap()
+        });
+
+        (shutdown, handle)
+    }
+
+    fn restart(
+        addr: SocketAddr,
+        (shutdown, handle): (ShutdownHandle, JoinHandle<SmartSocket>),
+    ) -> (ShutdownHandle, JoinHandle<SmartSocket>) {
+        shutdown.shutdown();
+        start(addr, handle.join().unwrap())
+    }
+
+    let addr = Server::bind("127.0.0.1:0", KEY)
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let server = start(addr, SmartSocket::new("Socket1"));
+
+    let statuses = Arc::new(Mutex::new(Vec::new()));
+    let policy = RetryPolicy::new().with_initial_backoff(Duration::from_millis(10));
+    let mut client = ControlClient::reconnecting(addr, KEY, policy)
+        .unwrap()
+        .with_status_listener(StatusListener::new({
+            let statuses = statuses.clone();
+            move |s| statuses.lock().unwrap().push(s)
+        }));
+    client
+        .request(ControlRequest::switch_on_remote_device())
+        .unwrap();
+
+    let server = restart(addr, server);
+    let response = client
+        .request(ControlRequest::acquire_remote_device_state())
+        .unwrap();
+    assert!(response.state().unwrap().enabled().unwrap());
+    assert_eq!(client.status(), ConnectionStatus::Connected);
+    assert_eq!(
+        *statuses.lock().unwrap(),
+        [
+            ConnectionStatus::Reconnecting(1),
+            ConnectionStatus::Connected
+        ]
+    );
+
+    let server = restart(addr, server);
+    let response = client
+        .request(ControlRequest::switch_off_remote_device())
+        .unwrap();
+    assert!(!response.state().unwrap().enabled().unwrap());
+
+    let (shutdown, handle) = server;
+    shutdown.shutdown();
+    handle.join().unwrap();
+    assert!(client
+        .request(ControlRequest::acquire_remote_device_state())
+        .is_err());
+    assert_eq!(client.status(), ConnectionStatus::Disconnected);
+}
+
+#[test]
+fn reconnect_attempts_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = SmartSocketServer::with_server(server, SmartSocket::new("Socket1"));
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let policy = RetryPolicy::new()
+        .with_max_attempts(3)
+        .with_initial_backoff(Duration::from_millis(10));
+    let mut client = ControlClient::reconnecting_with(
+        {
+            let attempts = attempts.clone();
+            move || {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Client::connect(addr, KEY)
+            }
+        },
+        policy,
+    )
+    .unwrap();
+    client
+        .request(ControlRequest::acquire_remote_device_state())
+        .unwrap();
+    assert_eq!(attempts.load(Ordering::Relaxed), 1);
+
+    // После остановки сервера порт закрыт, и восстановление соединения
+    // ограничено числом попыток политики.
+    shutdown.shutdown();
+    handle.join().unwrap();
+    assert!(client
+        .request(ControlRequest::acquire_remote_device_state())
+        .is_err());
+    assert_eq!(attempts.load(Ordering::Relaxed), 1 + 3);
+    assert_eq!(client.status(), ConnectionStatus::Disconnected);
+}
+
+#[test]
+fn interrupted_request_test() {
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+        let mut incoming = server.incoming();
+        let mut connection = incoming.next().unwrap().unwrap();
+        connection.recv::<ControlRequest>().unwrap();
+        drop(connection);
+
+        let mut connection = incoming.next().unwrap().unwrap();
+        assert!(connection.recv::<ControlRequest>().is_err());
+    });
+
+    let mut client = ControlClient::reconnecting(addr, KEY, RetryPolicy::new()).unwrap();
+    assert!(matches!(
+        client.request(ControlRequest::switch_on_remote_device()),
+        Err(RequestError::Interrupted(_))
+    ));
+    assert_eq!(client.status(), ConnectionStatus::Connected);
+    assert_eq!(client.in_flight(), 0);
+
+    drop(client);
+    handle.join().unwrap();
+}
+
+#[test]
+fn stalled_frame_test() {
+    // Промежуточный узел, который по сигналу пересылает клиенту только
+    // начало очередного ответа сервера и больше ничего не передает.
+    fn stalling_proxy(server: SocketAddr) -> (SocketAddr, Arc<AtomicBool>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stall = Arc::new(AtomicBool::new(false));
+        thread::spawn({
+            let stall = stall.clone();
+            move || {
+                for client in listener.incoming() {
+                    let mut client = client.unwrap();
+                    let mut upstream = TcpStream::connect(server).unwrap();
+                    let (mut reader, mut writer) =
+                        (client.try_clone().unwrap(), upstream.try_clone().unwrap());
+                    thread::spawn(move || {
+                        let _ = io::copy(&mut reader, &mut writer);
+                        let _ = writer.shutdown(Shutdown::Both);
+                    });
+                    let stall = stall.clone();
+                    thread::spawn(move || {
+                        let mut buf = [0u8; 1024];
+                        let mut stalled = false;
+                        while let Ok(n @ 1..) = upstream.read(&mut buf) {
+                            if !stalled && stall.swap(false, Ordering::Relaxed) {
+                                stalled = true;
+                                let _ = client.write_all(&buf[..n.min(3)]);
+                            } else if !stalled && client.write_all(&buf[..n]).is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+            }
+        });
+
+        (addr, stall)
+    }
+
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let (proxy, stall) = stalling_proxy(server.local_addr().unwrap());
+    let server = SmartSocketServer::with_server(server, SmartSocket::new("Socket1"));
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+    let connect = move || {
+        Client::builder(KEY)
+            .with_read_timeout(Some(Duration::from_millis(200)))
+            .connect(proxy)
+    };
+
+    // Без восстановления соединения оно закрывается, и следующие запросы
+    // завершаются ошибкой, а не получают остаток прерванного ответа.
+    let mut client = ControlClient::from(connect().unwrap());
+    client
+        .request(ControlRequest::switch_on_remote_device())
+        .unwrap();
+    stall.store(true, Ordering::Relaxed);
+    match client.request(ControlRequest::acquire_remote_device_state()) {
+        Err(RequestError::Recv(RecvError::Io(e))) => {
+            assert_eq!(e.kind(), io::ErrorKind::TimedOut)
+        }
+        r => panic!("unexpected result: {:?}", r.map(|_| ())),
+    }
+    assert!(client
+        .request(ControlRequest::acquire_remote_device_state())
+        .is_err());
+
+    // В режиме восстановления соединения запрос повторяется через новое
+    // соединение.
+    let policy = RetryPolicy::new().with_initial_backoff(Duration::from_millis(10));
+    let mut client = ControlClient::reconnecting_with(connect, policy).unwrap();
+    stall.store(true, Ordering::Relaxed);
+    let response = client
+        .request(ControlRequest::acquire_remote_device_state())
+        .unwrap();
+    assert!(response.state().unwrap().enabled().unwrap());
+    assert_eq!(client.status(), ConnectionStatus::Connected);
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+}
+
+#[test]
+fn pipe_transport_test() {
+    let socket = SmartSocket::new("Socket1");
+    let socket_id = socket.id();
+    let mut room = SmartRoom::new("Room1");
+    let room_id = room.id();
+    room += socket;
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let listener = PipeListener::new();
+    let connector = listener.connector();
+    let server = Server::builder(KEY).listen(listener).unwrap();
+    assert_eq!(server.address().unwrap(), Address::Pipe);
+    assert!(server.local_addr().is_err());
+
+    let server = ControlServer::with_server(server, house);
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    let mut client = ControlClient::from(
+        Client::builder(KEY)
+            .connect_with(connector.connect().unwrap())
+            .unwrap(),
+    );
+    client
+        .request(ControlRequest::switch_on_device((room_id, socket_id)))
+        .unwrap();
+    let response = client
+        .request(ControlRequest::acquire_device_state((room_id, socket_id)))
+        .unwrap();
+    assert!(response.state().unwrap().enabled().unwrap());
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_transport_test() {
+    use std::{env, fs};
+
+    let path = env::temp_dir().join(format!("smarthome2-{}.sock", Uuid::new_v4()));
+    let server = Server::builder(KEY).bind_unix(&path).unwrap();
+    assert_eq!(server.address().unwrap(), Address::Unix(Some(path.clone())));
+
+    let server = SmartSocketServer::with_server(server, SmartSocket::new("Socket1"));
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    let mut client = ControlClient::from(Client::builder(KEY).connect_unix(&path).unwrap());
+    let response = client
+        .request(ControlRequest::acquire_remote_device_name())
+        .unwrap();
+    assert_eq!(response.name().unwrap().1, "Socket1");
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn codec_test() {
+    let listener = PipeListener::new();
+    let connector = listener.connector();
+    let server = Server::builder(KEY).listen(listener).unwrap();
+
+    let socket = SmartSocket::new("Socket1");
+    let socket_id = socket.id();
+    let server = SmartSocketServer::with_server(server, socket);
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    for &codec in Codec::all() {
+        let client = Client::builder(KEY)
+            .with_codec(codec)
+            .connect_with(connector.connect().unwrap())
+            .unwrap();
+        assert_eq!(client.codec(), codec);
+        assert!(client
+            .capabilities()
+            .contains(Capabilities::PUSH_NOTIFICATIONS));
+
+        let mut client = ControlClient::from(client);
+        let response = client
+            .request(ControlRequest::acquire_remote_device_name())
+            .unwrap();
+        assert_eq!(response.name(), Some((socket_id, "Socket1")));
+    }
+
+    // Сервер, не поддерживающий запрошенный кодек, отклоняет соединение.
+    let listener = PipeListener::new();
+    let connector = listener.connector();
+    let server = Server::builder(KEY)
+        .with_capabilities(Capabilities::BINCODE)
+        .listen(listener)
+        .unwrap();
+    thread::spawn(move || server.incoming().next().unwrap().err().unwrap());
+    assert!(matches!(
+        Client::builder(KEY)
+            .with_codec(Codec::Json)
+            .connect_with(connector.connect().unwrap()),
+        Err(ConnectionError::IncompatibleCapabilities(_))
+    ));
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+}
+
+#[test]
+fn error_code_test() {
+    let mut room = SmartRoom::new("Room1");
+    let room_id = room.id();
+    room += SmartSocket::new("Socket1");
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let listener = PipeListener::new();
+    let connector = listener.connector();
+    let server = ControlServer::with_server(Server::builder(KEY).listen(listener).unwrap(), house);
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    let mut client = ControlClient::from(
+        Client::builder(KEY)
+            .with_codec(Codec::Json)
+            .connect_with(connector.connect().unwrap())
+            .unwrap(),
+    );
+
+    let (unknown_room, unknown_device) = (Uuid::new_v4(), Uuid::new_v4());
+    match client.request(ControlRequest::acquire_devices(unknown_room)) {
+        Err(RequestError::ServerError(e)) => {
+            assert!(matches!(*e, DeviceError::IllegalRoomId(id) if id == unknown_room))
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+    match client.request(ControlRequest::switch_on_device((room_id, unknown_device))) {
+        Err(RequestError::ServerError(e)) => {
+            assert!(matches!(*e, DeviceError::IllegalDeviceId(id) if id == unknown_device))
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+}
+
+#[test]
+fn device_selector_test() {
+    let socket = SmartSocket::new("Socket1");
+    let socket_id = socket.id();
+    let mut room = SmartRoom::new("Room1");
+    let room_id = room.id();
+    room += socket;
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let listener = PipeListener::new();
+    let connector = listener.connector();
+    let server = ControlServer::with_server(Server::builder(KEY).listen(listener).unwrap(), house);
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    let mut client = ControlClient::from(
+        Client::builder(KEY)
+            .connect_with(connector.connect().unwrap())
+            .unwrap(),
+    );
+
+    let response = client
+        .request(ControlRequest::acquire_devices("Room1"))
+        .unwrap();
+    assert_eq!(
+        response.list().unwrap(),
+        [(socket_id, "Socket1".to_owned())]
+    );
+
+    let response = client
+        .request(ControlRequest::switch_on_device(("Room1", "Socket1")))
+        .unwrap();
+    assert_eq!(response.state().unwrap().device_id(), socket_id);
+    let response = client
+        .request(ControlRequest::acquire_device_state("Room1/Socket1"))
+        .unwrap();
+    assert!(response.state().unwrap().enabled().unwrap());
+    let response = client
+        .request(ControlRequest::switch_off_device(format!(
+            "{}/Socket1",
+            room_id
+        )))
+        .unwrap();
+    assert!(!response.state().unwrap().enabled().unwrap());
+    client
+        .request(ControlRequest::acquire_device_info(format!(
+            "Room1/{}",
+            socket_id
+        )))
+        .unwrap();
+
+    let server_error = |r: Result<_, RequestError>| match r {
+        Err(RequestError::ServerError(e)) => *e,
+        r => panic!("unexpected result {:?}", r),
+    };
+    assert!(matches!(
+        server_error(client.request(ControlRequest::acquire_devices("Room2"))),
+        DeviceError::IllegalRoomName(name) if name == "Room2"
+    ));
+    assert!(matches!(
+        server_error(client.request(ControlRequest::acquire_device_state("Room1"))),
+        DeviceError::IllegalDevicePath(path) if path == "Room1"
+    ));
+    assert!(matches!(
+        server_error(client.request(ControlRequest::switch_on_device(("Room1", "Socket2")))),
+        DeviceError::IllegalDeviceName(name) if name == "Socket2"
+    ));
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+}
+
+#[test]
+fn batch_test() {
+    let mut room = SmartRoom::new("Room1");
+    let room_id = room.id();
+    room += SmartSocket::new("Socket1");
+    room += SmartSocket::new("Socket2");
+    room += SmartThermometer::new("Thermometer1", 20.0);
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let listener = PipeListener::new();
+    let connector = listener.connector();
+    let server = ControlServer::with_server(Server::builder(KEY).listen(listener).unwrap(), house);
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    let mut client = ControlClient::from(
+        Client::builder(KEY)
+            .connect_with(connector.connect().unwrap())
+            .unwrap(),
+    );
+
+    let request = ControlRequest::batch([
+        ControlRequest::switch_on_device("Room1/Socket1"),
+        ControlRequest::switch_on_device("Room1/Socket3"),
+        ControlRequest::switch_on_device("Room1/Socket2"),
+    ]);
+    assert!(!request.is_idempotent());
+    let response = client.request(request).unwrap();
+    let results = response.results().unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(results[0].state().unwrap().enabled().unwrap());
+    assert!(results[1].error().is_some());
+    assert!(results[2].state().unwrap().enabled().unwrap());
+
+    let request = ControlRequest::transaction([
+        ControlRequest::switch_off_device("Room1/Socket1"),
+        ControlRequest::switch_off_device("Room1/Socket2"),
+        ControlRequest::switch_off_device("Room1/Thermometer1"),
+        ControlRequest::switch_off_device("Room1/Socket1"),
+    ]);
+    let response = client.request(request).unwrap();
+    let results = response.results().unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(!results[1].state().unwrap().enabled().unwrap());
+    let error: DeviceError = results[2].error().unwrap().clone().into();
+    assert!(matches!(error, DeviceError::NotImplementedEvent(_)));
+
+    let request = ControlRequest::batch([
+        ControlRequest::acquire_device_state("Room1/Socket1"),
+        ControlRequest::acquire_device_state("Room1/Socket2"),
+    ]);
+    assert!(request.is_idempotent());
+    let response = client.request(request).unwrap();
+    assert!(response
+        .results()
+        .unwrap()
+        .iter()
+        .all(|r| r.state().unwrap().enabled().unwrap()));
+
+    match client.request(ControlRequest::transaction([ControlRequest::create_room(
+        "Room2",
+    )])) {
+        Err(RequestError::ServerError(e)) => {
+            assert!(matches!(*e, DeviceError::UnexpectedMessage))
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    // Транзакция не может добавлять устройства, так как откатить это
+    // изменение невозможно; ни один запрос транзакции не выполняется.
+    match client.request(ControlRequest::transaction([
+        ControlRequest::switch_off_device("Room1/Socket1"),
+        ControlRequest::attach_device(
+            room_id,
+            DeviceSpec::Socket {
+                name: "Socket3".to_owned(),
+            },
+        ),
+    ])) {
+        Err(RequestError::ServerError(e)) => {
+            assert!(matches!(*e, DeviceError::UnexpectedMessage))
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+    let response = client
+        .request(ControlRequest::acquire_device_state("Room1/Socket1"))
+        .unwrap();
+    assert!(response.state().unwrap().enabled().unwrap());
+
+    // Подключение к удаленному устройству из пакета не блокирует дом:
+    // запросы других клиентов выполняются, пока устройство не отвечает.
+    let silent = TcpListener::bind("127.0.0.1:0").unwrap();
+    let silent_addr = silent.local_addr().unwrap();
+    let batch = thread::spawn(move || {
+        client.request(ControlRequest::batch([
+            ControlRequest::attach_device(
+                room_id,
+                DeviceSpec::RemoteSocket {
+                    address: silent_addr.to_string(),
+                },
+            ),
+            ControlRequest::attach_device(
+                room_id,
+                DeviceSpec::Socket {
+                    name: "Socket3".to_owned(),
+                },
+            ),
+        ]))
+    });
+    thread::sleep(Duration::from_millis(200));
+
+    let mut client2 = ControlClient::from(
+        Client::builder(KEY)
+            .with_read_timeout(Some(Duration::from_secs(1)))
+            .connect_with(connector.connect().unwrap())
+            .unwrap(),
+    );
+    let response = client2
+        .request(ControlRequest::acquire_devices(room_id))
+        .unwrap();
+    assert_eq!(response.list().unwrap().len(), 3);
+
+    drop(silent);
+    let response = batch.join().unwrap().unwrap();
+    let results = response.results().unwrap();
+    assert!(results[0].error().is_some());
+    assert_eq!(results[1].name().unwrap().1, "Socket3");
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+}
+
+#[test]
+fn event_test() {
+    let mut room = SmartRoom::new("Room1");
+    room += SmartSocket::new("Socket1");
+    room += SmartThermometer::new("Thermometer1", 20.0);
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, house);
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    let mut subscriber = ControlClient::connect(addr, KEY).unwrap();
+    subscriber
+        .request(ControlRequest::subscribe("Room1/Socket1"))
+        .unwrap();
+
+    let mut client = ControlClient::connect(addr, KEY).unwrap();
+    client
+        .request(ControlRequest::switch_on_device("Room1/Socket1"))
+        .unwrap();
+    let request =
+        ControlRequest::notify_device("Room1/Socket1", EventData::SetLoad { watts: 500.0 });
+    assert!(!request.is_idempotent());
+    let response = client.request(request).unwrap();
+    let state = response.state().unwrap();
+    assert_eq!(state.power(), Some(500.0));
+    assert_eq!(state.event_id(), SetLoadEvent::ID);
+
+    let (_, notification) = subscriber
+        .notifications()
+        .filter_map(Result::ok)
+        .find(|(_, state)| state.event_id() == SetLoadEvent::ID)
+        .unwrap();
+    assert_eq!(notification, state);
+    assert_eq!(
+        notification.measurement(Quantity::Power).unwrap().unit(),
+        Unit::Watt
+    );
+
+    let response = client
+        .request(ControlRequest::notify_device(
+            "Room1/Thermometer1",
+            EventData::SetTemperature { celsius: 23.5 },
+        ))
+        .unwrap();
+    assert_eq!(response.state().unwrap().themperature(), Some(23.5));
+
+    match client.request(ControlRequest::notify_device(
+        "Room1/Thermometer1",
+        EventData::SetLoad { watts: 100.0 },
+    )) {
+        Err(RequestError::ServerError(e)) => {
+            assert!(matches!(*e, DeviceError::NotImplementedEvent(_)))
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    // Недопустимые значения нагрузки и температуры отклоняются, и
+    // состояние устройств не изменяется.
+    let invalid = [
+        ("Room1/Socket1", EventData::SetLoad { watts: -1.0 }),
+        ("Room1/Socket1", EventData::SetLoad { watts: f64::NAN }),
+        (
+            "Room1/Socket1",
+            EventData::SetLoad {
+                watts: f64::INFINITY,
+            },
+        ),
+        (
+            "Room1/Thermometer1",
+            EventData::SetTemperature { celsius: f64::NAN },
+        ),
+        (
+            "Room1/Thermometer1",
+            EventData::SetTemperature {
+                celsius: f64::INFINITY,
+            },
+        ),
+    ];
+    for (device, event) in invalid {
+        match client.request(ControlRequest::notify_device(device, event)) {
+            Err(RequestError::ServerError(e)) => {
+                assert!(matches!(*e, DeviceError::InvalidArgument(_)))
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+    let response = client
+        .request(ControlRequest::acquire_device_state("Room1/Socket1"))
+        .unwrap();
+    assert_eq!(response.state().unwrap().power(), Some(500.0));
+    let response = client
+        .request(ControlRequest::acquire_device_state("Room1/Thermometer1"))
+        .unwrap();
+    assert_eq!(response.state().unwrap().themperature(), Some(23.5));
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = SmartSocketServer::with_server(server, SmartSocket::new("Socket2"));
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    let mut socket = RemoteSmartSocket::connect(addr, KEY).unwrap();
+    socket.switch_on().unwrap();
+    let state = socket.notify(&SetLoadEvent::new(1500.0)).unwrap();
+    assert_eq!(state.power(), Some(1500.0));
+    assert_eq!(socket.plug(750.0).unwrap().power(), Some(750.0));
+
+    drop(socket);
+    shutdown.shutdown();
+    handle.join().unwrap();
+}
+
+#[test]
+fn capabilities_test() {
+    let socket_server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let socket_addr = socket_server.local_addr().unwrap();
+    let socket_server = SmartSocketServer::with_server(socket_server, SmartSocket::new("Socket2"));
+    let socket_shutdown = socket_server.shutdown_handle();
+    let socket_handle = thread::spawn(move || socket_server.run());
+
+    let mut room = SmartRoom::new("Room1");
+    room += SmartSocket::new("Socket1");
+    room += SmartThermometer::new("Thermometer1", 20.0);
+    room += RemoteSmartSocket::connect(socket_addr, KEY).unwrap();
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, house);
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    let mut client = ControlClient::connect(addr, KEY).unwrap();
+    let request = ControlRequest::acquire_device_capabilities("Room1/Thermometer1");
+    assert!(request.is_idempotent());
+    let response = client.request(request).unwrap();
+    let capabilities = response.capabilities().unwrap();
+    assert_eq!(capabilities.kind(), DeviceKind::Thermometer);
+    assert!(capabilities.accepts(EventKind::SetTemperature));
+    assert!(!capabilities.accepts(EventKind::SwitchOn));
+    assert_eq!(capabilities.measurements(), [Quantity::Temperature]);
+
+    for device in ["Room1/Socket1", "Room1/Socket2"] {
+        let response = client
+            .request(ControlRequest::acquire_device_capabilities(device))
+            .unwrap();
+        assert_eq!(
+            response.capabilities().unwrap(),
+            &DeviceCapabilities::for_socket()
+        );
+    }
+
+    match client.request(ControlRequest::acquire_device_capabilities("Room1/Mixer")) {
+        Err(RequestError::ServerError(e)) => {
+            assert!(matches!(*e, DeviceError::IllegalDeviceName(_)))
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+    socket_shutdown.shutdown();
+    socket_handle.join().unwrap();
+}
+
+#[test]
+fn lamp_test() {
+    let lamp_server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let lamp_addr = lamp_server.local_addr().unwrap();
+    let lamp_server = SmartLampServer::with_server(
+        lamp_server,
+        SmartLamp::new("Lamp2")
+            .with_color_temperature(4000)
+            .unwrap(),
+    );
+    let lamp_shutdown = lamp_server.shutdown_handle();
+    let lamp_handle = thread::spawn(move || {
+        lamp_server.run();
+        lamp_server.into_lamp().ok().unwrap()
+    });
+
+    let mut lamp = RemoteSmartLamp::connect(lamp_addr, KEY).unwrap();
+    assert!(lamp.capabilities().accepts(EventKind::SetColorTemperature));
+    assert_eq!(lamp.switch_on().unwrap().enabled(), Some(true));
+    assert!(matches!(
+        lamp.set_brightness(150),
+        Err(DeviceError::InvalidArgument(_))
+    ));
+    assert_eq!(lamp.set_brightness(100).unwrap().brightness(), Some(100));
+    let state = lamp.notify(&SetColorTemperatureEvent::new(2700)).unwrap();
+    assert_eq!(state.color_temperature(), Some(2700));
+    assert_eq!(state.event_id(), SetColorTemperatureEvent::ID);
+
+    let mut room = SmartRoom::new("Room1");
+    room += SmartLamp::new("Lamp1");
+    room += lamp;
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let addr = server.local_addr().unwrap();
+    let server = ControlServer::with_server(server, house);
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    let mut client = ControlClient::connect(addr, KEY).unwrap();
+    for device in ["Room1/Lamp1", "Room1/Lamp2"] {
+        let response = client
+            .request(ControlRequest::notify_device(
+                device,
+                EventData::SetBrightness { percent: 40 },
+            ))
+            .unwrap();
+        let state = response.state().unwrap();
+        assert_eq!(state.brightness(), Some(40));
+        assert_eq!(state.event_id(), SetBrightnessEvent::ID);
+
+        match client.request(ControlRequest::notify_device(
+            device,
+            EventData::SetBrightness { percent: 150 },
+        )) {
+            Err(RequestError::ServerError(e)) => {
+                assert!(matches!(*e, DeviceError::InvalidArgument(_)))
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        let response = client
+            .request(ControlRequest::acquire_device_state(device))
+            .unwrap();
+        assert_eq!(response.state().unwrap().brightness(), Some(40));
+    }
+
+    let response = client
+        .request(ControlRequest::acquire_device_capabilities("Room1/Lamp1"))
+        .unwrap();
+    assert_eq!(
+        response.capabilities().unwrap(),
+        &DeviceCapabilities::for_lamp(false)
+    );
+
+    match client.request(ControlRequest::notify_device(
+        "Room1/Lamp1",
+        EventData::SetColorTemperature { kelvin: 2700 },
+    )) {
+        Err(RequestError::ServerError(e)) => {
+            assert!(matches!(*e, DeviceError::NotImplementedEvent(_)))
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+    lamp_shutdown.shutdown();
+    let lamp = lamp_handle.join().unwrap();
+    assert!(lamp.enabled());
+    assert_eq!(lamp.brightness(), 40);
+    assert_eq!(lamp.color_temperature(), Some(2700));
+
+    let socket_server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let socket_addr = socket_server.local_addr().unwrap();
+    let socket_server = SmartSocketServer::with_server(socket_server, SmartSocket::new("Socket1"));
+    let socket_shutdown = socket_server.shutdown_handle();
+    let socket_handle = thread::spawn(move || socket_server.run());
+
+    assert!(matches!(
+        RemoteSmartLamp::connect(socket_addr, KEY),
+        Err(DeviceError::UnexpectedMessage)
+    ));
+
+    socket_shutdown.shutdown();
+    socket_handle.join().unwrap();
+}
+
+#[test]
+fn sensor_test() {
+    let mut motion = RemoteSensor::builder(SensorKind::Motion)
+        .with_name("Motion1")
+        .bind("127.0.0.1:55401")
+        .connect("127.0.0.1:55402")
+        .build();
+    let states = Arc::new(Mutex::new(Vec::new()));
+    motion.watch(StateListener::new({
+        let states = states.clone();
+        move |s| states.lock().unwrap().push(s)
+    }));
+    let door = RemoteSensor::builder(SensorKind::Contact)
+        .with_name("Door1")
+        .bind("127.0.0.1:55403")
+        .connect("127.0.0.1:55404")
+        .build();
+    assert_eq!(door.value(), None);
+    assert!(door.to_string().contains("Показания не получены"));
+    thread::sleep(Duration::from_millis(100));
+
+    let autonomous_motion = AutonomousSensor::builder()
+        .bind("127.0.0.1:55402")
+        .connect("127.0.0.1:55401")
+        .build(SmartSensor::new("Motion1", SensorKind::Motion, true))
+        .unwrap();
+    let autonomous_door = AutonomousSensor::builder()
+        .bind("127.0.0.1:55404")
+        .connect("127.0.0.1:55403")
+        .build(SmartSensor::new("Door1", SensorKind::Contact, true))
+        .unwrap();
+    let (motion_handle, motion_control) = autonomous_motion.run().unwrap();
+    let (door_handle, door_control) = autonomous_door.run().unwrap();
+
+    for _ in 0..40 {
+        if motion.value().is_some() && door.value().is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(motion.value(), Some(Value::Flag(true)));
+    assert_eq!(
+        door.capabilities(),
+        DeviceCapabilities::for_sensor(SensorKind::Contact)
+    );
+    assert_eq!(
+        states.lock().unwrap()[0].measurement(Quantity::Motion),
+        Some(&(Quantity::Motion, true).into())
+    );
+
+    let humidity = SmartSensor::new("Humidity1", SensorKind::Humidity, 45.0);
+    let humidity_id = humidity.id();
+    let mut room = SmartRoom::new("Room1");
+    room += humidity;
+    room += motion;
+    room += door;
+    let mut house = SmartHouse::new("House1");
+    house += room;
+
+    let measurements: Vec<_> = house
+        .notify_all(&StateEvent::new())
+        .flat_map(|s| s.measurements().to_vec())
+        .collect();
+    assert_eq!(measurements.len(), 3);
+    assert!(measurements.contains(&(Quantity::Humidity, 45.0).into()));
+    assert!(measurements.contains(&(Quantity::Motion, true).into()));
+    assert!(measurements.contains(&(Quantity::Open, true).into()));
+
+    match house.notify("Room1", "Door1", &SetLoadEvent::new(100.0)) {
+        Err(DeviceError::NotImplementedEvent(_)) => {}
+        r => panic!("unexpected result {:?}", r),
+    }
+    let state = house
+        .notify("Room1", "Humidity1", &StateEvent::new())
+        .unwrap();
+    assert_eq!(state.device_id(), humidity_id);
+
+    for control in [motion_control, door_control] {
+        control.upgrade().unwrap().store(false, Ordering::Relaxed);
+    }
+    motion_handle.join().unwrap().unwrap();
+    door_handle.join().unwrap().unwrap();
+}
+
+#[test]
+fn house_mutation_test() {
+    let socket_server = Server::bind("127.0.0.1:0", KEY).unwrap();
+    let socket_addr = socket_server.local_addr().unwrap();
+    let socket_server = SmartSocketServer::with_server(socket_server, SmartSocket::new("Socket2"));
+    let socket_shutdown = socket_server.shutdown_handle();
+    let socket_handle = thread::spawn(move || socket_server.run());
+
+    let listener = PipeListener::new();
+    let connector = listener.connector();
+    let server = ControlServer::with_server(
+        Server::builder(KEY).listen(listener).unwrap(),
+        SmartHouse::new("House1"),
+    );
+    let shutdown = server.shutdown_handle();
+    let handle = thread::spawn(move || server.run());
+
+    let mut client = ControlClient::from(
+        Client::builder(KEY)
+            .connect_with(connector.connect().unwrap())
+            .unwrap(),
+    );
+    let server_error = |r: Result<_, RequestError>| match r {
+        Err(RequestError::ServerError(e)) => *e,
+        r => panic!("unexpected result {:?}", r),
+    };
+
+    let response = client
+        .request(ControlRequest::create_room("Room1"))
+        .unwrap();
+    let (room_id, _) = response.name().unwrap();
+    assert!(matches!(
+        server_error(client.request(ControlRequest::create_room("Room1"))),
+        DeviceError::IllegalRoomName(name) if name == "Room1"
+    ));
+    assert!(matches!(
+        server_error(client.request(ControlRequest::create_room(""))),
+        DeviceError::IllegalRoomName(_)
+    ));
+
+    // Имена, которые невозможно выбрать в пути "комната/устройство",
+    // отклоняются.
+    let unselectable = ["Room/1".to_owned(), Uuid::new_v4().to_string()];
+    for name in &unselectable {
+        assert!(matches!(
+            server_error(client.request(ControlRequest::create_room(name))),
+            DeviceError::InvalidArgument(_)
+        ));
+        assert!(matches!(
+            server_error(client.request(ControlRequest::rename_room(room_id, name))),
+            DeviceError::InvalidArgument(_)
+        ));
+        assert!(matches!(
+            server_error(client.request(ControlRequest::attach_device(
+                room_id,
+                DeviceSpec::Socket { name: name.clone() },
+            ))),
+            DeviceError::InvalidArgument(_)
+        ));
+    }
+
+    let response = client
+        .request(ControlRequest::create_room("Room2"))
+        .unwrap();
+    let (room2_id, _) = response.name().unwrap();
+    assert!(matches!(
+        server_error(client.request(ControlRequest::rename_room(room2_id, "Room1"))),
+        DeviceError::IllegalRoomName(name) if name == "Room1"
+    ));
+    client
+        .request(ControlRequest::rename_room(room2_id, "Kitchen"))
+        .unwrap();
+    client
+        .request(ControlRequest::delete_room(room2_id))
+        .unwrap();
+    assert!(matches!(
+        server_error(client.request(ControlRequest::delete_room(room2_id))),
+        DeviceError::IllegalRoomId(id) if id == room2_id
+    ));
+    let response = client.request(ControlRequest::acquire_rooms()).unwrap();
+    assert_eq!(response.list().unwrap(), [(room_id, "Room1".to_owned())]);
+
+    let response = client
+        .request(ControlRequest::attach_device(
+            room_id,
+            DeviceSpec::Socket {
+                name: "Socket1".to_owned(),
+            },
+        ))
+        .unwrap();
+    let (socket_id, _) = response.name().unwrap();
+    assert!(matches!(
+        server_error(client.request(ControlRequest::attach_device(
+            room_id,
+            DeviceSpec::Thermometer {
+                name: "Socket1".to_owned(),
+                temperature: 20.0,
+            },
+        ))),
+        DeviceError::IllegalDeviceName(name) if name == "Socket1"
+    ));
+    assert!(matches!(
+        server_error(client.request(ControlRequest::attach_device(
+            room2_id,
+            DeviceSpec::Socket {
+                name: "Socket3".to_owned(),
+            },
+        ))),
+        DeviceError::IllegalRoomId(id) if id == room2_id
+    ));
+
+    let response = client
+        .request(ControlRequest::attach_device(
+            room_id,
+            DeviceSpec::RemoteSocket {
+                address: socket_addr.to_string(),
+            },
+        ))
+        .unwrap();
+    let (remote_id, name) = response.name().unwrap();
+    assert_eq!(name, "Socket2");
+    let response = client
+        .request(ControlRequest::switch_on_device((room_id, remote_id)))
+        .unwrap();
+    assert!(response.state().unwrap().enabled().unwrap());
+
+    client
+        .request(ControlRequest::detach_device(room_id, socket_id))
+        .unwrap();
+    assert!(matches!(
+        server_error(client.request(ControlRequest::detach_device(room_id, socket_id))),
+        DeviceError::IllegalDeviceId(id) if id == socket_id
+    ));
+    let response = client
+        .request(ControlRequest::acquire_devices(room_id))
+        .unwrap();
+    assert_eq!(
+        response.list().unwrap(),
+        [(remote_id, "Socket2".to_owned())]
+    );
+
+    shutdown.shutdown();
+    handle.join().unwrap();
+    socket_shutdown.shutdown();
+    socket_handle.join().unwrap();
+}
+
+#[derive(Serialize, Deserialize)]
+struct Ping(u32);
+
+impl Message for Ping {
+    const TYPE: u16 = USER_MESSAGE_ID_MIN;
+}
+
+#[test]
+fn envelope_test() {
+    let listener = PipeListener::new();
+    let connector = listener.connector();
+    let server = Server::builder(KEY).listen(listener).unwrap();
+
+    let handle = thread::spawn(move || {
+        let router = Router::<Connection, Result<(), SendError>>::new()
+            .with_route(|c, m: Box<TextMessage>| c.send(TextMessage::new(m.to_string())))
+            .with_route(|c, m: Box<SensorMessage>| {
+                c.send(TextMessage::new(
+                    m.value(Quantity::Temperature).unwrap().to_string(),
+                ))
+            })
+            .with_route(|c, m: Box<Ping>| c.send(Ping(m.0 + 1)));
+
+        let mut connection = server.incoming().next().unwrap().unwrap();
+        for _ in 0..3 {
+            let envelope = connection.recv_any().unwrap();
+            router.route(&mut connection, &envelope).unwrap().unwrap();
+        }
+
+        let envelope = connection.recv_any().unwrap();
+        assert!(envelope.is::<ControlRequest>());
+        assert!(matches!(
+            router.route(&mut connection, &envelope),
+            Err(RecvError::BadType(_))
+        ));
+    });
+
+    let mut client = Client::builder(KEY)
+        .connect_with(connector.connect().unwrap())
+        .unwrap();
+
+    client.send(TextMessage::new("hello")).unwrap();
+    let response = client.recv_any().unwrap();
+    assert!(response.is::<TextMessage>());
+    assert_eq!(response.open::<TextMessage>().unwrap().to_string(), "hello");
+
+    client
+        .send(SensorMessage::new(Uuid::new_v4()).with_measurement((Quantity::Temperature, 21.5)))
+        .unwrap();
+    let response: Box<TextMessage> = client.recv().unwrap();
+    assert_eq!(response.to_string(), "21.5");
+
+    let response: Box<Ping> = client.request(Ping(41)).unwrap();
+    assert_eq!(response.0, 42);
+
+    client.send(ControlRequest::acquire_rooms()).unwrap();
+    handle.join().unwrap();
+}
+
+#[cfg(feature = "tls")]
+#[test]
+fn tls_test() {
+    use std::{env, fs, path::PathBuf};
+
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
+    use smarthome2::control::protocol::tls::{TlsClientConfig, TlsServerConfig};
+
+    let dir = env::temp_dir().join(format!("smarthome2-tls-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str, data: String| -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, data).unwrap();
+        path
+    };
+
+    let mut params = CertificateParams::new(vec![]);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = Certificate::from_params(params).unwrap();
+    let ca_path = write("ca.pem", ca.serialize_pem().unwrap());
+
+    let server_cert =
+        Certificate::from_params(CertificateParams::new(vec!["localhost".into()])).unwrap();
+    let server_cert_path = write(
+        "server.pem",
+        server_cert.serialize_pem_with_signer(&ca).unwrap(),
+    );
+    let server_key_path = write("server.key", server_cert.serialize_private_key_pem());
+
+    let client_cert =
+        Certificate::from_params(CertificateParams::new(vec!["client".into()])).unwrap();
+    let client_cert_path = write(
+        "client.pem",
+        client_cert.serialize_pem_with_signer(&ca).unwrap(),
+    );
+    let client_key_path = write("client.key", client_cert.serialize_private_key_pem());
+
+    let server = Server::builder(KEY)
+        .with_tls(
+            TlsServerConfig::from_pem(&server_cert_path, &server_key_path)
+                .unwrap()
+                .with_client_verification(&ca_path)
+                .unwrap(),
+        )
+        .bind("127.0.0.1:0")
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let handle = thread::spawn(move || {
+        let mut incoming = server.incoming();
+
+        let mut connection = incoming.next().unwrap().unwrap();
+        let request = connection.recv::<TextMessage>().unwrap();
+        connection
+            .send(TextMessage::new(request.to_string()))
+            .unwrap();
+
+        assert!(incoming.next().unwrap().is_err());
+    });
+
+    let mut client = Client::builder(KEY)
+        .with_tls(
+            TlsClientConfig::from_pem("localhost", &ca_path)
+                .unwrap()
+                .with_identity(&client_cert_path, &client_key_path)
+                .unwrap(),
+        )
+        .connect(addr)
+        .unwrap();
+    let response: Box<TextMessage> = client.request(TextMessage::new("ping")).unwrap();
+    assert_eq!(response.to_string(), "ping");
+
+    // Клиент без сертификата не может подключиться к серверу.
+    assert!(Client::builder(KEY)
+        .with_tls(TlsClientConfig::from_pem("localhost", &ca_path).unwrap())
+        .connect(addr)
+        .is_err());
+
+    handle.join().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
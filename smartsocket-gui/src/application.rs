@@ -7,7 +7,7 @@ use iced::{
 use crate::config::Config;
 use smarthome2::device::{
     socket::{RemoteSmartSocket, SwitchOffEvent, SwitchOnEvent},
-    Device, DeviceState, StateEvent,
+    Device, DeviceCapabilities, DeviceState, EventKind, StateEvent,
 };
 
 ///
@@ -46,6 +46,9 @@ pub struct SmartSocketClient {
     // Состояние удаленной розетки.
     socket_state: Option<DeviceState>,
 
+    // Возможности удаленной розетки.
+    socket_capabilities: Option<DeviceCapabilities>,
+
     // Состояние кнопки "Подключить".
     connect_btn_state: button::State,
 
@@ -84,6 +87,7 @@ impl Application for SmartSocketClient {
                 config: flags,
                 socket: None,
                 socket_state: None,
+                socket_capabilities: None,
 
                 connect_btn_state: button::State::new(),
                 disconnect_btn_state: button::State::new(),
@@ -112,10 +116,15 @@ impl Application for SmartSocketClient {
 
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
         match message {
-            Message::Connect => match RemoteSmartSocket::connect(self.config.server_addrs()) {
-                Ok(mut socket) => match socket.notify(&StateEvent::new()) {
+            Message::Connect => match self
+                .config
+                .server_key()
+                .map(|key| RemoteSmartSocket::connect(self.config.server_addrs(), key))
+            {
+                Some(Ok(mut socket)) => match socket.notify(&StateEvent::new()) {
                     Ok(state) => {
                         self.socket_state = Some(state);
+                        self.socket_capabilities = Some(socket.capabilities());
                         self.socket = Some(socket);
                         self.error_msg = None;
                     }
@@ -123,11 +132,13 @@ impl Application for SmartSocketClient {
                         self.error_msg = Some(format!("Ошибка запроса состояния: {}", error))
                     }
                 },
-                Err(error) => self.error_msg = Some(format!("Ошибка подключения: {}", error)),
+                Some(Err(error)) => self.error_msg = Some(format!("Ошибка подключения: {}", error)),
+                None => self.error_msg = Some("Не задан ключ для аутентификации".to_string()),
             },
 
             Message::Disconnect => {
                 self.socket_state = None;
+                self.socket_capabilities = None;
                 self.socket = None;
                 self.error_msg = None;
             }
@@ -167,6 +178,16 @@ impl Application for SmartSocketClient {
     fn view(&mut self) -> Element<'_, Self::Message> {
         let mut column;
         if self.socket.is_some() {
+            // Кнопки управления показываются только для событий,
+            // которые обрабатывает розетка.
+            let accepts = |event| {
+                self.socket_capabilities
+                    .as_ref()
+                    .is_some_and(|c| c.accepts(event))
+            };
+            let can_switch_on = accepts(EventKind::SwitchOn);
+            let can_switch_off = accepts(EventKind::SwitchOff);
+
             column = Column::new();
             if let Some(ref state) = self.socket_state {
                 if let Some(enabled) = state.enabled() {
@@ -181,23 +202,24 @@ impl Application for SmartSocketClient {
                                 .color([1.0, 0.0, 0.0])
                                 .size(16)
                         };
-                        let switch_off_btn =
-                            Button::new(&mut self.switch_off_btn_state, Text::new("Выключить"))
-                                .padding(10)
-                                .on_press(Message::TurnOff);
-
-                        column = column
-                            .push(socket_text)
-                            .push(power_text)
-                            .push(switch_off_btn);
+                        column = column.push(socket_text).push(power_text);
+                        if can_switch_off {
+                            let switch_off_btn =
+                                Button::new(&mut self.switch_off_btn_state, Text::new("Выключить"))
+                                    .padding(10)
+                                    .on_press(Message::TurnOff);
+                            column = column.push(switch_off_btn);
+                        }
                     } else {
                         let socket_text = Text::new("Розетка выключена").size(20);
-                        let switch_on_btn =
-                            Button::new(&mut self.switch_on_btn_state, Text::new("Включить"))
-                                .padding(10)
-                                .on_press(Message::TurnOn);
-
-                        column = column.push(socket_text).push(switch_on_btn);
+                        column = column.push(socket_text);
+                        if can_switch_on {
+                            let switch_on_btn =
+                                Button::new(&mut self.switch_on_btn_state, Text::new("Включить"))
+                                    .padding(10)
+                                    .on_press(Message::TurnOn);
+                            column = column.push(switch_on_btn);
+                        }
                     }
                 }
             }
@@ -25,6 +25,12 @@ pub(crate) struct SmartSocketServerConfig {
     ///
     #[serde(rename = "Port")]
     port: i32,
+
+    ///
+    /// Общий с сервером умной розетки ключ для аутентификации.
+    ///
+    #[serde(rename = "Key", default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
 }
 
 ///
@@ -79,6 +85,7 @@ impl Config {
                 server_config: SmartSocketServerConfig {
                     addr: "127.0.0.1".to_string(),
                     port: 55333,
+                    key: None,
                 },
             };
 
@@ -114,6 +121,14 @@ impl Config {
         self.server_config.port
     }
 
+    ///
+    /// Получить общий с сервером умной розетки ключ для аутентификации.
+    ///
+    #[inline]
+    pub fn server_key(&self) -> Option<&str> {
+        self.server_config.key.as_deref()
+    }
+
     ///
     /// Получить адрес для подключения к серверу.
     ///
@@ -146,8 +146,7 @@ impl SmartHouse {
                 )
                 .bind(self.house_id)
                 .fetch_all(pool)
-                .await?
-                .into_iter(),
+                .await?,
             )
             .zip(stream::iter(repeat_with(|| pool.clone())))
             .then(|(r, pool)| async move {
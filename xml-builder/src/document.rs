@@ -40,7 +40,7 @@ impl XmlObject for XmlDocument {
     /// Получить итератор для перечисления всех дочерних элементов
     /// документа XML.
     ///
-    fn children(&'static self) -> Box<dyn Iterator<Item = &dyn XmlObject>> {
+    fn children(&'static self) -> Box<dyn Iterator<Item = &'static dyn XmlObject>> {
         Box::new(self.children.iter().map(|r| r.as_ref()))
     }
 
@@ -51,7 +51,7 @@ impl XmlObject for XmlElement {
     ///
     /// Получить итератор для перечисления всех атрибутов элеметра XML.
     ///
-    fn attributes(&'static self) -> Box<dyn Iterator<Item = &XmlAttribute>> {
+    fn attributes(&'static self) -> Box<dyn Iterator<Item = &'static XmlAttribute>> {
         Box::new(self.attributes.iter())
     }
 
@@ -59,7 +59,7 @@ impl XmlObject for XmlElement {
     /// Получить итератор для перечисления всех дочерних элементов
     /// элемента XML.
     ///
-    fn children(&'static self) -> Box<dyn Iterator<Item = &dyn XmlObject>> {
+    fn children(&'static self) -> Box<dyn Iterator<Item = &'static dyn XmlObject>> {
         Box::new(self.children.iter().map(|r| r.as_ref()))
     }
 
@@ -11,7 +11,7 @@ pub trait XmlObject: fmt::Debug + fmt::Display {
     ///
     /// Получить итератор для последовательного перебора всех атрибутов узла XML.
     ///
-    fn attributes(&'static self) -> Box<dyn Iterator<Item = &attribute::XmlAttribute>> {
+    fn attributes(&'static self) -> Box<dyn Iterator<Item = &'static attribute::XmlAttribute>> {
         Box::new(EmptyAttributesIter::<'_> {
             phantom: PhantomData::<&'_ attribute::XmlAttribute> {},
         })
@@ -21,7 +21,7 @@ pub trait XmlObject: fmt::Debug + fmt::Display {
     /// Получить итератор для последовательного перебора всех дочерних узлов
     /// данного узла XML.
     ///
-    fn children(&'static self) -> Box<dyn Iterator<Item = &dyn XmlObject>> {
+    fn children(&'static self) -> Box<dyn Iterator<Item = &'static dyn XmlObject>> {
         Box::new(EmptyChildrenIter::<'_> {
             phantom: PhantomData::<&'_ dyn XmlObject> {},
         })